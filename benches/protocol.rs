@@ -0,0 +1,116 @@
+use chrono::Utc;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use ya_client::model::NodeId;
+
+use yachat::delivery::DeliveryQueue;
+use yachat::protocol::{SendText, TextMessage};
+
+fn message(content: &str) -> Arc<TextMessage> {
+    Arc::new(TextMessage {
+        id: Uuid::new_v4(),
+        content: content.to_string(),
+        timestamp: Utc::now(),
+        ttl: Some(86400),
+        reply_to: None,
+        relayed: None,
+        channel: None,
+        structured: None,
+    })
+}
+
+fn batch(messages: usize, size: usize) -> SendText {
+    let content = "x".repeat(size);
+    SendText {
+        messages: (0..messages).map(|_| message(&content)).collect(),
+        user: "bench".to_string(),
+        group: Some("bench".to_string()),
+        direct: false,
+        auto_reply: false,
+        delayed: false,
+    }
+}
+
+fn peers(count: usize) -> Vec<NodeId> {
+    (0..count)
+        .map(|idx| {
+            let mut bytes = [0u8; 20];
+            bytes[..8].copy_from_slice(&(idx as u64).to_be_bytes());
+            NodeId::from(bytes)
+        })
+        .collect()
+}
+
+fn encode_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("send_text");
+    for &size in &[64, 1024, 16384] {
+        let text = batch(1, size);
+        let json = serde_json::to_vec(&text).unwrap();
+        group.throughput(Throughput::Bytes(json.len() as u64));
+        group.bench_with_input(BenchmarkId::new("encode", size), &text, |b, text| {
+            b.iter(|| serde_json::to_vec(text).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("decode", size), &json, |b, json| {
+            b.iter(|| serde_json::from_slice::<SendText>(json).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// Queueing single messages for offline peers, as done while they are away,
+/// and taking them in order for resending.
+fn batching(c: &mut Criterion) {
+    let mut group = c.benchmark_group("delivery_queue");
+    for &messages in &[10, 100, 1000] {
+        let peers = peers(10);
+        let texts = (0..messages).map(|_| batch(1, 256)).collect::<Vec<_>>();
+        group.bench_with_input(
+            BenchmarkId::new("push_take", messages),
+            &texts,
+            |b, texts| {
+                b.iter(|| {
+                    let mut queue = DeliveryQueue::default();
+                    for (idx, text) in texts.iter().enumerate() {
+                        queue.push(peers[idx % peers.len()], text.clone());
+                    }
+                    for peer in peers.iter() {
+                        queue.take(peer);
+                    }
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Message posted to group is cloned and serialized for every user in roster.
+fn fan_out(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fan_out");
+    for &count in &[10, 100, 1000] {
+        let peers = peers(count);
+        let text = batch(1, 1024);
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::new("clone", count), &peers, |b, peers| {
+            b.iter(|| {
+                peers
+                    .iter()
+                    .map(|peer| (*peer, text.clone()))
+                    .collect::<Vec<_>>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("serialize", count), &peers, |b, peers| {
+            b.iter(|| {
+                peers
+                    .iter()
+                    .map(|peer| (*peer, serde_json::to_vec(&text.clone()).unwrap()))
+                    .collect::<Vec<_>>()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, encode_decode, batching, fan_out);
+criterion_main!(benches);
@@ -0,0 +1,28 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use yachat::protocol::*;
+
+// Every message bound on GSB endpoint of chat. First byte selects the type.
+fuzz_target!(|data: &[u8]| {
+    let (selector, payload) = match data.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    let _ = match selector % 14 {
+        0 => serde_json::from_slice::<SendText>(payload).is_ok(),
+        1 => serde_json::from_slice::<Poll>(payload).is_ok(),
+        2 => serde_json::from_slice::<Vote>(payload).is_ok(),
+        3 => serde_json::from_slice::<PollResults>(payload).is_ok(),
+        4 => serde_json::from_slice::<PinMessage>(payload).is_ok(),
+        5 => serde_json::from_slice::<Members>(payload).is_ok(),
+        6 => serde_json::from_slice::<WhoAreYou>(payload).is_ok(),
+        7 => serde_json::from_slice::<Pair>(payload).is_ok(),
+        8 => serde_json::from_slice::<SyncHistory>(payload).is_ok(),
+        9 => serde_json::from_slice::<RatchetInit>(payload).is_ok(),
+        10 => serde_json::from_slice::<SendSealed>(payload).is_ok(),
+        11 => serde_json::from_slice::<Profile>(payload).is_ok(),
+        12 => serde_json::from_slice::<FetchQueued>(payload).is_ok(),
+        _ => serde_json::from_slice::<PresenceUpdate>(payload).is_ok(),
+    };
+});
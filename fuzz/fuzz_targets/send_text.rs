@@ -0,0 +1,12 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use yachat::protocol::SendText;
+
+// Messages accepted from peers must survive being stored and resent.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(text) = serde_json::from_slice::<SendText>(data) {
+        let json = serde_json::to_vec(&text).unwrap();
+        serde_json::from_slice::<SendText>(&json).unwrap();
+    }
+});
@@ -0,0 +1,13 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use yachat::protocol::Structured;
+use yachat::structured;
+
+// Structured data comes from peers and plugins and is rendered unchecked.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(structured) = serde_json::from_slice::<Structured>(data) {
+        let _ = structured::render(&structured);
+        let _ = structured::fallback(&structured);
+    }
+});
@@ -0,0 +1,258 @@
+use actix::prelude::*;
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+use ya_client::cli::{ApiOpts, ProviderApi, RequestorApi};
+use ya_client::model::payment::{Invoice, InvoiceStatus};
+use ya_core_model::{activity, payment};
+use ya_service_bus::{typed as bus, RpcEndpoint};
+
+use crate::discover::Apis;
+
+/// Activity state of yagna, which means that provider lost contact with
+/// its ExeUnit.
+const UNRESPONSIVE: &str = "Unresponsive";
+
+/// `[alerts]` section of config file. Alerts are enabled by setting group,
+/// which is joined like groups of recurring messages.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct AlertsConfig {
+    pub group: Option<String>,
+    /// Alert, when balance of any payment account drops below this amount of GLM.
+    pub low_balance: Option<f64>,
+    pub interval_seconds: u64,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        AlertsConfig {
+            group: None,
+            low_balance: None,
+            interval_seconds: 300,
+        }
+    }
+}
+
+// =========================================== //
+// Public exposed messages
+// =========================================== //
+
+/// Starts watching local yagna. Alerts are sent to `notify`.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct WatchNode {
+    pub notify: Recipient<Alert>,
+}
+
+/// Problem found on our yagna node. Posted by chat to alerts group.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Alert {
+    pub group: String,
+    pub text: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+struct Check;
+
+// =========================================== //
+// Alerts implementation
+// =========================================== //
+
+/// What was already reported, so every problem is posted once.
+#[derive(Default)]
+struct Reported {
+    /// None until first check, so activities lost before start aren't reported.
+    unresponsive: Option<u64>,
+    invoices: HashSet<String>,
+    low_balance: HashSet<String>,
+}
+
+/// Polls activity and payment services of local yagna and reports failed
+/// activities, rejected or failed invoices and low balance.
+pub struct Alerts {
+    apis: Apis,
+    config: AlertsConfig,
+    notify: Option<Recipient<Alert>>,
+    since: DateTime<Utc>,
+    reported: Reported,
+    checking: bool,
+}
+
+impl Alerts {
+    /// Returns None, if alerts aren't enabled in config.
+    pub fn new(api: &ApiOpts, config: AlertsConfig) -> anyhow::Result<Option<Alerts>> {
+        if config.group.is_none() {
+            return Ok(None);
+        }
+        let apis = Apis {
+            provider: ProviderApi::try_from(api)?,
+            requestor: RequestorApi::try_from(api)?,
+        };
+        Ok(Some(Alerts {
+            apis,
+            config,
+            notify: None,
+            since: Utc::now(),
+            reported: Reported::default(),
+            checking: false,
+        }))
+    }
+
+    fn alert(&self, text: String) {
+        let (notify, group) = match (&self.notify, &self.config.group) {
+            (Some(notify), Some(group)) => (notify, group.clone()),
+            _ => return,
+        };
+        notify
+            .do_send(Alert { group, text })
+            .map_err(|e| log::warn!("Failed to post alert. Error: {}", e))
+            .ok();
+    }
+}
+
+impl Actor for Alerts {
+    type Context = Context<Self>;
+}
+
+impl Handler<WatchNode> for Alerts {
+    type Result = ();
+
+    fn handle(&mut self, msg: WatchNode, ctx: &mut Context<Self>) -> Self::Result {
+        self.notify = Some(msg.notify);
+        let interval = std::time::Duration::from_secs(self.config.interval_seconds.max(10));
+        ctx.notify(Check);
+        ctx.run_interval(interval, |_, ctx| ctx.notify(Check));
+    }
+}
+
+impl Handler<Check> for Alerts {
+    type Result = ();
+
+    fn handle(&mut self, _: Check, ctx: &mut Context<Self>) -> Self::Result {
+        // Slow yagna could otherwise pile up checks.
+        if self.checking {
+            return;
+        }
+        self.checking = true;
+
+        let apis = self.apis.clone();
+        let since = self.since;
+        let low_balance = self.config.low_balance;
+        let future = async move {
+            let unresponsive = unresponsive_activities().await;
+            let invoices = failed_invoices(&apis, since).await;
+            let balances = match low_balance {
+                Some(_) => balances(&apis).await,
+                None => Ok(vec![]),
+            };
+            (unresponsive, invoices, balances)
+        }
+        .into_actor(self)
+        .map(move |(unresponsive, invoices, balances), myself, _| {
+            myself.checking = false;
+
+            match unresponsive {
+                Ok(count) => {
+                    if let Some(last) = myself.reported.unresponsive {
+                        if count > last {
+                            myself.alert(format!(
+                                "{} activity(ies) became unresponsive.",
+                                count - last
+                            ));
+                        }
+                    }
+                    myself.reported.unresponsive = Some(count);
+                }
+                Err(e) => log::debug!("Failed to read activity stats. Error: {}", e),
+            }
+
+            match invoices {
+                Ok(invoices) => {
+                    for invoice in invoices {
+                        if myself.reported.invoices.insert(invoice.invoice_id.clone()) {
+                            myself.alert(format!(
+                                "Invoice {} for {} GLM is {:?}.",
+                                invoice.invoice_id, invoice.amount, invoice.status
+                            ));
+                        }
+                    }
+                }
+                Err(e) => log::debug!("Failed to read invoices. Error: {}", e),
+            }
+
+            let threshold = low_balance.unwrap_or_default();
+            match balances {
+                Ok(balances) => {
+                    for (account, amount) in balances {
+                        if amount >= threshold {
+                            myself.reported.low_balance.remove(&account);
+                        } else if myself.reported.low_balance.insert(account.clone()) {
+                            myself.alert(format!(
+                                "Low balance of {}: {} GLM, below {} GLM.",
+                                account, amount, threshold
+                            ));
+                        }
+                    }
+                }
+                Err(e) => log::debug!("Failed to read balances. Error: {}", e),
+            }
+        });
+        ctx.spawn(future);
+    }
+}
+
+async fn unresponsive_activities() -> anyhow::Result<u64> {
+    let stats = bus::service(activity::local::BUS_ID)
+        .send(activity::local::Stats {})
+        .await?
+        .map_err(|e| anyhow!("{}", e))?;
+    Ok(stats.total.get(UNRESPONSIVE).copied().unwrap_or(0))
+}
+
+/// Invoices issued by our provider or received by our requestor, which
+/// won't be paid.
+async fn failed_invoices(apis: &Apis, since: DateTime<Utc>) -> anyhow::Result<Vec<Invoice>> {
+    let mut invoices = apis
+        .provider
+        .payment
+        .get_invoices(Some(since), None)
+        .await?;
+    invoices.extend(
+        apis.requestor
+            .payment
+            .get_invoices(Some(since), None)
+            .await?,
+    );
+    invoices.retain(|invoice| {
+        invoice.status == InvoiceStatus::Rejected || invoice.status == InvoiceStatus::Failed
+    });
+    Ok(invoices)
+}
+
+/// Balance of every account, we can send payments from.
+async fn balances(apis: &Apis) -> anyhow::Result<Vec<(String, f64)>> {
+    let mut balances = vec![];
+    for account in apis.requestor.payment.get_requestor_accounts().await? {
+        let status = bus::service(payment::local::BUS_ID)
+            .send(payment::local::GetStatus {
+                address: account.address.clone(),
+                driver: account.driver.clone(),
+                network: Some(account.network.clone()),
+                token: Some(account.token.clone()),
+            })
+            .await?
+            .map_err(|e| anyhow!("{}", e))?;
+        let amount = status.amount.to_string().parse::<f64>()?;
+        balances.push((
+            format!("{} ({})", account.address, account.platform),
+            amount,
+        ));
+    }
+    Ok(balances)
+}
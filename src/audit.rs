@@ -0,0 +1,117 @@
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+use ya_client::model::NodeId;
+
+use crate::encryption::{load_sealed, save_sealed, Cipher};
+
+const AUDIT_FILE: &str = "events.json";
+/// Oldest events are dropped above this number.
+const MAX_EVENTS: usize = 1000;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuditKind {
+    /// Users joining groups and being forgotten.
+    Roster,
+    /// Pins, spam mutes and revoked memberships.
+    Moderation,
+    /// Verifications and keys changed under verified names.
+    Key,
+    /// Market subscriptions and membership Agreements of paid groups.
+    Subscription,
+    /// Messages rejected by recipients, expired or dropped from queue.
+    Delivery,
+}
+
+impl AuditKind {
+    fn name(&self) -> &'static str {
+        match self {
+            AuditKind::Roster => "roster",
+            AuditKind::Moderation => "moderation",
+            AuditKind::Key => "key",
+            AuditKind::Subscription => "subscription",
+            AuditKind::Delivery => "delivery",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEvent {
+    pub timestamp: DateTime<Utc>,
+    pub kind: AuditKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// User, the event is about. Events are removed with `/forget`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<NodeId>,
+    pub text: String,
+}
+
+/// System events, kept apart from message history, so group owners can
+/// check what happened while they were away. Sealed like pins, when
+/// storage is encrypted.
+pub struct AuditLog {
+    path: PathBuf,
+    events: VecDeque<AuditEvent>,
+    cipher: Option<Cipher>,
+}
+
+impl AuditLog {
+    pub fn load(data_dir: &Path, cipher: Option<Cipher>) -> anyhow::Result<AuditLog> {
+        let path = data_dir.join(AUDIT_FILE);
+        Ok(AuditLog {
+            events: load_sealed(&path, cipher.as_ref())?,
+            path,
+            cipher,
+        })
+    }
+
+    pub fn record(&mut self, event: AuditEvent) -> anyhow::Result<()> {
+        self.events.push_back(event);
+        while self.events.len() > MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.save()
+    }
+
+    /// Removes events about given users.
+    pub fn forget(&mut self, node_ids: &[NodeId]) -> anyhow::Result<()> {
+        self.events.retain(|event| match &event.node_id {
+            Some(node_id) => !node_ids.contains(node_id),
+            None => true,
+        });
+        self.save()
+    }
+
+    pub fn since(&self, since: Option<DateTime<Utc>>) -> Vec<&AuditEvent> {
+        self.events
+            .iter()
+            .filter(|event| since.map_or(true, |since| event.timestamp >= since))
+            .collect()
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        save_sealed(&self.path, &self.events, self.cipher.as_ref())
+    }
+}
+
+pub fn format_event(event: &AuditEvent) -> String {
+    let group = match &event.group {
+        Some(group) => format!(" [{}]", group),
+        None => String::new(),
+    };
+    format!(
+        "  {} {:<12}{} {}",
+        event
+            .timestamp
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M"),
+        event.kind.name(),
+        group,
+        event.text
+    )
+}
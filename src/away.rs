@@ -0,0 +1,115 @@
+use chrono::{DateTime, Duration, Local, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use ya_client::model::NodeId;
+
+/// Auto-reply settings from `[away]` section of config file.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct AwayConfig {
+    /// Reply template. `{user}`, `{me}` and `{since}` are substituted.
+    pub message: String,
+    /// Minimal time between replies to the same user.
+    pub interval_minutes: i64,
+    /// Groups, in which messages are auto-replied even without `/away`.
+    pub groups: Vec<String>,
+}
+
+impl Default for AwayConfig {
+    fn default() -> Self {
+        AwayConfig {
+            message: "{me} is away since {since} and will answer later.".to_string(),
+            interval_minutes: 60,
+            groups: vec![],
+        }
+    }
+}
+
+/// Decides, when incoming message should be answered with auto-reply.
+pub struct Away {
+    config: AwayConfig,
+    /// Message given to `/away` and time, when it was typed.
+    active: Option<(Option<String>, DateTime<Utc>)>,
+    /// Last reply sent to each user.
+    replied: HashMap<NodeId, DateTime<Utc>>,
+}
+
+impl Away {
+    pub fn new(config: AwayConfig) -> Away {
+        Away {
+            config,
+            active: None,
+            replied: HashMap::new(),
+        }
+    }
+
+    pub fn set_config(&mut self, config: AwayConfig) {
+        self.config = config;
+    }
+
+    pub fn is_away(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Message given to `/away`, if any.
+    pub fn message(&self) -> Option<&str> {
+        self.active
+            .as_ref()
+            .and_then(|(message, _)| message.as_deref())
+    }
+
+    pub fn set(&mut self, message: Option<String>) {
+        self.active = Some((message, Utc::now()));
+        self.replied.clear();
+    }
+
+    /// Returns number of users, who got auto-reply meanwhile.
+    pub fn back(&mut self) -> usize {
+        self.active = None;
+        std::mem::take(&mut self.replied).len()
+    }
+
+    /// Returns reply text for message from `user` or None, if he shouldn't
+    /// get one. Direct messages are answered while away, group messages
+    /// only in groups listed in config.
+    pub fn reply(
+        &mut self,
+        user: NodeId,
+        name: &str,
+        me: &str,
+        group: Option<&str>,
+    ) -> Option<String> {
+        let enabled = match group {
+            Some(group) => self.config.groups.iter().any(|name| name == group),
+            None => self.active.is_some(),
+        };
+        if !enabled {
+            return None;
+        }
+
+        let now = Utc::now();
+        let interval = Duration::minutes(self.config.interval_minutes);
+        if let Some(last) = self.replied.get(&user) {
+            if now - *last < interval {
+                return None;
+            }
+        }
+        self.replied.insert(user, now);
+
+        let (message, since) = match &self.active {
+            Some((message, since)) => (message.as_ref(), *since),
+            None => (None, now),
+        };
+        let template = message.unwrap_or(&self.config.message);
+        Some(
+            template
+                .replace("{user}", name)
+                .replace("{me}", me)
+                .replace(
+                    "{since}",
+                    &since.with_timezone(&Local).format("%H:%M").to_string(),
+                ),
+        )
+    }
+}
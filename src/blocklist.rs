@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use ya_client::model::NodeId;
+
+use crate::storage::{load_json, save_json};
+
+const BLOCK_FILE: &str = "blocked.json";
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Blocked {
+    /// Name at the time of blocking, so user can be unblocked, when he
+    /// isn't in any of our groups anymore.
+    pub name: String,
+    pub devices: Vec<NodeId>,
+}
+
+/// Users, whose messages are rejected before any processing, persisted in
+/// data dir.
+pub struct Blocklist {
+    path: PathBuf,
+    blocked: Vec<Blocked>,
+}
+
+impl Blocklist {
+    pub fn load(data_dir: &Path) -> anyhow::Result<Blocklist> {
+        let path = data_dir.join(BLOCK_FILE);
+        Ok(Blocklist {
+            blocked: load_json(&path)?,
+            path,
+        })
+    }
+
+    pub fn blocked(&self) -> &[Blocked] {
+        &self.blocked
+    }
+
+    pub fn contains(&self, node_id: &NodeId) -> bool {
+        self.blocked
+            .iter()
+            .any(|blocked| blocked.devices.contains(node_id))
+    }
+
+    /// Returns false, if all devices were already blocked.
+    pub fn add(&mut self, name: &str, devices: &[NodeId]) -> anyhow::Result<bool> {
+        let new = devices
+            .iter()
+            .filter(|device| !self.contains(device))
+            .cloned()
+            .collect::<Vec<_>>();
+        if new.is_empty() {
+            return Ok(false);
+        }
+        self.blocked.push(Blocked {
+            name: name.to_string(),
+            devices: new,
+        });
+        save_json(&self.path, &self.blocked)?;
+        Ok(true)
+    }
+
+    /// Matches name or NodeId prefix of any device. Returns removed entries.
+    pub fn remove(&mut self, pattern: &str) -> anyhow::Result<Vec<Blocked>> {
+        let pattern = pattern.trim_end_matches('…').to_lowercase();
+        let (removed, kept) = self.blocked.drain(..).partition(|blocked| {
+            blocked.name.to_lowercase() == pattern
+                || blocked
+                    .devices
+                    .iter()
+                    .any(|device| device.to_string().starts_with(&pattern))
+        });
+        self.blocked = kept;
+        if !removed.is_empty() {
+            save_json(&self.path, &self.blocked)?;
+        }
+        Ok(removed)
+    }
+}
@@ -0,0 +1,99 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use structopt::StructOpt;
+
+use ya_client::cli::ApiOpts;
+
+use crate::chat::{Chat, ChatHandle};
+use crate::encryption::{Cipher, KeySource};
+use crate::migrations;
+use crate::storage::Storage;
+use crate::Args;
+
+/// How chat talks to user.
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum Ui {
+    /// Messages are printed to stdout and input is read from stdin.
+    #[default]
+    Terminal,
+    /// Nothing is printed nor read. Embedders get messages with
+    /// `ChatHandle::subscribe` and send them with `ChatHandle::send`.
+    Headless,
+}
+
+/// Starts chat from library code, doing the wiring, which `main` does
+/// for command line: migrations, encryption and storage.
+///
+/// Options not covered by builder methods default as on command line.
+pub struct ChatBuilder {
+    args: Args,
+    storage: Option<Arc<dyn Storage>>,
+}
+
+impl ChatBuilder {
+    pub fn new() -> ChatBuilder {
+        ChatBuilder {
+            args: Args::from_iter(&["yachat"]),
+            storage: None,
+        }
+    }
+
+    pub fn name(mut self, name: &str) -> ChatBuilder {
+        self.args.name = Some(name.to_string());
+        self
+    }
+
+    /// Can be repeated. First group is active.
+    pub fn group(mut self, group: &str) -> ChatBuilder {
+        self.args.groups.push(group.to_string());
+        self
+    }
+
+    pub fn api(mut self, api: ApiOpts) -> ChatBuilder {
+        self.args.api = api;
+        self
+    }
+
+    /// Replaces backend selected with `--storage`.
+    pub fn storage(mut self, storage: Arc<dyn Storage>) -> ChatBuilder {
+        self.storage = Some(storage);
+        self
+    }
+
+    pub fn ui(mut self, ui: Ui) -> ChatBuilder {
+        self.args.ui = ui;
+        self
+    }
+
+    pub fn data_dir(mut self, data_dir: PathBuf) -> ChatBuilder {
+        self.args.data_dir = Some(data_dir);
+        self
+    }
+
+    pub fn encrypt(mut self, source: KeySource) -> ChatBuilder {
+        self.args.encrypt = Some(source);
+        self
+    }
+
+    /// Must be called inside actix system.
+    pub async fn build(self) -> anyhow::Result<ChatHandle> {
+        let args = self.args;
+        let data_dir = args.data_dir();
+        migrations::migrate(&data_dir)?;
+        let cipher = match args.encrypt {
+            Some(source) => Some(Cipher::init(&data_dir, source).await?),
+            None => None,
+        };
+        let chat = match self.storage {
+            Some(storage) => Chat::with_storage(args, cipher, storage)?,
+            None => Chat::new(args, cipher)?,
+        };
+        Ok(ChatHandle::start(chat))
+    }
+}
+
+impl Default for ChatBuilder {
+    fn default() -> Self {
+        ChatBuilder::new()
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::{anyhow, bail};
+use secp256k1::recovery::{RecoverableSignature, RecoveryId};
+use secp256k1::{Message, Secp256k1};
+use sha3::{Digest, Keccak256};
+use uuid::Uuid;
+
+use ya_client::model::NodeId;
+
+/// Prefix separating chat challenges from anything else signed with node key.
+const DOMAIN: &[u8] = b"yachat identity challenge";
+const DEVICE_DOMAIN: &[u8] = b"yachat device";
+const RATCHET_DOMAIN: &[u8] = b"yachat ratchet";
+const SAFETY_DOMAIN: &[u8] = b"yachat safety code";
+
+pub fn nonce() -> Vec<u8> {
+    rand::random::<[u8; 32]>().to_vec()
+}
+
+/// Hash signed by challenged node. Binds display name to the nonce,
+/// so response can't be reused for different name.
+pub fn challenge_hash(nonce: &[u8], name: &str) -> Vec<u8> {
+    let mut hasher = Keccak256::new();
+    hasher.update(DOMAIN);
+    hasher.update(nonce);
+    hasher.update(name.as_bytes());
+    hasher.finalize().to_vec()
+}
+
+/// Hash signed by user's primary node to link another device with the user.
+pub fn device_hash(device: &NodeId) -> Vec<u8> {
+    let mut hasher = Keccak256::new();
+    hasher.update(DEVICE_DOMAIN);
+    hasher.update(device.to_string().as_bytes());
+    hasher.finalize().to_vec()
+}
+
+/// Hash signed by both sides of ratchet handshake. Binds handshake key
+/// to session and to the peer, so it can't be replayed to anybody else.
+pub fn ratchet_hash(session: &Uuid, key: &[u8], peer: &NodeId) -> Vec<u8> {
+    let mut hasher = Keccak256::new();
+    hasher.update(RATCHET_DOMAIN);
+    hasher.update(session.as_bytes());
+    hasher.update(key);
+    hasher.update(peer.to_string().as_bytes());
+    hasher.finalize().to_vec()
+}
+
+/// Numeric code compared by two users over other channel. Both sides
+/// get the same code, since keys are hashed in sorted order.
+pub fn safety_code(ours: &NodeId, theirs: &NodeId) -> String {
+    let mut keys = [ours.to_string(), theirs.to_string()];
+    keys.sort();
+
+    let mut hasher = Keccak256::new();
+    hasher.update(SAFETY_DOMAIN);
+    for key in keys.iter() {
+        hasher.update(key.as_bytes());
+    }
+    let hash = hasher.finalize();
+
+    hash.chunks(4)
+        .take(5)
+        .map(|chunk| {
+            let value = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            format!("{:05}", value % 100_000)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Checks, that signature was made with key of `node_id`.
+pub fn verify(node_id: &NodeId, nonce: &[u8], name: &str, signature: &[u8]) -> anyhow::Result<()> {
+    expect_signer(node_id, &challenge_hash(nonce, name), signature)
+}
+
+/// Checks, that `device` was linked by `user`.
+pub fn verify_device(user: &NodeId, device: &NodeId, signature: &[u8]) -> anyhow::Result<()> {
+    expect_signer(user, &device_hash(device), signature)
+}
+
+/// Checks, that handshake key was sent by `signer` to `peer`.
+pub fn verify_ratchet(
+    signer: &NodeId,
+    session: &Uuid,
+    key: &[u8],
+    peer: &NodeId,
+    signature: &[u8],
+) -> anyhow::Result<()> {
+    expect_signer(signer, &ratchet_hash(session, key, peer), signature)
+}
+
+/// Identity service returns signatures as 65 bytes: recovery id followed by r and s.
+fn expect_signer(node_id: &NodeId, hash: &[u8], signature: &[u8]) -> anyhow::Result<()> {
+    if signature.len() != 65 {
+        bail!("Invalid signature length: {}", signature.len());
+    }
+
+    let v = match signature[0] {
+        v if v >= 27 => v - 27,
+        v => v,
+    };
+    let recovery_id = RecoveryId::from_i32(v as i32)?;
+    let signature = RecoverableSignature::from_compact(&signature[1..], recovery_id)?;
+    let message = Message::from_slice(hash)?;
+
+    let public_key = Secp256k1::verification_only().recover(&message, &signature)?;
+    // Ethereum address: last 20 bytes of hash of uncompressed key without prefix.
+    let hash = Keccak256::digest(&public_key.serialize_uncompressed()[1..]);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+
+    match NodeId::from(address) == *node_id {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "Signed by [{}] instead of [{}].",
+            NodeId::from(address),
+            node_id
+        )),
+    }
+}
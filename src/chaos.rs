@@ -0,0 +1,169 @@
+use anyhow::{anyhow, bail};
+use rand::Rng;
+use std::future::Future;
+use std::str::FromStr;
+use std::time::Duration;
+
+use ya_client::model::NodeId;
+
+use crate::error::SendError;
+use crate::protocol::ChatError;
+
+/// Fault injection settings, for example
+/// `drop=0.1,delay=0.2,duplicate=0.05,reorder=0.1,max-delay=3000`.
+const CHAOS_VAR: &str = "YACHAT_CHAOS";
+
+/// Probabilities of faults injected into messages sent to peers and longest
+/// delay in milliseconds. At most one fault is injected per message.
+#[derive(Debug)]
+pub struct Faults {
+    drop: f64,
+    delay: f64,
+    duplicate: f64,
+    reorder: f64,
+    max_delay: Duration,
+}
+
+enum Fault {
+    /// Message isn't sent and peer is reported unreachable.
+    Drop,
+    Delay(Duration),
+    /// Message is sent twice.
+    Duplicate,
+    /// Reported as delivered at once and sent after delay, so later
+    /// messages overtake it.
+    Reorder(Duration),
+}
+
+impl FromStr for Faults {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> anyhow::Result<Faults> {
+        let mut faults = Faults {
+            drop: 0.0,
+            delay: 0.0,
+            duplicate: 0.0,
+            reorder: 0.0,
+            max_delay: Duration::from_secs(2),
+        };
+        for part in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+        {
+            let (key, value) = match part.find('=') {
+                Some(pos) => (&part[..pos], &part[pos + 1..]),
+                None => bail!("Expected key=value, got '{}'.", part),
+            };
+            if key == "max-delay" {
+                let millis = value
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("Invalid max-delay: {}. Expected milliseconds.", value))?;
+                faults.max_delay = Duration::from_millis(millis);
+                continue;
+            }
+            let probability = value
+                .parse::<f64>()
+                .ok()
+                .filter(|probability| (0.0..=1.0).contains(probability))
+                .ok_or_else(|| anyhow!("Invalid probability of {}: {}.", key, value))?;
+            match key {
+                "drop" => faults.drop = probability,
+                "delay" => faults.delay = probability,
+                "duplicate" => faults.duplicate = probability,
+                "reorder" => faults.reorder = probability,
+                _ => bail!("Unknown fault: {}.", key),
+            }
+        }
+        Ok(faults)
+    }
+}
+
+impl Faults {
+    /// None, when fault injection isn't enabled.
+    pub fn from_env() -> anyhow::Result<Option<Faults>> {
+        match std::env::var(CHAOS_VAR) {
+            Ok(spec) => Ok(Some(spec.parse()?)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn draw(&self) -> Option<Fault> {
+        let mut rng = rand::thread_rng();
+        let delay = Duration::from_millis(rng.gen_range(0, self.max_delay.as_millis() as u64 + 1));
+        let roll = rng.gen::<f64>();
+        let mut threshold = self.drop;
+        if roll < threshold {
+            return Some(Fault::Drop);
+        }
+        threshold += self.delay;
+        if roll < threshold {
+            return Some(Fault::Delay(delay));
+        }
+        threshold += self.duplicate;
+        if roll < threshold {
+            return Some(Fault::Duplicate);
+        }
+        threshold += self.reorder;
+        if roll < threshold {
+            return Some(Fault::Reorder(delay));
+        }
+        None
+    }
+}
+
+/// Sends message with `send`, injecting fault drawn from `YACHAT_CHAOS`.
+/// Settings are read on every send. Invalid ones disable injection and
+/// are reported once, at chat start.
+pub async fn inject<M, F, Fut>(
+    peer: NodeId,
+    msg: M,
+    send: F,
+) -> Result<Result<(), ChatError>, SendError>
+where
+    M: Clone + 'static,
+    F: Fn(NodeId, M) -> Fut + 'static,
+    Fut: Future<Output = Result<Result<(), ChatError>, SendError>> + 'static,
+{
+    let fault = match Faults::from_env()
+        .ok()
+        .flatten()
+        .and_then(|faults| faults.draw())
+    {
+        Some(fault) => fault,
+        None => return send(peer, msg).await,
+    };
+    match fault {
+        Fault::Drop => {
+            log::debug!("Fault injection: dropping message to [{}].", peer);
+            Err(SendError::Unreachable {
+                peer,
+                reason: "Dropped by fault injection.".to_string(),
+            })
+        }
+        Fault::Delay(delay) => {
+            log::debug!(
+                "Fault injection: delaying message to [{}] by {:?}.",
+                peer,
+                delay
+            );
+            tokio::time::delay_for(delay).await;
+            send(peer, msg).await
+        }
+        Fault::Duplicate => {
+            log::debug!("Fault injection: duplicating message to [{}].", peer);
+            let _ = send(peer, msg.clone()).await;
+            send(peer, msg).await
+        }
+        Fault::Reorder(delay) => {
+            log::debug!("Fault injection: reordering message to [{}].", peer);
+            actix_rt::spawn(async move {
+                tokio::time::delay_for(delay).await;
+                if let Err(e) = send(peer, msg).await {
+                    log::debug!("Reordered message wasn't sent. Error: {}", e);
+                }
+            });
+            Ok(Ok(()))
+        }
+    }
+}
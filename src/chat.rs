@@ -1,19 +1,135 @@
 use actix::prelude::*;
 use actix::Actor;
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use async_std::io::{stdin, BufReader};
 use async_std::prelude::*;
-use chrono::{Local, Utc};
+use chrono::{DateTime, Datelike, Duration, Local, Utc};
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
 
 use ya_client::model::NodeId;
-use ya_service_bus::{actix_rpc, RpcEnvelope};
+use ya_core_model::identity;
+use ya_service_bus::{actix_rpc, RpcEnvelope, RpcMessage};
 use ya_service_bus::{typed as bus, RpcEndpoint};
 
-use crate::discover::{Discovery, InitChatGroup, Shutdown};
-use crate::protocol::{ChatError, SendText, TextMessage};
+use crate::alerts::{Alerts, WatchNode};
+use crate::audit::{AuditKind, AuditLog};
+use crate::away::Away;
+use crate::blocklist::Blocklist;
+use crate::builder::Ui;
+use crate::chatlog::ChatLog;
+use crate::clipboard::Clipboard;
+use crate::commands::{self, open_url, Command, QueueCommand};
+use crate::config::Config;
+use crate::console::{self, Console};
+use crate::contacts::Contacts;
+use crate::delivery::DeliveryQueue;
+use crate::device::Device;
+use crate::discover::{Discovery, InitChatGroup, LeaveGroup, ListSubscriptions, Shutdown};
+use crate::emoji;
+use crate::encryption::Cipher;
+use crate::error::SendError;
+use crate::feeds::{FeedConfig, SeenEntries};
+use crate::filter::{Filter, FilterChain};
+use crate::history::HistoryEntry;
+use crate::hooks::{Event, EventData, Hooks};
+use crate::layout::{self, MessageLayout};
+use crate::membership::Membership;
+use crate::plugin::Plugin;
+use crate::profile::Profiles;
+use crate::protocol::{
+    BotCommand, ChatError, DeviceCert, FetchQueued, Members, NodeFacts, Pair, PinMessage, Poll,
+    PollResults, PresenceUpdate, Profile, RatchetInit, SendSealed, SendText, Structured,
+    SyncHistory, TextMessage, Vote, WhoAreYou,
+};
+use crate::ratchet::Sessions;
+use crate::render::Renderer;
+use crate::report::{self, DeliveryDigest, ReportFormat};
+use crate::responder::Responders;
+use crate::retention::RetentionConfig;
+use crate::schedule::{parse_delay, Recurring, Schedule};
+use crate::session::Session;
+use crate::spam::SpamFilter;
+use crate::stats;
+use crate::storage::{self, Storage};
+use crate::structured;
+use crate::theme::Theme;
+use crate::throttle::Throttle;
+#[cfg(feature = "wasm")]
+use crate::wasm::WasmPlugin;
+use crate::watch::Watchlist;
+use crate::webhook::WebhookConfig;
+use crate::whoami::{self, GSB_ENDPOINT};
 use crate::Args;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+mod alerts;
+mod announcements;
+mod audit;
+mod away;
+mod banner;
+mod blocking;
+mod bots;
+mod bridge;
+mod capacity;
+mod channels;
+mod clipboard;
+mod compat;
+mod conversations;
+mod dedup;
+mod devices;
+mod events;
+mod feeds;
+mod fetch;
+mod forget;
+mod group;
+mod handle;
+mod inbound;
+mod muting;
+mod paid;
+mod pinning;
+mod plugins;
+mod polls;
+mod presence;
+mod profile;
+mod queue;
+mod reappear;
+mod reload;
+mod reply;
+mod responders;
+mod scheduler;
+mod sealed;
+mod separators;
+mod spam;
+mod sync;
+mod updates;
+mod verbosity;
+mod verification;
+#[cfg(feature = "wasm")]
+mod wasm_plugins;
+mod webhook;
+mod worker;
+
+use banner::SEARCH_NOTICE_INTERVAL;
+use channels::channel_tag;
+use conversations::Conversations;
+use dedup::Dedup;
+use group::Group;
+use inbound::Inbound;
+use paid::PaidGroup;
+use polls::PollState;
+use queue::EXPIRY_CHECK_INTERVAL;
+use reappear::{ReappearNotices, DEFAULT_REAPPEAR_INTERVAL_MINUTES};
+use reply::LastReceived;
+use sealed::{ForgetSession, SealDirect};
+use separators::StreamClock;
+use worker::StopWorker;
+
+pub use events::ChatEvent;
+pub use handle::ChatHandle;
+pub use reload::Reload;
 
 // =========================================== //
 // Public exposed messages
@@ -25,12 +141,26 @@ pub struct NewUser {
     pub user: String,
     pub address: NodeId,
     pub group: String,
+    pub announcers: Option<Vec<NodeId>>,
+    pub fee: Option<String>,
+    pub max_members: Option<usize>,
+    /// Client version of user. None for clients, which don't advertise it.
+    pub version: Option<String>,
+    pub protocol: Option<u32>,
 }
 
 #[derive(Message)]
 #[rtype(result = "anyhow::Result<()>")]
 pub struct NewLine(pub String);
 
+/// Reserves outbound bandwidth for message of given size. Returns time
+/// to wait before sending.
+#[derive(Message)]
+#[rtype(result = "std::time::Duration")]
+pub struct ReserveSend {
+    pub bytes: usize,
+}
+
 #[derive(Message)]
 #[rtype(result = "anyhow::Result<()>")]
 pub struct DeliverLater {
@@ -38,43 +168,301 @@ pub struct DeliverLater {
     pub messages: SendText,
 }
 
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct DeliveryReport {
+    pub ids: Vec<Uuid>,
+    pub recipient: NodeId,
+    pub delivery: Delivery,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Delivery {
+    Pending,
+    Delivered,
+    Queued,
+    Rejected,
+    /// Dropped from queue after message TTL passed.
+    Expired,
+    /// Discarded from queue by us with `/queue drop`.
+    Dropped,
+}
+
 // =========================================== //
 // Chat implementation
 // =========================================== //
 
-#[derive(Clone)]
-struct UserDesc {
-    name: String,
-    node_id: NodeId,
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+const TIMESTAMP_WIDTH: usize = 19;
+const ACCESSIBLE_TIME_FORMAT: &str = "%H:%M";
+const COMPACT_TIME_FORMAT: &str = "%H:%M";
+const VERBOSE_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
+/// Used, when `--message-ttl` isn't given.
+const DEFAULT_MESSAGE_TTL_HOURS: i64 = 24;
+/// Attempts to resend messages refused by busy peer, before they are queued.
+const SEND_RETRIES: u32 = 3;
+/// Doubled after each attempt.
+const SEND_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+/// Delay before subscribing group on market again.
+const DISCOVERY_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(10);
+/// How often history retention is enforced.
+const PRUNE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+/// How often local yagna is asked for node facts with `--node-presence`.
+const PRESENCE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Delivery state of our own message for each of recipients.
+struct SentMessage {
+    recipients: HashMap<NodeId, Delivery>,
+}
+
+impl SentMessage {
+    /// Single column status marker displayed next to message.
+    fn marker(&self) -> char {
+        let statuses = self.recipients.values().collect::<Vec<_>>();
+        if statuses.is_empty() {
+            '·'
+        } else if statuses.contains(&&Delivery::Pending) {
+            '…'
+        } else if statuses.contains(&&Delivery::Queued) {
+            '⧗'
+        } else if statuses.contains(&&Delivery::Rejected)
+            || statuses.contains(&&Delivery::Expired)
+            || statuses.contains(&&Delivery::Dropped)
+        {
+            '✗'
+        } else {
+            '✓'
+        }
+    }
 }
 
 pub struct Chat {
     me: String,
-    group: String,
+    /// Our identity. Unknown until we get response from yagna.
+    node_id: Option<NodeId>,
+    /// Alias of our identity in yagna.
+    identity_alias: Option<String>,
+    /// Proof, that we are linked device of other user.
+    device: Option<DeviceCert>,
+    /// Double ratchet sessions encrypting direct messages.
+    sessions: Sessions,
+    audit: AuditLog,
+    /// Code displayed by `/pair` with expiration time.
+    pairing: Option<(String, DateTime<Utc>)>,
+    data_dir: PathBuf,
+    /// Re-read by `/reload`.
+    config_path: PathBuf,
+    /// Groups listed in config, to report ones removed on reload.
+    config_groups: Vec<String>,
+    /// Encrypts history and pins of groups joined later.
+    cipher: Option<Cipher>,
+    storage: Arc<dyn Storage>,
 
-    users: Vec<UserDesc>,
-    delivery: HashMap<NodeId, SendText>,
+    groups: Vec<Group>,
+    /// Index of group, to which typed messages are sent.
+    active: usize,
+    /// Discovered users, we sent identity challenge to, with group.
+    verifying: HashSet<(NodeId, String)>,
+    /// Users, who failed identity challenge. Notice is printed only once.
+    unverified: HashSet<NodeId>,
+    /// Messages waiting for recipient to reappear. Single batch per group.
+    delivery: DeliveryQueue,
+    /// Decides, where date separators and session markers go.
+    clock: StreamClock,
+    /// Peers, whose queued messages are being resent. Live messages to them
+    /// wait in queue, so conversation isn't reordered.
+    flushing: HashSet<NodeId>,
+    /// TTL in seconds set on our messages.
+    message_ttl: Option<i64>,
+    /// Limits notices about rediscovered users.
+    reappeared: ReappearNotices,
+    /// Received messages waiting for display, per sender.
+    inbound: HashMap<NodeId, VecDeque<Inbound>>,
+    inbound_order: VecDeque<NodeId>,
+    draining: bool,
+    /// Recently received messages, so resent ones aren't displayed twice.
+    received: Dedup,
+    /// Peers muted locally for flooding.
+    spam: SpamFilter,
+    /// Outbound rate limit shared by all sends.
+    throttle: Throttle,
+    retention: RetentionConfig,
+    filters: FilterChain,
+    responders: Responders,
 
     discovery: Addr<Discovery>,
+    membership: Addr<Membership>,
+    webhook: WebhookConfig,
+    /// Set, when `[alerts]` are enabled in config.
+    alerts: Option<Addr<Alerts>>,
+    renderer: Renderer,
+    clipboard: Clipboard,
+    /// Screen reader friendly output.
+    accessible: bool,
+    layout: MessageLayout,
+    /// Sender, tag and time of the last message, for grouped layout.
+    last_header: Option<(NodeId, String, DateTime<Utc>)>,
+    /// Started by `yachat notify-endpoint`: no input and no auto-replies.
+    receive_only: bool,
+    /// Started by `ChatBuilder` without terminal: stdin isn't read.
+    headless: bool,
+    /// Newer release is looked up at start.
+    update_check: bool,
+    /// Number of `-v` flags. Short message ids are displayed from level 1.
+    verbosity: u8,
+    /// System notices aren't printed.
+    quiet: bool,
+    /// Pairs of groups relaying messages to each other.
+    bridges: Vec<(String, String)>,
+    last_received: Option<LastReceived>,
+    last_direct: Option<LastReceived>,
+    conversations: Conversations,
+    /// Peers warned about newer or incompatible client.
+    compat_warned: HashSet<NodeId>,
+    /// Streams of `ChatHandle::subscribe`.
+    subscribers: Vec<futures::channel::mpsc::UnboundedSender<ChatEvent>>,
+    hooks: Hooks,
+    /// Processes given with `--plugin`.
+    plugins: Vec<Plugin>,
+    /// Registered by plugins and advertised in our profile.
+    bot_commands: Vec<BotCommand>,
+    #[cfg(feature = "wasm")]
+    wasm_plugins: Vec<WasmPlugin>,
+    /// Auto-reply state set by `/away`.
+    away: Away,
+    /// Messages and reminders to send later.
+    schedule: Schedule,
+    recurring: Vec<Recurring>,
+    feeds: Vec<FeedConfig>,
+    seen_entries: SeenEntries,
+    /// Plaintext daily logs, enabled with `--chat-logs`.
+    chat_log: Option<ChatLog>,
+    expand_emoji: bool,
+    contacts: Contacts,
+    profiles: Profiles,
+    /// Last presence received from each device.
+    presence: HashMap<NodeId, PresenceUpdate>,
+    /// Set with `--node-presence`. Last facts read from yagna.
+    node_presence: bool,
+    node_facts: Option<NodeFacts>,
+    /// Terms highlighted in all groups.
+    watchlist: Watchlist,
+    /// Users, whose messages are rejected at GSB handlers.
+    blocklist: Blocklist,
+    /// Messages rejected from blocked users since start, for `/chatstats`.
+    blocked_rejections: usize,
+    console: Console,
+    sent: HashMap<Uuid, SentMessage>,
+    /// Outcomes of all our messages, for `/report`.
+    digest: DeliveryDigest,
+    /// Digest printed at exit, set with `--report`.
+    report: Option<ReportFormat>,
+    polls: HashMap<Uuid, PollState>,
 }
 
 impl Actor for Chat {
     type Context = Context<Self>;
 
     fn started(&mut self, ctx: &mut Self::Context) {
-        actix_rpc::bind::<SendText>(&format!("/public/yachat"), ctx.address().recipient());
+        actix_rpc::bind::<SendText>(GSB_ENDPOINT, ctx.address().recipient());
+        actix_rpc::bind::<Poll>(GSB_ENDPOINT, ctx.address().recipient());
+        actix_rpc::bind::<Vote>(GSB_ENDPOINT, ctx.address().recipient());
+        actix_rpc::bind::<PollResults>(GSB_ENDPOINT, ctx.address().recipient());
+        actix_rpc::bind::<PinMessage>(GSB_ENDPOINT, ctx.address().recipient());
+        actix_rpc::bind::<Members>(GSB_ENDPOINT, ctx.address().recipient());
+        actix_rpc::bind::<WhoAreYou>(GSB_ENDPOINT, ctx.address().recipient());
+        actix_rpc::bind::<Pair>(GSB_ENDPOINT, ctx.address().recipient());
+        actix_rpc::bind::<SyncHistory>(GSB_ENDPOINT, ctx.address().recipient());
+        actix_rpc::bind::<FetchQueued>(GSB_ENDPOINT, ctx.address().recipient());
+        actix_rpc::bind::<RatchetInit>(GSB_ENDPOINT, ctx.address().recipient());
+        actix_rpc::bind::<SendSealed>(GSB_ENDPOINT, ctx.address().recipient());
+        actix_rpc::bind::<Profile>(GSB_ENDPOINT, ctx.address().recipient());
+        actix_rpc::bind::<PresenceUpdate>(GSB_ENDPOINT, ctx.address().recipient());
         log::info!("Chat started as user: {}", &self.me);
 
-        let msg = InitChatGroup {
-            me: self.me.clone(),
-            group: self.group.clone(),
-            notify: ctx.address().recipient(),
-        };
-        self.discovery.do_send(msg);
-        println!("yachat\nVersion 0.1");
+        for idx in 0..self.groups.len() {
+            self.init_group(idx, ctx);
+        }
+        self.offer_membership(ctx);
+
+        let identity = async move {
+            bus::service(identity::BUS_ID)
+                .send(identity::Get::ByDefault)
+                .await
+        }
+        .into_actor(self)
+        .map(|result, myself, _| {
+            match result {
+                Ok(Ok(Some(info))) => {
+                    log::info!("Our NodeId: {}", info.node_id);
+                    myself.node_id = Some(info.node_id);
+                    myself.identity_alias = info.alias;
+                }
+                Ok(Ok(None)) => log::warn!("No default identity found."),
+                Ok(Err(e)) => log::warn!("Failed to get our identity. Error: {}", e),
+                Err(e) => log::warn!("Failed to get our identity. Error: {}", e),
+            }
+            // Banner shows our NodeId, so it waits for identity.
+            myself.print_banner();
+            for idx in 0..myself.groups.len() {
+                if myself.node_id.is_some() && myself.read_only(idx) {
+                    myself.notice(&format!(
+                        "You are not announcer in group {}. Read-only mode.",
+                        myself.groups[idx].name
+                    ));
+                }
+                myself.print_restored(idx);
+            }
+        });
+        ctx.spawn(identity);
+        ctx.run_interval(SEARCH_NOTICE_INTERVAL, |myself, _| {
+            myself.notice_searching()
+        });
+        self.check_update(ctx);
+        self.arm_schedule(ctx);
+        ctx.run_interval(EXPIRY_CHECK_INTERVAL, |myself, ctx| {
+            myself.expire_queued(ctx)
+        });
+        for idx in 0..self.recurring.len() {
+            self.arm_recurring(idx, ctx);
+        }
+        if !self.receive_only {
+            self.arm_feeds(ctx);
+        }
+        self.prune_history();
+        ctx.run_interval(PRUNE_INTERVAL, |myself, _| myself.prune_history());
+        if self.node_presence {
+            self.refresh_node_facts(ctx);
+            ctx.run_interval(PRESENCE_INTERVAL, |myself, ctx| {
+                myself.refresh_node_facts(ctx)
+            });
+        }
 
-        let recipient = ctx.address().recipient();
-        ctx.spawn(async move { input_reader(recipient).await }.into_actor(self));
+        for idx in 0..self.plugins.len() {
+            self.start_plugin(idx, ctx);
+        }
+        if !self.receive_only {
+            if let Err(e) = self.webhook.start(ctx.address().recipient()) {
+                self.notice(&e.to_string());
+            }
+        }
+        if let (Some(alerts), false) = (&self.alerts, self.receive_only) {
+            alerts.do_send(WatchNode {
+                notify: ctx.address().recipient(),
+            });
+        }
+
+        #[cfg(feature = "chaos")]
+        match crate::chaos::Faults::from_env() {
+            Ok(Some(faults)) => self.notice(&format!("Fault injection enabled: {:?}", faults)),
+            Ok(None) => (),
+            Err(e) => self.notice(&format!("Fault injection disabled. {}", e)),
+        }
+
+        if !self.receive_only && !self.headless {
+            let recipient = ctx.address().recipient();
+            ctx.spawn(async move { input_reader(recipient).await }.into_actor(self));
+        }
     }
 
     fn stopped(&mut self, _: &mut Self::Context) {
@@ -83,48 +471,1012 @@ impl Actor for Chat {
 }
 
 impl Chat {
-    pub fn new(args: Args) -> Result<Chat, anyhow::Error> {
+    /// Storage backend is selected with `--storage`.
+    pub fn new(args: Args, cipher: Option<Cipher>) -> Result<Chat, anyhow::Error> {
+        let storage = storage::open(
+            args.storage.unwrap_or_default(),
+            &args.data_dir(),
+            cipher.clone(),
+        )?;
+        Chat::with_storage(args, cipher, storage)
+    }
+
+    /// For embedders supplying their own storage backend.
+    pub fn with_storage(
+        args: Args,
+        cipher: Option<Cipher>,
+        storage: Arc<dyn Storage>,
+    ) -> Result<Chat, anyhow::Error> {
+        let me = args
+            .name
+            .clone()
+            .ok_or_else(|| anyhow!("No user name. Use --name or set name in config file."))?;
+        let data_dir = args.data_dir();
+        let theme = Theme::find(args.theme.as_deref().unwrap_or("dark"), &args.themes)?;
+        let plain = args.plain || !console::styled_output();
+        let mut renderer = Renderer::new(plain, args.accessible, args.hyperlinks, theme, &me);
+        let watchlist = Watchlist::load(&data_dir)?;
+        renderer.set_watched(watchlist.terms());
+        let blocklist = Blocklist::load(&data_dir)?;
+        let contacts = Contacts::load(&data_dir)?;
+        let profiles = Profiles::load(&data_dir)?;
+        let seen_entries = SeenEntries::load(&data_dir)?;
+        let device = Device::load(&data_dir)?.cert;
+        let schedule = Schedule::load(&data_dir, cipher.clone())?;
+        let sessions = Sessions::load(&data_dir, cipher.clone())?;
+        let audit = AuditLog::load(&data_dir, cipher.clone())?;
+        let filters = FilterChain::from_config(&args.filters)?;
+        let responders = Responders::from_config(&args.responders)?;
+        #[cfg(feature = "wasm")]
+        let wasm_plugins = args
+            .wasm_plugins
+            .into_iter()
+            .map(WasmPlugin::load)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        #[cfg(not(feature = "wasm"))]
+        if !args.wasm_plugins.is_empty() {
+            bail!("WASM plugins require yachat built with `wasm` feature.");
+        }
+        let message_ttl = match args.message_ttl.as_deref() {
+            None => Some(Duration::hours(DEFAULT_MESSAGE_TTL_HOURS).num_seconds()),
+            Some("none") => None,
+            Some(ttl) => Some(parse_delay(ttl)?.num_seconds()),
+        };
+        let reappear_interval = match args.reappear_interval.as_deref() {
+            None => Duration::minutes(DEFAULT_REAPPEAR_INTERVAL_MINUTES),
+            Some(interval) => parse_delay(interval)?,
+        };
+        let chat_log = match args.chat_logs {
+            true => Some(ChatLog::new(&data_dir.join("logs"))),
+            false => None,
+        };
+        let membership = Membership::new(&args.api)?.start();
+        let alerts = Alerts::new(&args.api, args.alerts.clone())?.map(Actor::start);
         let discovery = Discovery::new(args.api)?.start();
 
+        // Groups given explicitly come first, so the first of them is active.
+        let mut names = args.groups.clone();
+        if args.resume {
+            for group in Session::load(&data_dir)?.groups {
+                if !names.contains(&group) {
+                    names.push(group);
+                }
+            }
+        }
+        // Recurring messages and feeds can be posted only to groups we are in.
+        for group in args
+            .recurring
+            .iter()
+            .map(|recurring| &recurring.group)
+            .chain(args.feeds.iter().map(|feed| &feed.group))
+        {
+            if !names.contains(group) {
+                names.push(group.clone());
+            }
+        }
+        if let Some(group) = &args.alerts.group {
+            if !names.contains(group) {
+                names.push(group.clone());
+            }
+        }
+        if names.is_empty() {
+            return Err(anyhow!("No group to join. Use --group or --resume."));
+        }
+
+        let mut groups = names
+            .iter()
+            .map(|name| Group::load(&data_dir, name, cipher.as_ref(), &storage))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        // Group settings from command line apply to first group.
+        if !args.announcers.is_empty() {
+            groups[0].announcers = Some(args.announcers);
+            groups[0].announcers_configured = true;
+        }
+        if args.max_members.is_some() {
+            groups[0].max_members = args.max_members;
+            groups[0].max_members_configured = true;
+        }
+        groups[0].paid = args.fee.map(|fee| PaidGroup::Owner {
+            fee,
+            members: HashSet::new(),
+        });
+
+        let clock = StreamClock::resume(
+            groups
+                .iter()
+                .filter_map(|group| group.history.last().map(|entry| entry.timestamp))
+                .max(),
+        );
+        // Messages left undelivered at last shutdown.
+        let delivery = DeliveryQueue::restore(storage.load_queue()?);
+
         Ok(Chat {
-            me: args.name,
-            group: args.group,
-            users: vec![],
+            me,
+            node_id: None,
+            identity_alias: None,
+            device,
+            sessions,
+            audit,
+            pairing: None,
+            data_dir,
+            config_path: args.config.clone().unwrap_or_else(Config::default_path),
+            config_groups: args.config_groups,
+            cipher,
+            storage,
+            groups,
+            active: 0,
+            verifying: HashSet::new(),
+            unverified: HashSet::new(),
             discovery,
-            delivery: HashMap::new(),
+            membership,
+            alerts,
+            webhook: args.webhook,
+            delivery,
+            clock,
+            flushing: HashSet::new(),
+            message_ttl,
+            reappeared: ReappearNotices::new(reappear_interval),
+            inbound: HashMap::new(),
+            inbound_order: VecDeque::new(),
+            draining: false,
+            received: Dedup::new(),
+            spam: SpamFilter::new(args.spam),
+            throttle: Throttle::new(args.throttle),
+            retention: args.retention,
+            filters,
+            responders,
+            renderer,
+            clipboard: Clipboard::default(),
+            accessible: args.accessible,
+            layout: args.layout.unwrap_or_default(),
+            last_header: None,
+            receive_only: args.receive_only,
+            update_check: args.update_check,
+            verbosity: args.verbose,
+            quiet: args.quiet,
+            bridges: vec![],
+            last_received: None,
+            last_direct: None,
+            conversations: Conversations::default(),
+            compat_warned: HashSet::new(),
+            subscribers: vec![],
+            hooks: args.hooks,
+            plugins: args.plugins.into_iter().map(Plugin::new).collect(),
+            bot_commands: vec![],
+            #[cfg(feature = "wasm")]
+            wasm_plugins,
+            away: Away::new(args.away),
+            schedule,
+            recurring: args.recurring,
+            feeds: args.feeds,
+            seen_entries,
+            chat_log,
+            expand_emoji: !args.no_emoji,
+            contacts,
+            profiles,
+            presence: HashMap::new(),
+            node_presence: args.node_presence,
+            node_facts: None,
+            watchlist,
+            blocklist,
+            blocked_rejections: 0,
+            console: match args.ui {
+                Ui::Terminal => Console::new(args.accessible),
+                Ui::Headless => Console::silent(),
+            },
+            headless: args.ui == Ui::Headless,
+            sent: HashMap::new(),
+            digest: DeliveryDigest::default(),
+            report: args.report,
+            polls: HashMap::new(),
+        })
+    }
+
+    fn whoami(&mut self, ctx: &mut Context<Self>) {
+        let future =
+            self.discovery
+                .send(ListSubscriptions)
+                .into_actor(self)
+                .map(|result, myself, _| {
+                    let subscriptions = result
+                        .map_err(|e| log::warn!("Failed to list subscriptions. Error: {}", e))
+                        .ok();
+                    let lines = whoami::describe(
+                        myself.node_id,
+                        myself.identity_alias.as_deref(),
+                        myself.device.as_ref(),
+                        subscriptions.as_deref(),
+                    );
+                    myself.console.print(&lines.join("\n"));
+                });
+        ctx.spawn(future);
+    }
+
+    /// Adds custom filter after the ones defined in config file.
+    pub fn register_filter(&mut self, filter: Box<dyn Filter>) {
+        self.filters.register(filter);
+    }
+
+    fn group(&self) -> &Group {
+        &self.groups[self.active]
+    }
+
+    fn group_index(&self, name: &str) -> Option<usize> {
+        self.groups.iter().position(|group| group.name == name)
+    }
+
+    fn delivery_report(&self, format: ReportFormat) -> String {
+        let reports = self.digest.reports(|node_id| self.peer_name(node_id));
+        report::format(&reports, format)
+    }
+
+    /// Applies `[retention]` limits. Storage is pruned by group workers.
+    fn prune_history(&mut self) {
+        for group in self.groups.iter_mut() {
+            group.prune(self.retention.for_group(&group.name));
+        }
+    }
+
+    fn init_group(&mut self, idx: usize, ctx: &mut Context<Self>) {
+        let group = &self.groups[idx];
+        let msg = InitChatGroup {
+            me: self.me.clone(),
+            group: group.name.clone(),
+            announcers: match group.announcers_configured {
+                true => group.announcers.clone().unwrap_or_default(),
+                false => vec![],
+            },
+            fee: match &group.paid {
+                Some(PaidGroup::Owner { fee, .. }) => Some(fee.clone()),
+                _ => None,
+            },
+            max_members: match group.max_members_configured {
+                true => group.max_members,
+                false => None,
+            },
+            notify: ctx.address().recipient(),
+            cycles: ctx.address().recipient(),
+        };
+        let name = msg.group.clone();
+        let future = self
+            .discovery
+            .send(msg)
+            .into_actor(self)
+            .map(move |result, myself, ctx| {
+                let e = match result {
+                    Ok(Ok(())) => {
+                        if let Some(idx) = myself.group_index(&name) {
+                            myself.groups[idx].subscribed = true;
+                        }
+                        let text = "Subscribed to market.".to_string();
+                        myself.audit(AuditKind::Subscription, Some(&name), None, text);
+                        myself.fetch_queued(&name, ctx);
+                        myself.print_subscription(name.clone(), ctx);
+                        myself.emit(ChatEvent::Subscribed { group: name });
+                        return;
+                    }
+                    Ok(Err(e)) => e,
+                    Err(e) => {
+                        log::error!("Discovery unavailable. Error: {}", e);
+                        myself.emit(ChatEvent::Error(format!("Discovery unavailable. {}", e)));
+                        return;
+                    }
+                };
+                if !e.is_retryable() {
+                    myself.notice(&format!("Can't join group. {}", e));
+                    let text = format!("Market subscription failed. {}", e);
+                    myself.audit(AuditKind::Subscription, Some(&name), None, text);
+                    myself.emit(ChatEvent::DiscoveryFailed {
+                        group: name,
+                        error: e.to_string(),
+                    });
+                    return;
+                }
+                log::warn!("{} Retrying in {:?}.", e, DISCOVERY_RETRY_DELAY);
+                ctx.run_later(DISCOVERY_RETRY_DELAY, move |myself, ctx| {
+                    if let Some(idx) = myself.group_index(&name) {
+                        myself.init_group(idx, ctx)
+                    }
+                });
+            });
+        ctx.spawn(future);
+    }
+
+    fn print_restored(&mut self, idx: usize) {
+        if !self.groups[idx].pins.is_empty() {
+            self.print_pins(idx);
+        }
+
+        let group = &self.groups[idx];
+        if !group.users.is_empty() {
+            let notice = format!(
+                "Restored {} user(s) of group {} from previous session. Offline until rediscovered.",
+                group.users.len(),
+                group.name
+            );
+            self.notice(&notice);
+        }
+    }
+
+    /// Switches active group. Group is joined first, if we weren't there yet.
+    fn switch_group(&mut self, name: String, ctx: &mut Context<Self>) -> anyhow::Result<()> {
+        self.active = match self.group_index(&name) {
+            Some(idx) => idx,
+            None => self.join_group(&name, ctx)?,
+        };
+        self.conversations.open = None;
+        self.console
+            .print(&format!("Messages will be sent to group {}.", name));
+        Ok(())
+    }
+
+    fn join_group(&mut self, name: &str, ctx: &mut Context<Self>) -> anyhow::Result<usize> {
+        self.groups.push(Group::load(
+            &self.data_dir,
+            name,
+            self.cipher.as_ref(),
+            &self.storage,
+        )?);
+        let idx = self.groups.len() - 1;
+        self.init_group(idx, ctx);
+        self.print_restored(idx);
+        Ok(idx)
+    }
+
+    /// Unsubscribes from market and stops history worker of group. Local
+    /// history is kept, so group can be joined again later.
+    fn leave_group(&mut self, name: &str, ctx: &mut Context<Self>) -> anyhow::Result<()> {
+        let idx = self
+            .group_index(name)
+            .ok_or_else(|| anyhow!("We aren't in group {}.", name))?;
+        if self.groups.len() == 1 {
+            bail!("Can't leave the only group.");
+        }
+
+        let group = self.groups.remove(idx);
+        self.remove_group_polls(idx);
+        if self.active > idx || self.active == self.groups.len() {
+            self.active -= 1;
+        }
+
+        let discovery = self.discovery.clone();
+        let worker = group.worker.clone();
+        let name = group.name.clone();
+        let future = async move {
+            discovery.send(LeaveGroup { group: name }).await??;
+            worker.send(StopWorker).await?;
+            Ok::<_, anyhow::Error>(())
+        }
+        .into_actor(self)
+        .map(|result, _, _| {
+            if let Err(e) = result {
+                log::warn!("Failed to clean up left group. Error: {}", e);
+            }
+        });
+        ctx.spawn(future);
+        Ok(())
+    }
+
+    fn print_groups(&mut self) {
+        let listing = self
+            .groups
+            .iter()
+            .enumerate()
+            .map(|(idx, group)| {
+                let topic = match &group.topic {
+                    Some(topic) => format!(" - {}", topic),
+                    None => String::new(),
+                };
+                format!(
+                    "{} {} ({} users){}",
+                    if idx == self.active { "*" } else { " " },
+                    group.name,
+                    group.users.len(),
+                    topic
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.console.print(&listing);
+    }
+
+    fn print_users(&mut self) {
+        let group = self.group();
+        let listing = match group.users.is_empty() {
+            true => format!("No users in group {} yet.", group.name),
+            false => group
+                .users
+                .iter()
+                .map(|desc| {
+                    let mut state = match desc.online {
+                        true => "online".to_string(),
+                        false => format!(
+                            "offline, last seen {}",
+                            desc.last_seen
+                                .with_timezone(&Local)
+                                .format(TIMESTAMP_FORMAT)
+                        ),
+                    };
+                    if let Some(presence) = self.presence.get(&desc.node_id) {
+                        let facts = crate::presence::describe(presence);
+                        if !facts.is_empty() {
+                            state = format!("{}, {}", state, facts);
+                        }
+                    }
+                    format!(
+                        "  {}{} [{}] {}",
+                        self.display_user(desc),
+                        self.badge(&desc.node_id),
+                        desc.node_id,
+                        self.renderer.user_state(&state, desc.online)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        self.console.print(&listing);
+    }
+
+    fn save_session(&self) {
+        let session = Session {
+            groups: self.groups.iter().map(|group| group.name.clone()).collect(),
+        };
+        session
+            .save(&self.data_dir)
+            .map_err(|e| log::warn!("Failed to save session. Error: {}", e))
+            .ok();
+    }
+
+    /// Group name is shown only, when we are in more than one group.
+    fn group_tag(&self, group: &str) -> String {
+        match self.groups.len() {
+            1 => String::new(),
+            _ => format!(" [{}]", group),
+        }
+    }
+
+    /// Sender is None for our own messages, which are never grouped, so
+    /// their delivery marker stays visible.
+    fn message_header(
+        &mut self,
+        tag: &str,
+        text: &TextMessage,
+        user: &str,
+        sender: Option<NodeId>,
+    ) -> String {
+        let local = text.timestamp.with_timezone(&Local);
+        if self.accessible {
+            let format = match local.num_days_from_ce() == Local::now().num_days_from_ce() {
+                true => ACCESSIBLE_TIME_FORMAT,
+                false => TIMESTAMP_FORMAT,
+            };
+            return format!("Message from {}{} at {}: ", user, tag, local.format(format));
+        }
+
+        let header = match self.layout {
+            MessageLayout::Compact => format!(
+                "{} {}{}: ",
+                self.renderer
+                    .timestamp(&local.format(COMPACT_TIME_FORMAT).to_string()),
+                user,
+                tag,
+            ),
+            MessageLayout::Verbose => format!(
+                "{} {} [{}]{} #{} > ",
+                self.renderer
+                    .timestamp(&local.format(VERBOSE_TIMESTAMP_FORMAT).to_string()),
+                user,
+                sender
+                    .or(self.node_id)
+                    .map(|node_id| node_id.to_string())
+                    .unwrap_or_default(),
+                tag,
+                text.id.to_simple(),
+            ),
+            MessageLayout::Standard | MessageLayout::Grouped => format!(
+                "{} {}{} > ",
+                self.renderer
+                    .timestamp(&local.format(TIMESTAMP_FORMAT).to_string()),
+                user,
+                tag,
+            ),
+        };
+        if self.layout != MessageLayout::Grouped {
+            return header;
+        }
+
+        let current = sender.map(|sender| (sender, tag.to_string(), text.timestamp));
+        let repeated = match (&self.last_header, &current) {
+            (Some((last, last_tag, last_time)), Some((sender, tag, time))) => {
+                last == sender
+                    && last_tag == tag
+                    && *time >= *last_time
+                    && *time - *last_time < Duration::minutes(1)
+            }
+            _ => false,
+        };
+        self.last_header = current;
+        match repeated {
+            true => " ".repeat(layout::display_width(&header)),
+            false => header,
+        }
+    }
+
+    /// Our own messages have delivery status marker placed after timestamp.
+    /// In accessible mode marker isn't displayed, since it can't be updated.
+    fn print_own_message(&mut self, tag: &str, text: &TextMessage, marker: char) {
+        let user = match self.accessible {
+            true => "me".to_string(),
+            false => format!("{} {}", marker, self.renderer.own_name("me")),
+        };
+        self.mark_time(&text.timestamp);
+        let header = self.message_header(tag, text, &user, None);
+        let body = self.render_body(text, &text.content);
+        let message = self.format_message(&header, &body);
+        self.console.print_tracked(text.id, &message);
+    }
+
+    /// Structured content of known schema is rendered in place of its plain
+    /// text version. Content rewritten by filters is displayed as is.
+    fn render_body(&mut self, text: &TextMessage, content: &str) -> String {
+        let rendered = match &text.structured {
+            Some(structured) if content == text.content => structured::render(structured),
+            _ => None,
+        };
+        self.renderer.render(rendered.as_deref().unwrap_or(content))
+    }
+
+    /// Screen readers read wrapped lines separately, so in accessible mode
+    /// message is left for terminal to wrap.
+    fn format_message(&self, header: &str, body: &str) -> String {
+        match self.accessible {
+            true => format!("{}{}", header, body),
+            false => layout::format_message(header, body),
+        }
+    }
+
+    fn notice(&mut self, text: &str) {
+        if self.quiet {
+            return;
+        }
+        let notice = self.renderer.notice(text);
+        self.console.print(&notice);
+    }
+
+    fn can_post(&self, idx: usize) -> anyhow::Result<()> {
+        if self.read_only(idx) {
+            bail!("This group is announcement-only. Your message wasn't sent.");
+        }
+        if !self.groups[idx].joined() {
+            bail!("This group is paid. Type /join to become a member first.");
+        }
+        Ok(())
+    }
+
+    /// Displays and records our message. Returned future sends it to users
+    /// of the group.
+    fn post(
+        &mut self,
+        idx: usize,
+        text: String,
+        reply_to: Option<Uuid>,
+        ctx: &mut Context<Self>,
+    ) -> anyhow::Result<impl Future<Output = anyhow::Result<()>>> {
+        self.can_post(idx)?;
+
+        let content = match self.expand_emoji {
+            true => emoji::expand(&text),
+            false => text,
+        };
+        let message = TextMessage {
+            id: Uuid::new_v4(),
+            content,
+            timestamp: Utc::now(),
+            ttl: self.message_ttl,
+            reply_to,
+            relayed: None,
+            channel: self.groups[idx].channels.active.clone(),
+            structured: None,
+        };
+        self.post_message(idx, message, ctx)
+    }
+
+    /// Posts content for bots. Content for older clients is generated, when
+    /// `text` isn't given.
+    fn post_structured(
+        &mut self,
+        idx: usize,
+        structured: Structured,
+        text: Option<String>,
+        ctx: &mut Context<Self>,
+    ) -> anyhow::Result<impl Future<Output = anyhow::Result<()>>> {
+        self.can_post(idx)?;
+
+        let message = TextMessage {
+            id: Uuid::new_v4(),
+            content: text.unwrap_or_else(|| structured::fallback(&structured)),
+            timestamp: Utc::now(),
+            ttl: self.message_ttl,
+            reply_to: None,
+            relayed: None,
+            channel: self.groups[idx].channels.active.clone(),
+            structured: Some(structured),
+        };
+        self.post_message(idx, message, ctx)
+    }
+
+    fn post_message(
+        &mut self,
+        idx: usize,
+        message: TextMessage,
+        ctx: &mut Context<Self>,
+    ) -> anyhow::Result<impl Future<Output = anyhow::Result<()>>> {
+        let group = self.groups[idx].name.clone();
+        let me = self.me.clone();
+        self.relay(&group, &me, &message, ctx);
+        self.publish(idx, message, ctx)
+    }
+
+    /// Relayed messages aren't displayed nor tracked, since we saw the original.
+    fn publish(
+        &mut self,
+        idx: usize,
+        message: TextMessage,
+        ctx: &mut Context<Self>,
+    ) -> anyhow::Result<impl Future<Output = anyhow::Result<()>>> {
+        self.can_post(idx)?;
+
+        let group = self.groups[idx].name.clone();
+        let addresses: Vec<NodeId> = self.groups[idx]
+            .users
+            .iter()
+            .map(|desc| desc.node_id)
+            .collect();
+        let myself = ctx.address();
+        let user_me = self.me.clone();
+
+        let user = match &message.relayed {
+            Some(relayed) => format!("{} [{}]", relayed.user, relayed.group),
+            None => {
+                let sent = SentMessage {
+                    recipients: addresses
+                        .iter()
+                        .map(|addr| (*addr, Delivery::Pending))
+                        .collect(),
+                };
+                let tag = format!(
+                    "{}{}{}",
+                    self.group_tag(&group),
+                    channel_tag(message.channel.as_deref()),
+                    self.message_context(Some(&group), &message)
+                );
+                self.print_own_message(&tag, &message, sent.marker());
+                self.sent.insert(message.id, sent);
+                self.me.clone()
+            }
+        };
+        self.record(HistoryEntry {
+            id: message.id,
+            group: group.clone(),
+            sender: None,
+            user,
+            content: message.content.clone(),
+            timestamp: message.timestamp,
+            channel: message.channel.clone(),
+        });
+
+        let text = SendText {
+            user: user_me,
+            group: Some(group),
+            direct: false,
+            auto_reply: false,
+            delayed: false,
+            messages: vec![Arc::new(message)],
+        };
+        let addresses = self.hold_for_later(addresses, &text, ctx);
+        Ok(async move {
+            for addr in addresses.iter() {
+                send_text(myself.clone(), addr, &text).await?;
+            }
+            Ok(())
         })
     }
+
+    fn fire(
+        &self,
+        event: Event,
+        group: Option<&str>,
+        user: &str,
+        node_id: NodeId,
+        text: Option<&str>,
+    ) {
+        let mut data = EventData::new(event);
+        data.group = group.map(str::to_string);
+        data.user = Some(user.to_string());
+        data.node_id = Some(node_id);
+        data.text = text.map(str::to_string);
+        self.fire_data(data);
+    }
+
+    fn fire_data(&self, data: EventData) {
+        self.notify_plugins(&data);
+        self.hooks.fire(data);
+    }
+
+    fn record(&mut self, entry: HistoryEntry) {
+        if let Some(chat_log) = &self.chat_log {
+            chat_log.message(&entry.group, &entry.timestamp, &entry.user, &entry.content);
+        }
+        match self.group_index(&entry.group) {
+            Some(idx) => self.groups[idx].record(entry),
+            None => log::warn!("Message for unknown group {} not recorded.", entry.group),
+        }
+    }
+
+    fn execute(&mut self, command: Command, ctx: &mut Context<Self>) -> anyhow::Result<()> {
+        match command {
+            Command::Help(name) => {
+                let mut help = commands::help(name.as_deref())?;
+                if let (None, Some(bots)) = (&name, self.bot_help()) {
+                    help = format!("{}\n{}", help, bots);
+                }
+                self.console.print(&help);
+                Ok(())
+            }
+            Command::Bot { name, args } => {
+                let future = self
+                    .post(self.active, bots::invocation(&name, &args), None, ctx)?
+                    .into_actor(self)
+                    .map(|result, _, _| {
+                        if let Err(e) = result {
+                            log::warn!("Failed to send bot command. Error: {}", e);
+                        }
+                    });
+                ctx.spawn(future);
+                Ok(())
+            }
+            Command::Open(index) => {
+                let url = self
+                    .renderer
+                    .link(index)
+                    .ok_or_else(|| anyhow!("No link with number {}, or it is too old.", index))?;
+                open_url(url)
+            }
+            Command::Emoji(query) => {
+                let found = emoji::search(&query);
+                if found.is_empty() {
+                    self.console
+                        .print(&format!("No emoji matching '{}'.", query));
+                } else {
+                    self.console.print(&emoji::format_listing(&found));
+                }
+                Ok(())
+            }
+            Command::Alias { pattern, alias } => {
+                let contact = self.contacts.set_alias(&pattern, alias)?;
+                self.console.print(&match &contact.alias {
+                    Some(alias) => format!(
+                        "{} [{}] is now known as {}.",
+                        contact.name, contact.node_id, alias
+                    ),
+                    None => format!("Removed alias of {} [{}].", contact.name, contact.node_id),
+                });
+                Ok(())
+            }
+            Command::Contacts => {
+                let listing = match self.contacts.is_empty() {
+                    true => "No contacts yet.".to_string(),
+                    false => self.contacts.list(),
+                };
+                self.console.print(&listing);
+                Ok(())
+            }
+            Command::ChatStats { group, period } => {
+                let idx = match group {
+                    Some(group) => self
+                        .group_index(&group)
+                        .ok_or_else(|| anyhow!("You aren't in group {}.", group))?,
+                    None => self.active,
+                };
+                let group = &self.groups[idx];
+                let entries = group.history.since(period.since(), usize::MAX);
+                let report = stats::report(&group.name, &entries);
+                self.console.print(&report);
+                self.print_blocked_rejections();
+                Ok(())
+            }
+            Command::Users => {
+                self.print_users();
+                Ok(())
+            }
+            Command::Poll { question, options } => {
+                self.create_poll(question, options);
+                Ok(())
+            }
+            Command::Vote { poll, option } => self.vote(&poll, option),
+            Command::Dm(Some(pattern)) => self.open_conversation(&pattern),
+            Command::Dm(None) => {
+                self.print_conversations();
+                Ok(())
+            }
+            Command::Events(period) => {
+                self.print_events(period);
+                Ok(())
+            }
+            Command::Pin(pattern) => self.pin(pattern),
+            Command::Paste { confirm } => self.paste(confirm, ctx),
+            Command::Copy(pattern) => self.copy(pattern),
+            Command::Pins => {
+                self.print_pins(self.active);
+                Ok(())
+            }
+            Command::Group(Some(name)) => self.switch_group(name, ctx),
+            Command::Group(None) => {
+                self.print_groups();
+                Ok(())
+            }
+            Command::Join => self.join(ctx),
+            Command::Direct { pattern, text } => self.send_direct(&pattern, text, ctx),
+            Command::Verify { pattern, confirm } => self.verify_user(&pattern, confirm),
+            Command::Forget { pattern, confirm } => self.forget_user(&pattern, confirm, ctx),
+            Command::Reply(text) => self.reply(text, ctx),
+            Command::ReplyDirect(text) => self.reply_direct(text, ctx),
+            Command::Away(message) => {
+                self.go_away(message);
+                Ok(())
+            }
+            Command::SendAt { at, text } => self.schedule(at, text, true, ctx),
+            Command::Remind { at, text } => self.schedule(at, text, false, ctx),
+            Command::Back => {
+                self.come_back();
+                Ok(())
+            }
+            Command::Unmute(pattern) => self.unmute(&pattern),
+            Command::Layout(None) => {
+                self.console
+                    .print(&format!("Message layout: {}.", self.layout.name()));
+                Ok(())
+            }
+            Command::Layout(Some(layout)) => {
+                self.layout = layout;
+                self.last_header = None;
+                self.console.print(&format!(
+                    "Messages will be displayed in {} layout.",
+                    layout.name()
+                ));
+                Ok(())
+            }
+            Command::Queue(QueueCommand::List) => {
+                self.print_queue();
+                Ok(())
+            }
+            Command::Queue(QueueCommand::Flush(pattern)) => {
+                self.flush_queue(pattern.as_deref(), ctx)
+            }
+            Command::Queue(QueueCommand::Drop(pattern)) => self.drop_queued(&pattern, ctx),
+            Command::Block(None) => {
+                self.print_blocked();
+                Ok(())
+            }
+            Command::Block(Some(pattern)) => self.block(&pattern),
+            Command::Unblock(pattern) => self.unblock(&pattern),
+            Command::Profile(pattern) => self.print_profile(pattern.as_deref()),
+            Command::SetProfile { field, value } => self.set_profile(&field, value),
+            Command::Sub(channel) => self.subscribe_channel(&channel),
+            Command::Unsub(channel) => self.unsubscribe_channel(&channel),
+            Command::Channels => {
+                self.print_channels();
+                Ok(())
+            }
+            Command::Bridge(None) => {
+                self.print_bridges();
+                Ok(())
+            }
+            Command::Bridge(Some((first, second))) => self.bridge(&first, &second),
+            Command::Unbridge(first, second) => self.unbridge(&first, &second),
+            Command::MuteGroup { group, until } => self.mute_group(&group, until, ctx),
+            Command::UnmuteGroup(group) => self.unmute_group(&group),
+            Command::Reload => self.reload(ctx),
+            Command::Report(format) => {
+                let format = format.or(self.report).unwrap_or(ReportFormat::Text);
+                self.console.print(&self.delivery_report(format));
+                Ok(())
+            }
+            Command::Whoami => {
+                self.whoami(ctx);
+                Ok(())
+            }
+            Command::Watch(None) => {
+                let listing = match self.watchlist.terms().is_empty() {
+                    true => "No watched terms.".to_string(),
+                    false => format!("Watched terms: {}", self.watchlist.terms().join(", ")),
+                };
+                self.console.print(&listing);
+                Ok(())
+            }
+            Command::Watch(Some(term)) => {
+                let notice = match self.watchlist.add(&term)? {
+                    true => format!("Watching '{}' in all groups.", term),
+                    false => format!("'{}' is already watched.", term),
+                };
+                self.renderer.set_watched(self.watchlist.terms());
+                self.console.print(&notice);
+                Ok(())
+            }
+            Command::Unwatch(term) => {
+                let notice = match self.watchlist.remove(&term)? {
+                    true => format!("Stopped watching '{}'.", term),
+                    false => format!("'{}' isn't watched.", term),
+                };
+                self.renderer.set_watched(self.watchlist.terms());
+                self.console.print(&notice);
+                Ok(())
+            }
+            Command::Device(node_id) => self.link_device(&node_id, ctx),
+            Command::Link(code) => self.link(&code),
+            Command::Pair(None) => {
+                self.start_pairing();
+                Ok(())
+            }
+            Command::Pair(Some((primary, code))) => self.pair(&primary, code, ctx),
+            Command::Revoke(pattern) => self.revoke(&pattern, ctx),
+        }
+    }
 }
 
 impl Handler<RpcEnvelope<SendText>> for Chat {
     type Result = ActorResponse<Self, (), ChatError>;
 
-    fn handle(&mut self, msg: RpcEnvelope<SendText>, _: &mut Context<Self>) -> Self::Result {
+    fn handle(&mut self, msg: RpcEnvelope<SendText>, ctx: &mut Context<Self>) -> Self::Result {
         let caller = match NodeId::from_str(msg.caller()) {
             Ok(caller) => caller,
             Err(_) => return ActorResponse::reply(Err(ChatError::InvalidNodeId)),
         };
+        if self.reject_blocked(&caller) {
+            return ActorResponse::reply(Err(ChatError::Rejected));
+        }
 
-        let user = match self.users.iter().find(|desc| desc.node_id == caller) {
-            Some(desc) => desc.name.clone(),
+        // Direct messages must come encrypted in `SendSealed`.
+        if msg.direct {
+            log::info!("Rejected unencrypted direct message from [{}].", caller);
+            return ActorResponse::reply(Err(ChatError::Rejected));
+        }
+
+        // Older clients don't send group. They can be in single group only.
+        let idx = match &msg.group {
+            Some(name) => self.group_index(name),
+            None => self
+                .groups
+                .iter()
+                .position(|group| group.contains(&caller))
+                .or(Some(0)),
+        };
+        let group = match idx {
+            Some(idx) => &self.groups[idx],
+            None => return ActorResponse::reply(Err(ChatError::UnknownGroup)),
+        };
+
+        if !group.may_post(&caller) {
+            log::info!("Rejected messages from non-announcer [{}].", caller);
+            return ActorResponse::reply(Err(ChatError::Rejected));
+        }
+
+        if !group.admitted(&caller) {
+            log::info!("Rejected messages from non-member [{}].", caller);
+            return ActorResponse::reply(Err(ChatError::Rejected));
+        }
+
+        let user = match group.users.iter().find(|desc| desc.node_id == caller) {
+            Some(desc) => self.display_user(desc),
             None => {
                 log::warn!("Got messages from unknown user: {}", caller);
-                msg.user.clone()
+                self.contacts.display_name(&caller, &msg.user)
             }
         };
+        let group = group.name.clone();
 
-        let sends = msg.into_inner();
-        for text in sends.messages {
-            println!(
-                "{} {} > {}",
-                text.timestamp
-                    .with_timezone(&Local)
-                    .format("%Y-%m-%d %H:%M:%S"),
-                &user,
-                &text.content
-            );
-        }
-        ActorResponse::reply(Ok(()))
+        ActorResponse::reply(self.enqueue(caller, Some(group), user, msg.into_inner(), ctx))
     }
 }
 
@@ -133,52 +1485,64 @@ impl Handler<NewUser> for Chat {
 
     fn handle(&mut self, msg: NewUser, ctx: &mut Context<Self>) -> Self::Result {
         match (|| -> anyhow::Result<()> {
-            // Filter our own occurrences.
-            if msg.user == self.me {
+            // Filter our own occurrences. Before we know our NodeId, we can
+            // only compare names.
+            let ours = match self.node_id {
+                Some(node_id) => node_id == msg.address,
+                None => msg.user == self.me,
+            };
+            if ours {
                 log::debug!("Rejected our own user discovery.");
                 return Ok(());
             }
+            self.check_compatibility(&msg);
+            self.detail(
+                1,
+                &format!(
+                    "Discovered {} [{}] in group {}.",
+                    msg.user, msg.address, msg.group
+                ),
+            );
+
+            let idx = self
+                .group_index(&msg.group)
+                .ok_or_else(|| anyhow!("User discovered in unknown group {}.", msg.group))?;
 
-            match self
+            let known = self.groups[idx]
                 .users
                 .iter()
                 .find(|desc| desc.node_id == msg.address)
-                .map(|desc| desc.clone())
-            {
+                .cloned();
+            match known {
                 Some(returning_user) => {
-                    println!("<===> User reappeared: {} <===>", &returning_user.name);
-
-                    if let Some(messages) = self.delivery.remove(&returning_user.node_id) {
-                        log::info!(
-                            "Resending old messages to {} [{}].",
-                            &returning_user.name,
-                            &returning_user.node_id
-                        );
-
-                        let myself = ctx.address();
-                        let resend = async move {
-                            send_text(myself, &returning_user.node_id, &messages)
-                                .await
-                                .map_err(|e| {
-                                    log::error!(
-                                        "Error delivering messages to {} [{}]. Error: {}",
-                                        returning_user.name,
-                                        returning_user.node_id,
-                                        e
-                                    )
-                                })
-                                .ok();
-                        };
-                        Arbiter::spawn(resend);
+                    let display_name = self.register(idx, &msg);
+                    let group = &mut self.groups[idx];
+                    let was_online = group.users.confirm(&msg.address, &msg.user);
+                    group.save_roster();
+                    let name = format!("{}{}", display_name, self.group_tag(&msg.group));
+                    self.notice_reappeared(msg.address, name, was_online, ctx);
+
+                    if Some(returning_user.user_id()) == self.user_id() {
+                        self.sync_history(msg.address, msg.group.clone(), ctx);
                     }
+
+                    self.expire_queued(ctx);
+                    self.flush(returning_user.node_id, ctx);
                 }
-                None => {
-                    println!("<===> New user appeared: {} <===>", &msg.user);
-                    self.users.push(UserDesc {
-                        name: msg.user,
-                        node_id: msg.address,
-                    });
+                // Users verified in other group don't need to be challenged again.
+                None if self.groups[idx].is_full() => {
+                    log::info!(
+                        "Group {} is full. Not admitting {} [{}].",
+                        msg.group,
+                        msg.user,
+                        msg.address
+                    );
+                }
+                None if self.groups.iter().any(|group| group.contains(&msg.address)) => {
+                    let user = self.find_user(&msg.address).and_then(|desc| desc.user);
+                    self.admit(idx, msg, user, ctx)
                 }
+                None => self.challenge(msg, ctx),
             }
             Ok(())
         })() {
@@ -188,69 +1552,313 @@ impl Handler<NewUser> for Chat {
     }
 }
 
-pub async fn send_text(chat: Addr<Chat>, addr: &NodeId, text: &SendText) -> anyhow::Result<()> {
-    if let Err(_) = bus::service(format!("/net/{}/yachat", addr))
-        .send(text.clone())
-        .await
-    {
-        let msg = DeliverLater {
-            address: addr.clone(),
-            messages: text.clone(),
-        };
-        chat.send(msg).await??;
+/// Returns `Delivery::Queued`, when recipient is unreachable or busy and
+/// messages wait for him in queue.
+pub async fn send_text(
+    chat: Addr<Chat>,
+    addr: &NodeId,
+    text: &SendText,
+) -> anyhow::Result<Delivery> {
+    let wait = chat.send(ReserveSend { bytes: text.size() }).await?;
+    if wait > std::time::Duration::default() {
+        log::debug!(
+            "Outbound limit reached. Sending to [{}] in {:?}.",
+            addr,
+            wait
+        );
+        tokio::time::delay_for(wait).await;
+    }
+
+    let mut attempt = 0;
+    let result = loop {
+        match deliver_text(&chat, addr, text).await {
+            // Unreachable peer is retried, when he reappears in discovery.
+            Err(e @ SendError::Refused { .. }) if e.is_retryable() && attempt < SEND_RETRIES => {
+                log::debug!("{} Retrying.", e);
+                tokio::time::delay_for(SEND_RETRY_DELAY * 2u32.pow(attempt)).await;
+                attempt += 1;
+            }
+            result => break result,
+        }
+    };
+
+    let delivery = match result {
+        Ok(()) => Delivery::Delivered,
+        Err(e) if e.is_retryable() => {
+            log::info!("{}", e);
+            let msg = DeliverLater {
+                address: *addr,
+                messages: text.clone(),
+            };
+            chat.send(msg).await??;
+            return Ok(Delivery::Queued);
+        }
+        Err(e) => {
+            log::info!("{}", e);
+            Delivery::Rejected
+        }
+    };
+
+    chat.do_send(DeliveryReport {
+        ids: text.messages.iter().map(|text| text.id).collect(),
+        recipient: *addr,
+        delivery,
+    });
+    Ok(delivery)
+}
+
+async fn deliver_text(chat: &Addr<Chat>, addr: &NodeId, text: &SendText) -> Result<(), SendError> {
+    let result = match text.direct {
+        true => send_sealed(chat, addr, text).await?,
+        false => net_send(*addr, text.clone()).await?,
+    };
+    result.map_err(|error| SendError::Refused {
+        peer: *addr,
+        group: text.group.clone(),
+        error,
+    })
+}
+
+/// Direct messages are encrypted in double ratchet session. Session is
+/// started again, if peer lost it.
+async fn send_sealed(
+    chat: &Addr<Chat>,
+    addr: &NodeId,
+    text: &SendText,
+) -> Result<Result<(), ChatError>, SendError> {
+    let local = |e: MailboxError| SendError::Local {
+        peer: *addr,
+        reason: e.to_string(),
+    };
+    for _ in 0..2 {
+        let sealed = chat
+            .send(SealDirect {
+                address: *addr,
+                text: text.clone(),
+            })
+            .await
+            .map_err(local)?
+            // Handshake needs peer online.
+            .map_err(|e| SendError::Unreachable {
+                peer: *addr,
+                reason: e.to_string(),
+            })?;
+        let session = sealed.session;
+        match net_send(*addr, sealed).await? {
+            Err(ChatError::UnknownSession) => {
+                chat.send(ForgetSession(session)).await.map_err(local)?
+            }
+            result => return Ok(result),
+        }
     }
-    Ok(())
+    Ok(Err(ChatError::UnknownSession))
+}
+
+pub async fn send_message<M>(addr: NodeId, msg: M) -> Result<(), SendError>
+where
+    M: RpcMessage<Item = (), Error = ChatError> + Clone,
+{
+    net_send(addr, msg)
+        .await?
+        .map_err(|error| SendError::Refused {
+            peer: addr,
+            group: None,
+            error,
+        })
+}
+
+/// All messages to peers go through here, so faults can be injected with
+/// `chaos` feature.
+async fn net_send<M>(addr: NodeId, msg: M) -> Result<Result<(), ChatError>, SendError>
+where
+    M: RpcMessage<Item = (), Error = ChatError> + Clone,
+{
+    #[cfg(feature = "chaos")]
+    return crate::chaos::inject(addr, msg, gsb_send).await;
+    #[cfg(not(feature = "chaos"))]
+    gsb_send(addr, msg).await
+}
+
+async fn gsb_send<M>(addr: NodeId, msg: M) -> Result<Result<(), ChatError>, SendError>
+where
+    M: RpcMessage<Item = (), Error = ChatError>,
+{
+    bus::service(format!("/net/{}/yachat", addr))
+        .send(msg)
+        .await
+        .map_err(|e| SendError::Unreachable {
+            peer: addr,
+            reason: e.to_string(),
+        })
 }
 
 impl Handler<NewLine> for Chat {
     type Result = ActorResponse<Self, (), anyhow::Error>;
 
     fn handle(&mut self, line: NewLine, ctx: &mut Context<Self>) -> Self::Result {
-        let addresses: Vec<NodeId> = self.users.iter().map(|desc| desc.node_id.clone()).collect();
-        let myself = ctx.address();
-        let user_me = self.me.clone();
+        self.console.input_echoed(&line.0);
 
-        let future = async move {
-            let text = SendText {
-                user: user_me,
-                messages: vec![TextMessage {
-                    content: line.0,
-                    timestamp: Utc::now(),
-                }],
-            };
-            for addr in addresses.iter() {
-                send_text(myself.clone(), addr, &text).await?;
+        if let Some(command) = Command::parse(&line.0) {
+            if let Err(e) = command.and_then(|command| self.execute(command, ctx)) {
+                self.console.print(&e.to_string());
             }
-            Ok(())
-        };
-        ActorResponse::r#async(future.into_actor(self))
+            return ActorResponse::reply(Ok(()));
+        }
+
+        if self.conversations.open.is_some() {
+            self.console.erase_input(&line.0);
+            if let Err(e) = self.send_to_conversation(line.0, ctx) {
+                self.console.print(&e.to_string());
+            }
+            return ActorResponse::reply(Ok(()));
+        }
+
+        if let Err(e) = self.can_post(self.active) {
+            self.console.print(&e.to_string());
+            return ActorResponse::reply(Ok(()));
+        }
+
+        self.console.erase_input(&line.0);
+        match self.post(self.active, line.0, None, ctx) {
+            Ok(future) => ActorResponse::r#async(future.into_actor(self)),
+            Err(e) => {
+                self.console.print(&e.to_string());
+                ActorResponse::reply(Ok(()))
+            }
+        }
+    }
+}
+
+impl Handler<ReserveSend> for Chat {
+    type Result = MessageResult<ReserveSend>;
+
+    fn handle(&mut self, msg: ReserveSend, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(self.throttle.reserve(msg.bytes))
     }
 }
 
 impl Handler<DeliverLater> for Chat {
     type Result = ActorResponse<Self, (), anyhow::Error>;
 
-    fn handle(&mut self, msg: DeliverLater, _: &mut Context<Self>) -> Self::Result {
+    fn handle(&mut self, msg: DeliverLater, ctx: &mut Context<Self>) -> Self::Result {
         log::info!("Messages scheduled to deliver later to [{}].", &msg.address);
 
-        self.delivery
-            .entry(msg.address.clone())
-            .or_insert(SendText {
-                messages: vec![],
-                user: self.me.clone(),
-            })
-            .messages
-            .extend(msg.messages.messages.into_iter());
+        let report = DeliveryReport {
+            ids: msg.messages.messages.iter().map(|text| text.id).collect(),
+            recipient: msg.address,
+            delivery: Delivery::Queued,
+        };
+        self.handle(report, ctx);
+
+        // Announced once, not for every message sent while peer is away.
+        if !self.delivery.contains(&msg.address) && !self.flushing.contains(&msg.address) {
+            let notice = format!(
+                "⚠ {} unreachable, message queued.",
+                self.peer_name(&msg.address)
+            );
+            self.notice(&notice);
+        }
+
+        let mut messages = msg.messages;
+        messages.delayed = true;
+        self.queue(msg.address, messages);
         ActorResponse::reply(Ok(()))
     }
 }
 
+impl Handler<DeliveryReport> for Chat {
+    type Result = ();
+
+    fn handle(&mut self, msg: DeliveryReport, _: &mut Context<Self>) -> Self::Result {
+        self.digest.record(msg.recipient, &msg.ids, msg.delivery);
+        let attempt = format!(
+            "{} message(s) to {}: {}.",
+            msg.ids.len(),
+            self.peer_name(&msg.recipient),
+            format!("{:?}", msg.delivery).to_lowercase()
+        );
+        self.detail(2, &attempt);
+        self.emit(ChatEvent::Delivery {
+            ids: msg.ids.clone(),
+            recipient: msg.recipient,
+            delivery: msg.delivery,
+        });
+        if msg.delivery == Delivery::Rejected || msg.delivery == Delivery::Expired {
+            let user = self
+                .find_user(&msg.recipient)
+                .map(|desc| self.display_user(desc))
+                .unwrap_or_default();
+            self.fire(Event::DeliveryFailed, None, &user, msg.recipient, None);
+            let text = match msg.delivery {
+                Delivery::Rejected => format!("{} messages rejected by {}.", msg.ids.len(), user),
+                _ => format!("{} messages to {} expired.", msg.ids.len(), user),
+            };
+            self.audit(AuditKind::Delivery, None, Some(msg.recipient), text);
+        }
+
+        for id in msg.ids.iter() {
+            let sent = match self.sent.get_mut(id) {
+                Some(sent) => sent,
+                None => continue,
+            };
+
+            let previous = sent.marker();
+            sent.recipients.insert(msg.recipient, msg.delivery);
+            let marker = sent.marker();
+
+            if marker != previous {
+                match self.accessible {
+                    // Only problems are announced. Delivery is expected.
+                    true if marker == '⧗' => self
+                        .notice("Your message will be delivered, when recipient is back online."),
+                    true if marker == '✗' && msg.delivery == Delivery::Rejected => {
+                        self.notice("Your message was rejected.")
+                    }
+                    true => (),
+                    // Marker is placed right after timestamp and space.
+                    false => self
+                        .console
+                        .overwrite(id, TIMESTAMP_WIDTH + 1, &marker.to_string()),
+                }
+            }
+
+            // Nothing will change anymore for delivered or rejected messages.
+            if marker == '✓' || marker == '✗' {
+                self.sent.remove(id);
+                self.console.forget(id);
+            }
+        }
+    }
+}
+
 impl Handler<Shutdown> for Chat {
     type Result = ActorResponse<Self, (), anyhow::Error>;
 
     fn handle(&mut self, _: Shutdown, _: &mut Context<Self>) -> Self::Result {
+        self.save_session();
+        self.save_contacts();
+        if let Some(format) = self.report {
+            println!("{}", self.delivery_report(format));
+        }
+        self.store_queue();
+
         let discovery = self.discovery.clone();
-        ActorResponse::r#async(async move { discovery.send(Shutdown {}).await? }.into_actor(self))
+        let membership = self.membership.clone();
+        let workers = self
+            .groups
+            .iter()
+            .map(|group| group.worker.clone())
+            .collect::<Vec<_>>();
+        let future = async move {
+            membership.send(Shutdown {}).await??;
+            discovery.send(Shutdown {}).await??;
+            // Waits for pending history writes.
+            for worker in workers {
+                worker.send(StopWorker).await?;
+            }
+            Ok(())
+        };
+        ActorResponse::r#async(future.into_actor(self))
     }
 }
 
@@ -261,6 +1869,8 @@ async fn input_reader(recipient: Recipient<NewLine>) {
     while let Some(line) = lines.next().await {
         match line {
             Ok(line) => {
+                // Windows console ends lines with CRLF.
+                let line = line.trim_end_matches('\r').to_string();
                 log::debug!("New line read: {}", &line);
                 match recipient.send(NewLine(line)).await {
                     Ok(_) => (),
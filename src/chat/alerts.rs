@@ -0,0 +1,33 @@
+use actix::prelude::*;
+
+use super::Chat;
+use crate::alerts::Alert;
+
+impl Handler<Alert> for Chat {
+    type Result = ();
+
+    fn handle(&mut self, msg: Alert, ctx: &mut Context<Self>) -> Self::Result {
+        let idx = match self.group_index(&msg.group) {
+            Some(idx) => idx,
+            None => {
+                log::warn!(
+                    "Alert not posted. Group {} was left: {}",
+                    msg.group,
+                    msg.text
+                );
+                return;
+            }
+        };
+        match self.post(idx, format!("[alert] {}", msg.text), None, ctx) {
+            Ok(future) => {
+                let future = future.into_actor(self).map(|result, _, _| {
+                    if let Err(e) = result {
+                        log::warn!("Failed to post alert. Error: {}", e);
+                    }
+                });
+                ctx.spawn(future);
+            }
+            Err(e) => self.notice(&format!("Alert wasn't posted. {}", e)),
+        }
+    }
+}
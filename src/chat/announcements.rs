@@ -0,0 +1,85 @@
+use ya_client::model::NodeId;
+
+use super::group::Group;
+use super::paid::PaidGroup;
+use super::Chat;
+
+impl Group {
+    /// In announcement-only groups only designated senders can post.
+    pub(super) fn may_post(&self, sender: &NodeId) -> bool {
+        match &self.announcers {
+            Some(announcers) => announcers.contains(sender),
+            None => true,
+        }
+    }
+
+    /// Creator of group or owner of paid group we joined. Only owner
+    /// decides, who can post.
+    pub(super) fn owner(&self) -> Option<NodeId> {
+        match &self.paid {
+            Some(PaidGroup::Member { owner, .. }) => Some(*owner),
+            _ => self.owner,
+        }
+    }
+}
+
+impl Chat {
+    /// We join announcement-only groups in read-only mode, unless we are
+    /// one of announcers. When we don't know our own NodeId, we let
+    /// other users decide and reject our messages.
+    pub(super) fn read_only(&self, group: usize) -> bool {
+        match (&self.groups[group].announcers, &self.node_id) {
+            (Some(announcers), Some(node_id)) => !announcers.contains(node_id),
+            _ => false,
+        }
+    }
+
+    /// Adopts list of announcers advertised by owner of group. Lists from
+    /// other users are ignored, otherwise any member could make open group
+    /// announcement-only. Locally configured list always takes precedence.
+    pub(super) fn adopt_announcers(
+        &mut self,
+        group: usize,
+        advertiser: NodeId,
+        advertised: Vec<NodeId>,
+        user: &str,
+    ) {
+        if self.groups[group].announcers_configured {
+            return;
+        }
+        if self.groups[group].owner() != Some(advertiser) {
+            log::debug!(
+                "Ignored announcers list advertised by {}, who doesn't own group {}.",
+                user,
+                self.groups[group].name
+            );
+            return;
+        }
+
+        match &self.groups[group].announcers {
+            None => {
+                self.notice(&format!(
+                    "Group {} is announcement-only. Only {} designated sender(s) can post.",
+                    self.groups[group].name,
+                    advertised.len()
+                ));
+                self.groups[group].announcers = Some(advertised);
+                if self.read_only(group) {
+                    self.notice("You joined in read-only mode.");
+                }
+            }
+            Some(announcers) => {
+                if announcers.len() != advertised.len()
+                    || !advertised
+                        .iter()
+                        .all(|node_id| announcers.contains(node_id))
+                {
+                    log::warn!(
+                        "User {} advertises different announcers list. Keeping previous one.",
+                        user
+                    );
+                }
+            }
+        }
+    }
+}
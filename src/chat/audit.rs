@@ -0,0 +1,41 @@
+use chrono::Utc;
+
+use ya_client::model::NodeId;
+
+use super::Chat;
+use crate::audit::{format_event, AuditEvent, AuditKind};
+use crate::stats::Period;
+
+impl Chat {
+    pub(super) fn audit(
+        &mut self,
+        kind: AuditKind,
+        group: Option<&str>,
+        node_id: Option<NodeId>,
+        text: String,
+    ) {
+        let event = AuditEvent {
+            timestamp: Utc::now(),
+            kind,
+            group: group.map(str::to_string),
+            node_id,
+            text,
+        };
+        if let Err(e) = self.audit.record(event) {
+            log::warn!("Failed to record system event. Error: {}", e);
+        }
+    }
+
+    pub(super) fn print_events(&mut self, period: Period) {
+        let events = self.audit.since(period.since());
+        let listing = match events.is_empty() {
+            true => "No system events.".to_string(),
+            false => events
+                .into_iter()
+                .map(format_event)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        self.console.print(&listing);
+    }
+}
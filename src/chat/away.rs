@@ -0,0 +1,61 @@
+use actix::prelude::*;
+
+use ya_client::model::NodeId;
+
+use super::Chat;
+
+impl Chat {
+    pub(super) fn go_away(&mut self, message: Option<String>) {
+        let notice = match &message {
+            Some(message) => format!(
+                "You are away. Direct messages will be answered: {}",
+                message
+            ),
+            None => "You are away. Direct messages will be answered automatically.".to_string(),
+        };
+        self.away.set(message);
+        self.broadcast_presence();
+        self.notice(&notice);
+    }
+
+    pub(super) fn come_back(&mut self) {
+        if !self.away.is_away() {
+            self.console.print("You aren't away.");
+            return;
+        }
+        let replied = self.away.back();
+        self.broadcast_presence();
+        self.notice(&format!(
+            "Welcome back. Auto-reply was sent to {} user(s).",
+            replied
+        ));
+    }
+
+    /// Reply goes to all devices of the sender, same as `/msg`.
+    pub(super) fn auto_reply(
+        &mut self,
+        sender: NodeId,
+        group: Option<&str>,
+        name: &str,
+        ctx: &mut Context<Self>,
+    ) {
+        if self.receive_only {
+            return;
+        }
+        let user_id = match self.find_user(&sender) {
+            Some(desc) => desc.user_id(),
+            None => return,
+        };
+        if Some(user_id) == self.user_id() {
+            return;
+        }
+
+        let content = match self.away.reply(user_id, name, &self.me, group) {
+            Some(content) => content,
+            None => return,
+        };
+        let tag = format!(" [auto-reply to {}]", name);
+        let devices = self.devices_of(user_id);
+        self.deliver_direct(&tag, devices, content, None, true, ctx);
+    }
+}
@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use super::Chat;
+
+/// How often we remind, that groups without users are still being searched.
+pub(super) const SEARCH_NOTICE_INTERVAL: Duration = Duration::from_secs(60);
+
+impl Chat {
+    /// Summary of our identity and groups, printed once at start.
+    pub(super) fn print_banner(&mut self) {
+        let node_id = match self.node_id {
+            Some(node_id) => node_id.to_string(),
+            None => "unknown (is yagna running?)".to_string(),
+        };
+        let groups = self
+            .groups
+            .iter()
+            .enumerate()
+            .map(|(idx, group)| {
+                let state = match group.subscribed {
+                    true => "discovering users",
+                    false => "subscribing to market",
+                };
+                let active = match idx == self.active {
+                    true => ", active",
+                    false => "",
+                };
+                format!("{} ({}{})", group.name, state, active)
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let hint = match self.receive_only {
+            true => "Receiving messages only.".to_string(),
+            false => format!(
+                "Type /help to list commands. Typed messages are sent to group {}.",
+                self.group().name
+            ),
+        };
+        let banner = format!(
+            "yachat {}\n  Name:     {}\n  NodeId:   {}\n  Groups:   {}\n  Data dir: {}\n{}",
+            env!("CARGO_PKG_VERSION"),
+            self.me,
+            node_id,
+            groups,
+            self.data_dir.display(),
+            hint
+        );
+        self.console.print(&banner);
+    }
+
+    /// Users appear only after discovery finds them, which can take
+    /// a while. Silence would look like chat is broken.
+    pub(super) fn notice_searching(&mut self) {
+        let empty = self
+            .groups
+            .iter()
+            .filter(|group| group.users.is_empty())
+            .map(|group| group.name.clone())
+            .collect::<Vec<_>>();
+        for group in empty {
+            self.notice(&format!("Still searching for users in group {}…", group));
+        }
+    }
+}
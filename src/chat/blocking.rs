@@ -0,0 +1,80 @@
+use anyhow::bail;
+
+use ya_client::model::NodeId;
+
+use super::Chat;
+use crate::audit::AuditKind;
+
+impl Chat {
+    /// Checked first in GSB handlers, so blocked users don't reach
+    /// challenges, queues or spam filter.
+    pub(super) fn reject_blocked(&mut self, caller: &NodeId) -> bool {
+        if !self.blocklist.contains(caller) {
+            return false;
+        }
+        log::debug!("Rejected message from blocked [{}].", caller);
+        self.blocked_rejections += 1;
+        true
+    }
+
+    pub(super) fn block(&mut self, pattern: &str) -> anyhow::Result<()> {
+        let (name, devices) = self.resolve_user(pattern)?;
+        if !self.blocklist.add(&name, &devices)? {
+            self.console.print(&format!("{} is already blocked.", name));
+            return Ok(());
+        }
+        let text = format!("Blocked {} on {} device(s).", name, devices.len());
+        self.audit(AuditKind::Moderation, None, Some(devices[0]), text.clone());
+        self.console.print(&text);
+        Ok(())
+    }
+
+    pub(super) fn unblock(&mut self, pattern: &str) -> anyhow::Result<()> {
+        let removed = self.blocklist.remove(pattern)?;
+        if removed.is_empty() {
+            bail!("No blocked user matching '{}'.", pattern);
+        }
+        for blocked in removed {
+            let text = format!("Unblocked {}.", blocked.name);
+            self.audit(
+                AuditKind::Moderation,
+                None,
+                blocked.devices.first().cloned(),
+                text.clone(),
+            );
+            self.console.print(&text);
+        }
+        Ok(())
+    }
+
+    pub(super) fn print_blocked(&mut self) {
+        let listing = match self.blocklist.blocked().is_empty() {
+            true => "No blocked users.".to_string(),
+            false => self
+                .blocklist
+                .blocked()
+                .iter()
+                .map(|blocked| {
+                    let devices = blocked
+                        .devices
+                        .iter()
+                        .map(|device| device.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    format!("  {} [{}]", blocked.name, devices)
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        self.console.print(&listing);
+    }
+
+    pub(super) fn print_blocked_rejections(&mut self) {
+        if self.blocked_rejections > 0 {
+            self.console.print(&format!(
+                "Rejected {} messages from blocked users since start.",
+                self.blocked_rejections
+            ));
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use ya_client::model::NodeId;
+
+use super::Chat;
+use crate::hooks::{Event, EventData};
+use crate::protocol::BotCommand;
+
+/// Splits `!name args` into command name and arguments.
+fn parse_invocation(content: &str) -> Option<(&str, &str)> {
+    let content = content.trim().strip_prefix('!')?;
+    let (name, args) = match content.find(char::is_whitespace) {
+        Some(end) => (&content[..end], content[end..].trim()),
+        None => (content, ""),
+    };
+    let valid = |c: char| c.is_alphanumeric() || c == '-' || c == '_';
+    match !name.is_empty() && name.chars().all(valid) {
+        true => Some((name, args)),
+        false => None,
+    }
+}
+
+/// Message posted by `/bot <name> [args]`.
+pub(super) fn invocation(name: &str, args: &str) -> String {
+    let name = name.trim_start_matches('!');
+    match args.is_empty() {
+        true => format!("!{}", name),
+        false => format!("!{} {}", name, args),
+    }
+}
+
+impl Chat {
+    /// Command registered by plugin. Users learn about it from our profile.
+    pub(super) fn register_bot_command(&mut self, name: String, help: Option<String>) {
+        let name = name.trim_start_matches('!').to_lowercase();
+        self.bot_commands.retain(|command| command.name != name);
+        self.bot_commands.push(BotCommand {
+            name,
+            help: help.unwrap_or_default(),
+        });
+        self.broadcast_profile();
+    }
+
+    /// Fires `bot-command` event, when group message invokes one of our
+    /// bot commands. Direct messages aren't dispatched.
+    pub(super) fn dispatch_bot_command(
+        &self,
+        sender: NodeId,
+        group: Option<&str>,
+        name: &str,
+        content: &str,
+    ) {
+        let (command, args) = match (group, parse_invocation(content)) {
+            (Some(_), Some(invocation)) => invocation,
+            _ => return,
+        };
+        let command = command.to_lowercase();
+        if !self.bot_commands.iter().any(|known| known.name == command) {
+            return;
+        }
+        let mut data = EventData::new(Event::BotCommand);
+        data.group = group.map(str::to_string);
+        data.user = Some(name.to_string());
+        data.node_id = Some(sender);
+        data.text = Some(args.to_string());
+        data.command = Some(command);
+        self.fire_data(data);
+    }
+
+    /// Bot commands advertised by users of active group and our own, for `/help`.
+    pub(super) fn bot_help(&self) -> Option<String> {
+        let group = self.group();
+        let mut lines = self
+            .bot_commands
+            .iter()
+            .map(|command| (self.me.clone(), command))
+            .collect::<Vec<_>>();
+        for desc in group.users.iter() {
+            if let Some(profile) = self.profiles.get(&desc.node_id) {
+                let name = self.display_user(desc);
+                lines.extend(
+                    profile
+                        .commands
+                        .iter()
+                        .map(|command| (name.clone(), command)),
+                );
+            }
+        }
+        if lines.is_empty() {
+            return None;
+        }
+        let lines = lines
+            .into_iter()
+            .map(|(user, command)| format!("  !{}  {} ({})", command.name, command.help, user))
+            .collect::<Vec<_>>();
+        Some(format!(
+            "Bot commands in {}:\n{}",
+            group.name,
+            lines.join("\n")
+        ))
+    }
+}
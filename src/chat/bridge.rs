@@ -0,0 +1,115 @@
+use actix::prelude::*;
+use anyhow::{anyhow, bail};
+use chrono::Utc;
+use uuid::Uuid;
+
+use super::Chat;
+use crate::protocol::{Relayed, TextMessage};
+
+impl Chat {
+    /// Relays messages between two joined groups, until `/unbridge`.
+    pub(super) fn bridge(&mut self, first: &str, second: &str) -> anyhow::Result<()> {
+        if first == second {
+            bail!("Can't bridge group with itself.");
+        }
+        for name in [first, second].iter() {
+            let idx = self
+                .group_index(name)
+                .ok_or_else(|| anyhow!("You aren't in group {}.", name))?;
+            self.can_post(idx)?;
+        }
+        if self.bridged(first, second) {
+            bail!("Groups {} and {} are bridged already.", first, second);
+        }
+
+        self.bridges.push((first.to_string(), second.to_string()));
+        self.notice(&format!(
+            "Messages are relayed between groups {} and {}.",
+            first, second
+        ));
+        Ok(())
+    }
+
+    pub(super) fn unbridge(&mut self, first: &str, second: &str) -> anyhow::Result<()> {
+        if !self.bridged(first, second) {
+            bail!("Groups {} and {} aren't bridged.", first, second);
+        }
+        self.bridges
+            .retain(|(a, b)| !((a == first && b == second) || (a == second && b == first)));
+        self.notice(&format!("Bridge between {} and {} removed.", first, second));
+        Ok(())
+    }
+
+    pub(super) fn print_bridges(&mut self) {
+        let listing = match self.bridges.is_empty() {
+            true => "No bridged groups.".to_string(),
+            false => self
+                .bridges
+                .iter()
+                .map(|(first, second)| format!("  {} <-> {}", first, second))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        self.console.print(&listing);
+    }
+
+    fn bridged(&self, first: &str, second: &str) -> bool {
+        self.bridges
+            .iter()
+            .any(|(a, b)| (a == first && b == second) || (a == second && b == first))
+    }
+
+    /// Sends copy of message to groups bridged with `group`. Copies are
+    /// marked as relayed, so no bridge relays them again.
+    pub(super) fn relay(
+        &mut self,
+        group: &str,
+        user: &str,
+        text: &TextMessage,
+        ctx: &mut Context<Self>,
+    ) {
+        if text.relayed.is_some() {
+            return;
+        }
+        let targets = self
+            .bridges
+            .iter()
+            .filter_map(|(a, b)| match (a == group, b == group) {
+                (true, _) => Some(b.clone()),
+                (_, true) => Some(a.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        for target in targets {
+            let idx = match self.group_index(&target) {
+                Some(idx) => idx,
+                None => continue,
+            };
+            let message = TextMessage {
+                id: Uuid::new_v4(),
+                content: text.content.clone(),
+                timestamp: Utc::now(),
+                ttl: self.message_ttl,
+                reply_to: None,
+                relayed: Some(Relayed {
+                    group: group.to_string(),
+                    user: user.to_string(),
+                }),
+                channel: text.channel.clone(),
+                structured: text.structured.clone(),
+            };
+            match self.publish(idx, message, ctx) {
+                Ok(future) => {
+                    let future = future.into_actor(self).map(move |result, _, _| {
+                        if let Err(e) = result {
+                            log::warn!("Failed to relay message to {}. Error: {}", target, e);
+                        }
+                    });
+                    ctx.spawn(future);
+                }
+                Err(e) => log::warn!("Message not relayed to {}. Error: {}", target, e),
+            }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use ya_client::model::NodeId;
+
+use super::{Chat, NewUser};
+
+impl Chat {
+    /// Adopts member limit advertised by group owner, unless we set our own.
+    pub(super) fn adopt_max_members(&mut self, group: usize, advertised: usize) {
+        let group = &mut self.groups[group];
+        if group.max_members_configured || group.max_members == Some(advertised) {
+            return;
+        }
+        log::info!("Group {} is limited to {} members.", group.name, advertised);
+        group.max_members = Some(advertised);
+    }
+
+    /// Full group doesn't answer identity challenges of new users, so
+    /// they are never admitted.
+    pub(super) fn refuses_member(&self, group: &str, caller: &NodeId) -> bool {
+        match self.group_index(group) {
+            Some(idx) => self.groups[idx].is_full() && !self.groups[idx].contains(caller),
+            None => false,
+        }
+    }
+
+    /// Explains, why we don't see anybody in group, instead of leaving
+    /// user in empty room. Notice is printed once per group.
+    pub(super) fn rejected_as_full(&mut self, group: usize, msg: &NewUser) {
+        log::info!(
+            "{} [{}] refused us in full group {}.",
+            msg.user,
+            msg.address,
+            msg.group
+        );
+        if self.groups[group].rejected_full {
+            return;
+        }
+        self.groups[group].rejected_full = true;
+        let limit = match self.groups[group].max_members {
+            Some(max_members) => format!(" of {} members", max_members),
+            None => String::new(),
+        };
+        self.notice(&format!(
+            "Group {} reached its limit{}. Members refuse new users, so you won't see \
+             their messages. Try again later.",
+            msg.group, limit
+        ));
+    }
+}
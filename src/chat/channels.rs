@@ -0,0 +1,127 @@
+use anyhow::bail;
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::Chat;
+
+/// Channel of messages sent without one.
+const GENERAL: &str = "general";
+
+/// Lightweight topics inside single group, carried in `TextMessage`.
+/// Messages of channels, we aren't subscribed to, are only counted.
+/// `#general` is always displayed.
+#[derive(Default)]
+pub(super) struct Channels {
+    /// Channel of our messages. None for `#general`.
+    pub(super) active: Option<String>,
+    subscribed: BTreeSet<String>,
+    /// All channels seen in group.
+    unread: BTreeMap<String, usize>,
+}
+
+impl Channels {
+    /// Returns false, if message shouldn't be displayed. It is counted
+    /// as unread instead.
+    pub(super) fn receive(&mut self, channel: Option<&str>) -> bool {
+        let channel = match channel {
+            Some(channel) => channel,
+            None => return true,
+        };
+        let unread = self.unread.entry(channel.to_string()).or_insert(0);
+        if self.subscribed.contains(channel) {
+            return true;
+        }
+        *unread += 1;
+        false
+    }
+
+    /// Returns number of messages missed before subscribing.
+    fn subscribe(&mut self, channel: &str) -> usize {
+        self.subscribed.insert(channel.to_string());
+        self.unread.insert(channel.to_string(), 0).unwrap_or(0)
+    }
+
+    fn unsubscribe(&mut self, channel: &str) -> bool {
+        if self.active.as_deref() == Some(channel) {
+            self.active = None;
+        }
+        self.subscribed.remove(channel)
+    }
+
+    fn list(&self) -> String {
+        let mut lines = vec![format!("  #{}", GENERAL)];
+        for (channel, unread) in self.unread.iter() {
+            let mut line = format!("  #{}", channel);
+            if self.active.as_deref() == Some(channel) {
+                line.push_str(" (posting)");
+            } else if self.subscribed.contains(channel) {
+                line.push_str(" (subscribed)");
+            }
+            if *unread > 0 {
+                line.push_str(&format!(" - {} unread", unread));
+            }
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+}
+
+/// Accepts name with or without `#`. None for `#general`.
+fn parse_channel(name: &str) -> anyhow::Result<Option<String>> {
+    let channel = name.trim_start_matches('#').to_lowercase();
+    if channel.is_empty() || channel.chars().any(char::is_whitespace) {
+        bail!("Invalid channel name: {}.", name);
+    }
+    Ok(match channel == GENERAL {
+        true => None,
+        false => Some(channel),
+    })
+}
+
+/// Displayed after group tag of messages.
+pub(super) fn channel_tag(channel: Option<&str>) -> String {
+    match channel {
+        Some(channel) => format!(" #{}", channel),
+        None => String::new(),
+    }
+}
+
+impl Chat {
+    /// Displays channel in active group and posts our messages there.
+    pub(super) fn subscribe_channel(&mut self, name: &str) -> anyhow::Result<()> {
+        let channel = parse_channel(name)?;
+        let group = &mut self.groups[self.active];
+        let notice = match &channel {
+            None => format!("Posting to #{} in {}.", GENERAL, group.name),
+            Some(channel) => {
+                let unread = group.channels.subscribe(channel);
+                format!(
+                    "Subscribed to #{} in {}. {} message(s) arrived while unsubscribed.",
+                    channel, group.name, unread
+                )
+            }
+        };
+        group.channels.active = channel;
+        self.notice(&notice);
+        Ok(())
+    }
+
+    pub(super) fn unsubscribe_channel(&mut self, name: &str) -> anyhow::Result<()> {
+        let channel = match parse_channel(name)? {
+            Some(channel) => channel,
+            None => bail!("#{} can't be unsubscribed.", GENERAL),
+        };
+        let group = &mut self.groups[self.active];
+        let notice = match group.channels.unsubscribe(&channel) {
+            true => format!("Unsubscribed from #{} in {}.", channel, group.name),
+            false => format!("You aren't subscribed to #{} in {}.", channel, group.name),
+        };
+        self.notice(&notice);
+        Ok(())
+    }
+
+    pub(super) fn print_channels(&mut self) {
+        let group = self.group();
+        let channels = format!("Channels of {}:\n{}", group.name, group.channels.list());
+        self.console.print(&channels);
+    }
+}
@@ -0,0 +1,59 @@
+use actix::prelude::*;
+use anyhow::{anyhow, bail};
+
+use super::Chat;
+
+/// Pastes larger than this are sent only after confirmation.
+const PASTE_CONFIRM_LINES: usize = 20;
+const PASTE_CONFIRM_BYTES: usize = 4096;
+
+impl Chat {
+    /// Sends clipboard contents to active group as single message, keeping
+    /// its lines together. Large pastes are only described, until confirmed.
+    pub(super) fn paste(&mut self, confirm: bool, ctx: &mut Context<Self>) -> anyhow::Result<()> {
+        let text = self.clipboard.get()?;
+        if text.trim().is_empty() {
+            bail!("Clipboard is empty.");
+        }
+        let lines = text.lines().count();
+        if !confirm && (lines > PASTE_CONFIRM_LINES || text.len() > PASTE_CONFIRM_BYTES) {
+            self.console.print(&format!(
+                "Clipboard holds {} lines ({} bytes). Type /paste confirm to send it.",
+                lines,
+                text.len()
+            ));
+            return Ok(());
+        }
+
+        let future = self
+            .post(self.active, text, None, ctx)?
+            .into_actor(self)
+            .map(|result, _, _| {
+                if let Err(e) = result {
+                    log::warn!("Failed to send pasted message. Error: {}", e);
+                }
+            });
+        ctx.spawn(future);
+        Ok(())
+    }
+
+    /// Copies content of message from history of active group with given id
+    /// prefix (or the last one). When clipboard isn't available, content is
+    /// printed instead, so it can be selected in terminal.
+    pub(super) fn copy(&mut self, pattern: Option<String>) -> anyhow::Result<()> {
+        let group = self.group();
+        let content = match &pattern {
+            Some(pattern) => group.history.find(pattern),
+            None => group.history.last(),
+        }
+        .ok_or_else(|| anyhow!("No message to copy."))?
+        .content
+        .clone();
+
+        match self.clipboard.set(&content) {
+            Ok(()) => self.console.print("Message copied to clipboard."),
+            Err(e) => self.console.print(&format!("{}\n{}", e, content)),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use ya_client::model::NodeId;
+
+use super::{Chat, NewUser};
+use crate::protocol::PROTOCOL_VERSION;
+use crate::update::{self, VERSION};
+
+impl Chat {
+    /// Warns once per peer, so the user knows, why some of his content
+    /// doesn't look right, without repeating it for every message.
+    pub(super) fn warn_compatibility(&mut self, node_id: NodeId, warning: &str) {
+        if self.compat_warned.insert(node_id) {
+            self.notice(warning);
+        }
+    }
+
+    /// Checks client version advertised in discovery.
+    pub(super) fn check_compatibility(&mut self, msg: &NewUser) {
+        let newer_protocol = msg
+            .protocol
+            .map_or(false, |protocol| protocol > PROTOCOL_VERSION);
+        let incompatible = msg
+            .version
+            .as_deref()
+            .map_or(false, |version| !update::compatible(VERSION, version));
+        if !newer_protocol && !incompatible {
+            return;
+        }
+        let warning = format!(
+            "{} uses yachat {}, you run {}. Some features may not render.",
+            msg.user,
+            msg.version.as_deref().unwrap_or("with newer protocol"),
+            VERSION
+        );
+        self.warn_compatibility(msg.address, &warning);
+    }
+}
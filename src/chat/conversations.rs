@@ -0,0 +1,153 @@
+use actix::prelude::*;
+use anyhow::{anyhow, bail};
+use std::collections::HashMap;
+
+use ya_client::model::NodeId;
+
+use super::Chat;
+use crate::emoji;
+
+/// Direct messages exchanged with single user, on all his devices.
+pub(super) struct Conversation {
+    name: String,
+    /// Formatted messages received, while conversation wasn't open.
+    unread: Vec<String>,
+}
+
+/// Direct messages are kept out of group traffic. Messages from users,
+/// whose conversation isn't open, wait for `/dm`.
+#[derive(Default)]
+pub(super) struct Conversations {
+    conversations: HashMap<NodeId, Conversation>,
+    /// User id of open conversation. Input goes to him instead of group.
+    pub(super) open: Option<NodeId>,
+}
+
+impl Conversations {
+    pub(super) fn remove(&mut self, node_ids: &[NodeId]) {
+        self.conversations
+            .retain(|user_id, _| !node_ids.contains(user_id));
+        if let Some(open) = &self.open {
+            if node_ids.contains(open) {
+                self.open = None;
+            }
+        }
+    }
+}
+
+impl Chat {
+    fn sender_id(&self, sender: NodeId) -> NodeId {
+        self.find_user(&sender)
+            .map(|desc| desc.user_id())
+            .unwrap_or(sender)
+    }
+
+    /// Prints direct message, when conversation with sender is open.
+    /// Otherwise keeps it unread and tells, how many messages wait. Without
+    /// input there is no `/dm`, so messages are always printed.
+    pub(super) fn show_direct(&mut self, sender: NodeId, name: &str, message: String) {
+        let user_id = self.sender_id(sender);
+        if self.receive_only || self.conversations.open == Some(user_id) {
+            self.console.print(&message);
+            return;
+        }
+
+        let conversation = self
+            .conversations
+            .conversations
+            .entry(user_id)
+            .or_insert_with(|| Conversation {
+                name: name.to_string(),
+                unread: vec![],
+            });
+        conversation.name = name.to_string();
+        conversation.unread.push(message);
+        let notice = format!(
+            "{} unread from {}. Type /dm {} to read.",
+            conversation.unread.len(),
+            name,
+            name
+        );
+        self.notice(&notice);
+    }
+
+    /// Opens conversation with user: prints his unread messages and sends
+    /// input directly to him, until group is switched.
+    pub(super) fn open_conversation(&mut self, pattern: &str) -> anyhow::Result<()> {
+        let (name, devices) = self.resolve_user(pattern)?;
+        let user_id = self.sender_id(devices[0]);
+
+        let unread = match self.conversations.conversations.get_mut(&user_id) {
+            Some(conversation) => std::mem::take(&mut conversation.unread),
+            None => {
+                self.conversations.conversations.insert(
+                    user_id,
+                    Conversation {
+                        name: name.clone(),
+                        unread: vec![],
+                    },
+                );
+                vec![]
+            }
+        };
+        self.conversations.open = Some(user_id);
+        self.console.print(&format!(
+            "Messages will be sent directly to {}. Type /group <name> to go back.",
+            name
+        ));
+        for message in unread {
+            self.console.print(&message);
+        }
+        Ok(())
+    }
+
+    pub(super) fn print_conversations(&mut self) {
+        if self.conversations.conversations.is_empty() {
+            self.console.print("No direct conversations.");
+            return;
+        }
+        let open = self.conversations.open;
+        let mut conversations = self.conversations.conversations.iter().collect::<Vec<_>>();
+        conversations.sort_by(|(_, a), (_, b)| a.name.cmp(&b.name));
+        let listing = conversations
+            .into_iter()
+            .map(|(user_id, conversation)| {
+                format!(
+                    "{} {} ({} unread)",
+                    if open == Some(*user_id) { "*" } else { " " },
+                    conversation.name,
+                    conversation.unread.len()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.console.print(&listing);
+    }
+
+    /// Sends input line to user of open conversation.
+    pub(super) fn send_to_conversation(
+        &mut self,
+        text: String,
+        ctx: &mut Context<Self>,
+    ) -> anyhow::Result<()> {
+        let user_id = self
+            .conversations
+            .open
+            .ok_or_else(|| anyhow!("No conversation is open."))?;
+        let devices = self.devices_of(user_id);
+        let name = match self.conversations.conversations.get(&user_id) {
+            Some(conversation) => conversation.name.clone(),
+            None => user_id.to_string(),
+        };
+        if devices.is_empty() {
+            bail!("{} isn't in any of our groups anymore.", name);
+        }
+        let content = match self.expand_emoji {
+            true => emoji::expand(&text),
+            false => text,
+        };
+        let tag = format!(" [direct to {}]", name);
+        self.deliver_direct(&tag, devices, content, None, false, ctx);
+        Ok(())
+    }
+}
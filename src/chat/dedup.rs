@@ -0,0 +1,67 @@
+use chrono::{DateTime, Duration, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::{HashSet, VecDeque};
+
+use ya_client::model::NodeId;
+
+use crate::protocol::TextMessage;
+
+/// How long received message is remembered.
+const DEDUP_WINDOW_MINUTES: i64 = 30;
+/// Bounds memory, when peers send a lot in short time.
+const DEDUP_CAPACITY: usize = 10000;
+
+/// Short-term cache of received messages. Messages retried or resent
+/// from sender's delivery queue are displayed only once.
+pub(super) struct Dedup {
+    seen: HashSet<[u8; 32]>,
+    order: VecDeque<([u8; 32], DateTime<Utc>)>,
+}
+
+impl Dedup {
+    pub(super) fn new() -> Dedup {
+        Dedup {
+            seen: HashSet::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Message was already received recently.
+    pub(super) fn contains(&mut self, sender: &NodeId, text: &TextMessage) -> bool {
+        self.evict(&Utc::now());
+        self.seen.contains(&digest(sender, text))
+    }
+
+    /// Returns false, if message was already received recently.
+    pub(super) fn insert(&mut self, sender: &NodeId, text: &TextMessage) -> bool {
+        let now = Utc::now();
+        self.evict(&now);
+
+        let hash = digest(sender, text);
+        if !self.seen.insert(hash) {
+            return false;
+        }
+        self.order.push_back((hash, now));
+        true
+    }
+
+    fn evict(&mut self, now: &DateTime<Utc>) {
+        let window = Duration::minutes(DEDUP_WINDOW_MINUTES);
+        while let Some((hash, received)) = self.order.front() {
+            if *now - *received < window && self.order.len() < DEDUP_CAPACITY {
+                break;
+            }
+            self.seen.remove(hash);
+            self.order.pop_front();
+        }
+    }
+}
+
+fn digest(sender: &NodeId, text: &TextMessage) -> [u8; 32] {
+    let hash = Sha256::new()
+        .chain(sender.to_string().as_bytes())
+        .chain(text.timestamp.to_rfc3339().as_bytes())
+        .chain(text.content.as_bytes())
+        .finalize();
+    hash.into()
+}
@@ -0,0 +1,360 @@
+use actix::prelude::*;
+use anyhow::{anyhow, bail};
+use chrono::{Duration, Utc};
+use rand::Rng;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use ya_client::model::NodeId;
+use ya_core_model::identity;
+use ya_service_bus::{typed as bus, RpcEndpoint, RpcEnvelope};
+
+use super::{send_text, Chat, Delivery, SentMessage};
+use crate::challenge;
+use crate::device::{self, Device};
+use crate::emoji;
+use crate::protocol::{ChatError, DeviceCert, Pair, SendText, TextMessage};
+use crate::roster::UserDesc;
+
+/// How long pairing code displayed on primary node is valid.
+const PAIRING_MINUTES: i64 = 5;
+
+impl Chat {
+    /// NodeId of our primary device. Other users see all our devices under it.
+    pub(super) fn user_id(&self) -> Option<NodeId> {
+        match &self.device {
+            Some(cert) => Some(cert.user),
+            None => self.node_id,
+        }
+    }
+
+    pub(super) fn find_user(&self, node_id: &NodeId) -> Option<&UserDesc> {
+        self.groups
+            .iter()
+            .flat_map(|group| group.users.iter())
+            .find(|desc| &desc.node_id == node_id)
+    }
+
+    /// Checks if user is already known from device other than `device`.
+    pub(super) fn has_other_device(&self, user_id: NodeId, device: &NodeId) -> bool {
+        self.groups
+            .iter()
+            .flat_map(|group| group.users.iter())
+            .any(|desc| desc.user_id() == user_id && &desc.node_id != device)
+    }
+
+    /// Alias set for any device of the user applies to all of them.
+    pub(super) fn display_user(&self, desc: &UserDesc) -> String {
+        [desc.node_id, desc.user_id()]
+            .iter()
+            .filter_map(|node_id| self.contacts.get(node_id))
+            .find_map(|contact| contact.alias.clone())
+            .unwrap_or_else(|| desc.name.clone())
+    }
+
+    /// Finds user by NodeId prefix of any of his devices, alias or name.
+    /// Returns display name and all known devices.
+    pub(super) fn resolve_user(&self, pattern: &str) -> anyhow::Result<(String, Vec<NodeId>)> {
+        let pattern = pattern.trim_end_matches('…').to_lowercase();
+        let mut users: HashMap<NodeId, (String, Vec<NodeId>)> = HashMap::new();
+
+        for desc in self.groups.iter().flat_map(|group| group.users.iter()) {
+            let name = self.display_user(desc);
+            let matches = desc.node_id.to_string().starts_with(&pattern)
+                || desc.user_id().to_string().starts_with(&pattern)
+                || name.to_lowercase() == pattern
+                || desc.name.to_lowercase() == pattern;
+            if !matches {
+                continue;
+            }
+
+            let (_, devices) = users
+                .entry(desc.user_id())
+                .or_insert_with(|| (name, vec![]));
+            if !devices.contains(&desc.node_id) {
+                devices.push(desc.node_id);
+            }
+        }
+
+        match users.len() {
+            0 => bail!("No user matching '{}'.", pattern),
+            1 => Ok(users.into_iter().next().unwrap().1),
+            _ => bail!("'{}' is ambiguous. Use longer NodeId prefix.", pattern),
+        }
+    }
+
+    /// Direct messages are fanned out to all devices of the user, so he
+    /// gets it wherever he is online.
+    pub(super) fn send_direct(
+        &mut self,
+        pattern: &str,
+        text: String,
+        ctx: &mut Context<Self>,
+    ) -> anyhow::Result<()> {
+        let (name, devices) = self.resolve_user(pattern)?;
+        let content = match self.expand_emoji {
+            true => emoji::expand(&text),
+            false => text,
+        };
+        let tag = format!(" [direct to {}]", name);
+        self.deliver_direct(&tag, devices, content, None, false, ctx);
+        Ok(())
+    }
+
+    /// All known devices of user.
+    pub(super) fn devices_of(&self, user_id: NodeId) -> Vec<NodeId> {
+        let mut devices = vec![];
+        for desc in self.groups.iter().flat_map(|group| group.users.iter()) {
+            if desc.user_id() == user_id && !devices.contains(&desc.node_id) {
+                devices.push(desc.node_id);
+            }
+        }
+        devices
+    }
+
+    pub(super) fn deliver_direct(
+        &mut self,
+        tag: &str,
+        devices: Vec<NodeId>,
+        content: String,
+        reply_to: Option<Uuid>,
+        auto_reply: bool,
+        ctx: &mut Context<Self>,
+    ) {
+        let message = TextMessage {
+            id: Uuid::new_v4(),
+            content,
+            timestamp: Utc::now(),
+            ttl: self.message_ttl,
+            reply_to,
+            relayed: None,
+            channel: None,
+            structured: None,
+        };
+
+        let sent = SentMessage {
+            recipients: devices
+                .iter()
+                .map(|addr| (*addr, Delivery::Pending))
+                .collect(),
+        };
+        let tag = format!("{}{}", tag, self.message_context(None, &message));
+        self.print_own_message(&tag, &message, sent.marker());
+        self.sent.insert(message.id, sent);
+
+        let text = SendText {
+            user: self.me.clone(),
+            group: None,
+            direct: true,
+            auto_reply,
+            delayed: false,
+            messages: vec![Arc::new(message)],
+        };
+        let devices = self.hold_for_later(devices, &text, ctx);
+        let myself = ctx.address();
+        Arbiter::spawn(async move {
+            for addr in devices.iter() {
+                send_text(myself.clone(), addr, &text)
+                    .await
+                    .map_err(|e| log::warn!("Failed to send direct message. Error: {}", e))
+                    .ok();
+            }
+        });
+    }
+
+    /// Direct messages are accepted from users verified in any of our groups.
+    pub(super) fn receive_direct(
+        &mut self,
+        caller: NodeId,
+        msg: SendText,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), ChatError> {
+        let user = match self.find_user(&caller) {
+            Some(desc) => self.display_user(desc),
+            None => {
+                log::info!("Rejected direct message from unknown user [{}].", caller);
+                return Err(ChatError::UnknownUser);
+            }
+        };
+        self.enqueue(caller, None, user, msg, ctx)
+    }
+
+    /// Signs certificate on primary device, which user copies to the other one.
+    pub(super) fn link_device(
+        &mut self,
+        device: &str,
+        ctx: &mut Context<Self>,
+    ) -> anyhow::Result<()> {
+        if self.device.is_some() {
+            bail!("This node is linked device itself. Run /device on your primary node.");
+        }
+        let node_id = self
+            .node_id
+            .ok_or_else(|| anyhow!("Our identity isn't known yet. Try again later."))?;
+        let device = NodeId::from_str(device).map_err(|_| anyhow!("Invalid NodeId: {}", device))?;
+
+        let payload = challenge::device_hash(&device);
+        let future = async move {
+            bus::service(identity::BUS_ID)
+                .send(identity::Sign { node_id, payload })
+                .await
+        }
+        .into_actor(self)
+        .map(move |result, myself, _| {
+            let notice = match result.map_err(anyhow::Error::from).and_then(|r| Ok(r?)) {
+                Ok(signature) => {
+                    let code = device::encode(&DeviceCert {
+                        user: node_id,
+                        signature,
+                    });
+                    format!("Type on device [{}]:\n/link {}", device, code)
+                }
+                Err(e) => format!("Failed to sign device certificate. Error: {}", e),
+            };
+            myself.console.print(&notice);
+        });
+        ctx.spawn(future);
+        Ok(())
+    }
+
+    /// Takes effect for users, who challenge us from now on.
+    pub(super) fn link(&mut self, code: &str) -> anyhow::Result<()> {
+        let node_id = self
+            .node_id
+            .ok_or_else(|| anyhow!("Our identity isn't known yet. Try again later."))?;
+        self.install_cert(node_id, device::decode(code)?)
+    }
+
+    pub(super) fn install_cert(&mut self, node_id: NodeId, cert: DeviceCert) -> anyhow::Result<()> {
+        if cert.user == node_id {
+            bail!("Can't link node with itself.");
+        }
+        challenge::verify_device(&cert.user, &node_id, &cert.signature)
+            .map_err(|e| anyhow!("Certificate isn't valid for this node. {}", e))?;
+
+        let device = Device {
+            cert: Some(cert.clone()),
+        };
+        device.save(&self.data_dir)?;
+
+        self.console.print(&format!(
+            "This node is now linked with user [{}]. Users discovering us from now on will see single user.",
+            cert.user
+        ));
+        self.device = Some(cert);
+        Ok(())
+    }
+
+    /// Pairing avoids copying certificate by hand: user types code displayed
+    /// on primary node into the other device.
+    pub(super) fn start_pairing(&mut self) {
+        if self.device.is_some() {
+            self.console
+                .print("This node is linked device itself. Run /pair on your primary node.");
+            return;
+        }
+
+        let code = format!("{:06}", rand::thread_rng().gen_range(0, 1_000_000));
+        let expires = Utc::now() + Duration::minutes(PAIRING_MINUTES);
+        self.console.print(&format!(
+            "Type on your other device within {} minutes:\n/pair {} {}",
+            PAIRING_MINUTES,
+            self.node_id.map(|id| id.to_string()).unwrap_or_default(),
+            code
+        ));
+        self.pairing = Some((code, expires));
+    }
+
+    pub(super) fn pair(
+        &mut self,
+        primary: &str,
+        code: String,
+        ctx: &mut Context<Self>,
+    ) -> anyhow::Result<()> {
+        let node_id = self
+            .node_id
+            .ok_or_else(|| anyhow!("Our identity isn't known yet. Try again later."))?;
+        let primary =
+            NodeId::from_str(primary).map_err(|_| anyhow!("Invalid NodeId: {}", primary))?;
+
+        let future = async move {
+            bus::service(format!("/net/{}/yachat", primary))
+                .send(Pair { code })
+                .await
+        }
+        .into_actor(self)
+        .map(move |result, myself, _| {
+            if let Err(e) = result
+                .map_err(anyhow::Error::from)
+                .and_then(|r| Ok(r?))
+                .and_then(|cert| myself.install_cert(node_id, cert))
+            {
+                myself
+                    .console
+                    .print(&format!("Pairing failed. Error: {}", e));
+            }
+        });
+        ctx.spawn(future);
+        Ok(())
+    }
+}
+
+impl Handler<RpcEnvelope<Pair>> for Chat {
+    type Result = ActorResponse<Self, DeviceCert, ChatError>;
+
+    fn handle(&mut self, msg: RpcEnvelope<Pair>, _: &mut Context<Self>) -> Self::Result {
+        let caller = match NodeId::from_str(msg.caller()) {
+            Ok(caller) => caller,
+            Err(_) => return ActorResponse::reply(Err(ChatError::InvalidNodeId)),
+        };
+        if self.reject_blocked(&caller) {
+            return ActorResponse::reply(Err(ChatError::Rejected));
+        }
+        let node_id = match self.node_id {
+            Some(node_id) => node_id,
+            None => return ActorResponse::reply(Err(ChatError::IdentityUnavailable)),
+        };
+
+        // Code is single use. Any attempt invalidates it, so it can't be guessed.
+        let valid = match self.pairing.take() {
+            Some((code, expires)) => code == msg.code && Utc::now() < expires,
+            None => false,
+        };
+        if !valid {
+            log::warn!("Invalid pairing attempt from [{}].", caller);
+            self.notice(&format!(
+                "Pairing attempt from [{}] with invalid code. Run /pair again.",
+                caller
+            ));
+            return ActorResponse::reply(Err(ChatError::InvalidPairingCode));
+        }
+
+        let payload = challenge::device_hash(&caller);
+        let future = async move {
+            bus::service(identity::BUS_ID)
+                .send(identity::Sign { node_id, payload })
+                .await
+                .map_err(|e| log::warn!("Failed to sign device certificate. Error: {}", e))
+                .and_then(|result| {
+                    result
+                        .map_err(|e| log::warn!("Failed to sign device certificate. Error: {}", e))
+                })
+                .map_err(|_| ChatError::IdentityUnavailable)
+        }
+        .into_actor(self)
+        .map(move |result, myself, _| {
+            let signature = result?;
+            myself.notice(&format!(
+                "Device [{}] paired. It will sync history with this node.",
+                caller
+            ));
+            Ok(DeviceCert {
+                user: node_id,
+                signature,
+            })
+        });
+        ActorResponse::r#async(future)
+    }
+}
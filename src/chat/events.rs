@@ -0,0 +1,71 @@
+use actix::prelude::*;
+use chrono::{DateTime, Utc};
+use futures::channel::mpsc;
+use uuid::Uuid;
+
+use ya_client::model::NodeId;
+
+use super::{Chat, Delivery};
+use crate::protocol::{NodeFacts, Structured};
+
+/// Event emitted by chat to subscribers of `ChatHandle`.
+#[derive(Clone, Debug)]
+pub enum ChatEvent {
+    /// Message received and displayed. Group is None for direct messages.
+    Message {
+        id: Uuid,
+        group: Option<String>,
+        user: String,
+        node_id: NodeId,
+        content: String,
+        structured: Option<Structured>,
+        timestamp: DateTime<Utc>,
+    },
+    Presence {
+        node_id: NodeId,
+        away: bool,
+        status: Option<String>,
+        node: Option<NodeFacts>,
+    },
+    /// Delivery state of our messages changed for recipient.
+    Delivery {
+        ids: Vec<Uuid>,
+        recipient: NodeId,
+        delivery: Delivery,
+    },
+    /// User proved his identity and was added to group.
+    UserJoined {
+        group: String,
+        user: String,
+        node_id: NodeId,
+    },
+    /// Group is advertised on market and users are discovered.
+    Subscribed {
+        group: String,
+    },
+    DiscoveryFailed {
+        group: String,
+        error: String,
+    },
+    Error(String),
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(super) struct Subscribe(pub(super) mpsc::UnboundedSender<ChatEvent>);
+
+impl Chat {
+    /// Subscribers, which dropped their streams, are removed.
+    pub(super) fn emit(&mut self, event: ChatEvent) {
+        self.subscribers
+            .retain(|subscriber| subscriber.unbounded_send(event.clone()).is_ok());
+    }
+}
+
+impl Handler<Subscribe> for Chat {
+    type Result = ();
+
+    fn handle(&mut self, msg: Subscribe, _: &mut Context<Self>) -> Self::Result {
+        self.subscribers.push(msg.0);
+    }
+}
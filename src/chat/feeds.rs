@@ -0,0 +1,74 @@
+use actix::prelude::*;
+
+use super::Chat;
+use crate::feeds::{self, FeedFetched};
+
+impl Chat {
+    pub(super) fn arm_feeds(&mut self, ctx: &mut Context<Self>) {
+        for idx in 0..self.feeds.len() {
+            self.poll_feed(idx, ctx);
+            let interval = std::time::Duration::from_secs(self.feeds[idx].interval_minutes * 60);
+            ctx.run_interval(interval, move |myself, ctx| myself.poll_feed(idx, ctx));
+        }
+    }
+
+    /// Feeds are downloaded in background thread, since curl blocks.
+    fn poll_feed(&mut self, idx: usize, ctx: &mut Context<Self>) {
+        let urls = self.feeds[idx].urls.clone();
+        let chat = ctx.address().recipient();
+        std::thread::spawn(move || {
+            for url in urls {
+                match feeds::fetch(&url) {
+                    Ok((feed, entries)) => {
+                        chat.do_send(FeedFetched {
+                            idx,
+                            url,
+                            feed,
+                            entries,
+                        })
+                        .ok();
+                    }
+                    Err(e) => log::warn!("Failed to fetch feed {}. Error: {}", url, e),
+                }
+            }
+        });
+    }
+}
+
+impl Handler<FeedFetched> for Chat {
+    type Result = ();
+
+    fn handle(&mut self, msg: FeedFetched, ctx: &mut Context<Self>) -> Self::Result {
+        let group = self.feeds[msg.idx].group.clone();
+        let idx = match self.group_index(&group) {
+            Some(idx) => idx,
+            None => {
+                log::warn!("Feed {} not posted. Group {} was left.", msg.url, group);
+                return;
+            }
+        };
+        let entries = self.seen_entries.unseen(&msg.url, msg.entries);
+        self.seen_entries
+            .save()
+            .map_err(|e| log::warn!("Failed to save seen feed entries. Error: {}", e))
+            .ok();
+
+        // Feeds list newest entries first.
+        for entry in entries.iter().rev() {
+            match self.post(idx, feeds::format(&msg.feed, entry), None, ctx) {
+                Ok(future) => {
+                    let future = future.into_actor(self).map(|result, _, _| {
+                        if let Err(e) = result {
+                            log::warn!("Failed to send feed entry. Error: {}", e);
+                        }
+                    });
+                    ctx.spawn(future);
+                }
+                Err(e) => {
+                    log::warn!("Feed entry not posted. {}", e);
+                    return;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use actix::prelude::*;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use ya_client::model::NodeId;
+use ya_service_bus::{typed as bus, RpcEndpoint, RpcEnvelope};
+
+use super::Chat;
+use crate::protocol::{ChatError, FetchQueued};
+
+impl Chat {
+    /// Asks known members of group for messages queued for us, once we are
+    /// advertised on market again. Peers offline meanwhile just fail.
+    pub(super) fn fetch_queued(&mut self, group: &str, ctx: &mut Context<Self>) {
+        let idx = match self.group_index(group) {
+            Some(idx) => idx,
+            None => return,
+        };
+        let ours = self.node_id;
+        let peers = self.groups[idx]
+            .users
+            .iter()
+            .map(|desc| desc.node_id)
+            .filter(|node_id| Some(*node_id) != ours && !self.blocklist.contains(node_id))
+            .collect::<HashSet<_>>();
+
+        for peer in peers {
+            let future = async move {
+                bus::service(format!("/net/{}/yachat", peer))
+                    .send(FetchQueued {})
+                    .await
+            }
+            .into_actor(self)
+            .map(move |result, myself, _| {
+                match result.map_err(anyhow::Error::from).and_then(|r| Ok(r?)) {
+                    Ok(0) => (),
+                    Ok(count) => myself.detail(
+                        1,
+                        &format!(
+                            "{} is resending {} queued message(s).",
+                            myself.peer_name(&peer),
+                            count
+                        ),
+                    ),
+                    Err(e) => log::debug!("Failed to fetch queued from [{}]. Error: {}", peer, e),
+                }
+            });
+            ctx.spawn(future);
+        }
+    }
+}
+
+impl Handler<RpcEnvelope<FetchQueued>> for Chat {
+    type Result = Result<usize, ChatError>;
+
+    fn handle(&mut self, msg: RpcEnvelope<FetchQueued>, ctx: &mut Context<Self>) -> Self::Result {
+        let caller = NodeId::from_str(msg.caller()).map_err(|_| ChatError::InvalidNodeId)?;
+        if self.reject_blocked(&caller) {
+            return Err(ChatError::Rejected);
+        }
+
+        // Queue holds only messages addressed to caller, so nothing else
+        // has to be checked.
+        let count = self.delivery.count(&caller);
+        if count > 0 {
+            log::info!("[{}] asked for {} queued message(s).", caller, count);
+            self.flush(caller, ctx);
+        }
+        Ok(count)
+    }
+}
@@ -0,0 +1,97 @@
+use actix::prelude::*;
+
+use super::reply::LastReceived;
+use super::Chat;
+use crate::audit::AuditKind;
+
+impl Chat {
+    /// Removes everything stored about user: messages in history of all
+    /// groups, messages queued for delivery, ratchet sessions, alias and
+    /// verification. Prints what will be removed, until confirmed.
+    pub(super) fn forget_user(
+        &mut self,
+        pattern: &str,
+        confirm: bool,
+        ctx: &mut Context<Self>,
+    ) -> anyhow::Result<()> {
+        let (name, mut node_ids) = match self.resolve_user(pattern) {
+            Ok(found) => found,
+            Err(_) => {
+                let contact = self.contacts.find(pattern)?;
+                (contact.display_name().to_string(), vec![contact.node_id])
+            }
+        };
+        let users = node_ids
+            .iter()
+            .filter_map(|node_id| self.find_user(node_id))
+            .map(|desc| desc.user_id())
+            .collect::<Vec<_>>();
+        for user in users {
+            if !node_ids.contains(&user) {
+                node_ids.push(user);
+            }
+        }
+
+        if !confirm {
+            self.console.print(&format!(
+                "This removes messages of {} from history of all groups, messages queued for \
+                 them, encryption sessions, profile, alias and verification. Plaintext chat logs aren't \
+                 edited. Type /forget {} confirm to continue.",
+                name, pattern
+            ));
+            return Ok(());
+        }
+
+        let removed = self
+            .groups
+            .iter_mut()
+            .map(|group| group.forget(&node_ids))
+            .collect::<Vec<_>>();
+        let mut queued = 0;
+        for node_id in node_ids.iter() {
+            queued += self
+                .delivery
+                .remove(node_id)
+                .map_or(0, |batches| batches.len());
+            self.flushing.remove(node_id);
+            self.sessions.forget(node_id);
+            self.presence.remove(node_id);
+            self.compat_warned.remove(node_id);
+        }
+        self.conversations.remove(&node_ids);
+        self.reappeared.forget(&node_ids);
+        self.audit.forget(&node_ids)?;
+        self.contacts.remove(&node_ids)?;
+        self.profiles.remove(&node_ids)?;
+        let forgotten = |last: &Option<LastReceived>| {
+            last.as_ref()
+                .map_or(false, |last| node_ids.contains(&last.sender))
+        };
+        if forgotten(&self.last_received) {
+            self.last_received = None;
+        }
+        if forgotten(&self.last_direct) {
+            self.last_direct = None;
+        }
+
+        log::info!("Forgot user {}.", name);
+        self.audit(AuditKind::Roster, None, None, format!("Forgot {}.", name));
+        // Messages not loaded in memory are removed by group workers.
+        let future = async move {
+            let mut messages = 0;
+            for removed in removed {
+                messages += removed.await;
+            }
+            messages
+        }
+        .into_actor(self)
+        .map(move |messages, myself, _| {
+            myself.console.print(&format!(
+                "Forgot {}. Removed {} messages and {} queued batches.",
+                name, messages, queued
+            ))
+        });
+        ctx.spawn(future);
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+use actix::prelude::*;
+use std::path::Path;
+use std::sync::Arc;
+
+use ya_client::model::NodeId;
+use ya_service_bus::RpcMessage;
+
+use super::channels::Channels;
+use super::muting::Muted;
+use super::paid::PaidGroup;
+use super::send_message;
+use super::worker::{AppendHistory, ForgetSenders, GroupWorker, Prune, SaveRoster};
+use crate::encryption::Cipher;
+use crate::history::{History, HistoryEntry};
+use crate::pins::Pins;
+use crate::protocol::ChatError;
+use crate::retention::Retention;
+use crate::room::RoomDefinition;
+use crate::roster::Roster;
+use crate::storage::Storage;
+
+/// State of single group we joined. Each group has its own roster,
+/// history and pins, stored separately.
+pub(super) struct Group {
+    pub(super) name: String,
+    /// From imported group definition.
+    pub(super) topic: Option<String>,
+    pub(super) users: Roster,
+    pub(super) history: History,
+    pub(super) pins: Pins,
+    /// Creator of group from imported definition.
+    pub(super) owner: Option<NodeId>,
+    /// Senders allowed to post in announcement-only group. None for open groups.
+    pub(super) announcers: Option<Vec<NodeId>>,
+    pub(super) announcers_configured: bool,
+    /// Limit of users in roster, including us. None for unlimited groups.
+    pub(super) max_members: Option<usize>,
+    pub(super) max_members_configured: bool,
+    /// Some member refused us, because group is full.
+    pub(super) rejected_full: bool,
+    pub(super) paid: Option<PaidGroup>,
+    pub(super) muted: Option<Muted>,
+    pub(super) channels: Channels,
+    /// Our Offer and Demand are on market.
+    pub(super) subscribed: bool,
+    /// Writes history and roster, so disk access doesn't block `Chat`.
+    pub(super) worker: Addr<GroupWorker>,
+}
+
+impl Group {
+    pub(super) fn load(
+        data_dir: &Path,
+        name: &str,
+        cipher: Option<&Cipher>,
+        storage: &Arc<dyn Storage>,
+    ) -> anyhow::Result<Group> {
+        // Announcers from imported definition count as configured locally.
+        let definition = RoomDefinition::load(data_dir, name)?.unwrap_or_default();
+        let announcers = match definition.announcers.is_empty() {
+            true => None,
+            false => Some(definition.announcers),
+        };
+        Ok(Group {
+            name: name.to_string(),
+            topic: definition.topic,
+            owner: definition.owner,
+            users: Roster::load(storage.as_ref(), name)?,
+            history: History::load(storage.as_ref(), name)?,
+            worker: GroupWorker::spawn(name, storage.clone()),
+            pins: Pins::load(data_dir, name, cipher.cloned())?,
+            announcers_configured: announcers.is_some(),
+            announcers,
+            max_members_configured: definition.max_members.is_some(),
+            max_members: definition.max_members,
+            rejected_full: false,
+            paid: None,
+            muted: None,
+            channels: Channels::default(),
+            subscribed: false,
+        })
+    }
+
+    /// Sends control message to all users of group. Unlike text messages,
+    /// it isn't queued, when user is unreachable.
+    pub(super) fn broadcast<M>(&self, msg: M)
+    where
+        M: RpcMessage<Item = (), Error = ChatError> + Clone,
+    {
+        for user in self.users.iter() {
+            let node_id = user.node_id;
+            let msg = msg.clone();
+            Arbiter::spawn(async move {
+                if let Err(e) = send_message(node_id, msg).await {
+                    log::warn!("Failed to send {} to [{}]. Error: {}", M::ID, node_id, e);
+                }
+            });
+        }
+    }
+
+    /// No more users can join. Users already in roster can always return.
+    pub(super) fn is_full(&self) -> bool {
+        self.max_members
+            .map_or(false, |max_members| self.users.len() + 1 >= max_members)
+    }
+
+    pub(super) fn contains(&self, node_id: &NodeId) -> bool {
+        self.users.iter().any(|desc| &desc.node_id == node_id)
+    }
+
+    pub(super) fn save_roster(&self) {
+        self.worker.do_send(SaveRoster(self.users.clone()));
+    }
+
+    pub(super) fn record(&mut self, entry: HistoryEntry) {
+        self.history.push(entry.clone());
+        self.worker.do_send(AppendHistory(vec![entry]));
+    }
+
+    /// Adds history synced from other device. Returns number of new entries.
+    pub(super) fn merge_history(&mut self, entries: Vec<HistoryEntry>) -> usize {
+        let added = self.history.merge(entries);
+        let count = added.len();
+        if count > 0 {
+            self.worker.do_send(AppendHistory(added));
+        }
+        count
+    }
+
+    /// Removes users from roster and their messages from history, also
+    /// ones older than loaded in memory. Resolves to number of messages
+    /// removed from storage.
+    pub(super) fn forget(&mut self, node_ids: &[NodeId]) -> impl Future<Output = usize> {
+        let mut removed = false;
+        for node_id in node_ids {
+            removed |= self.users.remove(node_id);
+        }
+        if removed {
+            self.save_roster();
+        }
+
+        self.history.forget(node_ids);
+        let request = self.worker.send(ForgetSenders(node_ids.to_vec()));
+        let name = self.name.clone();
+        async move {
+            request.await.unwrap_or_else(|e| {
+                log::error!("Failed to forget messages in {}. Error: {}", name, e);
+                0
+            })
+        }
+    }
+
+    /// Applies retention to history in memory and in storage.
+    pub(super) fn prune(&mut self, retention: &Retention) {
+        if retention.is_unlimited() {
+            return;
+        }
+        self.history.prune(retention.cutoff(), retention.messages);
+        self.worker.do_send(Prune(retention.clone()));
+    }
+}
@@ -0,0 +1,120 @@
+use actix::prelude::*;
+use anyhow::anyhow;
+use futures::channel::mpsc;
+use futures::Stream;
+
+use super::events::Subscribe;
+use super::{Chat, ChatEvent};
+use crate::discover::Shutdown;
+
+/// Sends text to group, we are in.
+#[derive(Message)]
+#[rtype(result = "Result<(), anyhow::Error>")]
+struct Post {
+    group: String,
+    text: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<(), anyhow::Error>")]
+struct Join {
+    group: String,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<(), anyhow::Error>")]
+struct Leave {
+    group: String,
+}
+
+/// Started chat for embedders. Events are received as typed stream and
+/// groups are driven with handle methods, so embedders don't need to
+/// implement actix handlers of internal messages.
+#[derive(Clone)]
+pub struct ChatHandle {
+    addr: Addr<Chat>,
+}
+
+impl ChatHandle {
+    pub fn start(chat: Chat) -> ChatHandle {
+        ChatHandle { addr: chat.start() }
+    }
+
+    /// For messages, which don't have handle methods, like `Reload`.
+    pub fn addr(&self) -> &Addr<Chat> {
+        &self.addr
+    }
+
+    /// Events emitted from now on. Stream ends, when chat stops.
+    pub fn subscribe(&self) -> impl Stream<Item = ChatEvent> {
+        let (sender, events) = mpsc::unbounded();
+        self.addr.do_send(Subscribe(sender));
+        events
+    }
+
+    /// Resolves, when message was sent to all online users of group.
+    pub async fn send(&self, group: &str, text: &str) -> anyhow::Result<()> {
+        self.addr
+            .send(Post {
+                group: group.to_string(),
+                text: text.to_string(),
+            })
+            .await?
+    }
+
+    /// Joining group, we are already in, does nothing.
+    pub async fn join(&self, group: &str) -> anyhow::Result<()> {
+        self.addr
+            .send(Join {
+                group: group.to_string(),
+            })
+            .await?
+    }
+
+    pub async fn leave(&self, group: &str) -> anyhow::Result<()> {
+        self.addr
+            .send(Leave {
+                group: group.to_string(),
+            })
+            .await?
+    }
+
+    /// Saves session and undelivered messages and unsubscribes from market.
+    pub async fn shutdown(&self) -> anyhow::Result<()> {
+        self.addr.send(Shutdown {}).await?
+    }
+}
+
+impl Handler<Post> for Chat {
+    type Result = ActorResponse<Self, (), anyhow::Error>;
+
+    fn handle(&mut self, msg: Post, ctx: &mut Context<Self>) -> Self::Result {
+        let post = self
+            .group_index(&msg.group)
+            .ok_or_else(|| anyhow!("We aren't in group {}.", msg.group))
+            .and_then(|idx| self.post(idx, msg.text, None, ctx));
+        match post {
+            Ok(future) => ActorResponse::r#async(future.into_actor(self)),
+            Err(e) => ActorResponse::reply(Err(e)),
+        }
+    }
+}
+
+impl Handler<Join> for Chat {
+    type Result = anyhow::Result<()>;
+
+    fn handle(&mut self, msg: Join, ctx: &mut Context<Self>) -> Self::Result {
+        if self.group_index(&msg.group).is_none() {
+            self.join_group(&msg.group, ctx)?;
+        }
+        Ok(())
+    }
+}
+
+impl Handler<Leave> for Chat {
+    type Result = anyhow::Result<()>;
+
+    fn handle(&mut self, msg: Leave, ctx: &mut Context<Self>) -> Self::Result {
+        self.leave_group(&msg.group, ctx)
+    }
+}
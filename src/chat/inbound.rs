@@ -0,0 +1,283 @@
+use actix::prelude::*;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use ya_client::model::NodeId;
+
+use super::channels::channel_tag;
+use super::{Chat, ChatEvent, LastReceived};
+use crate::filter::Incoming;
+use crate::history::HistoryEntry;
+use crate::hooks::{Event, EventData};
+use crate::layout;
+use crate::protocol::{ChatError, SendText, TextMessage};
+use crate::structured;
+use crate::update;
+
+/// Messages waiting for display from single peer. Above this limit
+/// we stop accepting messages from the peer.
+const MAX_QUEUED: usize = 1000;
+/// Messages displayed before giving other actor messages chance to run.
+const DRAIN_BATCH: usize = 20;
+
+pub(super) struct Inbound {
+    /// None for direct messages.
+    group: Option<String>,
+    /// Name resolved from roster and contacts.
+    display_name: String,
+    /// Name reported by sender.
+    user: String,
+    /// Sent by away responder of the peer.
+    auto_reply: bool,
+    /// Resent by peer from his queue, after we reappeared.
+    delayed: bool,
+    text: TextMessage,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(super) struct DrainInbound;
+
+impl Chat {
+    /// Queues received messages. Queues are drained round-robin, so peer
+    /// sending huge batches can't delay messages from others.
+    pub(super) fn enqueue(
+        &mut self,
+        caller: NodeId,
+        group: Option<String>,
+        display_name: String,
+        mut sends: SendText,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), ChatError> {
+        if self.inbound.get(&caller).map_or(0, VecDeque::len) >= MAX_QUEUED {
+            log::warn!("Inbound queue of [{}] is full. Rejecting messages.", caller);
+            return Err(ChatError::QueueFull);
+        }
+
+        // Messages are recorded as received only after they are queued, so
+        // rejected ones are accepted, when sender retries.
+        let received = &mut self.received;
+        sends
+            .messages
+            .retain(|text| !received.contains(&caller, text));
+        if sends.messages.is_empty() {
+            log::debug!("Dropped duplicated messages from [{}].", caller);
+            return Ok(());
+        }
+
+        // Whole batch is rejected, so sender queues it and retries, instead
+        // of marking messages, we dropped, as delivered. Batch bigger than
+        // the limit is accepted into empty queue, otherwise it would never
+        // get through.
+        let queued = self.inbound.get(&caller).map_or(0, VecDeque::len);
+        if queued > 0 && sends.messages.len() > MAX_QUEUED - queued {
+            log::warn!(
+                "{} messages from [{}] don't fit in inbound queue. Rejecting batch.",
+                sends.messages.len(),
+                caller
+            );
+            return Err(ChatError::QueueFull);
+        }
+
+        let received = &mut self.received;
+        sends.messages.retain(|text| received.insert(&caller, text));
+
+        let queue = self.inbound.entry(caller).or_default();
+        let was_empty = queue.is_empty();
+        let user = sends.user;
+        let auto_reply = sends.auto_reply;
+        let delayed = sends.delayed;
+        queue.extend(sends.messages.into_iter().map(|text| Inbound {
+            group: group.clone(),
+            display_name: display_name.clone(),
+            user: user.clone(),
+            auto_reply,
+            delayed,
+            // Received batch isn't shared, so contents aren't copied.
+            text: Arc::try_unwrap(text).unwrap_or_else(|text| (*text).clone()),
+        }));
+
+        if was_empty {
+            self.inbound_order.push_back(caller);
+        }
+        if !self.draining {
+            self.draining = true;
+            ctx.notify(DrainInbound);
+        }
+        Ok(())
+    }
+
+    fn display(&mut self, sender: NodeId, inbound: Inbound, ctx: &mut Context<Self>) {
+        let text = inbound.text;
+        let group = inbound.group.as_deref();
+        let filtered = match self.filters.apply(&Incoming {
+            group,
+            sender,
+            user: &inbound.user,
+            content: &text.content,
+        }) {
+            Some(filtered) => filtered,
+            None => {
+                log::info!("Filtered out message from [{}].", sender);
+                return;
+            }
+        };
+        if !self.screen(sender, group, &inbound.display_name, &text.content) {
+            return;
+        }
+
+        // Groups have history, direct messages are only displayed.
+        if let Some(group) = &inbound.group {
+            self.record(HistoryEntry {
+                id: text.id,
+                group: group.clone(),
+                sender: Some(sender),
+                user: inbound.user.clone(),
+                content: text.content.clone(),
+                timestamp: text.timestamp,
+                channel: text.channel.clone(),
+            });
+            self.relay(group, &inbound.user, &text, ctx);
+            if self.count_muted(group, &text.content) {
+                return;
+            }
+            if let Some(idx) = self.group_index(group) {
+                if !self.groups[idx].channels.receive(text.channel.as_deref()) {
+                    return;
+                }
+            }
+        }
+
+        let mut tag = match &inbound.group {
+            Some(group) => self.group_tag(group) + &channel_tag(text.channel.as_deref()),
+            None => " [direct]".to_string(),
+        };
+        tag.push_str(&self.message_context(group, &text));
+        if inbound.delayed {
+            tag.push_str(" (delayed)");
+        }
+        for reason in filtered.flags.iter() {
+            tag.push_str(&format!(" (flagged: {})", reason));
+        }
+        let mut name = format!(
+            "{}{}",
+            layout::isolate(&inbound.display_name),
+            self.badge(&sender)
+        );
+        if let Some(relayed) = &text.relayed {
+            name = format!(
+                "{} [{} via {}]",
+                layout::isolate(&relayed.user),
+                layout::isolate(&relayed.group),
+                name
+            );
+        }
+        if group.is_some() {
+            self.mark_time(&text.timestamp);
+        }
+        let header = self.message_header(&tag, &text, &name, Some(sender));
+        let body = self.render_body(&text, &filtered.content);
+        if let Some(structured) = &text.structured {
+            if !structured::known(&structured.schema) {
+                let warning = format!(
+                    "{} sends {} content, which yachat {} can't show. Consider updating.",
+                    inbound.display_name,
+                    structured.schema,
+                    update::VERSION
+                );
+                self.warn_compatibility(sender, &warning);
+            }
+        }
+        let message = self.format_message(&header, &body);
+        match group {
+            Some(_) => self.console.print(&message),
+            None => self.show_direct(sender, &inbound.display_name, message),
+        }
+
+        let mut data = EventData::new(match group {
+            Some(_) => Event::Message,
+            None => Event::Direct,
+        });
+        data.group = group.map(str::to_string);
+        data.user = Some(inbound.display_name.clone());
+        data.node_id = Some(sender);
+        data.text = Some(text.content.clone());
+        data.structured = text.structured.clone();
+        self.fire_data(data);
+        self.emit(ChatEvent::Message {
+            id: text.id,
+            group: inbound.group.clone(),
+            user: inbound.display_name.clone(),
+            node_id: sender,
+            content: text.content.clone(),
+            structured: text.structured.clone(),
+            timestamp: text.timestamp,
+        });
+        if group.is_some() && self.renderer.mentions_me(&text.content) {
+            let name = &inbound.display_name;
+            self.fire(Event::Mention, group, name, sender, Some(&text.content));
+        }
+        if self.renderer.watched_in(&filtered.content).is_some() {
+            let name = &inbound.display_name;
+            self.fire(Event::Watch, group, name, sender, Some(&text.content));
+        }
+        if !inbound.auto_reply {
+            self.auto_reply(sender, group, &inbound.display_name, ctx);
+            // Delayed messages are out of context already.
+            if !inbound.delayed {
+                self.run_responders(sender, group, &inbound.display_name, &text, ctx);
+                self.dispatch_bot_command(sender, group, &inbound.display_name, &text.content);
+                #[cfg(feature = "wasm")]
+                self.run_wasm_plugins(sender, group, &inbound.display_name, &text, ctx);
+            }
+        }
+        if group.is_none() {
+            self.last_direct = Some(LastReceived {
+                group: None,
+                id: text.id,
+                sender,
+            });
+        }
+        self.last_received = Some(LastReceived {
+            group: inbound.group,
+            id: text.id,
+            sender,
+        });
+    }
+}
+
+impl Handler<DrainInbound> for Chat {
+    type Result = ();
+
+    fn handle(&mut self, _: DrainInbound, ctx: &mut Context<Self>) -> Self::Result {
+        for _ in 0..DRAIN_BATCH {
+            let sender = match self.inbound_order.pop_front() {
+                Some(sender) => sender,
+                None => break,
+            };
+
+            let queue = match self.inbound.get_mut(&sender) {
+                Some(queue) => queue,
+                None => continue,
+            };
+            let inbound = queue.pop_front();
+            match queue.is_empty() {
+                true => {
+                    self.inbound.remove(&sender);
+                }
+                false => self.inbound_order.push_back(sender),
+            }
+
+            if let Some(inbound) = inbound {
+                self.display(sender, inbound, ctx);
+            }
+        }
+
+        // Continue through mailbox, so messages arriving meanwhile get queued
+        // and take part in round-robin.
+        match self.inbound_order.is_empty() {
+            true => self.draining = false,
+            false => ctx.notify(DrainInbound),
+        }
+    }
+}
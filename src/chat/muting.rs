@@ -0,0 +1,88 @@
+use actix::prelude::*;
+use anyhow::anyhow;
+use chrono::{DateTime, Local, Utc};
+
+use super::{Chat, TIMESTAMP_FORMAT};
+
+/// Group muted with `/mute-group`. Messages are still recorded in history,
+/// only counted instead of being displayed.
+pub(super) struct Muted {
+    until: DateTime<Utc>,
+    messages: usize,
+    mentions: usize,
+}
+
+impl Chat {
+    pub(super) fn mute_group(
+        &mut self,
+        name: &str,
+        until: DateTime<Utc>,
+        ctx: &mut Context<Self>,
+    ) -> anyhow::Result<()> {
+        let idx = self
+            .group_index(name)
+            .ok_or_else(|| anyhow!("You aren't in group {}.", name))?;
+        let (messages, mentions) = match &self.groups[idx].muted {
+            Some(muted) => (muted.messages, muted.mentions),
+            None => (0, 0),
+        };
+        self.groups[idx].muted = Some(Muted {
+            until,
+            messages,
+            mentions,
+        });
+        self.notice(&format!(
+            "Group {} muted until {}.",
+            name,
+            until.with_timezone(&Local).format(TIMESTAMP_FORMAT)
+        ));
+
+        let name = name.to_string();
+        let delay = (until - Utc::now()).to_std().unwrap_or_default();
+        ctx.run_later(delay, move |myself, _| {
+            // Group could be muted again for longer meanwhile.
+            let expired = myself
+                .group_index(&name)
+                .and_then(|idx| myself.groups[idx].muted.as_ref())
+                .map_or(false, |muted| muted.until <= Utc::now());
+            if expired {
+                myself.unmute_group(&name).ok();
+            }
+        });
+        Ok(())
+    }
+
+    /// Displays summary of messages, which arrived while group was muted.
+    pub(super) fn unmute_group(&mut self, name: &str) -> anyhow::Result<()> {
+        let idx = self
+            .group_index(name)
+            .ok_or_else(|| anyhow!("You aren't in group {}.", name))?;
+        let muted = self.groups[idx]
+            .muted
+            .take()
+            .ok_or_else(|| anyhow!("Group {} isn't muted.", name))?;
+        self.notice(&format!(
+            "Group {} unmuted. {} message(s), {} mention(s) while muted.",
+            name, muted.messages, muted.mentions
+        ));
+        Ok(())
+    }
+
+    /// Counts message, if group is muted. Returns true, if message
+    /// shouldn't be displayed.
+    pub(super) fn count_muted(&mut self, group: &str, content: &str) -> bool {
+        let mention = self.renderer.mentions_me(content);
+        let muted = match self
+            .group_index(group)
+            .and_then(|idx| self.groups[idx].muted.as_mut())
+        {
+            Some(muted) => muted,
+            None => return false,
+        };
+        muted.messages += 1;
+        if mention {
+            muted.mentions += 1;
+        }
+        true
+    }
+}
@@ -0,0 +1,279 @@
+use actix::prelude::*;
+use anyhow::bail;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use ya_client::model::NodeId;
+use ya_service_bus::RpcEnvelope;
+
+use super::group::Group;
+use super::{send_message, Chat};
+use crate::audit::AuditKind;
+use crate::membership::{JoinGroup, MembershipChanged, OfferMembership, Revoke};
+use crate::protocol::{ChatError, Members};
+
+/// Paid group requires membership Agreement with group owner. Owner
+/// tracks Agreements and broadcasts list of members to everyone else.
+pub(super) enum PaidGroup {
+    Owner {
+        fee: String,
+        members: HashSet<NodeId>,
+    },
+    Member {
+        owner: NodeId,
+        fee: String,
+        joined: bool,
+        members: Vec<NodeId>,
+    },
+}
+
+impl Group {
+    /// Messages in paid group are accepted only from owner and members.
+    pub(super) fn admitted(&self, sender: &NodeId) -> bool {
+        match &self.paid {
+            None => true,
+            Some(PaidGroup::Owner { members, .. }) => members.contains(sender),
+            Some(PaidGroup::Member { owner, members, .. }) => {
+                owner == sender || members.contains(sender)
+            }
+        }
+    }
+
+    /// Whether we can post in the group.
+    pub(super) fn joined(&self) -> bool {
+        match &self.paid {
+            Some(PaidGroup::Member { joined, .. }) => *joined,
+            _ => true,
+        }
+    }
+
+    fn members(&self) -> Option<Members> {
+        match &self.paid {
+            Some(PaidGroup::Owner { members, .. }) => Some(Members {
+                group: self.name.clone(),
+                members: members.iter().cloned().collect(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Owner sends list of members to user, who just appeared.
+    pub(super) fn send_members(&self, node_id: NodeId) {
+        if let Some(members) = self.members() {
+            Arbiter::spawn(async move {
+                if let Err(e) = send_message(node_id, members).await {
+                    log::warn!("Failed to send members to [{}]. Error: {}", node_id, e);
+                }
+            });
+        }
+    }
+}
+
+impl Chat {
+    /// Only single group can be paid in one session, so we look for
+    /// the first group we own.
+    pub(super) fn offer_membership(&mut self, ctx: &mut Context<Self>) {
+        let (group, fee) = match self.groups.iter().find_map(|group| match &group.paid {
+            Some(PaidGroup::Owner { fee, .. }) => Some((group.name.clone(), fee.clone())),
+            _ => None,
+        }) {
+            Some(owned) => owned,
+            None => return,
+        };
+
+        let msg = OfferMembership {
+            group,
+            fee,
+            notify: ctx.address().recipient(),
+        };
+        let future = self
+            .membership
+            .send(msg)
+            .into_actor(self)
+            .map(|result, myself, _| {
+                if let Err(e) = result.map_err(anyhow::Error::from).and_then(|r| r) {
+                    myself
+                        .console
+                        .print(&format!("Failed to offer paid membership. Error: {}", e));
+                }
+            });
+        ctx.spawn(future);
+    }
+
+    /// First user advertising fee is treated as group owner.
+    pub(super) fn adopt_fee(&mut self, group: usize, owner: NodeId, fee: String, user: &str) {
+        match &self.groups[group].paid {
+            None => {
+                self.notice(&format!(
+                    "Group {} is paid. Membership costs {} GLM paid to {}. Type /join to conclude Agreement.",
+                    self.groups[group].name, fee, user
+                ));
+                self.groups[group].paid = Some(PaidGroup::Member {
+                    owner,
+                    fee,
+                    joined: false,
+                    members: vec![],
+                });
+            }
+            Some(PaidGroup::Member { owner: known, .. }) if *known != owner => {
+                log::warn!(
+                    "User {} [{}] claims ownership of paid group owned by [{}]. Ignoring.",
+                    user,
+                    owner,
+                    known
+                );
+            }
+            _ => (),
+        }
+    }
+
+    /// Joins paid active group.
+    pub(super) fn join(&mut self, ctx: &mut Context<Self>) -> anyhow::Result<()> {
+        let (owner, fee) = match &self.group().paid {
+            Some(PaidGroup::Member {
+                joined: false,
+                owner,
+                fee,
+                ..
+            }) => (*owner, fee.clone()),
+            Some(PaidGroup::Member { joined: true, .. }) => bail!("You are already a member."),
+            Some(PaidGroup::Owner { .. }) => bail!("You are owner of this group."),
+            None => bail!("This group is free to join."),
+        };
+
+        let msg = JoinGroup {
+            group: self.group().name.clone(),
+            owner,
+            fee: fee.clone(),
+            notify: ctx.address().recipient(),
+        };
+        let future = self
+            .membership
+            .send(msg)
+            .into_actor(self)
+            .map(|result, myself, _| {
+                if let Err(e) = result.map_err(anyhow::Error::from).and_then(|r| r) {
+                    myself
+                        .console
+                        .print(&format!("Failed to join group. Error: {}", e));
+                }
+            });
+        ctx.spawn(future);
+
+        self.console.print(&format!(
+            "Negotiating membership Agreement. Invoice up to {} GLM will be paid.",
+            fee
+        ));
+        Ok(())
+    }
+
+    pub(super) fn revoke(&mut self, pattern: &str, ctx: &mut Context<Self>) -> anyhow::Result<()> {
+        match &self.group().paid {
+            Some(PaidGroup::Owner { .. }) => (),
+            _ => bail!("Only group owner can revoke membership."),
+        }
+
+        let member = self.contacts.find(pattern)?.node_id;
+        let group = self.group().name.clone();
+        let future = self
+            .membership
+            .send(Revoke { member })
+            .into_actor(self)
+            .map(move |result, myself, _| {
+                match result.map_err(anyhow::Error::from).and_then(|r| r) {
+                    Ok(()) => {
+                        let text = format!("Revoked membership of [{}].", member);
+                        myself.audit(AuditKind::Moderation, Some(&group), Some(member), text);
+                    }
+                    Err(e) => myself
+                        .console
+                        .print(&format!("Failed to revoke membership. Error: {}", e)),
+                }
+            });
+        ctx.spawn(future);
+        Ok(())
+    }
+}
+
+impl Handler<MembershipChanged> for Chat {
+    type Result = ();
+
+    fn handle(&mut self, msg: MembershipChanged, _: &mut Context<Self>) -> Self::Result {
+        let idx = match self.group_index(&msg.group) {
+            Some(idx) => idx,
+            None => return,
+        };
+
+        let name = self.groups[idx]
+            .users
+            .iter()
+            .find(|desc| desc.node_id == msg.node_id)
+            .map(|desc| desc.name.clone())
+            .unwrap_or_default();
+        let name = self.contacts.display_name(&msg.node_id, &name);
+
+        let notice = match &mut self.groups[idx].paid {
+            Some(PaidGroup::Owner { members, .. }) => {
+                match msg.active {
+                    true => members.insert(msg.node_id),
+                    false => members.remove(&msg.node_id),
+                };
+                match msg.active {
+                    true => format!("{} joined paid group {}.", name, msg.group),
+                    false => format!("Membership of {} ended.", name),
+                }
+            }
+            Some(PaidGroup::Member { owner, joined, .. }) if *owner == msg.node_id => {
+                *joined = msg.active;
+                match msg.active {
+                    true => format!(
+                        "Membership Agreement in group {} approved. You can post now.",
+                        msg.group
+                    ),
+                    false => format!(
+                        "Your membership in group {} expired or was revoked. Type /join to join again.",
+                        msg.group
+                    ),
+                }
+            }
+            _ => return,
+        };
+        self.notice(&notice);
+        let (group, node_id) = (msg.group.clone(), Some(msg.node_id));
+        self.audit(AuditKind::Subscription, Some(&group), node_id, notice);
+
+        let group = &self.groups[idx];
+        if let Some(members) = group.members() {
+            group.broadcast(members);
+        }
+    }
+}
+
+impl Handler<RpcEnvelope<Members>> for Chat {
+    type Result = ActorResponse<Self, (), ChatError>;
+
+    fn handle(&mut self, msg: RpcEnvelope<Members>, _: &mut Context<Self>) -> Self::Result {
+        let caller = match NodeId::from_str(msg.caller()) {
+            Ok(caller) => caller,
+            Err(_) => return ActorResponse::reply(Err(ChatError::InvalidNodeId)),
+        };
+        if self.reject_blocked(&caller) {
+            return ActorResponse::reply(Err(ChatError::Rejected));
+        }
+
+        let msg = msg.into_inner();
+        let idx = match self.group_index(&msg.group) {
+            Some(idx) => idx,
+            None => return ActorResponse::reply(Err(ChatError::UnknownGroup)),
+        };
+
+        match &mut self.groups[idx].paid {
+            Some(PaidGroup::Member { owner, members, .. }) if *owner == caller => {
+                log::info!("Got {} members of paid group.", msg.members.len());
+                *members = msg.members;
+                ActorResponse::reply(Ok(()))
+            }
+            _ => ActorResponse::reply(Err(ChatError::Rejected)),
+        }
+    }
+}
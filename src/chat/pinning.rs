@@ -0,0 +1,101 @@
+use actix::prelude::*;
+use anyhow::anyhow;
+use std::str::FromStr;
+
+use ya_client::model::NodeId;
+use ya_service_bus::RpcEnvelope;
+
+use super::Chat;
+use crate::audit::AuditKind;
+use crate::pins::{format_pin, Pin};
+use crate::protocol::{ChatError, PinMessage};
+
+impl Chat {
+    /// Pins message from history of active group with given id prefix
+    /// (or the last one) and announces it to group.
+    pub(super) fn pin(&mut self, pattern: Option<String>) -> anyhow::Result<()> {
+        let group = self.group();
+        let entry = match &pattern {
+            Some(pattern) => group.history.find(pattern),
+            None => group.history.last(),
+        }
+        .ok_or_else(|| anyhow!("No message to pin."))?;
+
+        let msg = PinMessage {
+            group: group.name.clone(),
+            message_id: entry.id,
+            user: entry.user.clone(),
+            content: entry.content.clone(),
+            timestamp: entry.timestamp,
+            pinned_by: self.me.clone(),
+        };
+        self.store_pin(self.active, &msg, "You")?;
+        self.group().broadcast(msg);
+        Ok(())
+    }
+
+    pub(super) fn print_pins(&mut self, group: usize) {
+        let group = &self.groups[group];
+        let listing = match group.pins.is_empty() {
+            true => "No pinned messages.".to_string(),
+            false => format!(
+                "Pinned messages{}:\n{}",
+                self.group_tag(&group.name),
+                group.pins.list()
+            ),
+        };
+        self.console.print(&listing);
+    }
+
+    fn store_pin(&mut self, group: usize, msg: &PinMessage, pinned_by: &str) -> anyhow::Result<()> {
+        let pin = Pin {
+            message_id: msg.message_id,
+            user: msg.user.clone(),
+            content: msg.content.clone(),
+            timestamp: msg.timestamp,
+            pinned_by: pinned_by.to_string(),
+        };
+
+        if self.groups[group].pins.add(pin.clone())? {
+            let text = format!("{} pinned message of {}.", pinned_by, msg.user);
+            self.audit(AuditKind::Moderation, Some(&msg.group), None, text);
+            self.notice(&format!(
+                "{} pinned message{}",
+                pinned_by,
+                self.group_tag(&msg.group)
+            ));
+            self.console.print(&format_pin(&pin));
+        }
+        Ok(())
+    }
+}
+
+impl Handler<RpcEnvelope<PinMessage>> for Chat {
+    type Result = ActorResponse<Self, (), ChatError>;
+
+    fn handle(&mut self, msg: RpcEnvelope<PinMessage>, _: &mut Context<Self>) -> Self::Result {
+        let caller = match NodeId::from_str(msg.caller()) {
+            Ok(caller) => caller,
+            Err(_) => return ActorResponse::reply(Err(ChatError::InvalidNodeId)),
+        };
+        if self.reject_blocked(&caller) {
+            return ActorResponse::reply(Err(ChatError::Rejected));
+        }
+
+        let idx = match self.group_index(&msg.group) {
+            Some(idx) => idx,
+            None => return ActorResponse::reply(Err(ChatError::UnknownGroup)),
+        };
+
+        let group = &self.groups[idx];
+        if !group.may_post(&caller) || !group.admitted(&caller) {
+            return ActorResponse::reply(Err(ChatError::Rejected));
+        }
+
+        let pinned_by = self.contacts.display_name(&caller, &msg.pinned_by);
+        if let Err(e) = self.store_pin(idx, &msg, &pinned_by) {
+            log::error!("Failed to store pinned message. Error: {}", e);
+        }
+        ActorResponse::reply(Ok(()))
+    }
+}
@@ -0,0 +1,105 @@
+use actix::prelude::*;
+
+use super::Chat;
+use crate::hooks::EventData;
+use crate::plugin::{FromPlugin, PluginCommand, PluginExited};
+use crate::protocol::Structured;
+
+impl Chat {
+    pub(super) fn start_plugin(&mut self, idx: usize, ctx: &mut Context<Self>) {
+        let (commands, exited) = (ctx.address().recipient(), ctx.address().recipient());
+        let plugin = &mut self.plugins[idx];
+        match plugin.spawn(idx, commands, exited) {
+            Ok(()) => log::info!("Started plugin {}.", plugin.path.display()),
+            Err(e) => {
+                let path = plugin.path.display().to_string();
+                self.notice(&format!("Failed to start plugin {}. Error: {}", path, e));
+                self.restart_plugin(idx, ctx);
+            }
+        }
+    }
+
+    fn restart_plugin(&mut self, idx: usize, ctx: &mut Context<Self>) {
+        let delay = self.plugins[idx].restart_delay();
+        ctx.run_later(delay, move |myself, ctx| myself.start_plugin(idx, ctx));
+    }
+
+    pub(super) fn notify_plugins(&self, data: &EventData) {
+        if self.plugins.is_empty() {
+            return;
+        }
+        match serde_json::to_string(data) {
+            Ok(event) => self.plugins.iter().for_each(|plugin| plugin.send(&event)),
+            Err(e) => log::warn!("Failed to serialize event for plugins. Error: {}", e),
+        }
+    }
+
+    pub(super) fn run_plugin_command(
+        &mut self,
+        command: PluginCommand,
+        ctx: &mut Context<Self>,
+    ) -> anyhow::Result<()> {
+        match command {
+            PluginCommand::Send { group, text } => {
+                let idx = self.plugin_group(group)?;
+                let future = self.post(idx, text, None, ctx)?;
+                let future = future.into_actor(self).map(|result, _, _| {
+                    if let Err(e) = result {
+                        log::warn!("Failed to send plugin message. Error: {}", e);
+                    }
+                });
+                ctx.spawn(future);
+            }
+            PluginCommand::Structured {
+                group,
+                schema,
+                data,
+                text,
+            } => {
+                let idx = self.plugin_group(group)?;
+                let structured = Structured { schema, data };
+                let future = self.post_structured(idx, structured, text, ctx)?;
+                let future = future.into_actor(self).map(|result, _, _| {
+                    if let Err(e) = result {
+                        log::warn!("Failed to send plugin message. Error: {}", e);
+                    }
+                });
+                ctx.spawn(future);
+            }
+            PluginCommand::Direct { user, text } => self.send_direct(&user, text, ctx)?,
+            PluginCommand::Status { text: Some(text) } => self.go_away(Some(text)),
+            PluginCommand::Status { text: None } => self.come_back(),
+            PluginCommand::Register { name, help } => self.register_bot_command(name, help),
+        }
+        Ok(())
+    }
+
+    /// Plugins post to active group, unless they name one.
+    fn plugin_group(&self, group: Option<String>) -> anyhow::Result<usize> {
+        match group {
+            Some(group) => self
+                .group_index(&group)
+                .ok_or_else(|| anyhow::anyhow!("Not in group {}.", group)),
+            None => Ok(self.active),
+        }
+    }
+}
+
+impl Handler<FromPlugin> for Chat {
+    type Result = ();
+
+    fn handle(&mut self, msg: FromPlugin, ctx: &mut Context<Self>) -> Self::Result {
+        if let Err(e) = self.run_plugin_command(msg.command, ctx) {
+            let path = self.plugins[msg.idx].path.display().to_string();
+            log::warn!("Command of plugin {} failed. Error: {}", path, e);
+        }
+    }
+}
+
+impl Handler<PluginExited> for Chat {
+    type Result = ();
+
+    fn handle(&mut self, msg: PluginExited, ctx: &mut Context<Self>) -> Self::Result {
+        self.restart_plugin(msg.idx, ctx);
+    }
+}
@@ -0,0 +1,295 @@
+use actix::prelude::*;
+use anyhow::bail;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use ya_client::model::NodeId;
+use ya_service_bus::RpcEnvelope;
+
+use super::{send_message, Chat};
+use crate::protocol::{ChatError, Poll, PollResults, Vote};
+
+/// Poll known to us. Votes are collected only for polls created by us,
+/// for other polls we get aggregated results from originator.
+pub(super) struct PollState {
+    poll: Poll,
+    /// Index of group, where poll was asked.
+    group: usize,
+    /// None for polls created by us.
+    owner: Option<NodeId>,
+    /// Option chosen by each voter. None is our own vote.
+    votes: HashMap<Option<NodeId>, usize>,
+    results: Vec<u32>,
+}
+
+pub fn short_id(id: &Uuid) -> String {
+    id.to_simple().to_string()[..6].to_string()
+}
+
+impl Chat {
+    pub(super) fn create_poll(&mut self, question: String, options: Vec<String>) {
+        let poll = Poll {
+            id: Uuid::new_v4(),
+            question,
+            options,
+            group: Some(self.group().name.clone()),
+            user: self.me.clone(),
+            timestamp: Utc::now(),
+        };
+
+        self.print_poll(&poll, "You");
+        self.polls.insert(
+            poll.id,
+            PollState {
+                results: vec![0; poll.options.len()],
+                poll: poll.clone(),
+                group: self.active,
+                owner: None,
+                votes: HashMap::new(),
+            },
+        );
+        self.group().broadcast(poll);
+    }
+
+    pub(super) fn vote(&mut self, pattern: &str, option: usize) -> anyhow::Result<()> {
+        let id = self.find_poll(pattern)?;
+        let state = &self.polls[&id];
+
+        let num_options = state.poll.options.len();
+        if option == 0 || option > num_options {
+            bail!("Poll #{} has options 1-{}.", short_id(&id), num_options);
+        }
+
+        match state.owner {
+            None => self
+                .register_vote(id, None, option - 1)
+                .map_err(anyhow::Error::from)?,
+            Some(owner) => {
+                let vote = Vote {
+                    poll_id: id,
+                    option: option - 1,
+                };
+                Arbiter::spawn(async move {
+                    if let Err(e) = send_message(owner, vote).await {
+                        log::warn!("Failed to send vote to [{}]. Error: {}", owner, e);
+                    }
+                });
+                self.console.print("Vote sent.");
+            }
+        }
+        Ok(())
+    }
+
+    /// Polls of left group are dropped and indexes of later groups shifted.
+    pub(super) fn remove_group_polls(&mut self, group: usize) {
+        self.polls.retain(|_, state| state.group != group);
+        for state in self.polls.values_mut() {
+            if state.group > group {
+                state.group -= 1;
+            }
+        }
+    }
+
+    fn find_poll(&self, pattern: &str) -> anyhow::Result<Uuid> {
+        let matching = self
+            .polls
+            .keys()
+            .filter(|id| id.to_simple().to_string().starts_with(pattern))
+            .collect::<Vec<_>>();
+
+        match matching.as_slice() {
+            [id] => Ok(**id),
+            [] => bail!("No poll with id #{}.", pattern),
+            _ => bail!("Poll id #{} is ambiguous.", pattern),
+        }
+    }
+
+    /// Counts vote for poll created by us and broadcasts new results.
+    fn register_vote(
+        &mut self,
+        id: Uuid,
+        voter: Option<NodeId>,
+        option: usize,
+    ) -> Result<(), ChatError> {
+        let state = self
+            .polls
+            .get_mut(&id)
+            .filter(|state| state.owner.is_none())
+            .ok_or(ChatError::UnknownPoll)?;
+        if option >= state.poll.options.len() {
+            return Err(ChatError::InvalidOption);
+        }
+
+        state.votes.insert(voter, option);
+
+        let mut results = vec![0; state.poll.options.len()];
+        for option in state.votes.values() {
+            results[*option] += 1;
+        }
+        state.results = results.clone();
+        let group = state.group;
+
+        self.print_results(&id);
+        self.groups[group].broadcast(PollResults {
+            poll_id: id,
+            votes: results,
+        });
+        Ok(())
+    }
+
+    fn print_poll(&mut self, poll: &Poll, author: &str) {
+        let options = poll
+            .options
+            .iter()
+            .enumerate()
+            .map(|(idx, option)| format!("  {}. {}", idx + 1, option))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let tag = self.group_tag(poll.group.as_deref().unwrap_or_default());
+        self.console.print(&format!(
+            "<poll #{}>{} {} asked: {}\n{}\nVote with: /vote {} <number>",
+            short_id(&poll.id),
+            tag,
+            author,
+            poll.question,
+            options,
+            short_id(&poll.id),
+        ));
+    }
+
+    fn print_results(&mut self, id: &Uuid) {
+        let state = match self.polls.get(id) {
+            Some(state) => state,
+            None => return,
+        };
+
+        let results = state
+            .poll
+            .options
+            .iter()
+            .zip(state.results.iter())
+            .enumerate()
+            .map(|(idx, (option, votes))| format!("  {}. {} - {}", idx + 1, option, votes))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let text = format!(
+            "<poll #{}> Results: {}\n{}",
+            short_id(id),
+            state.poll.question,
+            results
+        );
+        self.console.print(&text);
+    }
+}
+
+impl Handler<RpcEnvelope<Poll>> for Chat {
+    type Result = ActorResponse<Self, (), ChatError>;
+
+    fn handle(&mut self, msg: RpcEnvelope<Poll>, _: &mut Context<Self>) -> Self::Result {
+        let caller = match NodeId::from_str(msg.caller()) {
+            Ok(caller) => caller,
+            Err(_) => return ActorResponse::reply(Err(ChatError::InvalidNodeId)),
+        };
+        if self.reject_blocked(&caller) {
+            return ActorResponse::reply(Err(ChatError::Rejected));
+        }
+
+        // Older clients don't send group. They can be in single group only.
+        let idx = match &msg.group {
+            Some(name) => self.group_index(name),
+            None => self
+                .groups
+                .iter()
+                .position(|group| group.contains(&caller))
+                .or(Some(0)),
+        };
+        let idx = match idx {
+            Some(idx) => idx,
+            None => return ActorResponse::reply(Err(ChatError::UnknownGroup)),
+        };
+
+        let group = &self.groups[idx];
+        if !group.may_post(&caller) || !group.admitted(&caller) {
+            return ActorResponse::reply(Err(ChatError::Rejected));
+        }
+
+        let mut poll = msg.into_inner();
+        poll.group = Some(group.name.clone());
+        // Known poll can't be replaced, neither ours with its votes, nor
+        // one asked by other user.
+        if poll.options.is_empty() || self.polls.contains_key(&poll.id) {
+            return ActorResponse::reply(Err(ChatError::Rejected));
+        }
+
+        let author = self.contacts.display_name(&caller, &poll.user);
+        self.print_poll(&poll, &author);
+        self.polls.insert(
+            poll.id,
+            PollState {
+                results: vec![0; poll.options.len()],
+                poll,
+                group: idx,
+                owner: Some(caller),
+                votes: HashMap::new(),
+            },
+        );
+        ActorResponse::reply(Ok(()))
+    }
+}
+
+impl Handler<RpcEnvelope<Vote>> for Chat {
+    type Result = ActorResponse<Self, (), ChatError>;
+
+    fn handle(&mut self, msg: RpcEnvelope<Vote>, _: &mut Context<Self>) -> Self::Result {
+        let caller = match NodeId::from_str(msg.caller()) {
+            Ok(caller) => caller,
+            Err(_) => return ActorResponse::reply(Err(ChatError::InvalidNodeId)),
+        };
+        if self.reject_blocked(&caller) {
+            return ActorResponse::reply(Err(ChatError::Rejected));
+        }
+
+        // Only users of group, where poll was asked, can vote.
+        let outsider = self.polls.get(&msg.poll_id).map_or(false, |state| {
+            let group = &self.groups[state.group];
+            !group.contains(&caller) || !group.admitted(&caller)
+        });
+        if outsider {
+            return ActorResponse::reply(Err(ChatError::Rejected));
+        }
+
+        ActorResponse::reply(self.register_vote(msg.poll_id, Some(caller), msg.option))
+    }
+}
+
+impl Handler<RpcEnvelope<PollResults>> for Chat {
+    type Result = ActorResponse<Self, (), ChatError>;
+
+    fn handle(&mut self, msg: RpcEnvelope<PollResults>, _: &mut Context<Self>) -> Self::Result {
+        let caller = match NodeId::from_str(msg.caller()) {
+            Ok(caller) => caller,
+            Err(_) => return ActorResponse::reply(Err(ChatError::InvalidNodeId)),
+        };
+        if self.reject_blocked(&caller) {
+            return ActorResponse::reply(Err(ChatError::Rejected));
+        }
+
+        // Only originator of poll can publish results.
+        match self.polls.get_mut(&msg.poll_id) {
+            Some(state) if state.owner == Some(caller) => {
+                if msg.votes.len() != state.poll.options.len() {
+                    return ActorResponse::reply(Err(ChatError::InvalidOption));
+                }
+                state.results = msg.into_inner().votes;
+                let id = state.poll.id;
+                self.print_results(&id);
+                ActorResponse::reply(Ok(()))
+            }
+            _ => ActorResponse::reply(Err(ChatError::UnknownPoll)),
+        }
+    }
+}
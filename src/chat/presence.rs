@@ -0,0 +1,86 @@
+use actix::prelude::*;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use ya_client::model::NodeId;
+use ya_service_bus::RpcEnvelope;
+
+use super::{send_message, Chat, ChatEvent};
+use crate::presence;
+use crate::protocol::{ChatError, PresenceUpdate};
+
+impl Chat {
+    fn own_presence(&self) -> PresenceUpdate {
+        PresenceUpdate {
+            away: self.away.is_away(),
+            status: self.away.message().map(str::to_string),
+            node: self.node_facts.clone(),
+        }
+    }
+
+    pub(super) fn send_presence(&self, node_id: NodeId) {
+        if self.receive_only {
+            return;
+        }
+        let presence = self.own_presence();
+        Arbiter::spawn(async move {
+            if let Err(e) = send_message(node_id, presence).await {
+                log::debug!("Failed to send presence to [{}]. Error: {}", node_id, e);
+            }
+        });
+    }
+
+    /// Sends presence to everybody we know.
+    pub(super) fn broadcast_presence(&self) {
+        let users = self
+            .groups
+            .iter()
+            .flat_map(|group| group.users.iter())
+            .map(|desc| desc.node_id)
+            .collect::<HashSet<_>>();
+        for node_id in users {
+            self.send_presence(node_id);
+        }
+    }
+
+    /// Presence is broadcast only, when facts changed, so idle fleet
+    /// doesn't send anything.
+    pub(super) fn refresh_node_facts(&mut self, ctx: &mut Context<Self>) {
+        let future = async move { presence::node_facts().await }
+            .into_actor(self)
+            .map(|result, myself, _| match result {
+                Ok(facts) => {
+                    if myself.node_facts.as_ref() != Some(&facts) {
+                        myself.node_facts = Some(facts);
+                        myself.broadcast_presence();
+                    }
+                }
+                Err(e) => log::debug!("Failed to read node facts from yagna. Error: {}", e),
+            });
+        ctx.spawn(future);
+    }
+}
+
+impl Handler<RpcEnvelope<PresenceUpdate>> for Chat {
+    type Result = Result<(), ChatError>;
+
+    fn handle(&mut self, msg: RpcEnvelope<PresenceUpdate>, _: &mut Context<Self>) -> Self::Result {
+        let caller = NodeId::from_str(msg.caller()).map_err(|_| ChatError::InvalidNodeId)?;
+        if self.reject_blocked(&caller) {
+            return Err(ChatError::Rejected);
+        }
+        if self.find_user(&caller).is_none() {
+            return Err(ChatError::UnknownUser);
+        }
+        log::debug!("Got presence of [{}].", caller);
+        let presence = msg.into_inner();
+        self.emit(ChatEvent::Presence {
+            node_id: caller,
+            away: presence.away,
+            status: presence.status.clone(),
+            node: presence.node.clone(),
+        });
+        self.presence.insert(caller, presence);
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+use actix::prelude::*;
+use anyhow::bail;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use ya_client::model::NodeId;
+use ya_service_bus::RpcEnvelope;
+
+use super::{send_message, Chat};
+use crate::profile;
+use crate::protocol::{ChatError, Profile};
+
+impl Chat {
+    fn own_profile(&self) -> Profile {
+        let mut profile = self.profiles.own.clone();
+        if profile.name.is_empty() {
+            profile.name = self.me.clone();
+        }
+        profile.commands = self.bot_commands.clone();
+        profile
+    }
+
+    pub(super) fn send_profile(&self, node_id: NodeId) {
+        if self.receive_only {
+            return;
+        }
+        let profile = self.own_profile();
+        Arbiter::spawn(async move {
+            if let Err(e) = send_message(node_id, profile).await {
+                log::debug!("Failed to send profile to [{}]. Error: {}", node_id, e);
+            }
+        });
+    }
+
+    /// Sends profile to everybody we know.
+    pub(super) fn broadcast_profile(&self) {
+        let users = self
+            .groups
+            .iter()
+            .flat_map(|group| group.users.iter())
+            .map(|desc| desc.node_id)
+            .collect::<HashSet<_>>();
+        for node_id in users {
+            self.send_profile(node_id);
+        }
+    }
+
+    /// Changes field of our profile and sends it to everybody we know.
+    /// Empty value clears the field.
+    pub(super) fn set_profile(&mut self, field: &str, value: Vec<String>) -> anyhow::Result<()> {
+        let text = match value.is_empty() {
+            true => None,
+            false => Some(value.join(" ")),
+        };
+        let own = &mut self.profiles.own;
+        match field {
+            "name" => own.name = text.unwrap_or_default(),
+            "bio" => own.bio = text,
+            "avatar" => own.avatar = text,
+            "links" => own.links = value,
+            _ => bail!(
+                "Unknown profile field: {}. Use name, bio, avatar or links.",
+                field
+            ),
+        }
+        self.profiles.save_own()?;
+        self.broadcast_profile();
+        self.console.print(&format!(
+            "Profile updated:\n{}",
+            profile::format(&self.own_profile())
+        ));
+        Ok(())
+    }
+
+    pub(super) fn print_profile(&mut self, pattern: Option<&str>) -> anyhow::Result<()> {
+        let (node_id, profile) = match pattern {
+            None => match self.user_id() {
+                Some(node_id) => (node_id, self.own_profile()),
+                None => bail!("Our identity isn't known yet. Try again later."),
+            },
+            Some(pattern) => {
+                let (name, devices) = self.resolve_user(pattern)?;
+                match devices
+                    .iter()
+                    .find_map(|device| self.profiles.get(device).map(|p| (*device, p.clone())))
+                {
+                    Some(found) => found,
+                    None => bail!("{} didn't share profile yet.", name),
+                }
+            }
+        };
+        let avatar = self.renderer.avatar(&profile.name, &node_id);
+        self.console.print(&format!(
+            "{} [{}]\n{}",
+            avatar,
+            node_id,
+            profile::format(&profile)
+        ));
+        Ok(())
+    }
+}
+
+impl Handler<RpcEnvelope<Profile>> for Chat {
+    type Result = Result<(), ChatError>;
+
+    fn handle(&mut self, msg: RpcEnvelope<Profile>, _: &mut Context<Self>) -> Self::Result {
+        let caller = NodeId::from_str(msg.caller()).map_err(|_| ChatError::InvalidNodeId)?;
+        if self.reject_blocked(&caller) {
+            return Err(ChatError::Rejected);
+        }
+        if self.find_user(&caller).is_none() {
+            return Err(ChatError::UnknownUser);
+        }
+        log::debug!("Got profile of [{}].", caller);
+        self.profiles
+            .insert(caller, msg.into_inner())
+            .map_err(|e| log::warn!("Failed to save profile of [{}]. Error: {}", caller, e))
+            .ok();
+        Ok(())
+    }
+}
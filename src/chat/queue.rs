@@ -0,0 +1,301 @@
+use actix::prelude::*;
+use anyhow::bail;
+use chrono::Utc;
+use std::time::Duration;
+
+use ya_client::model::NodeId;
+
+use super::{send_text, Chat, Delivery, DeliveryReport};
+use crate::audit::AuditKind;
+use crate::protocol::SendText;
+
+pub(super) const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+impl Chat {
+    pub(super) fn peer_name(&self, node_id: &NodeId) -> String {
+        match self.find_user(node_id) {
+            Some(desc) => self.display_user(desc),
+            None => node_id.to_string(),
+        }
+    }
+
+    pub(super) fn queue(&mut self, address: NodeId, messages: SendText) {
+        self.delivery.push(address, messages);
+        self.store_queue();
+    }
+
+    /// Queue is saved on every change, so undelivered messages survive
+    /// crash, not only clean shutdown.
+    pub(super) fn store_queue(&self) {
+        if let Err(e) = self.storage.store_queue(&self.delivery.stored()) {
+            log::error!("Failed to save undelivered messages. Error: {}", e);
+        }
+    }
+
+    /// Returns addresses, to which messages can be sent right away. Messages
+    /// to peers with queue being resent wait in queue behind older ones.
+    /// Peers known to be offline get messages queued without attempt, which
+    /// would only wait for GSB timeout.
+    pub(super) fn hold_for_later(
+        &mut self,
+        addresses: Vec<NodeId>,
+        text: &SendText,
+        ctx: &mut Context<Self>,
+    ) -> Vec<NodeId> {
+        let (held, live): (Vec<_>, Vec<_>) = addresses
+            .into_iter()
+            .partition(|addr| self.flushing.contains(addr));
+        for addr in held {
+            self.queue(addr, text.clone());
+        }
+
+        let (offline, live): (Vec<_>, Vec<_>) =
+            live.into_iter().partition(|addr| self.known_offline(addr));
+        for addr in offline {
+            log::debug!("[{}] is offline. Message queued without sending.", addr);
+            self.handle(
+                DeliveryReport {
+                    ids: text.messages.iter().map(|text| text.id).collect(),
+                    recipient: addr,
+                    delivery: Delivery::Queued,
+                },
+                ctx,
+            );
+            let mut text = text.clone();
+            text.delayed = true;
+            self.queue(addr, text);
+        }
+        live
+    }
+
+    /// Peers missing from all rosters are assumed online.
+    fn known_offline(&self, node_id: &NodeId) -> bool {
+        let mut known = false;
+        for desc in self.groups.iter().flat_map(|group| group.users.iter()) {
+            if &desc.node_id == node_id {
+                if desc.online {
+                    return false;
+                }
+                known = true;
+            }
+        }
+        known
+    }
+
+    /// Resends queued messages in timestamp order. Messages queued meanwhile
+    /// are sent afterwards, unless peer disappeared again.
+    pub(super) fn flush(&mut self, node_id: NodeId, ctx: &mut Context<Self>) {
+        if self.flushing.contains(&node_id) {
+            return;
+        }
+        let batches = match self.delivery.take(&node_id) {
+            Some(batches) => batches,
+            None => return,
+        };
+        log::info!("Resending old messages to [{}].", node_id);
+
+        self.flushing.insert(node_id);
+        // Live messages held during previous flush aren't announced.
+        let delayed = batches
+            .iter()
+            .filter(|batch| batch.delayed)
+            .map(|batch| batch.messages.len())
+            .sum::<usize>();
+
+        let myself = ctx.address();
+        let future = async move {
+            let mut batches = batches.into_iter();
+            for batch in batches.by_ref() {
+                match send_text(myself.clone(), &node_id, &batch).await {
+                    Ok(Delivery::Queued) => return Some(batches.collect::<Vec<_>>()),
+                    Ok(_) => (),
+                    Err(e) => {
+                        log::error!("Error delivering messages to [{}]. Error: {}", node_id, e);
+                        return Some(batches.collect());
+                    }
+                }
+            }
+            None
+        }
+        .into_actor(self)
+        .map(move |interrupted, myself, ctx| {
+            myself.flushing.remove(&node_id);
+            match interrupted {
+                // Peer is gone again. Rest waits for his next appearance.
+                Some(remaining) => {
+                    myself.delivery.requeue(node_id, remaining);
+                    myself.store_queue();
+                }
+                None => {
+                    if delayed > 0 {
+                        let notice = format!(
+                            "{} is back. Resent {} queued message(s).",
+                            myself.peer_name(&node_id),
+                            delayed
+                        );
+                        myself.notice(&notice);
+                    }
+                    myself.store_queue();
+                    myself.flush(node_id, ctx)
+                }
+            }
+        });
+        ctx.spawn(future);
+    }
+
+    /// Drops queued messages older than their TTL and tells the sender,
+    /// which messages won't be delivered.
+    pub(super) fn expire_queued(&mut self, ctx: &mut Context<Self>) {
+        let expired = self.delivery.expire(&Utc::now());
+        if !expired.is_empty() {
+            self.store_queue();
+        }
+        for (recipient, ids) in expired {
+            let name = self.peer_name(&recipient);
+            log::info!(
+                "{} queued message(s) to [{}] expired.",
+                ids.len(),
+                recipient
+            );
+            self.notice(&format!(
+                "{} message(s) to {} expired undelivered.",
+                ids.len(),
+                name
+            ));
+            self.handle(
+                DeliveryReport {
+                    ids,
+                    recipient,
+                    delivery: Delivery::Expired,
+                },
+                ctx,
+            );
+        }
+    }
+
+    pub(super) fn print_queue(&mut self) {
+        let mut summary = self.delivery.summary();
+        if summary.is_empty() {
+            self.console.print("No queued messages.");
+            return;
+        }
+        summary.sort_by_key(|(_, _, oldest)| *oldest);
+        let now = Utc::now();
+        let listing = summary
+            .into_iter()
+            .map(|(address, count, oldest)| {
+                format!(
+                    "  {} [{}]: {} message(s){}{}",
+                    self.peer_name(&address),
+                    address,
+                    count,
+                    oldest
+                        .map(|oldest| format!(", oldest {} ago", format_age(now - oldest)))
+                        .unwrap_or_default(),
+                    match self.flushing.contains(&address) {
+                        true => ", resending",
+                        false => "",
+                    }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.console
+            .print(&format!("Queued messages:\n{}", listing));
+    }
+
+    /// Retries delivery without waiting for recipient to be rediscovered.
+    /// Messages stay queued, if he is still unreachable.
+    pub(super) fn flush_queue(
+        &mut self,
+        pattern: Option<&str>,
+        ctx: &mut Context<Self>,
+    ) -> anyhow::Result<()> {
+        let recipients = match pattern {
+            Some(pattern) => self.queued_devices(pattern)?,
+            None => self.delivery.recipients(),
+        };
+        if recipients.is_empty() {
+            bail!("No queued messages.");
+        }
+        for recipient in recipients.iter() {
+            self.flush(*recipient, ctx);
+        }
+        self.console.print(&format!(
+            "Retrying delivery to {} recipient(s).",
+            recipients.len()
+        ));
+        Ok(())
+    }
+
+    pub(super) fn drop_queued(
+        &mut self,
+        pattern: &str,
+        ctx: &mut Context<Self>,
+    ) -> anyhow::Result<()> {
+        let recipients = self.queued_devices(pattern)?;
+        if recipients.is_empty() {
+            bail!("No messages queued for '{}'.", pattern);
+        }
+        for recipient in recipients {
+            let ids = self
+                .delivery
+                .remove(&recipient)
+                .unwrap_or_default()
+                .into_iter()
+                .flat_map(|batch| batch.messages.into_iter().map(|text| text.id))
+                .collect::<Vec<_>>();
+            let text = format!(
+                "Dropped {} queued message(s) to {}.",
+                ids.len(),
+                self.peer_name(&recipient)
+            );
+            self.audit(AuditKind::Delivery, None, Some(recipient), text.clone());
+            self.console.print(&text);
+            self.handle(
+                DeliveryReport {
+                    ids,
+                    recipient,
+                    delivery: Delivery::Dropped,
+                },
+                ctx,
+            );
+        }
+        self.store_queue();
+        Ok(())
+    }
+
+    /// Devices of user with queued messages. Recipients, which aren't in
+    /// any roster anymore, can be still selected by NodeId prefix.
+    fn queued_devices(&self, pattern: &str) -> anyhow::Result<Vec<NodeId>> {
+        let queued = self.delivery.recipients();
+        match self.resolve_user(pattern) {
+            Ok((_, devices)) => Ok(devices
+                .into_iter()
+                .filter(|device| queued.contains(device))
+                .collect()),
+            Err(e) => {
+                let prefix = pattern.to_lowercase();
+                let matching = queued
+                    .into_iter()
+                    .filter(|address| address.to_string().starts_with(&prefix))
+                    .collect::<Vec<_>>();
+                match matching.is_empty() {
+                    true => Err(e),
+                    false => Ok(matching),
+                }
+            }
+        }
+    }
+}
+
+/// Rounded down to minutes, e.g. `3h 12m`.
+pub(super) fn format_age(age: chrono::Duration) -> String {
+    let minutes = age.num_minutes().max(0);
+    match minutes {
+        0 => "less than a minute".to_string(),
+        1..=59 => format!("{}m", minutes),
+        60..=1439 => format!("{}h {}m", minutes / 60, minutes % 60),
+        _ => format!("{}d {}h", minutes / 1440, minutes % 1440 / 60),
+    }
+}
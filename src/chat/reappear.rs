@@ -0,0 +1,79 @@
+use actix::prelude::*;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+
+use ya_client::model::NodeId;
+
+use super::Chat;
+
+/// Used, when `--reappear-interval` isn't given.
+pub(super) const DEFAULT_REAPPEAR_INTERVAL_MINUTES: i64 = 10;
+/// Notices arriving within this time after the first one are printed as
+/// single line.
+const BURST_WINDOW: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Discovery reports peers again on every proposal, so notices about
+/// returning users are limited per peer and collapsed in bursts.
+pub(super) struct ReappearNotices {
+    interval: Duration,
+    last: HashMap<NodeId, DateTime<Utc>>,
+    /// Display names with group tags, waiting for burst to end.
+    pending: Vec<String>,
+    /// Some of pending users were offline, not just rediscovered.
+    back_online: bool,
+}
+
+impl ReappearNotices {
+    pub(super) fn new(interval: Duration) -> ReappearNotices {
+        ReappearNotices {
+            interval,
+            last: HashMap::new(),
+            pending: vec![],
+            back_online: false,
+        }
+    }
+
+    pub(super) fn forget(&mut self, node_ids: &[NodeId]) {
+        self.last.retain(|node_id, _| !node_ids.contains(node_id));
+    }
+}
+
+impl Chat {
+    pub(super) fn notice_reappeared(
+        &mut self,
+        node_id: NodeId,
+        name: String,
+        was_online: bool,
+        ctx: &mut Context<Self>,
+    ) {
+        let now = Utc::now();
+        let notices = &mut self.reappeared;
+        if let Some(last) = notices.last.get(&node_id) {
+            if now - *last < notices.interval {
+                log::debug!("Suppressed reappear notice about {} [{}].", name, node_id);
+                return;
+            }
+        }
+        notices.last.insert(node_id, now);
+        notices.back_online |= !was_online;
+        notices.pending.push(name);
+        if notices.pending.len() == 1 {
+            ctx.run_later(BURST_WINDOW, |myself, _| myself.print_reappeared());
+        }
+    }
+
+    fn print_reappeared(&mut self) {
+        let pending = std::mem::take(&mut self.reappeared.pending);
+        let back_online = std::mem::replace(&mut self.reappeared.back_online, false);
+        let state = match back_online {
+            true => "back online",
+            false => "reappeared",
+        };
+        let notice = match pending.len() {
+            0 => return,
+            1 => format!("User {}: {}", state, pending[0]),
+            count => format!("{} users {}: {}", count, state, pending.join(", ")),
+        };
+        self.notice(&notice);
+    }
+}
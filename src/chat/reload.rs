@@ -0,0 +1,83 @@
+use actix::prelude::*;
+
+use super::Chat;
+use crate::config::Config;
+use crate::theme::Theme;
+use crate::watch::Watchlist;
+
+/// Re-reads config file. Sent by `/reload` and on SIGHUP.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Reload;
+
+impl Chat {
+    /// Applies settings, which can change while running. Groups removed
+    /// from config stay joined until restart, so their subscriptions
+    /// aren't dropped under active conversations.
+    pub(super) fn reload(&mut self, ctx: &mut Context<Self>) -> anyhow::Result<()> {
+        let config = Config::load(&self.config_path)?;
+        // Validated first, so broken config doesn't leave settings half applied.
+        let theme = match &config.theme {
+            Some(name) => Some(Theme::find(name, &config.themes)?),
+            None => None,
+        };
+        let watchlist = Watchlist::load(&self.data_dir)?;
+        self.filters.configure(&config.filter)?;
+        self.responders.configure(&config.responder)?;
+
+        if let Some(theme) = theme {
+            self.renderer.set_theme(theme);
+        }
+        if let Some(layout) = config.layout {
+            self.layout = layout;
+            self.last_header = None;
+        }
+        self.renderer.set_watched(watchlist.terms());
+        self.watchlist = watchlist;
+        self.hooks = config.hooks;
+        self.away.set_config(config.away);
+        self.spam.set_config(config.spam);
+        self.throttle.set_config(config.throttle);
+        self.retention = config.retention;
+
+        let groups = config.groups;
+        let mut joined = vec![];
+        for name in groups.iter() {
+            if self.group_index(name).is_none() {
+                self.join_group(name, ctx)?;
+                joined.push(name.clone());
+            }
+        }
+        let removed = self
+            .config_groups
+            .iter()
+            .filter(|name| !groups.contains(name))
+            .cloned()
+            .collect::<Vec<_>>();
+        self.config_groups = groups;
+
+        let mut notice = format!("Reloaded {}.", self.config_path.display());
+        if !joined.is_empty() {
+            notice.push_str(&format!(" Joined: {}.", joined.join(", ")));
+        }
+        if !removed.is_empty() {
+            notice.push_str(&format!(
+                " Removed groups stay joined until restart: {}.",
+                removed.join(", ")
+            ));
+        }
+        self.notice(&notice);
+        Ok(())
+    }
+}
+
+impl Handler<Reload> for Chat {
+    type Result = ();
+
+    fn handle(&mut self, _: Reload, ctx: &mut Context<Self>) -> Self::Result {
+        if let Err(e) = self.reload(ctx) {
+            log::warn!("Failed to reload config. Error: {}", e);
+            self.notice(&format!("Config not reloaded. {}", e));
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use actix::prelude::*;
+use anyhow::anyhow;
+use uuid::Uuid;
+
+use ya_client::model::NodeId;
+
+use super::polls::short_id;
+use super::Chat;
+use crate::emoji;
+use crate::layout::MessageLayout;
+use crate::protocol::TextMessage;
+
+/// Quoted part of replied message.
+const SNIPPET_CHARS: usize = 30;
+
+/// Target of `r` and `rr` shortcuts.
+pub(super) struct LastReceived {
+    /// None for direct messages.
+    pub group: Option<String>,
+    pub id: Uuid,
+    pub sender: NodeId,
+}
+
+impl Chat {
+    /// Short id in verbose mode and quote of replied message, appended
+    /// to message tag.
+    pub(super) fn message_context(&self, group: Option<&str>, text: &TextMessage) -> String {
+        let mut context = String::new();
+        // Verbose layout shows full id already.
+        if self.verbosity > 0 && self.layout != MessageLayout::Verbose {
+            context.push_str(&format!(" #{}", short_id(&text.id)));
+        }
+        let reply_to = match text.reply_to {
+            Some(reply_to) => reply_to,
+            None => return context,
+        };
+        let arrow = match self.accessible {
+            true => " in reply to",
+            false => " ↪",
+        };
+        let original = group
+            .and_then(|group| self.group_index(group))
+            .and_then(|idx| {
+                self.groups[idx]
+                    .history
+                    .find(&reply_to.to_simple().to_string())
+            });
+        match original {
+            Some(entry) => context.push_str(&format!(
+                "{} {}: \"{}\"",
+                arrow,
+                entry.user,
+                snippet(&entry.content)
+            )),
+            None => context.push_str(&format!("{} #{}", arrow, short_id(&reply_to))),
+        }
+        context
+    }
+
+    pub(super) fn reply(&mut self, text: String, ctx: &mut Context<Self>) -> anyhow::Result<()> {
+        let last = self
+            .last_received
+            .as_ref()
+            .ok_or_else(|| anyhow!("No message to reply to yet."))?;
+        let (id, sender) = (last.id, last.sender);
+        let group = match &last.group {
+            Some(group) => group.clone(),
+            None => return self.reply_to_user(sender, id, text, ctx),
+        };
+
+        let idx = self
+            .group_index(&group)
+            .ok_or_else(|| anyhow!("You left group {}.", group))?;
+        let future = self
+            .post(idx, text, Some(id), ctx)?
+            .into_actor(self)
+            .map(|result, _, _| {
+                if let Err(e) = result {
+                    log::warn!("Failed to send reply. Error: {}", e);
+                }
+            });
+        ctx.spawn(future);
+        Ok(())
+    }
+
+    pub(super) fn reply_direct(
+        &mut self,
+        text: String,
+        ctx: &mut Context<Self>,
+    ) -> anyhow::Result<()> {
+        let last = self
+            .last_direct
+            .as_ref()
+            .ok_or_else(|| anyhow!("No direct message to reply to yet."))?;
+        let (id, sender) = (last.id, last.sender);
+        self.reply_to_user(sender, id, text, ctx)
+    }
+
+    /// Reply goes to all devices of the sender, like `/msg`.
+    fn reply_to_user(
+        &mut self,
+        sender: NodeId,
+        id: Uuid,
+        text: String,
+        ctx: &mut Context<Self>,
+    ) -> anyhow::Result<()> {
+        let devices = match self.find_user(&sender) {
+            Some(desc) => self.devices_of(desc.user_id()),
+            None => vec![],
+        };
+        if devices.is_empty() {
+            return Err(anyhow!(
+                "{} isn't in any of our groups anymore.",
+                self.peer_name(&sender)
+            ));
+        }
+        let content = match self.expand_emoji {
+            true => emoji::expand(&text),
+            false => text,
+        };
+        let tag = format!(" [direct to {}]", self.peer_name(&sender));
+        self.deliver_direct(&tag, devices, content, Some(id), false, ctx);
+        Ok(())
+    }
+}
+
+fn snippet(content: &str) -> String {
+    let line = content.lines().next().unwrap_or_default();
+    match line.chars().count() > SNIPPET_CHARS || content.lines().nth(1).is_some() {
+        true => format!("{}…", line.chars().take(SNIPPET_CHARS).collect::<String>()),
+        false => line.to_string(),
+    }
+}
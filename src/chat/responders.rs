@@ -0,0 +1,61 @@
+use actix::prelude::*;
+
+use ya_client::model::NodeId;
+
+use super::Chat;
+use crate::protocol::TextMessage;
+
+impl Chat {
+    /// Group messages are answered in group, as replies to them. Direct
+    /// messages are answered like auto-reply, so peers don't answer back.
+    pub(super) fn run_responders(
+        &mut self,
+        sender: NodeId,
+        group: Option<&str>,
+        name: &str,
+        text: &TextMessage,
+        ctx: &mut Context<Self>,
+    ) {
+        if self.receive_only {
+            return;
+        }
+        let user_id = match self.find_user(&sender) {
+            Some(desc) => desc.user_id(),
+            None => return,
+        };
+        if Some(user_id) == self.user_id() {
+            return;
+        }
+
+        let content = match self
+            .responders
+            .respond(user_id, name, &self.me, group, &text.content)
+        {
+            Some(content) => content,
+            None => return,
+        };
+        let idx = match group {
+            Some(group) => match self.group_index(group) {
+                Some(idx) => idx,
+                None => return,
+            },
+            None => {
+                let tag = format!(" [auto-reply to {}]", name);
+                let devices = self.devices_of(user_id);
+                self.deliver_direct(&tag, devices, content, Some(text.id), true, ctx);
+                return;
+            }
+        };
+        match self.post(idx, content, Some(text.id), ctx) {
+            Ok(future) => {
+                let future = future.into_actor(self).map(|result, _, _| {
+                    if let Err(e) = result {
+                        log::warn!("Failed to send responder reply. Error: {}", e);
+                    }
+                });
+                ctx.spawn(future);
+            }
+            Err(e) => log::debug!("Responder reply not sent. {}", e),
+        }
+    }
+}
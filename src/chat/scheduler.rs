@@ -0,0 +1,152 @@
+use actix::prelude::*;
+use chrono::{DateTime, Local, Utc};
+use uuid::Uuid;
+
+use super::{Chat, TIMESTAMP_FORMAT};
+use crate::schedule::Scheduled;
+
+impl Chat {
+    /// Arms timers for entries restored from previous session.
+    pub(super) fn arm_schedule(&mut self, ctx: &mut Context<Self>) {
+        let entries = self
+            .schedule
+            .entries()
+            .iter()
+            .map(|entry| (entry.id, entry.at))
+            .collect::<Vec<_>>();
+        for (id, at) in entries {
+            self.arm(id, at, ctx);
+        }
+    }
+
+    fn arm(&mut self, id: Uuid, at: DateTime<Utc>, ctx: &mut Context<Self>) {
+        // Entries, which passed while chat was off, fire immediately.
+        let delay = (at - Utc::now()).to_std().unwrap_or_default();
+        ctx.run_later(delay, move |myself, ctx| myself.fire_scheduled(id, ctx));
+    }
+
+    /// Schedules message to active group or reminder, when `to_group` is false.
+    pub(super) fn schedule(
+        &mut self,
+        at: DateTime<Utc>,
+        text: String,
+        to_group: bool,
+        ctx: &mut Context<Self>,
+    ) -> anyhow::Result<()> {
+        let group = match to_group {
+            true => {
+                self.can_post(self.active)?;
+                Some(self.group().name.clone())
+            }
+            false => None,
+        };
+        let entry = Scheduled {
+            id: Uuid::new_v4(),
+            at,
+            group,
+            text,
+        };
+        let (id, at) = (entry.id, entry.at);
+        let notice = match &entry.group {
+            Some(group) => format!(
+                "Message to group {} scheduled for {}.",
+                group,
+                at.with_timezone(&Local).format(TIMESTAMP_FORMAT)
+            ),
+            None => format!(
+                "Reminder set for {}.",
+                at.with_timezone(&Local).format(TIMESTAMP_FORMAT)
+            ),
+        };
+
+        self.schedule.add(entry)?;
+        self.arm(id, at, ctx);
+        self.console.print(&notice);
+        Ok(())
+    }
+
+    pub(super) fn arm_recurring(&mut self, idx: usize, ctx: &mut Context<Self>) {
+        let recurring = &self.recurring[idx];
+        let next = match recurring.schedule.next_after(Local::now()) {
+            Some(next) => next,
+            None => {
+                log::warn!(
+                    "Recurring message to group {} never fires. Check its schedule.",
+                    recurring.group
+                );
+                return;
+            }
+        };
+        log::debug!(
+            "Next recurring message to group {} at {}.",
+            recurring.group,
+            next
+        );
+
+        let delay = (next.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default();
+        ctx.run_later(delay, move |myself, ctx| {
+            myself.fire_recurring(idx, ctx);
+            myself.arm_recurring(idx, ctx);
+        });
+    }
+
+    fn fire_recurring(&mut self, idx: usize, ctx: &mut Context<Self>) {
+        let recurring = &self.recurring[idx];
+        let (group, text) = (recurring.group.clone(), recurring.text.clone());
+        let group_idx = match self.group_index(&group) {
+            Some(group_idx) => group_idx,
+            None => {
+                log::warn!("Recurring message not sent. Group {} was left.", group);
+                return;
+            }
+        };
+        self.send_later(group_idx, text, ctx);
+    }
+
+    fn send_later(&mut self, group_idx: usize, text: String, ctx: &mut Context<Self>) {
+        match self.post(group_idx, text, None, ctx) {
+            Ok(future) => {
+                let future = future.into_actor(self).map(|result, _, _| {
+                    if let Err(e) = result {
+                        log::warn!("Failed to send scheduled message. Error: {}", e);
+                    }
+                });
+                ctx.spawn(future);
+            }
+            Err(e) => self.notice(&format!("Scheduled message wasn't sent. {}", e)),
+        }
+    }
+
+    fn fire_scheduled(&mut self, id: Uuid, ctx: &mut Context<Self>) {
+        let entry = match self.schedule.remove(&id) {
+            Ok(Some(entry)) => entry,
+            Ok(None) => return,
+            Err(e) => {
+                log::warn!("Failed to save schedule. Error: {}", e);
+                return;
+            }
+        };
+
+        let group = match entry.group {
+            Some(group) => group,
+            None => {
+                self.notice(&format!("Reminder: {}", entry.text));
+                return;
+            }
+        };
+        let idx = match self.group_index(&group) {
+            Some(idx) => idx,
+            None => {
+                self.notice(&format!(
+                    "Scheduled message wasn't sent. You left group {}: {}",
+                    group, entry.text
+                ));
+                return;
+            }
+        };
+
+        self.send_later(idx, entry.text, ctx);
+    }
+}
@@ -0,0 +1,191 @@
+use actix::prelude::*;
+use anyhow::anyhow;
+use secp256k1::PublicKey;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use ya_client::model::NodeId;
+use ya_core_model::identity;
+use ya_service_bus::{typed as bus, RpcEndpoint, RpcEnvelope};
+
+use super::Chat;
+use crate::challenge;
+use crate::protocol::{ChatError, RatchetInit, RatchetKey, SendSealed, SendText};
+use crate::ratchet::{self, Ratchet};
+
+/// Encrypts direct message for single device. Session is started first,
+/// if we don't have one with the device.
+#[derive(Message)]
+#[rtype(result = "anyhow::Result<SendSealed>")]
+pub struct SealDirect {
+    pub address: NodeId,
+    pub text: SendText,
+}
+
+/// Peer doesn't know session anymore, for example after losing data dir.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct ForgetSession(pub Uuid);
+
+impl Chat {
+    fn seal(&mut self, session: Uuid, text: &SendText) -> anyhow::Result<SendSealed> {
+        let (header, ciphertext) = self
+            .sessions
+            .encrypt(&session, &serde_json::to_vec(text)?)?;
+        Ok(SendSealed {
+            session,
+            header,
+            ciphertext,
+        })
+    }
+}
+
+async fn sign(node_id: NodeId, payload: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    Ok(bus::service(identity::BUS_ID)
+        .send(identity::Sign { node_id, payload })
+        .await??)
+}
+
+impl Handler<SealDirect> for Chat {
+    type Result = ActorResponse<Self, SendSealed, anyhow::Error>;
+
+    fn handle(&mut self, msg: SealDirect, _: &mut Context<Self>) -> Self::Result {
+        if let Some(session) = self.sessions.sending(&msg.address) {
+            return ActorResponse::reply(self.seal(session, &msg.text));
+        }
+        let node_id = match self.node_id {
+            Some(node_id) => node_id,
+            None => return ActorResponse::reply(Err(anyhow!("Our identity isn't known yet."))),
+        };
+
+        let address = msg.address;
+        let session = Uuid::new_v4();
+        let (secret, public) = ratchet::generate();
+        let key = public.serialize().to_vec();
+
+        let future = async move {
+            let signature =
+                sign(node_id, challenge::ratchet_hash(&session, &key, &address)).await?;
+            let response = bus::service(format!("/net/{}/yachat", address))
+                .send(RatchetInit {
+                    session,
+                    key,
+                    signature,
+                })
+                .await??;
+            challenge::verify_ratchet(
+                &address,
+                &session,
+                &response.key,
+                &node_id,
+                &response.signature,
+            )?;
+
+            let their_key = PublicKey::from_slice(&response.key)?;
+            Ratchet::initiator(&ratchet::shared_secret(&secret, &their_key), &their_key)
+        }
+        .into_actor(self)
+        .map(move |result, myself, _| {
+            let ratchet = result?;
+            log::info!("Started encrypted session with [{}].", address);
+            myself.sessions.insert(session, address, ratchet);
+            myself.seal(session, &msg.text)
+        });
+        ActorResponse::r#async(future)
+    }
+}
+
+impl Handler<ForgetSession> for Chat {
+    type Result = ();
+
+    fn handle(&mut self, msg: ForgetSession, _: &mut Context<Self>) -> Self::Result {
+        self.sessions.remove(&msg.0);
+    }
+}
+
+impl Handler<RpcEnvelope<RatchetInit>> for Chat {
+    type Result = ActorResponse<Self, RatchetKey, ChatError>;
+
+    fn handle(&mut self, msg: RpcEnvelope<RatchetInit>, _: &mut Context<Self>) -> Self::Result {
+        let caller = match NodeId::from_str(msg.caller()) {
+            Ok(caller) => caller,
+            Err(_) => return ActorResponse::reply(Err(ChatError::InvalidNodeId)),
+        };
+        if self.reject_blocked(&caller) {
+            return ActorResponse::reply(Err(ChatError::Rejected));
+        }
+        let node_id = match self.node_id {
+            Some(node_id) => node_id,
+            None => return ActorResponse::reply(Err(ChatError::IdentityUnavailable)),
+        };
+        let init = msg.into_inner();
+        if let Err(e) =
+            challenge::verify_ratchet(&caller, &init.session, &init.key, &node_id, &init.signature)
+        {
+            log::warn!("Invalid session handshake from [{}]. Error: {}", caller, e);
+            return ActorResponse::reply(Err(ChatError::Rejected));
+        }
+        let their_key = match PublicKey::from_slice(&init.key) {
+            Ok(key) => key,
+            Err(_) => return ActorResponse::reply(Err(ChatError::Rejected)),
+        };
+
+        let session = init.session;
+        let (secret, public) = ratchet::generate();
+        let key = public.serialize().to_vec();
+        let payload = challenge::ratchet_hash(&session, &key, &caller);
+
+        let future = async move {
+            sign(node_id, payload)
+                .await
+                .map_err(|e| log::warn!("Failed to sign session handshake. Error: {}", e))
+                .map_err(|_| ChatError::IdentityUnavailable)
+        }
+        .into_actor(self)
+        .map(move |result, myself, _| {
+            let signature = result?;
+            let shared = ratchet::shared_secret(&secret, &their_key);
+            log::info!("Accepted encrypted session from [{}].", caller);
+            myself
+                .sessions
+                .insert(session, caller, Ratchet::responder(&shared, &secret));
+            Ok(RatchetKey { key, signature })
+        });
+        ActorResponse::r#async(future)
+    }
+}
+
+impl Handler<RpcEnvelope<SendSealed>> for Chat {
+    type Result = ActorResponse<Self, (), ChatError>;
+
+    fn handle(&mut self, msg: RpcEnvelope<SendSealed>, ctx: &mut Context<Self>) -> Self::Result {
+        let caller = match NodeId::from_str(msg.caller()) {
+            Ok(caller) => caller,
+            Err(_) => return ActorResponse::reply(Err(ChatError::InvalidNodeId)),
+        };
+        if self.reject_blocked(&caller) {
+            return ActorResponse::reply(Err(ChatError::Rejected));
+        }
+
+        let sealed = msg.into_inner();
+        let plaintext = match self.sessions.decrypt(
+            &sealed.session,
+            &caller,
+            &sealed.header,
+            &sealed.ciphertext,
+        ) {
+            Some(Ok(plaintext)) => plaintext,
+            Some(Err(e)) => {
+                log::warn!("Failed to decrypt message from [{}]. Error: {}", caller, e);
+                return ActorResponse::reply(Err(ChatError::DecryptionFailed));
+            }
+            None => return ActorResponse::reply(Err(ChatError::UnknownSession)),
+        };
+
+        // Only direct messages are sealed, so group routing is never bypassed.
+        match serde_json::from_slice::<SendText>(&plaintext) {
+            Ok(text) if text.direct => ActorResponse::reply(self.receive_direct(caller, text, ctx)),
+            _ => ActorResponse::reply(Err(ChatError::Rejected)),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use chrono::{DateTime, Duration, Local, Utc};
+
+use super::queue::format_age;
+use super::Chat;
+
+/// Gap between consecutive messages, after which it is marked in stream.
+const SESSION_GAP_HOURS: i64 = 1;
+
+/// Timestamp of the last message shown in console, shared by all groups,
+/// since they are displayed in single stream.
+pub(super) struct StreamClock {
+    last: Option<DateTime<Utc>>,
+    /// Set, until first message after start is shown. `last` is then taken
+    /// from history of previous session.
+    resumed: bool,
+}
+
+impl StreamClock {
+    pub(super) fn resume(last: Option<DateTime<Utc>>) -> StreamClock {
+        StreamClock {
+            last,
+            resumed: last.is_some(),
+        }
+    }
+}
+
+impl Chat {
+    /// Prints date separator, when message is from other day than previous
+    /// one, and session marker after long gap or restart. Delayed messages
+    /// older than the last shown don't move the clock back.
+    pub(super) fn mark_time(&mut self, timestamp: &DateTime<Utc>) {
+        let last = match self.clock.last {
+            Some(last) if *timestamp < last => return,
+            last => last,
+        };
+        let resumed = std::mem::replace(&mut self.clock.resumed, false);
+        self.clock.last = Some(*timestamp);
+
+        let day = timestamp.with_timezone(&Local).naive_local().date();
+        let gap = last.map(|last| *timestamp - last);
+        if last.map(|last| last.with_timezone(&Local).naive_local().date()) != Some(day) {
+            let separator = self.renderer.separator(&day.format("%Y-%m-%d").to_string());
+            self.console.print(&separator);
+            self.last_header = None;
+        }
+
+        let long_gap = gap.filter(|gap| *gap >= Duration::hours(SESSION_GAP_HOURS));
+        let marker = match (resumed, long_gap) {
+            (true, Some(gap)) => format!("reconnected, {} gap", format_age(gap)),
+            (true, None) => "reconnected".to_string(),
+            (false, Some(gap)) => format!("{} gap", format_age(gap)),
+            (false, None) => return,
+        };
+        let marker = self.renderer.separator(&marker);
+        self.console.print(&marker);
+        self.last_header = None;
+    }
+}
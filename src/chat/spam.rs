@@ -0,0 +1,48 @@
+use chrono::Local;
+
+use ya_client::model::NodeId;
+
+use super::Chat;
+use crate::audit::AuditKind;
+use crate::spam::Verdict;
+
+impl Chat {
+    /// Returns false, if message of muted peer shouldn't be displayed.
+    pub(super) fn screen(
+        &mut self,
+        sender: NodeId,
+        group: Option<&str>,
+        name: &str,
+        content: &str,
+    ) -> bool {
+        match self.spam.check(sender, group, content) {
+            Verdict::Accept => true,
+            Verdict::Dropped => false,
+            Verdict::Muted(until) => {
+                log::info!("Muted [{}] as spammer.", sender);
+                let text = format!("{} [{}] muted as likely spam.", name, sender);
+                self.audit(AuditKind::Moderation, group, Some(sender), text);
+                self.notice(&format!(
+                    "{} is muted as likely spam until {}. Use /unmute {} to show messages again.",
+                    name,
+                    until.with_timezone(&Local).format("%H:%M"),
+                    sender
+                ));
+                false
+            }
+        }
+    }
+
+    pub(super) fn unmute(&mut self, pattern: &str) -> anyhow::Result<()> {
+        let (name, devices) = self.resolve_user(pattern)?;
+        let mut muted = false;
+        for device in devices {
+            muted |= self.spam.unmute(device);
+        }
+        self.console.print(&match muted {
+            true => format!("Unmuted {}. He won't be muted automatically again.", name),
+            false => format!("{} wasn't muted. He won't be muted automatically.", name),
+        });
+        Ok(())
+    }
+}
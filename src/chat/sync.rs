@@ -0,0 +1,90 @@
+use actix::prelude::*;
+use std::str::FromStr;
+
+use ya_client::model::NodeId;
+use ya_service_bus::{typed as bus, RpcEndpoint, RpcEnvelope};
+
+use super::Chat;
+use crate::history::HistoryEntry;
+use crate::protocol::{ChatError, SyncHistory};
+
+/// Maximal number of messages sent in single sync response.
+const MAX_SYNC: usize = 1000;
+
+impl Chat {
+    /// Asks our other device for messages, which were received in group,
+    /// while we were offline.
+    pub(super) fn sync_history(&mut self, device: NodeId, group: String, ctx: &mut Context<Self>) {
+        let since = match self.group_index(&group) {
+            Some(idx) => self.groups[idx].history.last().map(|entry| entry.timestamp),
+            None => return,
+        };
+
+        let msg = SyncHistory {
+            group: group.clone(),
+            since,
+        };
+        let future = async move {
+            bus::service(format!("/net/{}/yachat", device))
+                .send(msg)
+                .await
+        }
+        .into_actor(self)
+        .map(move |result, myself, _| {
+            let entries = match result.map_err(anyhow::Error::from).and_then(|r| Ok(r?)) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    log::warn!("Failed to sync history with [{}]. Error: {}", device, e);
+                    return;
+                }
+            };
+
+            let idx = match myself.group_index(&group) {
+                Some(idx) => idx,
+                None => return,
+            };
+            match myself.groups[idx].merge_history(entries) {
+                0 => (),
+                added => myself.notice(&format!(
+                    "Synced {} message(s){} from your other device.",
+                    added,
+                    myself.group_tag(&group)
+                )),
+            }
+        });
+        ctx.spawn(future);
+    }
+}
+
+impl Handler<RpcEnvelope<SyncHistory>> for Chat {
+    type Result = Result<Vec<HistoryEntry>, ChatError>;
+
+    fn handle(&mut self, msg: RpcEnvelope<SyncHistory>, _: &mut Context<Self>) -> Self::Result {
+        let caller = NodeId::from_str(msg.caller()).map_err(|_| ChatError::InvalidNodeId)?;
+        if self.reject_blocked(&caller) {
+            return Err(ChatError::Rejected);
+        }
+
+        // History is shared only with devices linked with the same user.
+        let own_device = match self.find_user(&caller) {
+            Some(desc) => Some(desc.user_id()) == self.user_id(),
+            None => false,
+        };
+        if !own_device {
+            log::warn!("Rejected history sync request from [{}].", caller);
+            return Err(ChatError::Rejected);
+        }
+
+        let idx = self
+            .group_index(&msg.group)
+            .ok_or(ChatError::UnknownGroup)?;
+        let entries = self.groups[idx].history.since(msg.since, MAX_SYNC);
+        log::info!(
+            "Sending {} history entries of group {} to [{}].",
+            entries.len(),
+            msg.group,
+            caller
+        );
+        Ok(entries)
+    }
+}
@@ -0,0 +1,34 @@
+use actix::prelude::*;
+
+use super::Chat;
+use crate::update;
+
+/// Hint printed, when newer release is found.
+#[derive(Message)]
+#[rtype(result = "()")]
+struct UpdateAvailable(String);
+
+impl Chat {
+    /// Release is checked in background thread, since curl blocks.
+    pub(super) fn check_update(&mut self, ctx: &mut Context<Self>) {
+        if !self.update_check {
+            return;
+        }
+        let chat = ctx.address().recipient();
+        std::thread::spawn(move || match update::check() {
+            Ok(Some(release)) => {
+                chat.do_send(UpdateAvailable(update::hint(&release))).ok();
+            }
+            Ok(None) => log::info!("yachat {} is up to date.", update::VERSION),
+            Err(e) => log::warn!("Failed to check for update. Error: {}", e),
+        });
+    }
+}
+
+impl Handler<UpdateAvailable> for Chat {
+    type Result = ();
+
+    fn handle(&mut self, msg: UpdateAvailable, _: &mut Context<Self>) -> Self::Result {
+        self.notice(&msg.0);
+    }
+}
@@ -0,0 +1,45 @@
+use actix::prelude::*;
+
+use super::Chat;
+use crate::discover::{DiscoveryCycle, ListSubscriptions};
+
+impl Chat {
+    /// Prints notice only with at least `level` of `-v` flags.
+    pub(super) fn detail(&mut self, level: u8, text: &str) {
+        if self.verbosity >= level {
+            self.notice(text);
+        }
+    }
+
+    /// Offer and Demand ids of group, for looking them up in yagna logs.
+    pub(super) fn print_subscription(&mut self, group: String, ctx: &mut Context<Self>) {
+        if self.verbosity < 1 {
+            return;
+        }
+        let future = self.discovery.send(ListSubscriptions).into_actor(self).map(
+            move |result, myself, _| {
+                let subscriptions = result.unwrap_or_default();
+                for sub in subscriptions.iter().filter(|sub| sub.group == group) {
+                    let text = format!(
+                        "Group {} subscribed. Offer: {}, Demand: {}.",
+                        sub.group, sub.offer, sub.demand
+                    );
+                    myself.notice(&text);
+                }
+            },
+        );
+        ctx.spawn(future);
+    }
+}
+
+impl Handler<DiscoveryCycle> for Chat {
+    type Result = ();
+
+    fn handle(&mut self, msg: DiscoveryCycle, _: &mut Context<Self>) -> Self::Result {
+        let text = format!(
+            "Discovery in group {}: {} market event(s).",
+            msg.group, msg.events
+        );
+        self.detail(2, &text);
+    }
+}
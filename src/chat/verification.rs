@@ -0,0 +1,300 @@
+use actix::prelude::*;
+use anyhow::{anyhow, bail};
+use chrono::Utc;
+use std::str::FromStr;
+
+use ya_client::model::NodeId;
+use ya_core_model::identity;
+use ya_service_bus::{typed as bus, RpcEndpoint, RpcEnvelope};
+
+use super::{Chat, ChatEvent, NewUser};
+use crate::audit::AuditKind;
+use crate::challenge;
+use crate::hooks::Event;
+use crate::protocol::{ChatError, IAm, WhoAreYou};
+
+impl Chat {
+    /// Records user in contacts and adopts group settings advertised by him.
+    /// Returns name, under which user should be displayed.
+    pub(super) fn register(&mut self, group: usize, msg: &NewUser) -> String {
+        if self.contacts.seen(msg.address, &msg.user, &msg.group) {
+            self.save_contacts();
+        }
+
+        let display_name = self.contacts.display_name(&msg.address, &msg.user);
+        if let Some(announcers) = msg.announcers.clone() {
+            self.adopt_announcers(group, msg.address, announcers, &display_name);
+        }
+        if let Some(fee) = msg.fee.clone() {
+            self.adopt_fee(group, msg.address, fee, &display_name);
+        }
+        if let Some(max_members) = msg.max_members {
+            self.adopt_max_members(group, max_members);
+        }
+        display_name
+    }
+
+    /// Last seen times are saved only with other changes and at shutdown.
+    pub(super) fn save_contacts(&self) {
+        self.contacts
+            .save()
+            .map_err(|e| log::warn!("Failed to save contacts. Error: {}", e))
+            .ok();
+    }
+
+    /// Verification is stored for user's primary node, so it covers
+    /// all his devices.
+    pub(super) fn is_verified(&self, node_id: &NodeId) -> bool {
+        self.find_user(node_id)
+            .map_or(false, |desc| self.contacts.is_verified(&desc.user_id()))
+    }
+
+    /// Badge displayed after name of verified user.
+    pub(super) fn badge(&self, node_id: &NodeId) -> &'static str {
+        match (self.is_verified(node_id), self.accessible) {
+            (false, _) => "",
+            (true, false) => " ✓",
+            (true, true) => " (verified)",
+        }
+    }
+
+    /// Displays safety code derived from our and user's keys or, after
+    /// user compared it with his, marks him verified.
+    pub(super) fn verify_user(&mut self, pattern: &str, confirm: bool) -> anyhow::Result<()> {
+        let ours = self
+            .user_id()
+            .ok_or_else(|| anyhow!("Our identity isn't known yet. Try again later."))?;
+        let (name, devices) = self.resolve_user(pattern)?;
+        let desc = devices
+            .first()
+            .and_then(|device| self.find_user(device))
+            .ok_or_else(|| anyhow!("No user matching '{}'.", pattern))?;
+        let (theirs, reported) = (desc.user_id(), desc.name.clone());
+
+        if !confirm {
+            let verified = match self.contacts.is_verified(&theirs) {
+                true => format!("{} is already verified. ", name),
+                false => String::new(),
+            };
+            self.console.print(&format!(
+                "Safety code with {} [{}]:\n  {}\n{}Compare it with the code {} sees, \
+                 using other channel. If they match, type /verify {} confirm.",
+                name,
+                theirs,
+                challenge::safety_code(&ours, &theirs),
+                verified,
+                name,
+                pattern
+            ));
+            return Ok(());
+        }
+
+        self.contacts.set_verified(theirs, &reported)?;
+        self.notice(&format!(
+            "{} [{}] is verified. You will be warned, if his key changes.",
+            name, theirs
+        ));
+        let text = format!("{} [{}] verified.", name, theirs);
+        self.audit(AuditKind::Key, None, Some(theirs), text);
+        Ok(())
+    }
+
+    /// User, whose key doesn't match the one we verified under the same
+    /// name, is either impersonator or the user with new key.
+    fn warn_key_change(&mut self, display_name: &str, user_id: NodeId) {
+        if self.contacts.is_verified(&user_id) {
+            return;
+        }
+        let previous = self
+            .contacts
+            .verified_named(display_name)
+            .into_iter()
+            .map(|contact| contact.node_id)
+            .filter(|node_id| node_id != &user_id)
+            .collect::<Vec<_>>();
+        for node_id in previous {
+            self.notice(&format!(
+                "⚠ {} [{}] isn't verified {} [{}]. Either key of the user changed or \
+                 somebody impersonates him. Compare safety code with /verify.",
+                display_name, user_id, display_name, node_id
+            ));
+            let text = format!(
+                "{} [{}] appeared with name of verified [{}].",
+                display_name, user_id, node_id
+            );
+            self.audit(AuditKind::Key, None, Some(user_id), text);
+        }
+    }
+
+    /// Discovered user is added to roster only after proving, that he controls
+    /// NodeId from his proposal and that he uses advertised name.
+    pub(super) fn challenge(&mut self, msg: NewUser, ctx: &mut Context<Self>) {
+        if !self.verifying.insert((msg.address, msg.group.clone())) {
+            return;
+        }
+
+        let nonce = challenge::nonce();
+        let address = msg.address;
+        let user = msg.user.clone();
+        let group_name = msg.group.clone();
+
+        let future = async move {
+            let response = bus::service(format!("/net/{}/yachat", address))
+                .send(WhoAreYou {
+                    nonce: nonce.clone(),
+                    group: Some(group_name),
+                })
+                .await??;
+            if response.name != user {
+                bail!(
+                    "Signed name '{}' doesn't match advertised '{}'.",
+                    response.name,
+                    user
+                );
+            }
+            challenge::verify(&address, &nonce, &response.name, &response.signature)?;
+
+            // Device linked to other user is displayed as that user.
+            match response.device {
+                Some(cert) => {
+                    challenge::verify_device(&cert.user, &address, &cert.signature)?;
+                    Ok(Some(cert.user))
+                }
+                None => Ok(None),
+            }
+        }
+        .into_actor(self)
+        .map(move |result, myself, ctx| {
+            myself.verifying.remove(&(msg.address, msg.group.clone()));
+            // Group could have been left, while challenge was pending.
+            let group = match myself.group_index(&msg.group) {
+                Some(group) => group,
+                None => return,
+            };
+            match result {
+                Ok(user) => myself.admit(group, msg, user, ctx),
+                Err(e) if matches!(e.downcast_ref(), Some(ChatError::GroupFull)) => {
+                    myself.rejected_as_full(group, &msg)
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Failed to verify identity of {} [{}]. Error: {}",
+                        msg.user,
+                        msg.address,
+                        e
+                    );
+                    if myself.unverified.insert(msg.address) {
+                        myself.notice(&format!(
+                            "Couldn't verify identity of {} [{}]. Ignoring.",
+                            msg.user, msg.address
+                        ));
+                    }
+                }
+            }
+        });
+        ctx.spawn(future);
+    }
+
+    pub(super) fn admit(
+        &mut self,
+        group: usize,
+        msg: NewUser,
+        user: Option<NodeId>,
+        ctx: &mut Context<Self>,
+    ) {
+        self.unverified.remove(&msg.address);
+        let display_name = self.register(group, &msg);
+
+        let tag = self.group_tag(&msg.group);
+        let user_id = user.unwrap_or(msg.address);
+        let own_device = Some(user_id) == self.user_id();
+        if !own_device {
+            self.warn_key_change(&display_name, user_id);
+        }
+        let notice = if own_device {
+            format!("Your other device [{}] joined{}", msg.address, tag)
+        } else if self.has_other_device(user_id, &msg.address) {
+            format!("{} connected another device{}", &display_name, tag)
+        } else {
+            format!("New user appeared: {}{}", &display_name, tag)
+        };
+        self.notice(&notice);
+        if let Some(chat_log) = &self.chat_log {
+            chat_log.event(
+                &msg.group,
+                &Utc::now(),
+                &format!("{} [{}] joined", display_name, msg.address),
+            );
+        }
+        self.emit(ChatEvent::UserJoined {
+            group: msg.group.clone(),
+            user: display_name.clone(),
+            node_id: msg.address,
+        });
+        let text = format!("{} [{}] joined.", display_name, msg.address);
+        self.audit(AuditKind::Roster, Some(&msg.group), Some(msg.address), text);
+        self.fire(
+            Event::UserJoined,
+            Some(&msg.group),
+            &display_name,
+            msg.address,
+            None,
+        );
+
+        let group = &mut self.groups[group];
+        group.users.add(&msg.user, msg.address, &msg.group, user);
+        group.save_roster();
+        group.send_members(msg.address);
+        self.send_profile(msg.address);
+        self.send_presence(msg.address);
+
+        if own_device {
+            self.sync_history(msg.address, msg.group, ctx);
+        }
+    }
+}
+
+impl Handler<RpcEnvelope<WhoAreYou>> for Chat {
+    type Result = ActorResponse<Self, IAm, ChatError>;
+
+    fn handle(&mut self, msg: RpcEnvelope<WhoAreYou>, _: &mut Context<Self>) -> Self::Result {
+        let node_id = match self.node_id {
+            Some(node_id) => node_id,
+            None => return ActorResponse::reply(Err(ChatError::IdentityUnavailable)),
+        };
+        let caller = match NodeId::from_str(msg.caller()) {
+            Ok(caller) => caller,
+            Err(_) => return ActorResponse::reply(Err(ChatError::InvalidNodeId)),
+        };
+        if self.reject_blocked(&caller) {
+            return ActorResponse::reply(Err(ChatError::Rejected));
+        }
+        if let Some(group) = &msg.group {
+            if self.refuses_member(group, &caller) {
+                log::info!("Refused [{}] joining full group {}.", caller, group);
+                return ActorResponse::reply(Err(ChatError::GroupFull));
+            }
+        }
+
+        let name = self.me.clone();
+        let device = self.device.clone();
+        let payload = challenge::challenge_hash(&msg.into_inner().nonce, &name);
+
+        let future = async move {
+            let signature = bus::service(identity::BUS_ID)
+                .send(identity::Sign { node_id, payload })
+                .await
+                .map_err(|e| log::warn!("Failed to sign challenge. Error: {}", e))
+                .and_then(|result| {
+                    result.map_err(|e| log::warn!("Failed to sign challenge. Error: {}", e))
+                })
+                .map_err(|_| ChatError::IdentityUnavailable)?;
+            Ok(IAm {
+                name,
+                signature,
+                device,
+            })
+        };
+        ActorResponse::r#async(future.into_actor(self))
+    }
+}
@@ -0,0 +1,71 @@
+use actix::prelude::*;
+
+use ya_client::model::NodeId;
+
+use super::Chat;
+use crate::hooks::{Event, EventData};
+use crate::plugin::PluginCommand;
+use crate::protocol::TextMessage;
+
+impl Chat {
+    /// Commands without group answer in group of the message. Direct
+    /// messages are answered directly.
+    pub(super) fn run_wasm_plugins(
+        &mut self,
+        sender: NodeId,
+        group: Option<&str>,
+        name: &str,
+        text: &TextMessage,
+        ctx: &mut Context<Self>,
+    ) {
+        if self.receive_only || self.wasm_plugins.is_empty() {
+            return;
+        }
+        if self.find_user(&sender).map(|desc| desc.user_id()) == self.user_id() {
+            return;
+        }
+        let mut data = EventData::new(match group {
+            Some(_) => Event::Message,
+            None => Event::Direct,
+        });
+        data.group = group.map(str::to_string);
+        data.user = Some(name.to_string());
+        data.node_id = Some(sender);
+        data.text = Some(text.content.clone());
+        let event = match serde_json::to_string(&data) {
+            Ok(event) => event,
+            Err(e) => return log::warn!("Failed to serialize event for plugins. Error: {}", e),
+        };
+
+        let mut commands = vec![];
+        for plugin in self.wasm_plugins.iter_mut() {
+            if !plugin.config.applies(group) {
+                continue;
+            }
+            match plugin.on_message(&event) {
+                Ok(output) => commands.extend(output),
+                Err(e) => log::warn!(
+                    "Plugin {} failed. Error: {}",
+                    plugin.config.path.display(),
+                    e
+                ),
+            }
+        }
+        for command in commands {
+            let command = match (command, group) {
+                (PluginCommand::Send { group: None, text }, Some(group)) => PluginCommand::Send {
+                    group: Some(group.to_string()),
+                    text,
+                },
+                (PluginCommand::Send { group: None, text }, None) => PluginCommand::Direct {
+                    user: sender.to_string(),
+                    text,
+                },
+                (command, _) => command,
+            };
+            if let Err(e) = self.run_plugin_command(command, ctx) {
+                log::warn!("Command of WASM plugin failed. Error: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use actix::prelude::*;
+use anyhow::anyhow;
+
+use super::Chat;
+use crate::webhook::IncomingWebhook;
+
+impl Handler<IncomingWebhook> for Chat {
+    type Result = anyhow::Result<()>;
+
+    /// Errors use Slack error codes, since they are returned to caller.
+    fn handle(&mut self, msg: IncomingWebhook, ctx: &mut Context<Self>) -> Self::Result {
+        let idx = match &msg.group {
+            Some(group) => self
+                .group_index(group)
+                .ok_or_else(|| anyhow!("channel_not_found"))?,
+            None => self.active,
+        };
+        let future = self
+            .post(idx, msg.text, None, ctx)
+            .map_err(|_| anyhow!("action_prohibited"))?
+            .into_actor(self)
+            .map(|result, _, _| {
+                if let Err(e) = result {
+                    log::warn!("Failed to send webhook message. Error: {}", e);
+                }
+            });
+        ctx.spawn(future);
+        Ok(())
+    }
+}
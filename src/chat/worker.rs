@@ -0,0 +1,119 @@
+use actix::prelude::*;
+use std::sync::Arc;
+
+use ya_client::model::NodeId;
+
+use crate::history::HistoryEntry;
+use crate::retention::Retention;
+use crate::roster::Roster;
+use crate::storage::Storage;
+
+/// Writes state of single group to storage on its own arbiter. Slow disk
+/// or encryption of one group doesn't stall `Chat` and other groups, and
+/// panic while writing stops only this worker.
+pub(super) struct GroupWorker {
+    group: String,
+    storage: Arc<dyn Storage>,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(super) struct AppendHistory(pub Vec<HistoryEntry>);
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(super) struct SaveRoster(pub Roster);
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(super) struct Prune(pub Retention);
+
+/// Replied with number of removed messages.
+#[derive(Message)]
+#[rtype(result = "usize")]
+pub(super) struct ForgetSenders(pub Vec<NodeId>);
+
+/// Replied after all writes queued before were done. Stops the worker.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(super) struct StopWorker;
+
+impl GroupWorker {
+    pub(super) fn spawn(group: &str, storage: Arc<dyn Storage>) -> Addr<GroupWorker> {
+        let group = group.to_string();
+        GroupWorker::start_in_arbiter(&Arbiter::new(), move |_| GroupWorker { group, storage })
+    }
+}
+
+impl Actor for GroupWorker {
+    type Context = Context<Self>;
+}
+
+impl Handler<AppendHistory> for GroupWorker {
+    type Result = ();
+
+    fn handle(&mut self, msg: AppendHistory, _: &mut Context<Self>) -> Self::Result {
+        for entry in msg.0.iter() {
+            if let Err(e) = self.storage.append_message(entry) {
+                log::error!(
+                    "Failed to save message in history of {}. Error: {}",
+                    self.group,
+                    e
+                );
+            }
+        }
+    }
+}
+
+impl Handler<SaveRoster> for GroupWorker {
+    type Result = ();
+
+    fn handle(&mut self, msg: SaveRoster, _: &mut Context<Self>) -> Self::Result {
+        msg.0
+            .save(self.storage.as_ref())
+            .map_err(|e| log::warn!("Failed to save roster of {}. Error: {}", self.group, e))
+            .ok();
+    }
+}
+
+impl Handler<Prune> for GroupWorker {
+    type Result = ();
+
+    fn handle(&mut self, msg: Prune, _: &mut Context<Self>) -> Self::Result {
+        match self
+            .storage
+            .prune(&self.group, msg.0.cutoff(), msg.0.messages)
+        {
+            Ok(0) => (),
+            Ok(removed) => log::info!("Pruned {} messages of {}.", removed, self.group),
+            Err(e) => log::warn!("Failed to prune history of {}. Error: {}", self.group, e),
+        }
+    }
+}
+
+impl Handler<ForgetSenders> for GroupWorker {
+    type Result = usize;
+
+    fn handle(&mut self, msg: ForgetSenders, _: &mut Context<Self>) -> Self::Result {
+        match self.storage.delete_by_sender(&self.group, &msg.0) {
+            Ok(removed) => removed,
+            Err(e) => {
+                log::error!(
+                    "Failed to delete messages from history of {}. Error: {}",
+                    self.group,
+                    e
+                );
+                0
+            }
+        }
+    }
+}
+
+impl Handler<StopWorker> for GroupWorker {
+    type Result = ();
+
+    fn handle(&mut self, _: StopWorker, ctx: &mut Context<Self>) -> Self::Result {
+        ctx.stop();
+        Arbiter::current().stop();
+    }
+}
@@ -0,0 +1,71 @@
+use chrono::{DateTime, Local, Utc};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::storage::file_name;
+
+const CHAT_LOGS_DIR: &str = "chat";
+
+/// Plaintext daily logs of group conversations in irssi-like format, for
+/// people, who grep their logs. Written next to history and never
+/// encrypted, even with `--encrypt`.
+pub struct ChatLog {
+    dir: PathBuf,
+}
+
+impl ChatLog {
+    pub fn new(logs_dir: &Path) -> ChatLog {
+        ChatLog {
+            dir: logs_dir.join(CHAT_LOGS_DIR),
+        }
+    }
+
+    pub fn message(&self, group: &str, timestamp: &DateTime<Utc>, user: &str, text: &str) {
+        let local = timestamp.with_timezone(&Local);
+        let lines = text
+            .lines()
+            .map(|line| format!("{} <{}> {}\n", local.format("%H:%M"), user, line))
+            .collect::<String>();
+        self.write(group, &local, &lines);
+    }
+
+    /// Joins and other notices, marked with `-!-`.
+    pub fn event(&self, group: &str, timestamp: &DateTime<Utc>, text: &str) {
+        let local = timestamp.with_timezone(&Local);
+        self.write(
+            group,
+            &local,
+            &format!("{} -!- {}\n", local.format("%H:%M"), text),
+        );
+    }
+
+    /// Removes all logs of group.
+    pub fn delete(&self, group: &str) -> anyhow::Result<()> {
+        let dir = self.dir.join(file_name(group));
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        Ok(())
+    }
+
+    fn write(&self, group: &str, local: &DateTime<Local>, lines: &str) {
+        if let Err(e) = self.append(group, local, lines) {
+            log::warn!("Failed to write chat log of group {}. Error: {}", group, e);
+        }
+    }
+
+    fn append(&self, group: &str, local: &DateTime<Local>, lines: &str) -> anyhow::Result<()> {
+        let dir = self.dir.join(file_name(group));
+        fs::create_dir_all(&dir)?;
+
+        let path = dir.join(format!("{}.log", local.format("%Y-%m-%d")));
+        let new = !path.exists();
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        if new {
+            writeln!(file, "--- Log opened {}", local.format("%a %b %d %Y"))?;
+        }
+        file.write_all(lines.as_bytes())?;
+        Ok(())
+    }
+}
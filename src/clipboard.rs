@@ -0,0 +1,36 @@
+use anyhow::anyhow;
+
+/// Connection to system clipboard, opened on first use. Kept open, since on
+/// X11 copied text is served only while connection lives.
+#[derive(Default)]
+pub struct Clipboard {
+    clipboard: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    /// Fails on headless systems and terminals without access to display.
+    fn connect(&mut self) -> anyhow::Result<&mut arboard::Clipboard> {
+        if self.clipboard.is_none() {
+            let clipboard = arboard::Clipboard::new()
+                .map_err(|e| anyhow!("Clipboard isn't available. Error: {}", e))?;
+            self.clipboard = Some(clipboard);
+        }
+        Ok(self.clipboard.as_mut().unwrap())
+    }
+
+    /// Text from clipboard with line endings normalized and trailing
+    /// whitespace removed.
+    pub fn get(&mut self) -> anyhow::Result<String> {
+        let text = self
+            .connect()?
+            .get_text()
+            .map_err(|e| anyhow!("Failed to read clipboard. Error: {}", e))?;
+        Ok(text.replace("\r\n", "\n").trim_end().to_string())
+    }
+
+    pub fn set(&mut self, text: &str) -> anyhow::Result<()> {
+        self.connect()?
+            .set_text(text.to_string())
+            .map_err(|e| anyhow!("Failed to write clipboard. Error: {}", e))
+    }
+}
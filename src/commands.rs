@@ -0,0 +1,974 @@
+use anyhow::{anyhow, bail};
+use chrono::{DateTime, Utc};
+use url::Url;
+
+use crate::layout::MessageLayout;
+use crate::report::ReportFormat;
+use crate::schedule::{parse_delay, parse_time};
+use crate::stats::Period;
+
+pub enum QueueCommand {
+    /// Queued message counts and ages per recipient.
+    List,
+    /// Retries delivery now, to given user or all recipients.
+    Flush(Option<String>),
+    /// Discards messages queued for user.
+    Drop(String),
+}
+
+/// Commands typed by user in input line. Every line starting with `/`
+/// is treated as command and is never sent to other users. Lines starting
+/// with `r ` and `rr ` are shortcuts for `/reply` and `/reply-direct`.
+pub enum Command {
+    /// Lists commands or describes single one.
+    Help(Option<String>),
+    Open(usize),
+    Emoji(String),
+    Alias {
+        pattern: String,
+        alias: Option<String>,
+    },
+    Contacts,
+    /// Lists users of active group with their state.
+    Users,
+    /// Statistics of given or active group.
+    ChatStats {
+        group: Option<String>,
+        period: Period,
+    },
+    Poll {
+        question: String,
+        options: Vec<String>,
+    },
+    Vote {
+        poll: String,
+        option: usize,
+    },
+    /// System events recorded in given period.
+    Events(Period),
+    /// Pins message with given id prefix or last message if None.
+    Pin(Option<String>),
+    Pins,
+    /// Sends clipboard contents. Large pastes need confirmation.
+    Paste {
+        confirm: bool,
+    },
+    /// Copies message with given id prefix or last message if None.
+    Copy(Option<String>),
+    /// Concludes membership Agreement with owner of paid group.
+    Join,
+    Revoke(String),
+    /// Switches active group (joining it if needed) or lists groups.
+    Group(Option<String>),
+    /// Subscribes to channel of active group and posts there.
+    Sub(String),
+    Unsub(String),
+    Channels,
+    /// Shows our profile or profile of user.
+    Profile(Option<String>),
+    SetProfile {
+        field: String,
+        value: Vec<String>,
+    },
+    /// Message sent to all devices of single user.
+    Direct {
+        pattern: String,
+        text: String,
+    },
+    /// Opens direct conversation with user or lists conversations.
+    Dm(Option<String>),
+    /// Answers last received message, in its group or directly.
+    Reply(String),
+    /// Answers sender of last direct message.
+    ReplyDirect(String),
+    /// Displays safety code of user or marks him verified.
+    Verify {
+        pattern: String,
+        confirm: bool,
+    },
+    /// Removes everything stored about user, after confirmation.
+    Forget {
+        pattern: String,
+        confirm: bool,
+    },
+    Reload,
+    /// Our identity, endpoints and market subscriptions.
+    Whoami,
+    /// Delivery outcome of our messages per recipient.
+    Report(Option<ReportFormat>),
+    /// Adds term to watch list or lists watched terms, if None.
+    Watch(Option<String>),
+    Unwatch(String),
+    /// Lifts automatic spam mute of user.
+    Unmute(String),
+    /// Switches message layout or shows current one, if None.
+    Layout(Option<MessageLayout>),
+    /// Inspects or controls messages waiting for offline recipients.
+    Queue(QueueCommand),
+    /// Rejects all messages of user or lists blocked users, if None.
+    Block(Option<String>),
+    Unblock(String),
+    /// Relays messages between two groups or lists bridges.
+    Bridge(Option<(String, String)>),
+    Unbridge(String, String),
+    /// Hides messages of group until given time.
+    MuteGroup {
+        group: String,
+        until: DateTime<Utc>,
+    },
+    UnmuteGroup(String),
+    /// Invokes bot command in active group, same as typing `!name args`.
+    Bot {
+        name: String,
+        args: String,
+    },
+    /// Enables auto-reply to direct messages, optionally with custom text.
+    Away(Option<String>),
+    Back,
+    /// Message sent to active group at given time.
+    SendAt {
+        at: DateTime<Utc>,
+        text: String,
+    },
+    /// Reminder displayed only to us.
+    Remind {
+        at: DateTime<Utc>,
+        text: String,
+    },
+    /// Signs certificate linking other node as our device.
+    Device(String),
+    /// Links this node with user, who signed certificate.
+    Link(String),
+    /// Displays pairing code on primary node or, given primary NodeId and
+    /// code, pairs this node with it.
+    Pair(Option<(String, String)>),
+}
+
+/// Entry of command registry. Registry is the single place, where
+/// commands are declared: it drives parsing, `/help` and suggestions.
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// Arguments in usage notation, for example `<NodeId or name> [nickname]`.
+    pub args: &'static str,
+    pub help: &'static str,
+    /// Rest of line after this many arguments is passed to `parse` as
+    /// single argument, keeping quotes and whitespace of message text.
+    text_after: Option<usize>,
+    /// Returns `Ok(None)`, when arguments don't match spec.
+    parse: fn(&[String]) -> anyhow::Result<Option<Command>>,
+}
+
+impl CommandSpec {
+    pub fn usage(&self) -> String {
+        match self.args.is_empty() {
+            true => format!("/{}", self.name),
+            false => format!("/{} {}", self.name, self.args),
+        }
+    }
+}
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "help",
+        args: "[command]",
+        help: "Lists commands or describes single command.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [] => Some(Command::Help(None)),
+                [name] => Some(Command::Help(Some(
+                    name.trim_start_matches('/').to_string(),
+                ))),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "open",
+        args: "<link number>",
+        help: "Opens link with given number in default browser.",
+        text_after: None,
+        parse: |args| match args {
+            [index] => Ok(Some(Command::Open(
+                index
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid link number: {}", index))?,
+            ))),
+            _ => Ok(None),
+        },
+    },
+    CommandSpec {
+        name: "emoji",
+        args: "[query]",
+        help: "Searches emoji shortcodes.",
+        text_after: None,
+        parse: |args| Ok(Some(Command::Emoji(args.join(" ")))),
+    },
+    CommandSpec {
+        name: "alias",
+        args: "<NodeId or name> [nickname]",
+        help: "Sets local nickname of user or removes it, when nickname is omitted.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [pattern] => Some(Command::Alias {
+                    pattern: pattern.to_string(),
+                    alias: None,
+                }),
+                [pattern, alias @ ..] => Some(Command::Alias {
+                    pattern: pattern.to_string(),
+                    alias: Some(alias.join(" ")),
+                }),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "profile",
+        args: "[<NodeId or name> | set <name|bio|avatar|links> [value]]",
+        help: "Shows profile of user or ours. Set changes our profile, empty value clears field.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [] => Some(Command::Profile(None)),
+                [set, field, value @ ..] if set == "set" => Some(Command::SetProfile {
+                    field: field.to_string(),
+                    value: value.iter().map(|word| word.to_string()).collect(),
+                }),
+                [pattern] => Some(Command::Profile(Some(pattern.to_string()))),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "contacts",
+        args: "",
+        help: "Lists users seen in any group.",
+        text_after: None,
+        parse: |args| Ok(no_args(args, Command::Contacts)),
+    },
+    CommandSpec {
+        name: "users",
+        args: "",
+        help: "Lists users of active group and shows, which of them are online.",
+        text_after: None,
+        parse: |args| Ok(no_args(args, Command::Users)),
+    },
+    CommandSpec {
+        name: "chatstats",
+        args: "[group] [all|day|week|month|3d]",
+        help: "Shows most active users, messages per hour and busiest day of group.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [] => Some(Command::ChatStats {
+                    group: None,
+                    period: Period::all(),
+                }),
+                [arg] => Some(match arg.parse() {
+                    Ok(period) => Command::ChatStats {
+                        group: None,
+                        period,
+                    },
+                    Err(_) => Command::ChatStats {
+                        group: Some(arg.to_string()),
+                        period: Period::all(),
+                    },
+                }),
+                [group, period] => Some(Command::ChatStats {
+                    group: Some(group.to_string()),
+                    period: period.parse()?,
+                }),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "events",
+        args: "[all|day|week|month|3d]",
+        help: "Shows system events: joins, moderation, key changes, subscriptions and delivery failures. Last day by default.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [] => Some(Command::Events(Period::day())),
+                [period] => Some(Command::Events(period.parse()?)),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "poll",
+        args: "\"Question?\" <option> <option> [option...]",
+        help: "Creates poll in active group.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [question, options @ ..] if options.len() >= 2 => Some(Command::Poll {
+                    question: question.to_string(),
+                    options: options.to_vec(),
+                }),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "vote",
+        args: "<poll id> <option number>",
+        help: "Votes in poll.",
+        text_after: None,
+        parse: |args| match args {
+            [poll, option] => Ok(Some(Command::Vote {
+                poll: poll.trim_start_matches('#').to_string(),
+                option: option
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid option number: {}", option))?,
+            })),
+            _ => Ok(None),
+        },
+    },
+    CommandSpec {
+        name: "pin",
+        args: "[message id]",
+        help: "Pins message in active group. Last message is pinned, when id is omitted.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [] => Some(Command::Pin(None)),
+                [id] => Some(Command::Pin(Some(id.trim_start_matches('#').to_string()))),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "paste",
+        args: "[confirm]",
+        help: "Sends clipboard contents to active group as single message.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [] => Some(Command::Paste { confirm: false }),
+                [confirm] if confirm == "confirm" => Some(Command::Paste { confirm: true }),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "copy",
+        args: "[message id]",
+        help: "Copies message from active group to clipboard. Last message is copied, when id is omitted.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [] => Some(Command::Copy(None)),
+                [id] => Some(Command::Copy(Some(id.trim_start_matches('#').to_string()))),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "pins",
+        args: "",
+        help: "Lists messages pinned in active group.",
+        text_after: None,
+        parse: |args| Ok(no_args(args, Command::Pins)),
+    },
+    CommandSpec {
+        name: "group",
+        args: "[name]",
+        help: "Switches active group, joining it if needed. Lists groups without name.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [] => Some(Command::Group(None)),
+                [name] => Some(Command::Group(Some(name.to_string()))),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "sub",
+        args: "<#channel>",
+        help: "Shows messages of channel in active group and posts there. #general is default.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [channel] => Some(Command::Sub(channel.to_string())),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "unsub",
+        args: "<#channel>",
+        help: "Hides messages of channel. They are counted as unread.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [channel] => Some(Command::Unsub(channel.to_string())),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "channels",
+        args: "",
+        help: "Lists channels seen in active group with unread counts.",
+        text_after: None,
+        parse: |args| Ok(no_args(args, Command::Channels)),
+    },
+    CommandSpec {
+        name: "join",
+        args: "",
+        help: "Concludes membership Agreement with owner of paid group.",
+        text_after: None,
+        parse: |args| Ok(no_args(args, Command::Join)),
+    },
+    CommandSpec {
+        name: "revoke",
+        args: "<NodeId or name>",
+        help: "Terminates membership Agreement in group owned by us.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [pattern] => Some(Command::Revoke(pattern.to_string())),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "msg",
+        args: "<NodeId or name> <text>",
+        help: "Sends direct message to all devices of user.",
+        text_after: Some(1),
+        parse: |args| {
+            Ok(match args {
+                [pattern, text] => Some(Command::Direct {
+                    pattern: pattern.to_string(),
+                    text: text.to_string(),
+                }),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "dm",
+        args: "[NodeId or name]",
+        help: "Opens direct conversation with user, showing his unread messages. Lists conversations without argument.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [] => Some(Command::Dm(None)),
+                [pattern] => Some(Command::Dm(Some(pattern.to_string()))),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "reply",
+        args: "<text>",
+        help: "Replies to last received message. Shortcut: r <text>.",
+        text_after: Some(0),
+        parse: |args| Ok(text_arg(args).map(Command::Reply)),
+    },
+    CommandSpec {
+        name: "reply-direct",
+        args: "<text>",
+        help: "Replies directly to sender of last direct message. Shortcut: rr <text>.",
+        text_after: Some(0),
+        parse: |args| Ok(text_arg(args).map(Command::ReplyDirect)),
+    },
+    CommandSpec {
+        name: "verify",
+        args: "<NodeId or name> [confirm]",
+        help: "Shows safety code to compare with user. Confirm marks him verified.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [pattern] => Some(Command::Verify {
+                    pattern: pattern.to_string(),
+                    confirm: false,
+                }),
+                [pattern, confirm] if confirm == "confirm" => Some(Command::Verify {
+                    pattern: pattern.to_string(),
+                    confirm: true,
+                }),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "forget",
+        args: "<NodeId or name> [confirm]",
+        help: "Removes user's messages, queued messages, sessions, alias and verification.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [pattern] => Some(Command::Forget {
+                    pattern: pattern.to_string(),
+                    confirm: false,
+                }),
+                [pattern, confirm] if confirm == "confirm" => Some(Command::Forget {
+                    pattern: pattern.to_string(),
+                    confirm: true,
+                }),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "reload",
+        args: "",
+        help: "Re-reads config file: hooks, filters, themes, layout, away and spam settings, watch list and groups.",
+        text_after: None,
+        parse: |args| Ok(no_args(args, Command::Reload)),
+    },
+    CommandSpec {
+        name: "whoami",
+        args: "",
+        help: "Shows our NodeId, identity alias, GSB endpoints, market subscriptions and version.",
+        text_after: None,
+        parse: |args| Ok(no_args(args, Command::Whoami)),
+    },
+    CommandSpec {
+        name: "report",
+        args: "[text|json]",
+        help: "Summarizes delivery of our messages per recipient since start.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [] => Some(Command::Report(None)),
+                [format] => Some(Command::Report(Some(format.parse()?))),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "watch",
+        args: "<word>|list",
+        help:
+            "Highlights word or phrase in all groups and runs watch hook. List shows watched terms.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [] => None,
+                [list] if list == "list" => Some(Command::Watch(None)),
+                terms => Some(Command::Watch(Some(terms.join(" ")))),
+            })
+        },
+    },
+    CommandSpec {
+        name: "unwatch",
+        args: "<word>",
+        help: "Removes word or phrase from watch list.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [] => None,
+                terms => Some(Command::Unwatch(terms.join(" "))),
+            })
+        },
+    },
+    CommandSpec {
+        name: "unmute",
+        args: "<NodeId or name>",
+        help: "Shows messages of user muted as spammer again and stops muting him.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [pattern] => Some(Command::Unmute(pattern.to_string())),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "layout",
+        args: "[standard|compact|verbose|grouped]",
+        help: "Switches how message headers are displayed or shows current layout.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [] => Some(Command::Layout(None)),
+                [layout] => Some(Command::Layout(Some(layout.parse()?))),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "queue",
+        args: "[flush [user]|drop <user>]",
+        help: "Shows messages waiting for offline users. Flush retries delivery now, drop discards them.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [] => Some(Command::Queue(QueueCommand::List)),
+                [flush] if flush == "flush" => Some(Command::Queue(QueueCommand::Flush(None))),
+                [flush, pattern] if flush == "flush" => Some(Command::Queue(
+                    QueueCommand::Flush(Some(pattern.to_string())),
+                )),
+                [drop, pattern] if drop == "drop" => {
+                    Some(Command::Queue(QueueCommand::Drop(pattern.to_string())))
+                }
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "block",
+        args: "<NodeId or name>|list",
+        help: "Rejects all messages of user on all his devices, until unblocked. List shows blocked users.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [list] if list == "list" => Some(Command::Block(None)),
+                [pattern] => Some(Command::Block(Some(pattern.to_string()))),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "unblock",
+        args: "<NodeId or name>",
+        help: "Accepts messages of blocked user again.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [pattern] => Some(Command::Unblock(pattern.to_string())),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "bridge",
+        args: "[<group> <group>]",
+        help: "Relays messages between two groups we joined, or lists bridges.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [] => Some(Command::Bridge(None)),
+                [first, second] => Some(Command::Bridge(Some((
+                    first.to_string(),
+                    second.to_string(),
+                )))),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "unbridge",
+        args: "<group> <group>",
+        help: "Stops relaying messages between two groups.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [first, second] => Some(Command::Unbridge(first.to_string(), second.to_string())),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "mute-group",
+        args: "<group> <duration like 2h or 30m>",
+        help: "Hides messages of group for given time. They are still saved in history.",
+        text_after: None,
+        parse: |args| match args {
+            [group, duration] => Ok(Some(Command::MuteGroup {
+                group: group.to_string(),
+                until: Utc::now() + parse_delay(duration)?,
+            })),
+            _ => Ok(None),
+        },
+    },
+    CommandSpec {
+        name: "unmute-group",
+        args: "<group>",
+        help: "Shows messages of muted group again and summarizes what was missed.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [group] => Some(Command::UnmuteGroup(group.to_string())),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "bot",
+        args: "<command> [args]",
+        help: "Invokes bot command in active group, same as typing !command args.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [name, args @ ..] => Some(Command::Bot {
+                    name: name.to_string(),
+                    args: args.join(" "),
+                }),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "away",
+        args: "[message]",
+        help:
+            "Answers direct messages automatically until /back. Message overrides config template.",
+        text_after: Some(0),
+        parse: |args| Ok(Some(Command::Away(args.first().cloned()))),
+    },
+    CommandSpec {
+        name: "back",
+        args: "",
+        help: "Disables auto-reply enabled by /away.",
+        text_after: None,
+        parse: |args| Ok(no_args(args, Command::Back)),
+    },
+    CommandSpec {
+        name: "send-at",
+        args: "<HH:MM> <text>",
+        help: "Sends message to active group at given time. Survives restart.",
+        text_after: Some(1),
+        parse: |args| match args {
+            [time, text] => Ok(Some(Command::SendAt {
+                at: parse_time(time)?,
+                text: text.to_string(),
+            })),
+            _ => Ok(None),
+        },
+    },
+    CommandSpec {
+        name: "remind",
+        args: "<delay like 20m or 1h30m> <text>",
+        help: "Displays reminder after given time. Reminders aren't sent to anybody.",
+        text_after: Some(1),
+        parse: |args| match args {
+            [delay, text] => Ok(Some(Command::Remind {
+                at: Utc::now() + parse_delay(delay)?,
+                text: text.to_string(),
+            })),
+            _ => Ok(None),
+        },
+    },
+    CommandSpec {
+        name: "pair",
+        args: "[<primary NodeId> <code>]",
+        help: "Displays pairing code on primary node. On other device pairs it with primary.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [] => Some(Command::Pair(None)),
+                [primary, code] => {
+                    Some(Command::Pair(Some((primary.to_string(), code.to_string()))))
+                }
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "device",
+        args: "<NodeId of your other node>",
+        help: "Prints certificate linking other node with us, to be used with /link.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [node_id] => Some(Command::Device(node_id.to_string())),
+                _ => None,
+            })
+        },
+    },
+    CommandSpec {
+        name: "link",
+        args: "<code printed by /device>",
+        help: "Links this node with user, who printed the certificate.",
+        text_after: None,
+        parse: |args| {
+            Ok(match args {
+                [code] => Some(Command::Link(code.to_string())),
+                _ => None,
+            })
+        },
+    },
+];
+
+fn no_args(args: &[String], command: Command) -> Option<Command> {
+    match args.is_empty() {
+        true => Some(command),
+        false => None,
+    }
+}
+
+/// Message text of command with `text_after: Some(0)`.
+fn text_arg(args: &[String]) -> Option<String> {
+    match args {
+        [text] => Some(text.to_string()),
+        _ => None,
+    }
+}
+
+pub fn find(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|spec| spec.name == name)
+}
+
+impl Command {
+    /// Returns `None` if line is normal text message.
+    pub fn parse(line: &str) -> Option<anyhow::Result<Command>> {
+        let line = line.trim();
+        if let Some(text) = line.strip_prefix("rr ") {
+            return Some(Ok(Command::ReplyDirect(text.trim().to_string())));
+        }
+        if let Some(text) = line.strip_prefix("r ") {
+            return Some(Ok(Command::Reply(text.trim().to_string())));
+        }
+        if !line.starts_with('/') {
+            return None;
+        }
+
+        let (name, rest) = next_arg(&line[1..]).unwrap_or_default();
+        Some(Command::parse_command(&name, rest))
+    }
+
+    fn parse_command(name: &str, rest: &str) -> anyhow::Result<Command> {
+        let spec = match find(name) {
+            Some(spec) => spec,
+            None => match suggest(name) {
+                Some(similar) => bail!("Unknown command: /{}. Did you mean /{}?", name, similar),
+                None => bail!("Unknown command: /{}. Type /help to list commands.", name),
+            },
+        };
+        let args = match spec.text_after {
+            Some(count) => split_text(rest, count),
+            None => split_args(rest),
+        };
+        (spec.parse)(&args)?.ok_or_else(|| anyhow!("Usage: {}", spec.usage()))
+    }
+}
+
+/// Output of `/help`: all commands or details of single one.
+pub fn help(name: Option<&str>) -> anyhow::Result<String> {
+    match name {
+        None => {
+            let width = COMMANDS
+                .iter()
+                .map(|spec| spec.usage().chars().count())
+                .max()
+                .unwrap_or_default();
+            Ok(COMMANDS
+                .iter()
+                .map(|spec| format!("  {:width$}  {}", spec.usage(), spec.help, width = width))
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        Some(name) => match find(name) {
+            Some(spec) => Ok(format!("Usage: {}\n  {}", spec.usage(), spec.help)),
+            None => bail!("No command /{}.", name),
+        },
+    }
+}
+
+/// Finds command, user probably meant: one with common prefix or
+/// differing by at most 2 edits.
+fn suggest(name: &str) -> Option<&'static str> {
+    if name.is_empty() {
+        return None;
+    }
+    if let Some(spec) = COMMANDS.iter().find(|spec| spec.name.starts_with(name)) {
+        return Some(spec.name);
+    }
+    COMMANDS
+        .iter()
+        .map(|spec| (edit_distance(name, spec.name), spec.name))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Splits command arguments on whitespace. Arguments containing spaces
+/// can be enclosed in double quotes.
+fn split_args(line: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut rest = line;
+    while let Some((arg, next)) = next_arg(rest) {
+        args.push(arg);
+        rest = next;
+    }
+    args
+}
+
+/// Splits `count` arguments and passes rest of line untouched as the
+/// last one, if it isn't empty.
+fn split_text(line: &str, count: usize) -> Vec<String> {
+    let mut args = vec![];
+    let mut rest = line;
+    while args.len() < count {
+        match next_arg(rest) {
+            Some((arg, next)) => {
+                args.push(arg);
+                rest = next;
+            }
+            None => break,
+        }
+    }
+    let text = rest.trim();
+    if !text.is_empty() {
+        args.push(text.to_string());
+    }
+    args
+}
+
+/// First argument of line and the rest after it. None, when there are no
+/// more arguments.
+fn next_arg(line: &str) -> Option<(String, &str)> {
+    let line = line.trim_start();
+    if line.is_empty() {
+        return None;
+    }
+
+    let mut arg = String::new();
+    let mut quoted = false;
+    for (idx, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => return Some((arg, &line[idx..])),
+            c => arg.push(c),
+        }
+    }
+    Some((arg, ""))
+}
+
+/// Opens url using platform default handler. Urls come from messages of
+/// other users, so only http and https are accepted and url is always
+/// passed as single argument, never through shell.
+pub fn open_url(url: &str) -> anyhow::Result<()> {
+    let parsed = Url::parse(url).map_err(|e| anyhow!("Invalid link {}. Error: {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        bail!("Only http and https links can be opened, got {}.", url);
+    }
+
+    let program = if cfg!(target_os = "windows") {
+        "explorer.exe"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    std::process::Command::new(program)
+        .arg(parsed.as_str())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .map_err(|e| anyhow!("Failed to open {}. Error: {}", url, e))?;
+    Ok(())
+}
@@ -0,0 +1,186 @@
+use anyhow::anyhow;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::alerts::AlertsConfig;
+use crate::away::AwayConfig;
+use crate::feeds::FeedConfig;
+use crate::filter::FilterRule;
+use crate::hooks::Hooks;
+use crate::keys;
+use crate::layout::MessageLayout;
+use crate::plugin::WasmPluginConfig;
+use crate::responder::ResponderRule;
+use crate::retention::RetentionConfig;
+use crate::schedule::Recurring;
+use crate::spam::SpamConfig;
+use crate::storage::StorageKind;
+use crate::theme::Palette;
+use crate::throttle::ThrottleConfig;
+use crate::webhook::WebhookConfig;
+use crate::Args;
+
+const CONFIG_FILE: &str = "config.toml";
+
+/// Defaults read from `config.toml` in config dir. Command line
+/// arguments take precedence.
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct Config {
+    pub name: Option<String>,
+    /// Used, when neither `--app-key` nor `YAGNA_APPKEY` is set.
+    pub app_key: Option<String>,
+    pub groups: Vec<String>,
+    pub data_dir: Option<PathBuf>,
+    pub theme: Option<String>,
+    pub layout: Option<MessageLayout>,
+    pub chat_logs: bool,
+    /// Check for newer release on GitHub at start.
+    pub update_check: bool,
+    pub message_ttl: Option<String>,
+    pub reappear_interval: Option<String>,
+    /// User-defined themes, selected by name like built-in ones.
+    pub themes: HashMap<String, Palette>,
+    pub hooks: Hooks,
+    pub away: AwayConfig,
+    pub alerts: AlertsConfig,
+    pub recurring: Vec<Recurring>,
+    /// RSS and Atom feeds, `[[feed]]` sections.
+    pub feed: Vec<FeedConfig>,
+    pub spam: SpamConfig,
+    pub throttle: ThrottleConfig,
+    pub retention: RetentionConfig,
+    pub storage: Option<StorageKind>,
+    /// Inbound filter rules, `[[filter]]` sections.
+    pub filter: Vec<FilterRule>,
+    /// Scripted responders, `[[responder]]` sections.
+    pub responder: Vec<ResponderRule>,
+    pub incoming_webhook: WebhookConfig,
+    /// Sandboxed plugins, `[[wasm-plugin]]` sections.
+    pub wasm_plugin: Vec<WasmPluginConfig>,
+    /// Faults injected into sent messages, used unless `YACHAT_CHAOS` is set.
+    /// Requires build with `chaos` feature.
+    pub chaos: Option<String>,
+}
+
+impl Config {
+    pub fn default_path() -> PathBuf {
+        match crate::storage::project_dirs() {
+            Some(dirs) => dirs.config_dir().join(CONFIG_FILE),
+            None => PathBuf::from(CONFIG_FILE),
+        }
+    }
+
+    /// Missing file isn't an error, config is optional.
+    pub fn load(path: &Path) -> anyhow::Result<Config> {
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}. Error: {}", path.display(), e))?;
+        toml::from_str(&content)
+            .map_err(|e| anyhow!("Failed to parse {}. Error: {}", path.display(), e))
+    }
+
+    /// Config path must be known before arguments are parsed, because
+    /// app-key from config is required to parse them.
+    pub fn path_from_args() -> PathBuf {
+        let mut args =
+            std::env::args().skip_while(|arg| arg != "--config" && !arg.starts_with("--config="));
+        match args.next() {
+            Some(arg) if arg.starts_with("--config=") => PathBuf::from(&arg["--config=".len()..]),
+            Some(_) => args
+                .next()
+                .map(PathBuf::from)
+                .unwrap_or_else(Config::default_path),
+            None => Config::default_path(),
+        }
+    }
+
+    /// App-key is passed to `ApiOpts` through environment.
+    pub fn export_app_key(&self) {
+        if let Some(app_key) = &self.app_key {
+            if std::env::var("YAGNA_APPKEY").is_err() {
+                std::env::set_var("YAGNA_APPKEY", app_key);
+            }
+        }
+    }
+
+    /// Fault injection reads its settings from environment on every send.
+    pub fn export_chaos(&self) {
+        if let Some(chaos) = &self.chaos {
+            if std::env::var("YACHAT_CHAOS").is_err() {
+                std::env::set_var("YACHAT_CHAOS", chaos);
+            }
+        }
+    }
+
+    /// Appends group to `groups` of config file, keeping the rest of it.
+    pub fn add_group(path: &Path, group: &str) -> anyhow::Result<()> {
+        let mut config = match path.exists() {
+            true => fs::read_to_string(path)?
+                .parse::<toml::Value>()
+                .map_err(|e| anyhow!("Failed to parse {}. Error: {}", path.display(), e))?,
+            false => toml::Value::Table(Default::default()),
+        };
+        let groups = config
+            .as_table_mut()
+            .ok_or_else(|| anyhow!("Invalid config file {}.", path.display()))?
+            .entry("groups")
+            .or_insert_with(|| toml::Value::Array(vec![]))
+            .as_array_mut()
+            .ok_or_else(|| anyhow!("Invalid groups in config file {}.", path.display()))?;
+        if groups.iter().any(|name| name.as_str() == Some(group)) {
+            return Ok(());
+        }
+        groups.push(toml::Value::String(group.to_string()));
+        // Config file can hold app-key.
+        keys::save_private(path, toml::to_string(&config)?.as_bytes())
+    }
+
+    pub fn apply(self, args: &mut Args) {
+        if args.name.is_none() {
+            args.name = self.name;
+        }
+        args.config_groups = self.groups.clone();
+        if args.groups.is_empty() {
+            args.groups = self.groups;
+        }
+        if args.data_dir.is_none() {
+            args.data_dir = self.data_dir;
+        }
+        if args.theme.is_none() {
+            args.theme = self.theme;
+        }
+        if args.layout.is_none() {
+            args.layout = self.layout;
+        }
+        args.chat_logs |= self.chat_logs;
+        args.update_check = self.update_check;
+        if args.message_ttl.is_none() {
+            args.message_ttl = self.message_ttl;
+        }
+        if args.reappear_interval.is_none() {
+            args.reappear_interval = self.reappear_interval;
+        }
+        args.themes = self.themes;
+        args.hooks = self.hooks;
+        args.away = self.away;
+        args.alerts = self.alerts;
+        args.recurring = self.recurring;
+        args.feeds = self.feed;
+        args.spam = self.spam;
+        args.throttle = self.throttle;
+        args.retention = self.retention;
+        if args.storage.is_none() {
+            args.storage = self.storage;
+        }
+        args.filters = self.filter;
+        args.responders = self.responder;
+        args.wasm_plugins = self.wasm_plugin;
+        args.webhook = self.incoming_webhook;
+    }
+}
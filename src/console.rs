@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::io::Write;
+use uuid::Uuid;
+
+use crate::layout::{display_width, terminal_height, terminal_width};
+
+/// Writes chat output to stdout and remembers, where tracked messages
+/// were printed, so they can be updated in place later.
+///
+/// Terminal position is computed by counting rows printed since start,
+/// including lines typed by user, which terminal echoes by itself.
+pub struct Console {
+    interactive: bool,
+    /// Stdout goes to file or other program. Every line is flushed, so
+    /// readers like `grep` get it right away.
+    piped: bool,
+    /// Nothing is printed. Used without terminal, when embedders get
+    /// output as events.
+    silent: bool,
+    printed: usize,
+    tracked: HashMap<Uuid, usize>,
+}
+
+impl Console {
+    /// In accessible mode printed lines are never rewritten, since screen
+    /// readers wouldn't announce the change.
+    pub fn new(accessible: bool) -> Console {
+        Console {
+            interactive: !accessible && styled_output() && atty::is(atty::Stream::Stdin),
+            piped: !atty::is(atty::Stream::Stdout),
+            silent: false,
+            printed: 0,
+            tracked: HashMap::new(),
+        }
+    }
+
+    pub fn silent() -> Console {
+        Console {
+            interactive: false,
+            piped: false,
+            silent: true,
+            printed: 0,
+            tracked: HashMap::new(),
+        }
+    }
+
+    pub fn print(&mut self, text: &str) {
+        if self.silent {
+            return;
+        }
+        println!("{}", text);
+        if self.piped {
+            flush();
+        }
+        self.printed += rows(text);
+    }
+
+    /// Prints message, which will be later updated with `overwrite`.
+    pub fn print_tracked(&mut self, id: Uuid, text: &str) {
+        self.tracked.insert(id, self.printed);
+        self.print(text);
+    }
+
+    /// Accounts for line typed by user and echoed by terminal.
+    pub fn input_echoed(&mut self, line: &str) {
+        self.printed += rows(line);
+    }
+
+    /// Removes echo of line typed by user, so it can be replaced
+    /// with formatted message.
+    pub fn erase_input(&mut self, line: &str) {
+        if !self.interactive {
+            return;
+        }
+
+        let rows = rows(line);
+        print!("\x1b[{}A\x1b[J", rows);
+        flush();
+        self.printed = self.printed.saturating_sub(rows);
+    }
+
+    /// Overwrites text starting at `column` in first row of tracked message.
+    /// Nothing happens if message already scrolled out of the screen.
+    pub fn overwrite(&mut self, id: &Uuid, column: usize, text: &str) {
+        let row = match self.tracked.get(id) {
+            Some(row) if self.interactive => *row,
+            _ => return,
+        };
+
+        let distance = self.printed - row;
+        if distance >= terminal_height().unwrap_or(0) {
+            self.tracked.remove(id);
+            return;
+        }
+
+        // Save cursor, go to message row and restore cursor position
+        // afterwards, so text user is typing currently isn't disturbed.
+        let right = match column {
+            0 => String::new(),
+            column => format!("\x1b[{}C", column),
+        };
+        print!("\x1b7\x1b[{}A\r{}{}\x1b8", distance, right, text);
+        flush();
+    }
+
+    pub fn forget(&mut self, id: &Uuid) {
+        self.tracked.remove(id);
+    }
+}
+
+/// Windows console interprets escape sequences only after virtual terminal
+/// processing is enabled. Consoles older than Windows 10 don't support it,
+/// so output falls back to plain text without in-place updates.
+fn ansi_supported() -> bool {
+    #[cfg(windows)]
+    return ansi_term::enable_ansi_support().is_ok();
+    #[cfg(not(windows))]
+    true
+}
+
+/// Colors and in-place updates are used only on terminal. Piped output
+/// is plain text, processed line by line.
+pub fn styled_output() -> bool {
+    atty::is(atty::Stream::Stdout) && ansi_supported()
+}
+
+fn flush() {
+    std::io::stdout().flush().ok();
+}
+
+/// Number of terminal rows occupied by text, after terminal wraps long lines.
+fn rows(text: &str) -> usize {
+    let width = terminal_width().unwrap_or(usize::MAX).max(1);
+    text.split('\n')
+        .map(|line| {
+            let line_width = display_width(line);
+            match line_width {
+                0 => 1,
+                line_width => 1 + (line_width - 1) / width,
+            }
+        })
+        .sum()
+}
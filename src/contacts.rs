@@ -0,0 +1,219 @@
+use anyhow::bail;
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use ya_client::model::NodeId;
+
+use crate::storage::{load_json, save_json};
+
+const CONTACTS_FILE: &str = "contacts.json";
+
+/// Peer we have ever seen in any group. Name is self-reported by peer
+/// in discovery, so it can't be trusted. Alias is set locally by us.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Contact {
+    pub node_id: NodeId,
+    pub name: String,
+    pub alias: Option<String>,
+    pub groups: BTreeSet<String>,
+    pub last_seen: DateTime<Utc>,
+    /// Safety code was compared with the user and confirmed by us.
+    #[serde(default)]
+    pub verified: bool,
+}
+
+impl Contact {
+    pub fn display_name(&self) -> &str {
+        self.alias.as_ref().unwrap_or(&self.name)
+    }
+}
+
+/// Local address book persisted in data dir.
+pub struct Contacts {
+    path: PathBuf,
+    contacts: Vec<Contact>,
+}
+
+impl Contacts {
+    pub fn load(data_dir: &Path) -> anyhow::Result<Contacts> {
+        let path = data_dir.join(CONTACTS_FILE);
+        Ok(Contacts {
+            contacts: load_json(&path)?,
+            path,
+        })
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        save_json(&self.path, &self.contacts)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contacts.is_empty()
+    }
+
+    pub fn get(&self, node_id: &NodeId) -> Option<&Contact> {
+        self.contacts
+            .iter()
+            .find(|contact| &contact.node_id == node_id)
+    }
+
+    /// Name which should be displayed for peer. Prefers local alias over
+    /// name reported by peer.
+    pub fn display_name(&self, node_id: &NodeId, reported: &str) -> String {
+        self.get(node_id)
+            .and_then(|contact| contact.alias.clone())
+            .unwrap_or_else(|| reported.to_string())
+    }
+
+    /// Records, that peer was seen in group. Returns true, if contact is
+    /// new or his name or groups changed. Change of `last_seen` alone isn't
+    /// worth saving, since discovery reports peers on every proposal.
+    pub fn seen(&mut self, node_id: NodeId, name: &str, group: &str) -> bool {
+        let now = Utc::now();
+        match self
+            .contacts
+            .iter_mut()
+            .find(|contact| contact.node_id == node_id)
+        {
+            Some(contact) => {
+                contact.last_seen = now;
+                let renamed = contact.name != name;
+                if renamed {
+                    contact.name = name.to_string();
+                }
+                contact.groups.insert(group.to_string()) || renamed
+            }
+            None => {
+                self.contacts.push(Contact {
+                    node_id,
+                    name: name.to_string(),
+                    alias: None,
+                    groups: vec![group.to_string()].into_iter().collect(),
+                    last_seen: now,
+                    verified: false,
+                });
+                true
+            }
+        }
+    }
+
+    pub fn is_verified(&self, node_id: &NodeId) -> bool {
+        self.get(node_id).map_or(false, |contact| contact.verified)
+    }
+
+    /// Verified users, which use given name. Used to warn about users
+    /// impersonating them or coming back with different key.
+    pub fn verified_named(&self, name: &str) -> Vec<&Contact> {
+        self.contacts
+            .iter()
+            .filter(|contact| contact.verified && contact.display_name() == name)
+            .collect()
+    }
+
+    /// Marks user as verified. Primary node of user with linked devices
+    /// may not be in contacts yet, so it is added.
+    pub fn set_verified(&mut self, node_id: NodeId, name: &str) -> anyhow::Result<()> {
+        match self
+            .contacts
+            .iter_mut()
+            .find(|contact| contact.node_id == node_id)
+        {
+            Some(contact) => contact.verified = true,
+            None => self.contacts.push(Contact {
+                node_id,
+                name: name.to_string(),
+                alias: None,
+                groups: BTreeSet::new(),
+                last_seen: Utc::now(),
+                verified: true,
+            }),
+        }
+        self.save()
+    }
+
+    /// Removes contacts with their aliases and verification. Returns
+    /// number of removed contacts.
+    pub fn remove(&mut self, node_ids: &[NodeId]) -> anyhow::Result<usize> {
+        let count = self.contacts.len();
+        self.contacts
+            .retain(|contact| !node_ids.contains(&contact.node_id));
+        let removed = count - self.contacts.len();
+        if removed > 0 {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// Finds contact by NodeId prefix, alias or reported name.
+    pub fn find(&self, pattern: &str) -> anyhow::Result<&Contact> {
+        let pattern = pattern.trim_end_matches('…').to_lowercase();
+        let matching = self
+            .contacts
+            .iter()
+            .filter(|contact| {
+                contact.node_id.to_string().starts_with(&pattern)
+                    || contact.alias.as_ref().map(|alias| alias.to_lowercase())
+                        == Some(pattern.clone())
+                    || contact.name.to_lowercase() == pattern
+            })
+            .collect::<Vec<_>>();
+
+        match matching.as_slice() {
+            [contact] => Ok(contact),
+            [] => bail!("No contact matching '{}'.", pattern),
+            _ => bail!("'{}' is ambiguous. Use longer NodeId prefix.", pattern),
+        }
+    }
+
+    /// Sets or clears (if `alias` is None) local alias of peer.
+    pub fn set_alias(&mut self, pattern: &str, alias: Option<String>) -> anyhow::Result<Contact> {
+        let node_id = self.find(pattern)?.node_id;
+        let contact = self
+            .contacts
+            .iter_mut()
+            .find(|contact| contact.node_id == node_id)
+            .unwrap();
+        contact.alias = alias;
+
+        let contact = contact.clone();
+        self.save()?;
+        Ok(contact)
+    }
+
+    pub fn list(&self) -> String {
+        let mut contacts = self.contacts.iter().collect::<Vec<_>>();
+        contacts.sort_by_key(|contact| contact.display_name().to_lowercase());
+
+        contacts
+            .iter()
+            .map(|contact| {
+                let mut name = match &contact.alias {
+                    Some(alias) => format!("{} ({})", alias, contact.name),
+                    None => contact.name.clone(),
+                };
+                if contact.verified {
+                    name.push_str(" (verified)");
+                }
+                format!(
+                    "  {} [{}] groups: {}, last seen: {}",
+                    name,
+                    contact.node_id,
+                    contact
+                        .groups
+                        .iter()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    contact
+                        .last_seen
+                        .with_timezone(&Local)
+                        .format("%Y-%m-%d %H:%M")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
@@ -0,0 +1,149 @@
+use anyhow::{anyhow, bail};
+use chrono::{DateTime, Datelike, Duration, Local, TimeZone, Timelike};
+use serde::Deserialize;
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+/// Next occurrence is searched at most this far in future.
+const SEARCH_DAYS: i64 = 4 * 366;
+
+/// Cron expression with standard five fields: minute, hour, day of month,
+/// month and day of week (0 or 7 is Sunday). Fields accept `*`, lists,
+/// ranges and steps, for example `0 9 * * 1-5` or `*/15 8-18 * * *`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Cron {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+    /// Cron matches day, when either day of month or day of week matches,
+    /// if both are restricted.
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl FromStr for Cron {
+    type Err = anyhow::Error;
+
+    fn from_str(expr: &str) -> anyhow::Result<Cron> {
+        let fields = expr.split_whitespace().collect::<Vec<_>>();
+        if fields.len() != 5 {
+            bail!(
+                "Invalid schedule '{}'. Expected 5 fields: minute hour day month weekday.",
+                expr
+            );
+        }
+
+        let mut weekdays = parse_field(fields[4], 0, 7)?;
+        if weekdays[7] {
+            weekdays[0] = true;
+        }
+        Ok(Cron {
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            weekdays,
+            any_day: fields[2].starts_with('*'),
+            any_weekday: fields[4].starts_with('*'),
+        })
+    }
+}
+
+impl TryFrom<String> for Cron {
+    type Error = anyhow::Error;
+
+    fn try_from(expr: String) -> anyhow::Result<Cron> {
+        expr.parse()
+    }
+}
+
+impl Cron {
+    /// First matching minute after `time`.
+    pub fn next_after(&self, time: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = time.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let end = start + Duration::days(SEARCH_DAYS);
+
+        let mut next = start;
+        while next < end {
+            if !self.day_matches(&next) {
+                next = next.date().and_hms_opt(0, 0, 0)? + Duration::days(1);
+                continue;
+            }
+            if !self.hours[next.hour() as usize] {
+                next = next.date().and_hms_opt(next.hour(), 0, 0)? + Duration::hours(1);
+                continue;
+            }
+            if !self.minutes[next.minute() as usize] {
+                next += Duration::minutes(1);
+                continue;
+            }
+
+            // Local time skipped by DST change doesn't exist, so search continues.
+            if let Some(local) = Local.from_local_datetime(&next).earliest() {
+                return Some(local);
+            }
+            next += Duration::minutes(1);
+        }
+        None
+    }
+
+    fn day_matches<T: Datelike>(&self, date: &T) -> bool {
+        if !self.months[date.month() as usize] {
+            return false;
+        }
+        let day = self.days[date.day() as usize];
+        let weekday = self.weekdays[date.weekday().num_days_from_sunday() as usize];
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+}
+
+/// Returns flags indexed by value, so index 0 is unused for 1-based fields.
+fn parse_field(field: &str, min: u32, max: u32) -> anyhow::Result<Vec<bool>> {
+    let invalid = || anyhow!("Invalid schedule field '{}'.", field);
+    let mut values = vec![false; max as usize + 1];
+
+    for part in field.split(',') {
+        let (range, step) = match part.find('/') {
+            Some(idx) => (
+                &part[..idx],
+                part[idx + 1..].parse::<u32>().map_err(|_| invalid())?,
+            ),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+
+        let (first, last) = match range {
+            "*" => (min, max),
+            range => match range.find('-') {
+                Some(idx) => (
+                    range[..idx].parse().map_err(|_| invalid())?,
+                    range[idx + 1..].parse().map_err(|_| invalid())?,
+                ),
+                None => {
+                    let value = range.parse().map_err(|_| invalid())?;
+                    // `5/10` means from 5 to the end with step 10.
+                    match part.contains('/') {
+                        true => (value, max),
+                        false => (value, value),
+                    }
+                }
+            },
+        };
+        if first < min || last > max || first > last {
+            bail!("Schedule field '{}' out of range {}-{}.", field, min, max);
+        }
+
+        for value in (first..=last).step_by(step as usize) {
+            values[value as usize] = true;
+        }
+    }
+    Ok(values)
+}
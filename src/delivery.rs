@@ -0,0 +1,126 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use ya_client::model::NodeId;
+
+use crate::protocol::SendText;
+
+/// Messages waiting for recipients to reappear. Messages are kept in
+/// batches per group and kind, so they can be resent as few `SendText`
+/// as possible.
+#[derive(Default)]
+pub struct DeliveryQueue {
+    batches: HashMap<NodeId, Vec<SendText>>,
+}
+
+impl DeliveryQueue {
+    /// Queue saved with `stored` at last shutdown.
+    pub fn restore(stored: Vec<(NodeId, SendText)>) -> DeliveryQueue {
+        let mut queue = DeliveryQueue::default();
+        for (address, messages) in stored {
+            queue.push(address, messages);
+        }
+        queue
+    }
+
+    pub fn stored(&self) -> Vec<(NodeId, SendText)> {
+        self.batches
+            .iter()
+            .flat_map(|(address, batches)| {
+                batches.iter().map(move |batch| (*address, batch.clone()))
+            })
+            .collect()
+    }
+
+    pub fn push(&mut self, address: NodeId, messages: SendText) {
+        let batches = self.batches.entry(address).or_default();
+        match batches.iter_mut().find(|batch| {
+            batch.group == messages.group
+                && batch.direct == messages.direct
+                && batch.auto_reply == messages.auto_reply
+                && batch.delayed == messages.delayed
+        }) {
+            Some(batch) => batch.messages.extend(messages.messages),
+            None => batches.push(messages),
+        }
+    }
+
+    /// Number of queued messages and timestamp of the oldest one per
+    /// recipient.
+    pub fn summary(&self) -> Vec<(NodeId, usize, Option<DateTime<Utc>>)> {
+        self.batches
+            .iter()
+            .map(|(address, batches)| {
+                let messages = batches.iter().flat_map(|batch| batch.messages.iter());
+                let count = messages.clone().count();
+                let oldest = messages.map(|text| text.timestamp).min();
+                (*address, count, oldest)
+            })
+            .collect()
+    }
+
+    /// Number of messages queued for recipient.
+    pub fn count(&self, address: &NodeId) -> usize {
+        self.batches
+            .get(address)
+            .map(|batches| batches.iter().map(|batch| batch.messages.len()).sum())
+            .unwrap_or(0)
+    }
+
+    pub fn recipients(&self) -> Vec<NodeId> {
+        self.batches.keys().cloned().collect()
+    }
+
+    pub fn contains(&self, address: &NodeId) -> bool {
+        self.batches.contains_key(address)
+    }
+
+    /// Removes batches of recipient without ordering them.
+    pub fn remove(&mut self, address: &NodeId) -> Option<Vec<SendText>> {
+        self.batches.remove(address)
+    }
+
+    /// Removes batches of recipient for resending. Messages are in timestamp
+    /// order inside batches and batches are ordered by their oldest message.
+    pub fn take(&mut self, address: &NodeId) -> Option<Vec<SendText>> {
+        let mut batches = self.batches.remove(address)?;
+        for batch in batches.iter_mut() {
+            batch.messages.sort_by_key(|text| text.timestamp);
+        }
+        batches.sort_by_key(|batch| batch.messages.first().map(|text| text.timestamp));
+        Some(batches)
+    }
+
+    /// Returns batches, which interrupted resending didn't reach. Messages
+    /// queued meanwhile stay, and next `take` orders them all together.
+    pub fn requeue(&mut self, address: NodeId, remaining: Vec<SendText>) {
+        for batch in remaining {
+            self.push(address, batch);
+        }
+    }
+
+    /// Drops messages older than their TTL. Returns ids of dropped messages
+    /// per recipient.
+    pub fn expire(&mut self, now: &DateTime<Utc>) -> Vec<(NodeId, Vec<Uuid>)> {
+        let mut expired = vec![];
+        for (recipient, batches) in self.batches.iter_mut() {
+            let mut ids = vec![];
+            for batch in batches.iter_mut() {
+                batch.messages.retain(|text| match text.expired(now) {
+                    true => {
+                        ids.push(text.id);
+                        false
+                    }
+                    false => true,
+                });
+            }
+            batches.retain(|batch| !batch.messages.is_empty());
+            if !ids.is_empty() {
+                expired.push((*recipient, ids));
+            }
+        }
+        self.batches.retain(|_, batches| !batches.is_empty());
+        expired
+    }
+}
@@ -0,0 +1,48 @@
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::str::FromStr;
+
+use ya_client::model::NodeId;
+
+use crate::protocol::DeviceCert;
+use crate::storage::{load_json, save_json};
+
+const DEVICE_FILE: &str = "device.json";
+
+/// Link of this node with user's primary node. Nodes without link
+/// are primary devices of their users.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Device {
+    pub cert: Option<DeviceCert>,
+}
+
+impl Device {
+    pub fn load(data_dir: &Path) -> anyhow::Result<Device> {
+        load_json(&data_dir.join(DEVICE_FILE))
+    }
+
+    pub fn save(&self, data_dir: &Path) -> anyhow::Result<()> {
+        save_json(&data_dir.join(DEVICE_FILE), self)
+    }
+}
+
+/// Certificate is copied by user from primary device to the linked one
+/// as `<user NodeId>:<base64 signature>`.
+pub fn encode(cert: &DeviceCert) -> String {
+    format!("{}:{}", cert.user, base64::encode(&cert.signature))
+}
+
+pub fn decode(code: &str) -> anyhow::Result<DeviceCert> {
+    let mut parts = code.trim().splitn(2, ':');
+    let (user, signature) = match (parts.next(), parts.next()) {
+        (Some(user), Some(signature)) => (user, signature),
+        _ => bail!("Invalid device code. Expected <NodeId>:<signature>."),
+    };
+    Ok(DeviceCert {
+        user: NodeId::from_str(user).map_err(|_| anyhow!("Invalid NodeId: {}", user))?,
+        signature: base64::decode(signature)
+            .map_err(|e| anyhow!("Invalid device code signature. Error: {}", e))?,
+    })
+}
@@ -1,5 +1,4 @@
 use actix::prelude::*;
-use anyhow::bail;
 use std::convert::TryFrom;
 use std::str::FromStr;
 
@@ -11,17 +10,35 @@ use ya_client::model::market::{Demand, Offer, RequestorEvent};
 use ya_client::model::NodeId;
 
 use crate::chat::NewUser;
+use crate::error::DiscoveryError;
+use crate::protocol::PROTOCOL_VERSION;
+use crate::update::VERSION;
 
 // =========================================== //
 // Public exposed messages
 // =========================================== //
 
 #[derive(Message)]
-#[rtype(result = "anyhow::Result<()>")]
+#[rtype(result = "Result<(), DiscoveryError>")]
 pub struct InitChatGroup {
     pub me: String,
     pub group: String,
+    /// Advertised only by announcement-only groups.
+    pub announcers: Vec<NodeId>,
+    /// Membership fee advertised by owner of paid group.
+    pub fee: Option<String>,
+    /// Member limit advertised by group owner.
+    pub max_members: Option<usize>,
     pub notify: Recipient<NewUser>,
+    pub cycles: Recipient<DiscoveryCycle>,
+}
+
+/// Sent after every collection of market events of group.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct DiscoveryCycle {
+    pub group: String,
+    pub events: usize,
 }
 
 #[derive(Message)]
@@ -32,6 +49,24 @@ pub struct DiscoverUsers;
 #[rtype(result = "Result<(), anyhow::Error>")]
 pub struct Shutdown;
 
+/// Removes our Offer and Demand of single group.
+#[derive(Message)]
+#[rtype(result = "Result<(), anyhow::Error>")]
+pub struct LeaveGroup {
+    pub group: String,
+}
+
+/// Lists market subscriptions of all groups.
+#[derive(Message)]
+#[rtype(result = "Vec<SubscriptionInfo>")]
+pub struct ListSubscriptions;
+
+pub struct SubscriptionInfo {
+    pub group: String,
+    pub offer: String,
+    pub demand: String,
+}
+
 // =========================================== //
 // Discovery implementation
 // =========================================== //
@@ -45,14 +80,16 @@ pub struct Apis {
 #[derive(Clone)]
 struct GroupSubscription {
     subscription: String,
+    /// Offer, making us discoverable in group.
+    offer: String,
     group: String,
     notify: Recipient<NewUser>,
+    cycles: Recipient<DiscoveryCycle>,
 }
 
 pub struct Discovery {
     apis: Apis,
     listeners: Vec<GroupSubscription>,
-    subscriptions: Vec<String>,
 }
 
 impl Discovery {
@@ -65,45 +102,62 @@ impl Discovery {
         Ok(Discovery {
             apis,
             listeners: vec![],
-            subscriptions: vec![],
         })
     }
 }
 
 impl Handler<InitChatGroup> for Discovery {
-    type Result = ActorResponse<Self, (), anyhow::Error>;
+    type Result = ActorResponse<Self, (), DiscoveryError>;
 
     fn handle(&mut self, msg: InitChatGroup, _: &mut Context<Self>) -> Self::Result {
         log::info!("Discovering users for group: {}", &msg.group);
 
-        let (properties, constraints) = discovery_properties(&msg.me, &msg.group);
+        let (properties, constraints) = discovery_properties(
+            &msg.me,
+            &msg.group,
+            &msg.announcers,
+            msg.fee.as_deref(),
+            msg.max_members,
+        );
         let offer = Offer::new(properties.clone(), constraints.to_string());
         let demand = Demand::new(properties, constraints.to_string());
 
         let apis = self.apis.clone();
+        let group = msg.group.clone();
         let future = async move {
-            let subscription = apis.provider.market.subscribe(&offer).await?;
-            let listener = apis.requestor.market.subscribe(&demand).await?;
+            let market = |e: ya_client::Error| DiscoveryError::Market {
+                group: group.clone(),
+                reason: e.to_string(),
+            };
+            let subscription = apis
+                .provider
+                .market
+                .subscribe(&offer)
+                .await
+                .map_err(market)?;
+            let listener = apis
+                .requestor
+                .market
+                .subscribe(&demand)
+                .await
+                .map_err(market)?;
             Ok((listener, subscription))
         }
         .into_actor(self)
         .map(
-            move |result: anyhow::Result<(String, String)>, myself, _| match result {
+            move |result: Result<(String, String), DiscoveryError>, myself, _| match result {
                 Ok((listener, subscription)) => {
                     myself.listeners.push(GroupSubscription {
                         subscription: listener,
+                        offer: subscription,
                         group: msg.group,
                         notify: msg.notify,
+                        cycles: msg.cycles,
                     });
-                    myself.subscriptions.push(subscription);
                     Ok(())
                 }
                 Err(e) => {
-                    log::error!(
-                        "Failed to initialize chat group {}. Error: {}",
-                        msg.group,
-                        e
-                    );
+                    log::error!("Failed to initialize chat group. {}", e);
                     Err(e)
                 }
             },
@@ -131,24 +185,42 @@ impl Handler<DiscoverUsers> for Discovery {
                 {
                     Ok(events) => events,
                     Err(e) => {
-                        log::error!("Failed to get discovery events from market. Error: {}", e);
+                        let e = DiscoveryError::Market {
+                            group: sub.group.clone(),
+                            reason: e.to_string(),
+                        };
+                        log::error!("Failed to get discovery events. {}", e);
                         tokio::time::delay_for(std::time::Duration::from_secs(4)).await;
                         continue;
                     }
                 };
 
                 log::debug!("Got {} events.", events.len());
+                sub.cycles
+                    .do_send(DiscoveryCycle {
+                        group: sub.group.clone(),
+                        events: events.len(),
+                    })
+                    .ok();
 
                 for event in events.into_iter() {
-                    if let Err(e) = async move {
+                    let user = async move {
                         let proposal = match event {
                             RequestorEvent::ProposalEvent { proposal, .. } => proposal,
-                            RequestorEvent::PropertyQueryEvent { .. } => {
-                                bail!("Unexpected PropertyQuery events when discovering users.")
+                            // Market API of ya-client has no endpoint for posting
+                            // query replies. All our properties are in the Offer
+                            // anyway, so querying node discovers us from it.
+                            RequestorEvent::PropertyQueryEvent { property_query, .. } => {
+                                log::debug!(
+                                    "Ignored property query for {:?} in group {}.",
+                                    property_query.queried_properties,
+                                    sub.group
+                                );
+                                return Ok(None);
                             }
                         };
 
-                        log::debug!("{}", proposal.properties.to_string());
+                        log::debug!("{}", proposal.properties);
 
                         let node_id = proposal.issuer_id()?.clone();
                         let proposal_view = AgreementView {
@@ -156,10 +228,41 @@ impl Handler<DiscoverUsers> for Discovery {
                             agreement_id: "".to_string(),
                         };
 
+                        let announcers = proposal_view
+                            .pointer_typed::<Vec<String>>("/yachat/talk/announcers")
+                            .ok()
+                            .map(|announcers| {
+                                announcers
+                                    .iter()
+                                    .filter_map(|node_id| NodeId::from_str(node_id).ok())
+                                    .collect()
+                            });
+
+                        let fee = proposal_view
+                            .pointer_typed::<String>("/yachat/talk/fee")
+                            .ok();
+
+                        let max_members = proposal_view
+                            .pointer_typed::<usize>("/yachat/talk/max-members")
+                            .ok();
+
+                        // Not advertised by older clients.
+                        let version = proposal_view
+                            .pointer_typed::<String>("/yachat/talk/version")
+                            .ok();
+                        let protocol = proposal_view
+                            .pointer_typed::<u32>("/yachat/talk/protocol")
+                            .ok();
+
                         let msg = NewUser {
                             group: sub.group.clone(),
                             address: NodeId::from_str(&node_id)?,
                             user: proposal_view.pointer_typed("/yachat/talk/me")?,
+                            announcers,
+                            fee,
+                            max_members,
+                            version,
+                            protocol,
                         };
 
                         log::info!(
@@ -168,13 +271,27 @@ impl Handler<DiscoverUsers> for Discovery {
                             &msg.address,
                             &msg.group
                         );
-                        let _ = sub.notify.send(msg).await?;
-                        anyhow::Result::<()>::Ok(())
+                        anyhow::Result::<_>::Ok(Some(msg))
                     }
                     .await
-                    {
-                        log::error!("Error while processing discovered users: {}", e);
+                    .map_err(|e| DiscoveryError::InvalidProposal {
+                        group: sub.group.clone(),
+                        reason: e.to_string(),
+                    });
+
+                    let result = match user {
+                        Ok(Some(msg)) => sub.notify.send(msg).await.map(|_| ()).map_err(|e| {
+                            DiscoveryError::Local {
+                                group: sub.group.clone(),
+                                reason: e.to_string(),
+                            }
+                        }),
+                        Ok(None) => Ok(()),
+                        Err(e) => Err(e),
                     };
+                    if let Err(e) = result {
+                        log::error!("Skipped discovered user. {}", e);
+                    }
                 }
             }
             myself.do_send(DiscoverUsers {});
@@ -188,46 +305,100 @@ impl Handler<Shutdown> for Discovery {
     type Result = ActorResponse<Self, (), anyhow::Error>;
 
     fn handle(&mut self, _: Shutdown, _: &mut Context<Self>) -> Self::Result {
-        let subs: Vec<_> = self.subscriptions.drain(..).collect();
-        let listeners = self
+        let groups = self.listeners.drain(..).collect();
+        let future = unsubscribe(self.apis.clone(), groups).into_actor(self);
+        ActorResponse::r#async(future.map(|_, _, _| Ok(())))
+    }
+}
+
+impl Handler<LeaveGroup> for Discovery {
+    type Result = ActorResponse<Self, (), anyhow::Error>;
+
+    fn handle(&mut self, msg: LeaveGroup, _: &mut Context<Self>) -> Self::Result {
+        let (groups, rest) = self
             .listeners
             .drain(..)
-            .map(|group| group.subscription)
-            .collect::<Vec<String>>();
-        let apis = self.apis.clone();
-        let future = async move {
-            for sub in subs.into_iter() {
-                log::info!("Unsubscribing {}", &sub);
-                apis.provider
-                    .market
-                    .unsubscribe(&sub)
-                    .await
-                    .map_err(|e| log::error!("Failed to unsubscribe: {}. Error: {}", sub, e))
-                    .ok();
-            }
-            for sub in listeners.into_iter() {
-                log::info!("Unsubscribing {}", &sub);
-                apis.requestor
-                    .market
-                    .unsubscribe(&sub)
-                    .await
-                    .map_err(|e| log::error!("Failed to unsubscribe: {}. Error: {}", sub, e))
-                    .ok();
-            }
-            log::info!("Finished cleanups.");
-        }
-        .into_actor(self);
-
+            .partition(|listener| listener.group == msg.group);
+        self.listeners = rest;
+        let future = unsubscribe(self.apis.clone(), groups).into_actor(self);
         ActorResponse::r#async(future.map(|_, _, _| Ok(())))
     }
 }
 
-pub fn discovery_properties(me: &str, group: &str) -> (serde_json::Value, Constraints) {
-    let properties = serde_json::json!({
+/// Failures are only logged, since subscriptions expire anyway.
+async fn unsubscribe(apis: Apis, groups: Vec<GroupSubscription>) {
+    let (subs, listeners): (Vec<_>, Vec<_>) = groups
+        .into_iter()
+        .map(|group| (group.offer, group.subscription))
+        .unzip();
+    for sub in subs.into_iter() {
+        log::info!("Unsubscribing {}", &sub);
+        apis.provider
+            .market
+            .unsubscribe(&sub)
+            .await
+            .map_err(|e| log::error!("Failed to unsubscribe: {}. Error: {}", sub, e))
+            .ok();
+    }
+    for sub in listeners.into_iter() {
+        log::info!("Unsubscribing {}", &sub);
+        apis.requestor
+            .market
+            .unsubscribe(&sub)
+            .await
+            .map_err(|e| log::error!("Failed to unsubscribe: {}. Error: {}", sub, e))
+            .ok();
+    }
+    log::info!("Finished cleanups.");
+}
+
+impl Handler<ListSubscriptions> for Discovery {
+    type Result = MessageResult<ListSubscriptions>;
+
+    fn handle(&mut self, _: ListSubscriptions, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(
+            self.listeners
+                .iter()
+                .map(|sub| SubscriptionInfo {
+                    group: sub.group.clone(),
+                    offer: sub.offer.clone(),
+                    demand: sub.subscription.clone(),
+                })
+                .collect(),
+        )
+    }
+}
+
+pub fn discovery_properties(
+    me: &str,
+    group: &str,
+    announcers: &[NodeId],
+    fee: Option<&str>,
+    max_members: Option<usize>,
+) -> (serde_json::Value, Constraints) {
+    let mut properties = serde_json::json!({
         "yachat.talk.me": me.to_string(),
-        "yachat.talk.group": group.to_string()
+        "yachat.talk.group": group.to_string(),
+        "yachat.talk.version": VERSION,
+        "yachat.talk.protocol": PROTOCOL_VERSION
     });
 
+    if !announcers.is_empty() {
+        properties["yachat.talk.announcers"] = announcers
+            .iter()
+            .map(|node_id| node_id.to_string())
+            .collect::<Vec<_>>()
+            .into();
+    }
+
+    if let Some(fee) = fee {
+        properties["yachat.talk.fee"] = fee.into();
+    }
+
+    if let Some(max_members) = max_members {
+        properties["yachat.talk.max-members"] = max_members.into();
+    }
+
     let constraints = constraints!["yachat.talk.group" == group];
     (properties, constraints)
 }
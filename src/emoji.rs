@@ -0,0 +1,205 @@
+use unicode_width::UnicodeWidthStr;
+
+/// Most common GitHub/Slack style shortcodes.
+const SHORTCODES: &[(&str, &str)] = &[
+    ("+1", "👍"),
+    ("-1", "👎"),
+    ("100", "💯"),
+    ("alarm_clock", "⏰"),
+    ("angry", "😠"),
+    ("astonished", "😲"),
+    ("balloon", "🎈"),
+    ("beer", "🍺"),
+    ("beers", "🍻"),
+    ("bell", "🔔"),
+    ("blush", "😊"),
+    ("boom", "💥"),
+    ("bomb", "💣"),
+    ("broken_heart", "💔"),
+    ("bug", "🐛"),
+    ("bulb", "💡"),
+    ("cake", "🍰"),
+    ("calendar", "📆"),
+    ("cat", "🐱"),
+    ("check", "✔️"),
+    ("clap", "👏"),
+    ("coffee", "☕"),
+    ("computer", "💻"),
+    ("confused", "😕"),
+    ("cool", "🆒"),
+    ("cry", "😢"),
+    ("crying_cat_face", "😿"),
+    ("dart", "🎯"),
+    ("disappointed", "😞"),
+    ("dizzy", "💫"),
+    ("dog", "🐶"),
+    ("eyes", "👀"),
+    ("facepalm", "🤦"),
+    ("fire", "🔥"),
+    ("flushed", "😳"),
+    ("gem", "💎"),
+    ("ghost", "👻"),
+    ("gift", "🎁"),
+    ("grimacing", "😬"),
+    ("grin", "😁"),
+    ("grinning", "😀"),
+    ("hammer", "🔨"),
+    ("hand", "✋"),
+    ("handshake", "🤝"),
+    ("heart", "❤️"),
+    ("heart_eyes", "😍"),
+    ("hourglass", "⌛"),
+    ("hugs", "🤗"),
+    ("hushed", "😯"),
+    ("innocent", "😇"),
+    ("joy", "😂"),
+    ("key", "🔑"),
+    ("kiss", "💋"),
+    ("kissing_heart", "😘"),
+    ("laughing", "😆"),
+    ("link", "🔗"),
+    ("lock", "🔒"),
+    ("mag", "🔍"),
+    ("mask", "😷"),
+    ("memo", "📝"),
+    ("money_with_wings", "💸"),
+    ("moneybag", "💰"),
+    ("muscle", "💪"),
+    ("neutral_face", "😐"),
+    ("no_entry", "⛔"),
+    ("ok", "🆗"),
+    ("ok_hand", "👌"),
+    ("open_mouth", "😮"),
+    ("package", "📦"),
+    ("partying_face", "🥳"),
+    ("pensive", "😔"),
+    ("point_down", "👇"),
+    ("point_left", "👈"),
+    ("point_right", "👉"),
+    ("point_up", "☝️"),
+    ("poop", "💩"),
+    ("pray", "🙏"),
+    ("question", "❓"),
+    ("raised_hands", "🙌"),
+    ("relaxed", "☺️"),
+    ("relieved", "😌"),
+    ("robot", "🤖"),
+    ("rocket", "🚀"),
+    ("rofl", "🤣"),
+    ("rage", "😡"),
+    ("scream", "😱"),
+    ("see_no_evil", "🙈"),
+    ("shrug", "🤷"),
+    ("skull", "💀"),
+    ("sleeping", "😴"),
+    ("sleepy", "😪"),
+    ("slightly_smiling_face", "🙂"),
+    ("smile", "😄"),
+    ("smiley", "😃"),
+    ("smirk", "😏"),
+    ("sob", "😭"),
+    ("sparkles", "✨"),
+    ("speech_balloon", "💬"),
+    ("star", "⭐"),
+    ("star_struck", "🤩"),
+    ("stuck_out_tongue", "😛"),
+    ("stuck_out_tongue_winking_eye", "😜"),
+    ("sunglasses", "😎"),
+    ("sweat", "😓"),
+    ("sweat_smile", "😅"),
+    ("tada", "🎉"),
+    ("thinking", "🤔"),
+    ("thumbsdown", "👎"),
+    ("thumbsup", "👍"),
+    ("tired_face", "😫"),
+    ("trophy", "🏆"),
+    ("unamused", "😒"),
+    ("upside_down_face", "🙃"),
+    ("v", "✌️"),
+    ("warning", "⚠️"),
+    ("wave", "👋"),
+    ("weary", "😩"),
+    ("white_check_mark", "✅"),
+    ("wink", "😉"),
+    ("wrench", "🔧"),
+    ("x", "❌"),
+    ("yum", "😋"),
+    ("zap", "⚡"),
+    ("zipper_mouth_face", "🤐"),
+    ("zzz", "💤"),
+];
+
+/// Column width reserved for emoji in listings. Most emoji are rendered
+/// using 2 columns, but some (with variation selectors) report less.
+const EMOJI_COLUMN: usize = 3;
+
+pub fn lookup(shortcode: &str) -> Option<&'static str> {
+    SHORTCODES
+        .iter()
+        .find(|(code, _)| *code == shortcode)
+        .map(|(_, emoji)| *emoji)
+}
+
+/// Replaces `:shortcode:` occurrences with unicode emoji. Unknown shortcodes
+/// and text inside code spans are left unchanged.
+pub fn expand(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut in_code = false;
+    let mut rest = text;
+
+    while let Some(idx) = rest.find([':', '`']) {
+        output.push_str(&rest[..idx]);
+        if rest[idx..].starts_with('`') {
+            in_code = !in_code;
+            output.push('`');
+            rest = &rest[idx + 1..];
+            continue;
+        }
+
+        let after = &rest[idx + 1..];
+        let candidate = after
+            .find(':')
+            .map(|end| &after[..end])
+            .filter(|code| !code.is_empty() && code.chars().all(is_shortcode_char));
+
+        match candidate.and_then(|code| lookup(code).map(|emoji| (code, emoji))) {
+            Some((code, emoji)) if !in_code => {
+                output.push_str(emoji);
+                rest = &after[code.len() + 1..];
+            }
+            // Closing colon can still start next shortcode, so we move only by one.
+            _ => {
+                output.push(':');
+                rest = after;
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+fn is_shortcode_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '+' || c == '-'
+}
+
+/// Finds shortcodes containing query.
+pub fn search(query: &str) -> Vec<(&'static str, &'static str)> {
+    let query = query.trim_matches(':').to_lowercase();
+    SHORTCODES
+        .iter()
+        .filter(|(code, _)| code.contains(&query))
+        .cloned()
+        .collect()
+}
+
+/// Formats search results in aligned column, independent of emoji display width.
+pub fn format_listing(entries: &[(&str, &str)]) -> String {
+    entries
+        .iter()
+        .map(|(code, emoji)| {
+            let padding = EMOJI_COLUMN.saturating_sub(emoji.width());
+            format!("  {}{} :{}:", emoji, " ".repeat(padding), code)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
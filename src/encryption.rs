@@ -0,0 +1,250 @@
+use anyhow::{anyhow, bail};
+use chacha20poly1305::aead::{Aead, NewAead};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use hmac::Hmac;
+use rand::RngCore;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use ya_core_model::identity;
+use ya_service_bus::{typed as bus, RpcEndpoint};
+
+use crate::storage::{load_json, save_atomic, save_json};
+
+const KEY_FILE: &str = "encryption.json";
+const PASSPHRASE_ENV: &str = "YACHAT_PASSPHRASE";
+const PBKDF2_ROUNDS: u32 = 100_000;
+const NONCE_LEN: usize = 24;
+/// Encrypted with derived key to detect wrong passphrase at startup,
+/// instead of failing to decrypt every single history entry.
+const CHECK_PLAINTEXT: &[u8] = b"yachat";
+const IDENTITY_DOMAIN: &[u8] = b"yachat storage key";
+/// Files written with `save_sealed`, relative to data dir.
+const SEALED_FILES: &[&str] = &["events.json", "queue.json", "schedule.json", "ratchet.json"];
+/// Directories with files written with `save_sealed`.
+const SEALED_DIRS: &[&str] = &["pins"];
+
+/// Secret, from which storage key is derived.
+#[derive(Clone, Copy)]
+pub enum KeySource {
+    /// Taken from `YACHAT_PASSPHRASE` or asked for at startup.
+    Passphrase,
+    /// Signature of fixed payload made by default yagna identity. Signatures
+    /// are deterministic, so the same key is derived on every run.
+    Identity,
+}
+
+impl FromStr for KeySource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "passphrase" => Ok(KeySource::Passphrase),
+            "identity" => Ok(KeySource::Identity),
+            _ => bail!("Expected `passphrase` or `identity`, got `{}`.", s),
+        }
+    }
+}
+
+/// Salt and key check, stored in data dir next to encrypted files.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeyParams {
+    salt: Option<String>,
+    check: Option<String>,
+}
+
+/// Encrypts history and pins stored in data dir. Sealed data is base64 of
+/// random nonce followed by XChaCha20-Poly1305 ciphertext.
+#[derive(Clone)]
+pub struct Cipher {
+    aead: Arc<XChaCha20Poly1305>,
+}
+
+impl Cipher {
+    pub async fn init(data_dir: &Path, source: KeySource) -> anyhow::Result<Cipher> {
+        let path = data_dir.join(KEY_FILE);
+        let mut params: KeyParams = load_json(&path)?;
+
+        let salt = match &params.salt {
+            Some(salt) => base64::decode(salt)?,
+            None => {
+                let mut salt = vec![0u8; 16];
+                rand::thread_rng().fill_bytes(&mut salt);
+                salt
+            }
+        };
+
+        let secret = match source {
+            KeySource::Passphrase => passphrase()?,
+            KeySource::Identity => identity_secret(&salt).await?,
+        };
+
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2::<Hmac<Sha256>>(&secret, &salt, PBKDF2_ROUNDS, &mut key);
+        let cipher = Cipher {
+            aead: Arc::new(XChaCha20Poly1305::new(&Key::from(key))),
+        };
+
+        match &params.check {
+            Some(check) => {
+                if cipher.open(check).ok().as_deref() != Some(CHECK_PLAINTEXT) {
+                    bail!("Invalid key. Can't decrypt data in {}.", data_dir.display());
+                }
+            }
+            // Encryption is enabled for the first time. Salt is saved before
+            // migration, so interrupted migration continues with the same key.
+            // Check is saved after it, and from then on plaintext is refused.
+            None => {
+                params.salt = Some(base64::encode(&salt));
+                save_json(&path, &params)?;
+                let sealed = seal_plaintext(data_dir, &cipher)?
+                    + crate::storage::seal_plaintext(data_dir, &cipher)?;
+                if sealed > 0 {
+                    log::info!("Encrypted {} existing file(s) or entries.", sealed);
+                }
+                params.check = Some(cipher.seal(CHECK_PLAINTEXT)?);
+                save_json(&path, &params)?;
+            }
+        }
+        Ok(cipher)
+    }
+
+    pub fn seal(&self, plaintext: &[u8]) -> anyhow::Result<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let ciphertext = self
+            .aead
+            .encrypt(&XNonce::from(nonce), plaintext)
+            .map_err(|_| anyhow!("Encryption failed."))?;
+        Ok(base64::encode([&nonce[..], &ciphertext].concat()))
+    }
+
+    pub fn open(&self, sealed: &str) -> anyhow::Result<Vec<u8>> {
+        let bytes = base64::decode(sealed.trim())?;
+        if bytes.len() < NONCE_LEN {
+            bail!("Encrypted data too short.");
+        }
+
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let nonce: [u8; NONCE_LEN] = nonce.try_into()?;
+        self.aead
+            .decrypt(&XNonce::from(nonce), ciphertext)
+            .map_err(|_| anyhow!("Decryption failed. Data corrupted or encrypted with other key."))
+    }
+}
+
+/// Plaintext json starts with `{` or `[`, sealed data never does. Plaintext
+/// is accepted with encryption enabled only once, when existing files are
+/// sealed by `Cipher::init`. Otherwise it could have been planted by anyone
+/// with write access to data dir.
+pub fn is_plaintext(content: &str) -> bool {
+    content.trim_start().starts_with(['{', '['])
+}
+
+/// Seals files written with `save_sealed` before encryption was enabled.
+/// Returns number of sealed files.
+fn seal_plaintext(data_dir: &Path, cipher: &Cipher) -> anyhow::Result<usize> {
+    let mut paths = SEALED_FILES
+        .iter()
+        .map(|file| data_dir.join(file))
+        .collect::<Vec<_>>();
+    for dir in SEALED_DIRS.iter().map(|dir| data_dir.join(dir)) {
+        if dir.is_dir() {
+            for entry in fs::read_dir(&dir)? {
+                paths.push(entry?.path());
+            }
+        }
+    }
+
+    let mut sealed = 0;
+    for path in paths.into_iter().filter(|path| path.is_file()) {
+        let content = fs::read_to_string(&path)
+            .map_err(|e| anyhow!("Failed to read {}. Error: {}", path.display(), e))?;
+        if is_plaintext(&content) {
+            save_atomic(&path, cipher.seal(content.as_bytes())?.as_bytes())?;
+            sealed += 1;
+        }
+    }
+    Ok(sealed)
+}
+
+/// Like `storage::load_json`, but decrypts file, when cipher is given.
+pub fn load_sealed<T: DeserializeOwned + Default>(
+    path: &Path,
+    cipher: Option<&Cipher>,
+) -> anyhow::Result<T> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+
+    let content = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read {}. Error: {}", path.display(), e))?;
+    let content = match (cipher, is_plaintext(&content)) {
+        (None, true) => content.into_bytes(),
+        (Some(_), true) => bail!(
+            "{} isn't encrypted, though encryption is enabled. Refusing to read it.",
+            path.display()
+        ),
+        (Some(cipher), false) => cipher.open(&content)?,
+        (None, false) => bail!(
+            "{} is encrypted. Run with --encrypt to read it.",
+            path.display()
+        ),
+    };
+    serde_json::from_slice(&content)
+        .map_err(|e| anyhow!("Failed to parse {}. Error: {}", path.display(), e))
+}
+
+/// Like `storage::save_json`, but encrypts file, when cipher is given.
+pub fn save_sealed<T: Serialize>(
+    path: &Path,
+    value: &T,
+    cipher: Option<&Cipher>,
+) -> anyhow::Result<()> {
+    match cipher {
+        None => save_json(path, value),
+        Some(cipher) => {
+            let sealed = cipher.seal(&serde_json::to_vec(value)?)?;
+            save_atomic(path, sealed.as_bytes())
+        }
+    }
+}
+
+fn passphrase() -> anyhow::Result<Vec<u8>> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase.into_bytes());
+    }
+
+    let passphrase = rpassword::read_password_from_tty(Some("Storage passphrase: "))
+        .map_err(|e| anyhow!("Failed to read passphrase. Error: {}", e))?;
+    if passphrase.is_empty() {
+        bail!("Empty passphrase.");
+    }
+    Ok(passphrase.into_bytes())
+}
+
+async fn identity_secret(salt: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let node_id = bus::service(identity::BUS_ID)
+        .send(identity::Get::ByDefault)
+        .await??
+        .ok_or_else(|| anyhow!("No default identity."))?
+        .node_id;
+
+    let payload = Sha256::new()
+        .chain(IDENTITY_DOMAIN)
+        .chain(salt)
+        .finalize()
+        .to_vec();
+    let signature = bus::service(identity::BUS_ID)
+        .send(identity::Sign { node_id, payload })
+        .await??;
+    Ok(signature)
+}
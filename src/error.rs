@@ -0,0 +1,61 @@
+use ya_client::model::NodeId;
+
+use crate::protocol::ChatError;
+
+/// Failure of sending message to single peer. Retryable failures end up
+/// in delivery queue, permanent ones are reported as rejected.
+#[derive(Debug, thiserror::Error)]
+pub enum SendError {
+    #[error("[{peer}] is unreachable. Error: {reason}")]
+    Unreachable { peer: NodeId, reason: String },
+    #[error("[{peer}] refused messages{}. Reason: {error}", in_group(.group))]
+    Refused {
+        peer: NodeId,
+        group: Option<String>,
+        error: ChatError,
+    },
+    /// Our side failed, for example chat is shutting down.
+    #[error("Messages to [{peer}] weren't sent. Error: {reason}")]
+    Local { peer: NodeId, reason: String },
+}
+
+impl SendError {
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            SendError::Unreachable { .. } => true,
+            SendError::Refused { error, .. } => error.is_retryable(),
+            SendError::Local { .. } => false,
+        }
+    }
+}
+
+/// Failure of discovering users through market.
+#[derive(Debug, thiserror::Error)]
+pub enum DiscoveryError {
+    /// Yagna market API failed. Usually yagna is restarting or overloaded.
+    #[error("Market API failed for group {group}. Error: {reason}")]
+    Market { group: String, reason: String },
+    /// Proposal doesn't describe chat user. Retrying won't help.
+    #[error("Invalid proposal in group {group}. Error: {reason}")]
+    InvalidProposal { group: String, reason: String },
+    /// Our side failed passing discovered user to chat, for example chat
+    /// is busy or restarting.
+    #[error("Discovered user in group {group} wasn't added. Error: {reason}")]
+    Local { group: String, reason: String },
+}
+
+impl DiscoveryError {
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            DiscoveryError::Market { .. } | DiscoveryError::Local { .. } => true,
+            DiscoveryError::InvalidProposal { .. } => false,
+        }
+    }
+}
+
+fn in_group(group: &Option<String>) -> String {
+    match group {
+        Some(group) => format!(" in group {}", group),
+        None => String::new(),
+    }
+}
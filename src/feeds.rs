@@ -0,0 +1,166 @@
+use actix::prelude::*;
+use anyhow::{anyhow, bail};
+use roxmltree::Node;
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::storage::{load_json, save_json};
+
+const FEEDS_FILE: &str = "feeds.json";
+/// Ids of posted entries remembered per feed. Feeds list only their newest
+/// entries, so older ids aren't needed.
+const MAX_SEEN: usize = 200;
+
+fn default_interval() -> u64 {
+    30
+}
+
+/// Feeds from `[[feed]]` section of config file, followed in group.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FeedConfig {
+    pub group: String,
+    /// RSS or Atom feed URLs.
+    pub urls: Vec<String>,
+    #[serde(default = "default_interval")]
+    pub interval_minutes: u64,
+}
+
+/// Entry of RSS or Atom feed.
+pub struct FeedEntry {
+    /// Guid or id. Link, when feed doesn't give one.
+    pub id: String,
+    pub title: String,
+    pub link: Option<String>,
+}
+
+/// Entries of feed from config with given index. Sent to chat from
+/// fetching thread.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct FeedFetched {
+    pub idx: usize,
+    pub url: String,
+    pub feed: String,
+    pub entries: Vec<FeedEntry>,
+}
+
+/// Ids of entries already posted, persisted in data dir, so restart
+/// doesn't post them again.
+pub struct SeenEntries {
+    path: PathBuf,
+    feeds: HashMap<String, VecDeque<String>>,
+}
+
+impl SeenEntries {
+    pub fn load(data_dir: &Path) -> anyhow::Result<SeenEntries> {
+        let path = data_dir.join(FEEDS_FILE);
+        Ok(SeenEntries {
+            feeds: load_json(&path)?,
+            path,
+        })
+    }
+
+    /// Returns entries, which weren't posted yet, and marks them seen.
+    /// Feed checked for the first time only remembers its entries, so
+    /// group isn't flooded with its whole history.
+    pub fn unseen(&mut self, url: &str, entries: Vec<FeedEntry>) -> Vec<FeedEntry> {
+        let first = !self.feeds.contains_key(url);
+        let seen = self.feeds.entry(url.to_string()).or_default();
+        let unseen = entries
+            .into_iter()
+            .filter(|entry| !seen.contains(&entry.id))
+            .collect::<Vec<_>>();
+        for entry in unseen.iter() {
+            seen.push_back(entry.id.clone());
+        }
+        while seen.len() > MAX_SEEN {
+            seen.pop_front();
+        }
+        match first {
+            true => vec![],
+            false => unseen,
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        save_json(&self.path, &self.feeds)
+    }
+}
+
+/// Downloads feed with curl, same as webhooks of hooks. Blocking.
+pub fn fetch(url: &str) -> anyhow::Result<(String, Vec<FeedEntry>)> {
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--location"])
+        .args(["--max-time", "30", url])
+        .output()
+        .map_err(|e| anyhow!("Failed to run curl. Error: {}", e))?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    parse(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Returns title of feed and its entries, newest first as listed by feed.
+/// Supports RSS 2.0 and Atom.
+pub fn parse(xml: &str) -> anyhow::Result<(String, Vec<FeedEntry>)> {
+    let document =
+        roxmltree::Document::parse(xml).map_err(|e| anyhow!("Invalid feed. Error: {}", e))?;
+    let root = document.root_element();
+
+    let (feed, items, entry_tag) = match root.tag_name().name() {
+        "rss" => {
+            let channel = child(root, "channel").ok_or_else(|| anyhow!("RSS without channel."))?;
+            (channel, channel, "item")
+        }
+        "feed" => (root, root, "entry"),
+        name => bail!("Unknown feed format: <{}>.", name),
+    };
+
+    let entries = items
+        .children()
+        .filter(|node| node.tag_name().name() == entry_tag)
+        .filter_map(|node| {
+            let link = match entry_tag {
+                "entry" => child(node, "link")
+                    .and_then(|link| link.attribute("href"))
+                    .map(str::to_string),
+                _ => text(node, "link"),
+            };
+            let id = text(node, "guid")
+                .or_else(|| text(node, "id"))
+                .or_else(|| link.clone())?;
+            Some(FeedEntry {
+                id,
+                title: text(node, "title").unwrap_or_default(),
+                link,
+            })
+        })
+        .collect();
+    Ok((text(feed, "title").unwrap_or_default(), entries))
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|child| child.tag_name().name() == name)
+}
+
+fn text(node: Node, name: &str) -> Option<String> {
+    child(node, name)
+        .and_then(|child| child.text())
+        .map(|text| text.trim().to_string())
+}
+
+/// Message posted to group for new entry.
+pub fn format(feed: &str, entry: &FeedEntry) -> String {
+    let mut message = match feed.is_empty() {
+        true => entry.title.clone(),
+        false => format!("[{}] {}", feed, entry.title),
+    };
+    if let Some(link) = &entry.link {
+        message.push_str(&format!("\n{}", link));
+    }
+    message
+}
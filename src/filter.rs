@@ -0,0 +1,200 @@
+use anyhow::{anyhow, bail};
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+
+use ya_client::model::NodeId;
+
+/// Received message passed through filters.
+pub struct Incoming<'a> {
+    /// None for direct messages.
+    pub group: Option<&'a str>,
+    pub sender: NodeId,
+    /// Name reported by sender.
+    pub user: &'a str,
+    pub content: &'a str,
+}
+
+pub enum Action {
+    Pass,
+    /// Message isn't displayed nor stored.
+    Drop,
+    /// Message is displayed with given reason.
+    Flag(String),
+    /// Content displayed instead of original. History keeps the original.
+    Mask(String),
+}
+
+/// Inbound filter. Filters are run in order of registration, each one
+/// gets content masked by previous ones.
+pub trait Filter {
+    fn filter(&self, message: &Incoming) -> Action;
+}
+
+/// Outcome of filter chain for message, which wasn't dropped.
+pub struct Filtered {
+    pub content: String,
+    pub flags: Vec<String>,
+}
+
+/// Filters from config file run first, then the ones registered
+/// programmatically.
+#[derive(Default)]
+pub struct FilterChain {
+    configured: Vec<Box<dyn Filter>>,
+    filters: Vec<Box<dyn Filter>>,
+}
+
+impl FilterChain {
+    pub fn from_config(rules: &[FilterRule]) -> anyhow::Result<FilterChain> {
+        let mut chain = FilterChain::default();
+        chain.configure(rules)?;
+        Ok(chain)
+    }
+
+    /// Replaces filters from config. Registered filters are kept.
+    pub fn configure(&mut self, rules: &[FilterRule]) -> anyhow::Result<()> {
+        self.configured = rules
+            .iter()
+            .map(FilterRule::build)
+            .collect::<anyhow::Result<_>>()?;
+        Ok(())
+    }
+
+    pub fn register(&mut self, filter: Box<dyn Filter>) {
+        self.filters.push(filter);
+    }
+
+    /// Returns None, if message should be dropped.
+    pub fn apply(&self, message: &Incoming) -> Option<Filtered> {
+        let mut filtered = Filtered {
+            content: message.content.to_string(),
+            flags: vec![],
+        };
+        for filter in self.configured.iter().chain(self.filters.iter()) {
+            let message = Incoming {
+                content: &filtered.content,
+                ..*message
+            };
+            match filter.filter(&message) {
+                Action::Pass => (),
+                Action::Drop => return None,
+                Action::Flag(reason) => filtered.flags.push(reason),
+                Action::Mask(content) => filtered.content = content,
+            }
+        }
+        Some(filtered)
+    }
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleAction {
+    Drop,
+    Flag,
+    Mask,
+}
+
+/// Rule from `[[filter]]` section of config file. `drop` and `flag` match
+/// `pattern` regex, `mask` replaces listed `words` with asterisks.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FilterRule {
+    pub action: RuleAction,
+    pub pattern: Option<String>,
+    #[serde(default)]
+    pub words: Vec<String>,
+    /// Displayed with flagged messages. Defaults to the pattern.
+    pub reason: Option<String>,
+    /// Groups, the rule applies to. Empty means all groups and direct messages.
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+impl FilterRule {
+    fn build(&self) -> anyhow::Result<Box<dyn Filter>> {
+        match self.action {
+            RuleAction::Drop | RuleAction::Flag => {
+                let pattern = self
+                    .pattern
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("Filter rule without pattern."))?;
+                let regex = Regex::new(pattern)
+                    .map_err(|e| anyhow!("Invalid filter pattern {}. Error: {}", pattern, e))?;
+                Ok(Box::new(RegexFilter {
+                    groups: self.groups.clone(),
+                    drop: matches!(self.action, RuleAction::Drop),
+                    reason: self.reason.clone().unwrap_or_else(|| pattern.clone()),
+                    regex,
+                }))
+            }
+            RuleAction::Mask => {
+                if self.words.is_empty() {
+                    bail!("Mask filter rule without words.");
+                }
+                Ok(Box::new(WordFilter::new(self.groups.clone(), &self.words)?))
+            }
+        }
+    }
+}
+
+/// Drops or flags messages matching regex.
+pub struct RegexFilter {
+    groups: Vec<String>,
+    regex: Regex,
+    drop: bool,
+    reason: String,
+}
+
+impl Filter for RegexFilter {
+    fn filter(&self, message: &Incoming) -> Action {
+        if !applies(&self.groups, message.group) || !self.regex.is_match(message.content) {
+            return Action::Pass;
+        }
+        match self.drop {
+            true => Action::Drop,
+            false => Action::Flag(self.reason.clone()),
+        }
+    }
+}
+
+/// Masks whole words, ignoring case.
+pub struct WordFilter {
+    groups: Vec<String>,
+    regex: Regex,
+}
+
+impl WordFilter {
+    pub fn new(groups: Vec<String>, words: &[String]) -> anyhow::Result<WordFilter> {
+        let words = words
+            .iter()
+            .map(|word| regex::escape(word))
+            .collect::<Vec<_>>()
+            .join("|");
+        let regex = RegexBuilder::new(&format!(r"\b(?:{})\b", words))
+            .case_insensitive(true)
+            .build()?;
+        Ok(WordFilter { groups, regex })
+    }
+}
+
+impl Filter for WordFilter {
+    fn filter(&self, message: &Incoming) -> Action {
+        if !applies(&self.groups, message.group) || !self.regex.is_match(message.content) {
+            return Action::Pass;
+        }
+        let masked = self
+            .regex
+            .replace_all(message.content, |captures: &regex::Captures| {
+                "*".repeat(captures[0].chars().count())
+            });
+        Action::Mask(masked.into_owned())
+    }
+}
+
+fn applies(groups: &[String], group: Option<&str>) -> bool {
+    match group {
+        _ if groups.is_empty() => true,
+        Some(group) => groups.iter().any(|name| name == group),
+        None => false,
+    }
+}
@@ -0,0 +1,152 @@
+use anyhow::anyhow;
+use serde::Deserialize;
+
+/// Commits listed in message about push. Rest is only counted.
+const MAX_COMMITS: usize = 5;
+
+#[derive(Deserialize)]
+struct Payload {
+    repository: Option<Repository>,
+    sender: Option<User>,
+    action: Option<String>,
+    #[serde(rename = "ref")]
+    git_ref: Option<String>,
+    #[serde(default)]
+    commits: Vec<Commit>,
+    compare: Option<String>,
+    pull_request: Option<Issue>,
+    issue: Option<Issue>,
+    release: Option<Release>,
+}
+
+#[derive(Deserialize)]
+struct Repository {
+    full_name: String,
+}
+
+#[derive(Deserialize)]
+struct User {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct Commit {
+    id: String,
+    message: String,
+}
+
+/// Issue or pull request.
+#[derive(Deserialize)]
+struct Issue {
+    number: u64,
+    title: String,
+    html_url: String,
+    #[serde(default)]
+    merged: bool,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    name: Option<String>,
+    html_url: String,
+}
+
+/// Formats GitHub webhook payload of given `X-GitHub-Event`. Returns
+/// repository full name with message, or None for events and actions,
+/// which aren't worth posting.
+pub fn format(event: &str, body: &[u8]) -> anyhow::Result<Option<(String, String)>> {
+    let payload =
+        serde_json::from_slice::<Payload>(body).map_err(|e| anyhow!("Invalid payload. {}", e))?;
+    let repository = match &payload.repository {
+        Some(repository) => repository.full_name.clone(),
+        None => return Ok(None),
+    };
+    let sender = payload
+        .sender
+        .as_ref()
+        .map(|sender| sender.login.as_str())
+        .unwrap_or("somebody");
+    let action = payload.action.as_deref().unwrap_or("");
+
+    let text = match (event, action) {
+        ("ping", _) => "Webhook connected.".to_string(),
+        ("push", _) => {
+            if payload.commits.is_empty() {
+                return Ok(None);
+            }
+            let branch = payload
+                .git_ref
+                .as_deref()
+                .unwrap_or("")
+                .trim_start_matches("refs/heads/");
+            let mut text = format!(
+                "{} pushed {} commit{} to {}",
+                sender,
+                payload.commits.len(),
+                if payload.commits.len() == 1 { "" } else { "s" },
+                branch
+            );
+            for commit in payload.commits.iter().take(MAX_COMMITS) {
+                text.push_str(&format!(
+                    "\n  {} {}",
+                    &commit.id[..commit.id.len().min(7)],
+                    commit.message.lines().next().unwrap_or("")
+                ));
+            }
+            if payload.commits.len() > MAX_COMMITS {
+                text.push_str(&format!(
+                    "\n  ... and {} more",
+                    payload.commits.len() - MAX_COMMITS
+                ));
+            }
+            if let Some(compare) = &payload.compare {
+                text.push_str(&format!("\n{}", compare));
+            }
+            text
+        }
+        ("pull_request", "opened") | ("pull_request", "closed") | ("pull_request", "reopened") => {
+            let pull = match &payload.pull_request {
+                Some(pull) => pull,
+                None => return Ok(None),
+            };
+            let action = match pull.merged {
+                true => "merged",
+                false => action,
+            };
+            format!(
+                "{} {} pull request #{}: {}\n{}",
+                sender, action, pull.number, pull.title, pull.html_url
+            )
+        }
+        ("issues", "opened") | ("issues", "closed") | ("issues", "reopened") => {
+            let issue = match &payload.issue {
+                Some(issue) => issue,
+                None => return Ok(None),
+            };
+            format!(
+                "{} {} issue #{}: {}\n{}",
+                sender, action, issue.number, issue.title, issue.html_url
+            )
+        }
+        ("release", "published") => {
+            let release = match &payload.release {
+                Some(release) => release,
+                None => return Ok(None),
+            };
+            let name = match &release.name {
+                Some(name) if !name.is_empty() && name != &release.tag_name => {
+                    format!(" {}", name)
+                }
+                _ => String::new(),
+            };
+            format!(
+                "{} published release {}{}\n{}",
+                sender, release.tag_name, name, release.html_url
+            )
+        }
+        _ => return Ok(None),
+    };
+    let text = format!("[{}] {}", repository, text);
+    Ok(Some((repository, text)))
+}
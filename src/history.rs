@@ -0,0 +1,108 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use ya_client::model::NodeId;
+
+use crate::storage::Storage;
+
+/// Number of recent messages kept in memory. Older ones are only in storage.
+const MAX_LOADED: usize = 10000;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub id: Uuid,
+    pub group: String,
+    /// None for messages sent by us.
+    pub sender: Option<NodeId>,
+    pub user: String,
+    pub content: String,
+    pub timestamp: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+}
+
+/// Recent messages of single group, kept in memory. Messages are
+/// written to storage by group worker.
+pub struct History {
+    entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    pub fn load(storage: &dyn Storage, group: &str) -> anyhow::Result<History> {
+        Ok(History {
+            entries: storage.query(group, None, None, MAX_LOADED)?,
+        })
+    }
+
+    /// Adds entry in memory only. Caller writes it to storage.
+    pub fn push(&mut self, entry: HistoryEntry) {
+        self.entries.push(entry);
+        if self.entries.len() > MAX_LOADED {
+            self.entries.remove(0);
+        }
+    }
+
+    /// Adds entries, we don't have yet, in memory. Returns added entries,
+    /// which caller writes to storage.
+    pub fn merge(&mut self, entries: Vec<HistoryEntry>) -> Vec<HistoryEntry> {
+        let mut added = vec![];
+        for entry in entries {
+            if self.entries.iter().any(|known| known.id == entry.id) {
+                continue;
+            }
+            self.push(entry.clone());
+            added.push(entry);
+        }
+        self.entries.sort_by_key(|entry| entry.timestamp);
+        added
+    }
+
+    /// Entries newer than `since`, at most `limit` of the newest ones.
+    pub fn since(&self, since: Option<DateTime<Utc>>, limit: usize) -> Vec<HistoryEntry> {
+        let newer = self
+            .entries
+            .iter()
+            .filter(|entry| since.map_or(true, |since| entry.timestamp > since))
+            .collect::<Vec<_>>();
+        let skip = newer.len().saturating_sub(limit);
+        newer.into_iter().skip(skip).cloned().collect()
+    }
+
+    /// Drops entries in memory the same way as `Storage::prune`.
+    pub fn prune(&mut self, before: Option<DateTime<Utc>>, keep_last: Option<usize>) {
+        if let Some(before) = before {
+            self.entries.retain(|entry| entry.timestamp >= before);
+        }
+        if let Some(keep_last) = keep_last {
+            let skip = self.entries.len().saturating_sub(keep_last);
+            self.entries.drain(..skip);
+        }
+    }
+
+    /// Drops messages of `senders` in memory. Returns number of dropped
+    /// messages. Older messages are only in storage, so caller removes
+    /// them there with `Storage::delete_by_sender`.
+    pub fn forget(&mut self, senders: &[NodeId]) -> usize {
+        let count = self.entries.len();
+        self.entries.retain(|entry| {
+            !entry
+                .sender
+                .map_or(false, |sender| senders.contains(&sender))
+        });
+        count - self.entries.len()
+    }
+
+    pub fn last(&self) -> Option<&HistoryEntry> {
+        self.entries.last()
+    }
+
+    /// Finds message by id prefix. Newest messages are searched first.
+    pub fn find(&self, pattern: &str) -> Option<&HistoryEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.id.to_simple().to_string().starts_with(pattern))
+    }
+}
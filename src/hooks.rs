@@ -0,0 +1,201 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use ya_client::model::NodeId;
+
+use crate::protocol::Structured;
+
+/// Command run on event. `bell` rings terminal bell instead and http(s)
+/// URL gets event json posted with curl.
+const BELL: &str = "bell";
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Event {
+    Message,
+    Mention,
+    /// Message containing term from `/watch` list.
+    Watch,
+    Direct,
+    UserJoined,
+    DeliveryFailed,
+    /// `!name` command of bot registered by our plugin.
+    BotCommand,
+}
+
+impl Event {
+    fn name(&self) -> &'static str {
+        match self {
+            Event::Message => "message",
+            Event::Mention => "mention",
+            Event::Watch => "watch",
+            Event::Direct => "direct",
+            Event::UserJoined => "user-joined",
+            Event::DeliveryFailed => "delivery-failed",
+            Event::BotCommand => "bot-command",
+        }
+    }
+}
+
+/// Event metadata. Passed to hook as json on stdin and as `YACHAT_*`
+/// environment variables.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventData {
+    pub event: Event,
+    pub group: Option<String>,
+    pub user: Option<String>,
+    pub node_id: Option<NodeId>,
+    pub text: Option<String>,
+    /// Name of invoked bot command. Text holds its arguments.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Structured content of message. Text holds its plain text version.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub structured: Option<Structured>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl EventData {
+    pub fn new(event: Event) -> EventData {
+        EventData {
+            event,
+            group: None,
+            user: None,
+            node_id: None,
+            text: None,
+            command: None,
+            structured: None,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// Shell commands run on chat events, configured in `[hooks]` section
+/// of config file. Generic integration point for notifications and sounds.
+#[derive(Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct Hooks {
+    pub message: Option<String>,
+    pub mention: Option<String>,
+    pub watch: Option<String>,
+    pub direct: Option<String>,
+    pub user_joined: Option<String>,
+    pub delivery_failed: Option<String>,
+    pub bot_command: Option<String>,
+}
+
+impl Hooks {
+    /// Runs `command` for every received group and direct message, used
+    /// by `yachat notify-endpoint`.
+    pub fn on_messages(command: String) -> Hooks {
+        Hooks {
+            message: Some(command.clone()),
+            direct: Some(command),
+            ..Hooks::default()
+        }
+    }
+
+    fn command(&self, event: Event) -> Option<&String> {
+        match event {
+            Event::Message => self.message.as_ref(),
+            Event::Mention => self.mention.as_ref(),
+            Event::Watch => self.watch.as_ref(),
+            Event::Direct => self.direct.as_ref(),
+            Event::UserJoined => self.user_joined.as_ref(),
+            Event::DeliveryFailed => self.delivery_failed.as_ref(),
+            Event::BotCommand => self.bot_command.as_ref(),
+        }
+    }
+
+    /// Runs hook in background. Chat never waits for hook to finish.
+    pub fn fire(&self, data: EventData) {
+        let command = match self.command(data.event) {
+            Some(command) => command.clone(),
+            None => return,
+        };
+
+        if command == BELL {
+            print!("\x07");
+            std::io::stdout().flush().ok();
+            return;
+        }
+
+        std::thread::spawn(move || {
+            if let Err(e) = run(&command, &data) {
+                log::warn!(
+                    "Hook for {} event failed. Command: {}. Error: {}",
+                    data.event.name(),
+                    command,
+                    e
+                );
+            }
+        });
+    }
+}
+
+fn run(command: &str, data: &EventData) -> anyhow::Result<()> {
+    if command.starts_with("http://") || command.starts_with("https://") {
+        let mut process = Command::new("curl");
+        process.args([
+            "--silent",
+            "--show-error",
+            "--fail",
+            "--header",
+            "Content-Type: application/json",
+            "--data-binary",
+            "@-",
+            command,
+        ]);
+        return feed(process, data);
+    }
+
+    let mut process = match cfg!(target_os = "windows") {
+        true => {
+            let mut process = Command::new("cmd");
+            process.args(["/C", command]);
+            process
+        }
+        false => {
+            let mut process = Command::new("sh");
+            process.args(["-c", command]);
+            process
+        }
+    };
+
+    let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+    process
+        .env("YACHAT_EVENT", data.event.name())
+        .env("YACHAT_GROUP", optional(&data.group))
+        .env("YACHAT_USER", optional(&data.user))
+        .env(
+            "YACHAT_NODE_ID",
+            data.node_id.map(|id| id.to_string()).unwrap_or_default(),
+        )
+        .env("YACHAT_TEXT", optional(&data.text))
+        .env("YACHAT_COMMAND", optional(&data.command));
+    feed(process, data)
+}
+
+/// Runs process with event json on stdin.
+fn feed(mut process: Command, data: &EventData) -> anyhow::Result<()> {
+    let mut child = process
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // Hook doesn't have to read stdin, so broken pipe isn't an error.
+        stdin
+            .write_all(serde_json::to_string(data)?.as_bytes())
+            .ok();
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        anyhow::bail!("Exited with {}", status);
+    }
+    Ok(())
+}
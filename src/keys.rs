@@ -0,0 +1,150 @@
+use anyhow::anyhow;
+use std::fs;
+use std::path::Path;
+use structopt::StructOpt;
+
+use ya_client::model::NodeId;
+use ya_core_model::{appkey, identity};
+use ya_service_bus::{typed as bus, RpcEndpoint};
+
+use crate::storage::save_atomic;
+
+pub const DEFAULT_KEY_NAME: &str = "yachat";
+
+/// `yachat key` commands, managing yagna app-keys.
+#[derive(StructOpt)]
+pub enum KeyCommand {
+    /// Create app-key for default yagna identity.
+    Create {
+        #[structopt(default_value = DEFAULT_KEY_NAME)]
+        name: String,
+        /// Store the key in config file, so it doesn't have to be set in environment.
+        #[structopt(long)]
+        save: bool,
+    },
+    /// List app-keys.
+    List,
+    /// Remove app-key.
+    Drop { name: String },
+}
+
+/// Parsed separately from `Args`, because they require app-key, which
+/// user may not have yet.
+#[derive(StructOpt)]
+#[structopt(name = "yachat key")]
+struct KeyArgs {
+    #[structopt(subcommand)]
+    command: KeyCommand,
+}
+
+impl KeyCommand {
+    /// Returns command, when `yachat key ...` was run.
+    pub fn from_cli() -> Option<KeyCommand> {
+        match std::env::args().nth(1).as_deref() {
+            Some("key") => Some(KeyArgs::from_iter(std::env::args().skip(1)).command),
+            _ => None,
+        }
+    }
+
+    pub async fn run(self, config_path: &Path) -> anyhow::Result<()> {
+        match self {
+            KeyCommand::Create { name, save } => {
+                let key = create(&name, default_identity().await?).await?;
+                println!("Created app-key {}: {}", name, key);
+                if save {
+                    save_in_config(config_path, &key)?;
+                    println!("App-key stored in {}.", config_path.display());
+                }
+            }
+            KeyCommand::List => {
+                let keys = list(None).await?;
+                if keys.is_empty() {
+                    println!("No app-keys. Create one with `yachat key create`.");
+                }
+                for key in keys {
+                    println!(
+                        "  {} [{}] role: {}, created: {}\n    {}",
+                        key.name,
+                        key.identity,
+                        key.role,
+                        key.created_date.format("%Y-%m-%d %H:%M"),
+                        key.key
+                    );
+                }
+            }
+            KeyCommand::Drop { name } => {
+                bus::service(appkey::BUS_ID)
+                    .send(appkey::Remove {
+                        name: name.clone(),
+                        identity: None,
+                    })
+                    .await??;
+                println!("Removed app-key {}.", name);
+            }
+        }
+        Ok(())
+    }
+}
+
+pub async fn default_identity() -> anyhow::Result<NodeId> {
+    Ok(bus::service(identity::BUS_ID)
+        .send(identity::Get::ByDefault)
+        .await??
+        .ok_or_else(|| {
+            anyhow!("Yagna has no default identity. Create one with `yagna id create`.")
+        })?
+        .node_id)
+}
+
+pub async fn list(identity: Option<NodeId>) -> anyhow::Result<Vec<appkey::AppKey>> {
+    let (keys, _) = bus::service(appkey::BUS_ID)
+        .send(appkey::List {
+            identity: identity.map(|node_id| node_id.to_string()),
+            page: 1,
+            per_page: 100,
+        })
+        .await??;
+    Ok(keys)
+}
+
+pub async fn create(name: &str, identity: NodeId) -> anyhow::Result<String> {
+    Ok(bus::service(appkey::BUS_ID)
+        .send(appkey::Create {
+            name: name.to_string(),
+            role: appkey::DEFAULT_ROLE.to_string(),
+            identity,
+        })
+        .await??)
+}
+
+/// Sets `app-key` in config file, keeping the rest of it.
+pub fn save_in_config(config_path: &Path, key: &str) -> anyhow::Result<()> {
+    let mut config = match config_path.exists() {
+        true => fs::read_to_string(config_path)?
+            .parse::<toml::Value>()
+            .map_err(|e| anyhow!("Failed to parse {}. Error: {}", config_path.display(), e))?,
+        false => toml::Value::Table(Default::default()),
+    };
+    let table = config
+        .as_table_mut()
+        .ok_or_else(|| anyhow!("Invalid config file {}.", config_path.display()))?;
+    table.insert("app-key".to_string(), toml::Value::String(key.to_string()));
+    save_private(config_path, toml::to_string(&config)?.as_bytes())
+}
+
+/// Config file with app-key is readable only by its owner.
+pub fn save_private(path: &Path, content: &[u8]) -> anyhow::Result<()> {
+    save_atomic(path, content)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600)).map_err(|e| {
+            anyhow!(
+                "Failed to restrict access to {}. Error: {}",
+                path.display(),
+                e
+            )
+        })?;
+    }
+    Ok(())
+}
@@ -0,0 +1,242 @@
+use anyhow::bail;
+use std::str::FromStr;
+use unicode_bidi::{bidi_class, BidiClass};
+use unicode_width::UnicodeWidthChar;
+
+const FIRST_STRONG_ISOLATE: char = '\u{2068}';
+const POP_DIRECTIONAL_ISOLATE: char = '\u{2069}';
+
+/// Below this width wrapping makes messages less readable than letting
+/// terminal break lines on its own.
+const MIN_BODY_WIDTH: usize = 20;
+
+// =========================================== //
+// Message header layouts
+// =========================================== //
+
+/// How message header is printed. Selected with `--layout`, `layout` in
+/// config file or `/layout` while running.
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum MessageLayout {
+    /// `2024-05-02 14:03:11 user > `
+    #[default]
+    Standard,
+    /// `14:03 user: `
+    Compact,
+    /// Full date, NodeId of sender and message id.
+    Verbose,
+    /// Standard, but repeated sender within a minute gets empty header.
+    Grouped,
+}
+
+impl MessageLayout {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MessageLayout::Standard => "standard",
+            MessageLayout::Compact => "compact",
+            MessageLayout::Verbose => "verbose",
+            MessageLayout::Grouped => "grouped",
+        }
+    }
+}
+
+impl FromStr for MessageLayout {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "standard" => Ok(MessageLayout::Standard),
+            "compact" => Ok(MessageLayout::Compact),
+            "verbose" => Ok(MessageLayout::Verbose),
+            "grouped" => Ok(MessageLayout::Grouped),
+            _ => bail!(
+                "Expected `standard`, `compact`, `verbose` or `grouped`, got `{}`.",
+                s
+            ),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for MessageLayout {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+// =========================================== //
+// Width-aware message layout
+// =========================================== //
+
+/// Lays out message as `header body`, where every following line of body
+/// (both explicit and wrapped) is aligned under the first character of body.
+/// Widths are measured in terminal columns, so CJK, emoji and combining
+/// characters don't break alignment.
+pub fn format_message(header: &str, body: &str) -> String {
+    format_message_width(header, body, terminal_width())
+}
+
+pub fn format_message_width(header: &str, body: &str, width: Option<usize>) -> String {
+    let indent = display_width(header);
+    let body_width = width
+        .map(|width| width.saturating_sub(indent))
+        .filter(|body_width| *body_width >= MIN_BODY_WIDTH);
+
+    let lines = body
+        .split('\n')
+        .flat_map(|line| match body_width {
+            Some(body_width) => wrap(line, body_width),
+            None => vec![line.to_string()],
+        })
+        .map(|line| isolate(&line))
+        .collect::<Vec<_>>();
+
+    let separator = format!("\n{}", " ".repeat(indent));
+    format!("{}{}", header, lines.join(&separator))
+}
+
+pub fn terminal_width() -> Option<usize> {
+    terminal_size::terminal_size().map(|(terminal_size::Width(width), _)| width as usize)
+}
+
+pub fn terminal_height() -> Option<usize> {
+    terminal_size::terminal_size().map(|(_, terminal_size::Height(height))| height as usize)
+}
+
+/// Wraps text containing right-to-left characters in bidi isolate, so it
+/// can't reorder surrounding timestamp and user name.
+pub fn isolate(text: &str) -> String {
+    let rtl = text
+        .chars()
+        .any(|c| matches!(bidi_class(c), BidiClass::R | BidiClass::AL));
+    match rtl {
+        true => format!(
+            "{}{}{}",
+            FIRST_STRONG_ISOLATE, text, POP_DIRECTIONAL_ISOLATE
+        ),
+        false => text.to_string(),
+    }
+}
+
+/// Number of terminal columns occupied by text. Ignores ANSI escape sequences.
+pub fn display_width(text: &str) -> usize {
+    atoms(text).iter().map(|(_, width)| width).sum()
+}
+
+/// Greedy word wrapping. Words longer than line are broken between characters.
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut current = String::new();
+    let mut current_width = 0;
+
+    for token in tokens(line) {
+        let token_width = display_width(token);
+        if current_width + token_width <= width {
+            current.push_str(token);
+            current_width += token_width;
+            continue;
+        }
+
+        // Whitespace at line break is dropped.
+        if token.chars().all(char::is_whitespace) {
+            lines.push(std::mem::take(&mut current));
+            current_width = 0;
+            continue;
+        }
+
+        if current_width > 0 {
+            lines.push(std::mem::take(&mut current));
+            current_width = 0;
+        }
+
+        for (atom, atom_width) in atoms(token) {
+            if current_width + atom_width > width && current_width > 0 {
+                lines.push(std::mem::take(&mut current));
+                current_width = 0;
+            }
+            current.push_str(atom);
+            current_width += atom_width;
+        }
+    }
+    lines.push(current);
+
+    lines
+        .into_iter()
+        .map(|line| line.trim_end().to_string())
+        .collect()
+}
+
+/// Splits text into alternating runs of whitespace and non-whitespace.
+fn tokens(text: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    let mut start = 0;
+    let mut space = None;
+
+    for (idx, c) in text.char_indices() {
+        let is_space = c.is_whitespace();
+        if space.is_some() && space != Some(is_space) {
+            tokens.push(&text[start..idx]);
+            start = idx;
+        }
+        space = Some(is_space);
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+/// Splits text into smallest unbreakable pieces with their display widths:
+/// single characters and whole ANSI escape sequences (which have zero width).
+fn atoms(text: &str) -> Vec<(&str, usize)> {
+    let mut atoms = vec![];
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        if c != '\x1b' {
+            let width = match c {
+                FIRST_STRONG_ISOLATE | POP_DIRECTIONAL_ISOLATE => 0,
+                c => c.width().unwrap_or(0),
+            };
+            atoms.push((&text[start..start + c.len_utf8()], width));
+            continue;
+        }
+
+        let mut end = start + c.len_utf8();
+        match chars.peek().map(|(_, c)| *c) {
+            // CSI sequence: ends with byte in range 0x40..=0x7E.
+            Some('[') => {
+                chars.next();
+                end += 1;
+                for (idx, c) in chars.by_ref() {
+                    end = idx + c.len_utf8();
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC sequence: ends with BEL or ESC \.
+            Some(']') => {
+                chars.next();
+                end += 1;
+                while let Some((idx, c)) = chars.next() {
+                    end = idx + c.len_utf8();
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' {
+                        if let Some((idx, '\\')) = chars.peek().cloned() {
+                            chars.next();
+                            end = idx + 1;
+                            break;
+                        }
+                    }
+                }
+            }
+            _ => (),
+        }
+        atoms.push((&text[start..end], 0));
+    }
+    atoms
+}
@@ -0,0 +1,293 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use structopt::clap;
+
+use alerts::AlertsConfig;
+use away::AwayConfig;
+use builder::Ui;
+use encryption::KeySource;
+use feeds::FeedConfig;
+use filter::FilterRule;
+use hooks::Hooks;
+use keys::KeyCommand;
+use layout::MessageLayout;
+use migrations::DbCommand;
+use plugin::WasmPluginConfig;
+use report::ReportFormat;
+use responder::ResponderRule;
+use retention::RetentionConfig;
+use room::GroupCommand;
+use schedule::Recurring;
+use spam::SpamConfig;
+use stats::Period;
+use storage::StorageKind;
+use theme::Palette;
+use throttle::ThrottleConfig;
+use webhook::WebhookConfig;
+
+use ya_client::cli::ApiOpts;
+use ya_client::model::NodeId;
+
+mod alerts;
+mod audit;
+mod away;
+mod blocklist;
+pub mod builder;
+mod challenge;
+#[cfg(feature = "chaos")]
+mod chaos;
+pub mod chat;
+mod chatlog;
+mod clipboard;
+mod commands;
+pub mod config;
+mod console;
+mod contacts;
+mod cron;
+pub mod delivery;
+mod device;
+pub mod discover;
+mod emoji;
+pub mod encryption;
+pub mod error;
+mod feeds;
+pub mod filter;
+mod github;
+pub mod history;
+pub mod hooks;
+pub mod keys;
+mod layout;
+mod membership;
+pub mod migrations;
+mod pins;
+mod plugin;
+mod presence;
+mod profile;
+pub mod protocol;
+mod ratchet;
+mod render;
+pub mod report;
+mod responder;
+pub mod retention;
+pub mod room;
+pub mod roster;
+mod schedule;
+mod session;
+pub mod setup;
+mod spam;
+pub mod stats;
+pub mod storage;
+pub mod structured;
+mod theme;
+mod throttle;
+pub mod update;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod watch;
+mod webhook;
+pub mod whoami;
+pub mod wipe;
+
+#[derive(structopt::StructOpt)]
+#[structopt(global_setting = clap::AppSettings::ColoredHelp)]
+pub struct Args {
+    /// Name visible to other users. Required, unless set in config file.
+    #[structopt(long, short)]
+    pub name: Option<String>,
+    /// Group to join. Can be repeated, first group is active.
+    #[structopt(long = "group", short)]
+    pub groups: Vec<String>,
+    /// Rejoin groups, we were in at last shutdown.
+    #[structopt(long)]
+    pub resume: bool,
+    /// Print messages as received, without rendering markup.
+    #[structopt(long)]
+    pub plain: bool,
+    /// Screen reader friendly output: no colors and no in-place updates. Messages
+    /// and notices start with explicit prefixes.
+    #[structopt(long)]
+    pub accessible: bool,
+    /// Display short message ids, for example `#4f2a9c`, usable with `/pin`, market subscription
+    /// ids and discovered users. Repeat (`-vv`) to show discovery cycles and delivery attempts.
+    #[structopt(long, short, parse(from_occurrences))]
+    pub verbose: u8,
+    /// Print only messages and command output, without system notices.
+    #[structopt(long, short, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// Color theme: dark, light, solarized or name of theme defined in config file.
+    #[structopt(long)]
+    pub theme: Option<String>,
+    /// Message header layout: standard, compact, verbose or grouped.
+    #[structopt(long)]
+    pub layout: Option<MessageLayout>,
+    /// Themes defined in config file.
+    #[structopt(skip)]
+    pub themes: HashMap<String, Palette>,
+    /// Commands run on events, defined in config file.
+    #[structopt(skip)]
+    pub hooks: Hooks,
+    /// Set by `notify-endpoint` subcommand. Chat never sends messages.
+    #[structopt(skip)]
+    pub receive_only: bool,
+    /// Set by `ChatBuilder`. Headless chat neither prints nor reads stdin.
+    #[structopt(skip)]
+    pub ui: Ui,
+    /// Check for newer release at start. Enabled in config file.
+    #[structopt(skip)]
+    pub update_check: bool,
+    /// Auto-reply settings from config file.
+    #[structopt(skip)]
+    pub away: AwayConfig,
+    /// Yagna node alerts from config file.
+    #[structopt(skip)]
+    pub alerts: AlertsConfig,
+    /// Messages broadcast on schedule, defined in config file.
+    #[structopt(skip)]
+    pub recurring: Vec<Recurring>,
+    /// Feeds posted to groups, defined in config file.
+    #[structopt(skip)]
+    pub feeds: Vec<FeedConfig>,
+    /// Spam thresholds from config file.
+    #[structopt(skip)]
+    pub spam: SpamConfig,
+    /// Outbound rate limits from config file.
+    #[structopt(skip)]
+    pub throttle: ThrottleConfig,
+    /// History retention from config file.
+    #[structopt(skip)]
+    pub retention: RetentionConfig,
+    /// Groups from config file, even if `--group` overrides them.
+    #[structopt(skip)]
+    pub config_groups: Vec<String>,
+    /// Inbound filter rules from config file.
+    #[structopt(skip)]
+    pub filters: Vec<FilterRule>,
+    /// Scripted responders from config file.
+    #[structopt(skip)]
+    pub responders: Vec<ResponderRule>,
+    /// Slack-compatible and GitHub incoming webhooks from config file.
+    #[structopt(skip)]
+    pub webhook: WebhookConfig,
+    /// Sandboxed plugins from config file.
+    #[structopt(skip)]
+    pub wasm_plugins: Vec<WasmPluginConfig>,
+    /// Undelivered messages are dropped after this time instead of being resent
+    /// out of context, for example `6h` or `2d`. Default 1 day, `none` disables.
+    #[structopt(long)]
+    pub message_ttl: Option<String>,
+    /// Notices about the same user reappearing are printed at most once per this
+    /// time, for example `5m` or `1h`. Default 10 minutes.
+    #[structopt(long)]
+    pub reappear_interval: Option<String>,
+    /// Write plaintext daily logs of groups to `logs/chat/<group>/<date>.log` in data dir.
+    #[structopt(long)]
+    pub chat_logs: bool,
+    /// Make links clickable in terminals supporting OSC 8 hyperlinks.
+    #[structopt(long)]
+    pub hyperlinks: bool,
+    /// Don't replace `:shortcode:` with emoji in sent messages.
+    #[structopt(long)]
+    pub no_emoji: bool,
+    /// Executable exchanging newline-delimited json with chat: events on stdin, commands
+    /// on stdout. Restarted, when it exits. Can be repeated.
+    #[structopt(long = "plugin")]
+    pub plugins: Vec<PathBuf>,
+    /// Include provider state and number of running tasks from local yagna in presence
+    /// sent to other users.
+    #[structopt(long)]
+    pub node_presence: bool,
+    /// Makes first group announcement-only: only listed NodeIds can post.
+    #[structopt(long = "announcer")]
+    pub announcers: Vec<NodeId>,
+    /// Makes first group paid: posting requires membership Agreement with us and fee in GLM.
+    #[structopt(long)]
+    pub fee: Option<String>,
+    /// Limits number of users in first group. Advertised to joining users.
+    #[structopt(long)]
+    pub max_members: Option<usize>,
+    /// Directory for persistent state: contacts, aliases, history, pins and logs.
+    /// Defaults to platform data dir, for example `~/.local/share/yachat` or
+    /// `%APPDATA%\golem\yachat\data` on Windows.
+    #[structopt(long)]
+    pub data_dir: Option<PathBuf>,
+    /// Encrypt history and pins at rest with key derived from `passphrase` or yagna `identity`.
+    #[structopt(long)]
+    pub encrypt: Option<KeySource>,
+    /// Print delivery outcome per recipient at exit: text or json.
+    #[structopt(long)]
+    pub report: Option<ReportFormat>,
+    /// Storage backend: files (default), memory or sqlite.
+    #[structopt(long)]
+    pub storage: Option<StorageKind>,
+    /// Config file with defaults for name, groups and data dir.
+    #[structopt(long)]
+    pub config: Option<PathBuf>,
+    #[structopt(flatten)]
+    pub api: ApiOpts,
+    #[structopt(subcommand)]
+    pub command: Option<Subcommand>,
+}
+
+#[derive(structopt::StructOpt)]
+pub enum Subcommand {
+    /// Print statistics of groups from history, without connecting to yagna.
+    Stats {
+        /// Groups to analyze. Defaults to --group or groups of last session.
+        groups: Vec<String>,
+        /// Time span: all, day, week, month or delay like 3d.
+        #[structopt(long, default_value = "all")]
+        period: Period,
+    },
+    /// Print our NodeId, identity alias and client version.
+    Whoami,
+    /// Manage yagna app-keys.
+    Key(KeyCommand),
+    /// Export or import shareable group definitions.
+    Group(GroupCommand),
+    /// Schema version and migrations of data dir.
+    Db(DbCommand),
+    /// Remove old messages from history, without connecting to yagna.
+    Prune {
+        /// Groups to prune. Defaults to --group or groups of last session.
+        groups: Vec<String>,
+        /// Messages older than this date (YYYY-MM-DD) are removed.
+        #[structopt(long, parse(try_from_str = retention::parse_date))]
+        before: DateTime<Utc>,
+    },
+    /// Only receive messages and run handler for each, without ever sending.
+    NotifyEndpoint {
+        /// Group to listen in. Can be repeated.
+        #[structopt(long = "group", short)]
+        groups: Vec<String>,
+        /// Command run for every message, with json on stdin and `YACHAT_*` variables.
+        #[structopt(long)]
+        exec: Option<String>,
+        /// Url, to which json of every message is posted. Requires curl.
+        #[structopt(long)]
+        webhook: Option<String>,
+    },
+    /// Compare version with the latest release on GitHub.
+    Update {
+        /// Exit with error, when newer release is available.
+        #[structopt(long)]
+        check: bool,
+    },
+    /// Remove all local data of group: history, roster, pins, queue and chat logs.
+    Wipe {
+        /// Group to wipe.
+        #[structopt(long)]
+        group: String,
+        /// Don't ask for confirmation.
+        #[structopt(long)]
+        yes: bool,
+    },
+}
+
+impl Args {
+    pub fn data_dir(&self) -> PathBuf {
+        self.data_dir
+            .clone()
+            .unwrap_or_else(storage::default_data_dir)
+    }
+}
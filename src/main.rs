@@ -1,45 +1,143 @@
-use actix::Actor;
-use structopt::{clap, StructOpt};
+use futures::future::{self, Either};
+use futures::pin_mut;
+use structopt::StructOpt;
 use tokio::signal;
 
-use chat::Chat;
-use discover::Shutdown;
-
-use ya_client::cli::ApiOpts;
-
-mod chat;
-mod discover;
-mod protocol;
-
-#[derive(structopt::StructOpt)]
-#[structopt(global_setting = clap::AppSettings::ColoredHelp)]
-pub struct Args {
-    #[structopt(long, short)]
-    pub name: String,
-    #[structopt(long, short)]
-    pub group: String,
-    #[structopt(flatten)]
-    pub api: ApiOpts,
-}
+use yachat::chat::{Chat, ChatHandle, Reload};
+use yachat::config::Config;
+use yachat::encryption::Cipher;
+use yachat::hooks::Hooks;
+use yachat::keys::KeyCommand;
+use yachat::{
+    migrations, retention, setup, stats, storage, update, whoami, wipe, Args, Subcommand,
+};
 
 #[actix_rt::main]
 async fn main() -> Result<(), anyhow::Error> {
     dotenv::dotenv().ok();
+
+    let config_path = Config::path_from_args();
+    if let Some(command) = KeyCommand::from_cli() {
+        return command.run(&config_path).await;
+    }
+    if setup::needed(&config_path) {
+        setup::run(&config_path).await?;
+    }
+    let config = Config::load(&config_path)?;
+    config.export_app_key();
+    config.export_chaos();
+
+    let mut args = Args::from_args();
+    config.apply(&mut args);
+
     flexi_logger::Logger::with_env()
         .log_to_file()
-        .directory("logs")
+        .directory(args.data_dir().join("logs"))
         .start()
         .expect("Failed to initialize logging");
-
-    let args = Args::from_args();
     log::info!("Starting ya-chat.");
 
-    let chat = Chat::new(args)?.start();
+    match args.command.take() {
+        Some(Subcommand::Db(command)) => return command.run(&args.data_dir()),
+        command => args.command = command,
+    }
+    migrations::migrate(&args.data_dir())?;
+
+    match args.command.take() {
+        Some(Subcommand::Whoami) => return whoami::print_offline(&args.data_dir()).await,
+        Some(Subcommand::Update { check }) => return update::run(check),
+        Some(Subcommand::Key(command)) => return command.run(&config_path).await,
+        Some(Subcommand::Group(command)) => {
+            let first_group = args.groups.first().map(String::as_str);
+            return command
+                .run(
+                    &config_path,
+                    &args.data_dir(),
+                    first_group,
+                    args.announcers.clone(),
+                    args.fee.clone(),
+                    args.max_members,
+                )
+                .await;
+        }
+        Some(Subcommand::NotifyEndpoint {
+            groups,
+            exec,
+            webhook,
+        }) => {
+            let handler = match (exec, webhook) {
+                (Some(command), None) => command,
+                (None, Some(url)) => url,
+                _ => anyhow::bail!("Give either --exec or --webhook."),
+            };
+            if !groups.is_empty() {
+                args.groups = groups;
+            }
+            args.hooks = Hooks::on_messages(handler);
+            args.recurring.clear();
+            args.receive_only = true;
+        }
+        command => args.command = command,
+    }
+    let cipher = match args.encrypt {
+        Some(source) => Some(Cipher::init(&args.data_dir(), source).await?),
+        None => None,
+    };
+    if let Some(Subcommand::Stats { groups, period }) = args.command.take() {
+        let groups = match groups.is_empty() {
+            true => args.groups.clone(),
+            false => groups,
+        };
+        let storage = storage::open(args.storage.unwrap_or_default(), &args.data_dir(), cipher)?;
+        return stats::print_offline(&args.data_dir(), groups, period, storage.as_ref());
+    }
+    if let Some(Subcommand::Prune { groups, before }) = args.command.take() {
+        let groups = match groups.is_empty() {
+            true => args.groups.clone(),
+            false => groups,
+        };
+        let storage = storage::open(args.storage.unwrap_or_default(), &args.data_dir(), cipher)?;
+        return retention::prune_offline(&args.data_dir(), groups, before, storage.as_ref());
+    }
+    if let Some(Subcommand::Wipe { group, yes }) = args.command.take() {
+        let storage = storage::open(args.storage.unwrap_or_default(), &args.data_dir(), cipher)?;
+        return wipe::wipe_group(&args.data_dir(), &group, storage.as_ref(), yes);
+    }
+
+    let chat = ChatHandle::start(Chat::new(args, cipher)?);
+
+    #[cfg(unix)]
+    {
+        let chat = chat.addr().clone();
+        let mut hangup = signal::unix::signal(signal::unix::SignalKind::hangup())?;
+        actix_rt::spawn(async move {
+            while hangup.recv().await.is_some() {
+                chat.do_send(Reload);
+            }
+        });
+    }
 
-    signal::ctrl_c().await.unwrap();
+    shutdown_signal().await?;
 
     println!("Shutting down. Wait for cleanup...");
-    chat.send(Shutdown {}).await??;
+    chat.shutdown().await?;
     println!("Finished.");
     Ok(())
 }
+
+/// Ctrl-C, SIGTERM on Unix and Ctrl-Break on Windows. Without handling
+/// the latter two, process would be killed without cleanup.
+async fn shutdown_signal() -> std::io::Result<()> {
+    #[cfg(unix)]
+    let mut other = signal::unix::signal(signal::unix::SignalKind::terminate())?;
+    #[cfg(windows)]
+    let mut other = signal::windows::ctrl_break()?;
+
+    let ctrl_c = signal::ctrl_c();
+    let other = other.recv();
+    pin_mut!(ctrl_c, other);
+    match future::select(ctrl_c, other).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => Ok(()),
+    }
+}
@@ -0,0 +1,748 @@
+use actix::prelude::*;
+use anyhow::{anyhow, bail};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use ya_agreement_utils::{constraints, ConstraintKey, Constraints};
+use ya_client::cli::ApiOpts;
+use ya_client::cli::{ProviderApi, RequestorApi};
+use ya_client::model::market::{
+    Agreement, AgreementProposal, AgreementState, Demand, Offer, Proposal, ProviderEvent,
+    RequestorEvent,
+};
+use ya_client::model::payment::{Acceptance, InvoiceStatus, NewAllocation, NewInvoice};
+use ya_client::model::NodeId;
+
+use crate::discover::{Apis, Shutdown};
+
+/// Membership agreements are concluded for limited time. After expiration
+/// member has to join (and pay) again.
+const MEMBERSHIP_HOURS: i64 = 24;
+/// Member, who doesn't pay invoice in this time, isn't admitted.
+const PAYMENT_HOURS: i64 = 1;
+const VERIFY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+// =========================================== //
+// Public exposed messages
+// =========================================== //
+
+/// Makes us owner of paid group. Anyone who wants to post in group
+/// must conclude Agreement with us and pay the fee.
+#[derive(Message)]
+#[rtype(result = "anyhow::Result<()>")]
+pub struct OfferMembership {
+    pub group: String,
+    pub fee: String,
+    pub notify: Recipient<MembershipChanged>,
+}
+
+/// Negotiates Agreement with owner of paid group. Invoice for the fee
+/// is accepted automatically, if it doesn't exceed advertised amount.
+#[derive(Message)]
+#[rtype(result = "anyhow::Result<()>")]
+pub struct JoinGroup {
+    pub group: String,
+    pub owner: NodeId,
+    pub fee: String,
+    pub notify: Recipient<MembershipChanged>,
+}
+
+/// Terminates Agreements with member. Available only for group owner.
+#[derive(Message)]
+#[rtype(result = "anyhow::Result<()>")]
+pub struct Revoke {
+    pub member: NodeId,
+}
+
+/// Sent to chat, when Agreement with other party was concluded or
+/// stopped being valid. For owner `node_id` is member, for member it's owner.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct MembershipChanged {
+    pub group: String,
+    pub node_id: NodeId,
+    pub active: bool,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<(), ()>")]
+struct CollectEvents;
+
+#[derive(Message)]
+#[rtype(result = "Result<(), ()>")]
+struct VerifyAgreements;
+
+// =========================================== //
+// Membership implementation
+// =========================================== //
+
+/// Agreement approved by owner, which waits for member to pay invoice.
+struct Unpaid {
+    member: NodeId,
+    invoice_id: String,
+    due: DateTime<Utc>,
+}
+
+#[derive(Clone)]
+enum Role {
+    Owner { fee: String },
+    Member { owner: NodeId, fee: String },
+}
+
+/// Handles market and payment side of paid groups. Owner acts as Provider
+/// and members as Requestors, so the whole flow goes through yagna market.
+pub struct Membership {
+    apis: Apis,
+    group: String,
+    role: Option<Role>,
+    notify: Option<Recipient<MembershipChanged>>,
+    /// Offer for owner or Demand for member.
+    subscription: Option<String>,
+    allocation: Option<String>,
+    /// Other party of each Agreement.
+    agreements: HashMap<String, NodeId>,
+    /// Agreements of owner, which become membership after payment.
+    unpaid: HashMap<String, Unpaid>,
+    paid_invoices: HashSet<String>,
+    running: bool,
+}
+
+impl Membership {
+    pub fn new(api: &ApiOpts) -> Result<Membership, anyhow::Error> {
+        let apis = Apis {
+            provider: ProviderApi::try_from(api)?,
+            requestor: RequestorApi::try_from(api)?,
+        };
+
+        Ok(Membership {
+            apis,
+            group: String::new(),
+            role: None,
+            notify: None,
+            subscription: None,
+            allocation: None,
+            agreements: HashMap::new(),
+            unpaid: HashMap::new(),
+            paid_invoices: HashSet::new(),
+            running: false,
+        })
+    }
+
+    fn notify(&self, node_id: NodeId, active: bool) {
+        if let Some(notify) = &self.notify {
+            notify
+                .do_send(MembershipChanged {
+                    group: self.group.clone(),
+                    node_id,
+                    active,
+                })
+                .map_err(|e| log::warn!("Failed to notify about membership. Error: {}", e))
+                .ok();
+        }
+    }
+
+    /// Collecting loop always uses current subscription, so it's started
+    /// only once, even if member joins group again.
+    fn start(&mut self, subscription: String, ctx: &mut Context<Self>) {
+        self.subscription = Some(subscription);
+        if !self.running {
+            self.running = true;
+            ctx.notify(CollectEvents);
+            ctx.run_interval(VERIFY_INTERVAL, |_, ctx| ctx.notify(VerifyAgreements));
+        }
+    }
+}
+
+impl Actor for Membership {
+    type Context = Context<Self>;
+}
+
+impl Handler<OfferMembership> for Membership {
+    type Result = ActorResponse<Self, (), anyhow::Error>;
+
+    fn handle(&mut self, msg: OfferMembership, _: &mut Context<Self>) -> Self::Result {
+        if self.role.is_some() {
+            return ActorResponse::reply(Err(anyhow!("Membership already initialized.")));
+        }
+
+        log::info!(
+            "Offering paid membership in group {} for {} GLM.",
+            msg.group,
+            msg.fee
+        );
+
+        let (properties, constraints) = membership_properties(&msg.group, &msg.fee);
+        let offer = Offer::new(properties, constraints.to_string());
+
+        self.group = msg.group;
+        self.role = Some(Role::Owner { fee: msg.fee });
+        self.notify = Some(msg.notify);
+
+        let apis = self.apis.clone();
+        let future = async move { Ok(apis.provider.market.subscribe(&offer).await?) }
+            .into_actor(self)
+            .map(|result: anyhow::Result<String>, myself, ctx| {
+                myself.start(result?, ctx);
+                Ok(())
+            });
+        ActorResponse::r#async(future)
+    }
+}
+
+impl Handler<JoinGroup> for Membership {
+    type Result = ActorResponse<Self, (), anyhow::Error>;
+
+    fn handle(&mut self, msg: JoinGroup, _: &mut Context<Self>) -> Self::Result {
+        match &self.role {
+            Some(Role::Owner { .. }) => {
+                return ActorResponse::reply(Err(anyhow!("Owner can't join own group.")))
+            }
+            Some(Role::Member { .. }) if self.group != msg.group && !self.agreements.is_empty() => {
+                return ActorResponse::reply(Err(anyhow!(
+                    "Membership is handled for single paid group at a time."
+                )))
+            }
+            Some(Role::Member { .. }) if !self.agreements.is_empty() => {
+                return ActorResponse::reply(Err(anyhow!("Already a member of the group.")))
+            }
+            _ => (),
+        }
+
+        log::info!("Joining paid group {} owned by [{}].", msg.group, msg.owner);
+
+        let (properties, constraints) = membership_properties(&msg.group, &msg.fee);
+        let demand = Demand::new(properties, constraints.to_string());
+        let allocation = NewAllocation {
+            payment_platform: None,
+            address: None,
+            total_amount: msg.fee.clone(),
+            timeout: None,
+            make_deposit: false,
+        };
+
+        self.group = msg.group;
+        self.role = Some(Role::Member {
+            owner: msg.owner,
+            fee: msg.fee,
+        });
+        self.notify = Some(msg.notify);
+
+        // Owner sends initial proposal only once per subscription, so joining
+        // again after membership expired requires new subscription.
+        let previous = self.subscription.take();
+        let previous_allocation = self.allocation.take();
+
+        let apis = self.apis.clone();
+        let future = async move {
+            if let Some(sub) = previous {
+                apis.requestor.market.unsubscribe(&sub).await.ok();
+            }
+            if let Some(allocation) = previous_allocation {
+                apis.requestor
+                    .payment
+                    .release_allocation(&allocation)
+                    .await
+                    .ok();
+            }
+
+            let allocation = apis
+                .requestor
+                .payment
+                .create_allocation(&allocation)
+                .await?;
+            let subscription = apis.requestor.market.subscribe(&demand).await?;
+            Ok((subscription, allocation.allocation_id))
+        }
+        .into_actor(self)
+        .map(
+            |result: anyhow::Result<(String, String)>, myself, ctx| match result {
+                Ok((subscription, allocation)) => {
+                    myself.allocation = Some(allocation);
+                    myself.start(subscription, ctx);
+                    Ok(())
+                }
+                Err(e) => {
+                    // Let user retry.
+                    myself.role = None;
+                    Err(e)
+                }
+            },
+        );
+        ActorResponse::r#async(future)
+    }
+}
+
+impl Handler<CollectEvents> for Membership {
+    type Result = ActorResponse<Self, (), ()>;
+
+    fn handle(&mut self, _: CollectEvents, ctx: &mut Context<Self>) -> Self::Result {
+        let (subscription, role) = match (&self.subscription, &self.role) {
+            (Some(subscription), Some(role)) => (subscription.clone(), role.clone()),
+            _ => return ActorResponse::reply(Ok(())),
+        };
+
+        let apis = self.apis.clone();
+        let group = self.group.clone();
+        let addr = ctx.address();
+
+        let future = async move {
+            let result = match role {
+                Role::Owner { fee } => collect_as_owner(&apis, &subscription, &group, &fee)
+                    .await
+                    .map(|unpaid| (vec![], unpaid)),
+                Role::Member { owner, fee } => {
+                    collect_as_member(&apis, &subscription, &group, &fee, owner)
+                        .await
+                        .map(|concluded| (concluded, vec![]))
+                }
+            };
+
+            if let Err(e) = &result {
+                log::error!("Failed to get membership events from market. Error: {}", e);
+                tokio::time::delay_for(std::time::Duration::from_secs(4)).await;
+            }
+            result.unwrap_or_default()
+        }
+        .into_actor(self)
+        .map(move |(concluded, unpaid), myself, _| {
+            for (agreement_id, node_id) in concluded {
+                log::info!("Membership Agreement {} with [{}].", agreement_id, node_id);
+                myself.agreements.insert(agreement_id, node_id);
+                myself.notify(node_id, true);
+            }
+            for (agreement_id, unpaid) in unpaid {
+                log::info!(
+                    "Membership Agreement {} with [{}] waits for payment of invoice {}.",
+                    agreement_id,
+                    unpaid.member,
+                    unpaid.invoice_id
+                );
+                myself.unpaid.insert(agreement_id, unpaid);
+            }
+            addr.do_send(CollectEvents);
+            Ok(())
+        });
+        ActorResponse::r#async(future)
+    }
+}
+
+impl Handler<VerifyAgreements> for Membership {
+    type Result = ActorResponse<Self, (), ()>;
+
+    fn handle(&mut self, _: VerifyAgreements, _: &mut Context<Self>) -> Self::Result {
+        let agreements = self.agreements.clone();
+        let allocation = self.allocation.clone();
+        let paid_invoices = self.paid_invoices.clone();
+        let unpaid = self
+            .unpaid
+            .iter()
+            .map(|(agreement_id, unpaid)| {
+                (agreement_id.clone(), unpaid.invoice_id.clone(), unpaid.due)
+            })
+            .collect::<Vec<_>>();
+        let apis = self.apis.clone();
+        let owner = matches!(&self.role, Some(Role::Owner { .. }));
+        let fee = match &self.role {
+            Some(Role::Owner { fee }) | Some(Role::Member { fee, .. }) => fee.clone(),
+            None => return ActorResponse::reply(Ok(())),
+        };
+
+        let future = async move {
+            let mut invalid = vec![];
+            for (agreement_id, node_id) in agreements.iter() {
+                let agreement = match owner {
+                    true => apis.provider.market.get_agreement(agreement_id).await,
+                    false => apis.requestor.market.get_agreement(agreement_id).await,
+                };
+                match agreement {
+                    Ok(agreement) if is_valid(&agreement) => (),
+                    Ok(_) => invalid.push((agreement_id.clone(), *node_id)),
+                    Err(e) => log::warn!(
+                        "Failed to verify Agreement {} with [{}]. Error: {}",
+                        agreement_id,
+                        node_id,
+                        e
+                    ),
+                }
+            }
+
+            let mut paid = vec![];
+            if let Some(allocation) = allocation {
+                match pay_invoices(&apis, &agreements, &paid_invoices, &allocation, &fee).await {
+                    Ok(invoices) => paid = invoices,
+                    Err(e) => log::warn!("Failed to pay membership invoices. Error: {}", e),
+                }
+            }
+
+            let (settled, unsettled) = check_payments(&apis, &unpaid).await;
+            (invalid, paid, settled, unsettled)
+        }
+        .into_actor(self)
+        .map(|(invalid, paid, settled, unsettled), myself, _| {
+            myself.paid_invoices.extend(paid);
+            for agreement_id in settled {
+                if let Some(unpaid) = myself.unpaid.remove(&agreement_id) {
+                    log::info!("Membership Agreement {} was paid.", agreement_id);
+                    myself.agreements.insert(agreement_id, unpaid.member);
+                    myself.notify(unpaid.member, true);
+                }
+            }
+            for agreement_id in unsettled {
+                myself.unpaid.remove(&agreement_id);
+            }
+            for (agreement_id, node_id) in invalid {
+                log::info!("Membership Agreement {} is no longer valid.", agreement_id);
+                myself.agreements.remove(&agreement_id);
+                if !myself.agreements.values().any(|other| *other == node_id) {
+                    myself.notify(node_id, false);
+                }
+            }
+            Ok(())
+        });
+        ActorResponse::r#async(future)
+    }
+}
+
+impl Handler<Revoke> for Membership {
+    type Result = ActorResponse<Self, (), anyhow::Error>;
+
+    fn handle(&mut self, msg: Revoke, _: &mut Context<Self>) -> Self::Result {
+        match &self.role {
+            Some(Role::Owner { .. }) => (),
+            _ => {
+                return ActorResponse::reply(Err(anyhow!(
+                    "Only group owner can revoke membership."
+                )))
+            }
+        }
+
+        let agreements = self
+            .agreements
+            .iter()
+            .filter(|(_, node_id)| **node_id == msg.member)
+            .map(|(agreement_id, _)| agreement_id.clone())
+            .collect::<Vec<_>>();
+        if agreements.is_empty() {
+            return ActorResponse::reply(Err(anyhow!("[{}] is not a member.", msg.member)));
+        }
+
+        let apis = self.apis.clone();
+        let future = async move {
+            let reason = Some(serde_json::json!({ "message": "Membership revoked by owner." }));
+            for agreement_id in agreements.iter() {
+                apis.provider
+                    .market
+                    .terminate_agreement(agreement_id, &reason)
+                    .await?;
+            }
+            Ok(agreements)
+        }
+        .into_actor(self)
+        .map(move |result: anyhow::Result<Vec<String>>, myself, _| {
+            for agreement_id in result? {
+                myself.agreements.remove(&agreement_id);
+            }
+            myself.notify(msg.member, false);
+            Ok(())
+        });
+        ActorResponse::r#async(future)
+    }
+}
+
+impl Handler<Shutdown> for Membership {
+    type Result = ActorResponse<Self, (), anyhow::Error>;
+
+    fn handle(&mut self, _: Shutdown, _: &mut Context<Self>) -> Self::Result {
+        let subscription = self.subscription.take();
+        let allocation = self.allocation.take();
+        let owner = matches!(&self.role, Some(Role::Owner { .. }));
+        let apis = self.apis.clone();
+
+        let future = async move {
+            if let Some(sub) = subscription {
+                log::info!("Unsubscribing {}", &sub);
+                match owner {
+                    true => apis.provider.market.unsubscribe(&sub).await,
+                    false => apis.requestor.market.unsubscribe(&sub).await,
+                }
+                .map_err(|e| log::error!("Failed to unsubscribe: {}. Error: {}", sub, e))
+                .ok();
+            }
+            if let Some(allocation) = allocation {
+                apis.requestor
+                    .payment
+                    .release_allocation(&allocation)
+                    .await
+                    .map_err(|e| {
+                        log::error!("Failed to release allocation {}. Error: {}", allocation, e)
+                    })
+                    .ok();
+            }
+        }
+        .into_actor(self);
+
+        ActorResponse::r#async(future.map(|_, _, _| Ok(())))
+    }
+}
+
+/// Owner counters every proposal in group, approves Agreements and issues
+/// invoice for the fee. Returns Agreements waiting for payment.
+async fn collect_as_owner(
+    apis: &Apis,
+    subscription: &str,
+    group: &str,
+    fee: &str,
+) -> anyhow::Result<Vec<(String, Unpaid)>> {
+    let events = apis
+        .provider
+        .market
+        .collect(subscription, Some(10.0), Some(20))
+        .await?;
+
+    let mut waiting = vec![];
+    for event in events {
+        let result = async {
+            match event {
+                ProviderEvent::ProposalEvent { proposal, .. } => {
+                    let (properties, constraints) = membership_properties(group, fee);
+                    let mut counter = Proposal::new(properties, constraints.to_string());
+                    counter.prev_proposal_id = Some(proposal.proposal_id()?.clone());
+                    apis.provider
+                        .market
+                        .counter_proposal(&counter, subscription)
+                        .await?;
+                }
+                ProviderEvent::AgreementEvent { agreement, .. } => {
+                    let member = agreement
+                        .demand
+                        .requestor_id
+                        .as_ref()
+                        .ok_or_else(|| anyhow!("Agreement without requestor."))?;
+                    let member = NodeId::from_str(member)
+                        .map_err(|_| anyhow!("Invalid requestor NodeId: {}", member))?;
+
+                    apis.provider
+                        .market
+                        .approve_agreement(&agreement.agreement_id, None, Some(10.0))
+                        .await?;
+
+                    let due = Utc::now() + Duration::hours(PAYMENT_HOURS);
+                    let invoice = NewInvoice {
+                        agreement_id: agreement.agreement_id.clone(),
+                        activity_ids: None,
+                        amount: fee.to_string(),
+                        payment_due_date: due,
+                    };
+                    let invoice = apis.provider.payment.issue_invoice(&invoice).await?;
+                    apis.provider
+                        .payment
+                        .send_invoice(&invoice.invoice_id)
+                        .await?;
+                    let unpaid = Unpaid {
+                        member,
+                        invoice_id: invoice.invoice_id,
+                        due,
+                    };
+                    waiting.push((agreement.agreement_id, unpaid));
+                }
+                ProviderEvent::PropertyQueryEvent { .. } => {
+                    bail!("Unexpected PropertyQuery event for membership.")
+                }
+            }
+            anyhow::Result::<()>::Ok(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            log::error!("Error while processing membership event: {}", e);
+        }
+    }
+    Ok(waiting)
+}
+
+/// Member counters initial proposal from owner and creates Agreement,
+/// when owner responds. Proposals from other nodes are ignored.
+async fn collect_as_member(
+    apis: &Apis,
+    subscription: &str,
+    group: &str,
+    fee: &str,
+    owner: NodeId,
+) -> anyhow::Result<Vec<(String, NodeId)>> {
+    let events = apis
+        .requestor
+        .market
+        .collect(subscription, Some(10.0), Some(20))
+        .await?;
+
+    let mut concluded = vec![];
+    for event in events {
+        let result = async {
+            let proposal = match event {
+                RequestorEvent::ProposalEvent { proposal, .. } => proposal,
+                RequestorEvent::PropertyQueryEvent { .. } => {
+                    bail!("Unexpected PropertyQuery event for membership.")
+                }
+            };
+
+            let issuer = proposal.issuer_id()?;
+            if NodeId::from_str(issuer).ok() != Some(owner) {
+                log::debug!("Ignoring membership proposal from [{}].", issuer);
+                return Ok(());
+            }
+
+            let proposal_id = proposal.proposal_id()?.clone();
+            match proposal.prev_proposal_id {
+                // Initial proposal generated from owner's Offer.
+                None => {
+                    let (properties, constraints) = membership_properties(group, fee);
+                    let mut counter = Proposal::new(properties, constraints.to_string());
+                    counter.prev_proposal_id = Some(proposal_id);
+                    apis.requestor
+                        .market
+                        .counter_proposal(&counter, subscription)
+                        .await?;
+                }
+                // Owner accepted our terms.
+                Some(_) => {
+                    let valid_to = Utc::now() + Duration::hours(MEMBERSHIP_HOURS);
+                    let agreement_id = apis
+                        .requestor
+                        .market
+                        .create_agreement(&AgreementProposal::new(proposal_id, valid_to))
+                        .await?;
+                    apis.requestor
+                        .market
+                        .confirm_agreement(&agreement_id, None)
+                        .await?;
+                    apis.requestor
+                        .market
+                        .wait_for_approval(&agreement_id, Some(15.0))
+                        .await?;
+
+                    let agreement = apis.requestor.market.get_agreement(&agreement_id).await?;
+                    if !is_valid(&agreement) {
+                        bail!("Owner didn't approve Agreement {}.", agreement_id);
+                    }
+                    concluded.push((agreement_id, owner));
+                }
+            }
+            Ok(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            log::error!("Error while processing membership event: {}", e);
+        }
+    }
+    Ok(concluded)
+}
+
+/// Owner checks invoices of Agreements waiting for payment. Returns paid
+/// Agreements and ones terminated, because invoice was rejected or not
+/// paid in time.
+async fn check_payments(
+    apis: &Apis,
+    unpaid: &[(String, String, DateTime<Utc>)],
+) -> (Vec<String>, Vec<String>) {
+    let mut settled = vec![];
+    let mut unsettled = vec![];
+    for (agreement_id, invoice_id, due) in unpaid {
+        let status = match apis.provider.payment.get_invoice(invoice_id).await {
+            Ok(invoice) => invoice.status,
+            Err(e) => {
+                log::warn!("Failed to check invoice {}. Error: {}", invoice_id, e);
+                continue;
+            }
+        };
+        match status {
+            InvoiceStatus::Accepted | InvoiceStatus::Settled => {
+                settled.push(agreement_id.clone());
+                continue;
+            }
+            InvoiceStatus::Rejected | InvoiceStatus::Failed | InvoiceStatus::Cancelled => (),
+            InvoiceStatus::Issued | InvoiceStatus::Received if *due < Utc::now() => (),
+            InvoiceStatus::Issued | InvoiceStatus::Received => continue,
+        }
+
+        log::info!(
+            "Membership invoice {} wasn't paid. Terminating Agreement {}.",
+            invoice_id,
+            agreement_id
+        );
+        let reason = Some(serde_json::json!({ "message": "Membership fee wasn't paid." }));
+        apis.provider
+            .market
+            .terminate_agreement(agreement_id, &reason)
+            .await
+            .map_err(|e| {
+                log::warn!(
+                    "Failed to terminate Agreement {}. Error: {}",
+                    agreement_id,
+                    e
+                )
+            })
+            .ok();
+        unsettled.push(agreement_id.clone());
+    }
+    (settled, unsettled)
+}
+
+/// Accepts owner's invoices for our Agreements. We never pay more,
+/// than the fee advertised by group.
+async fn pay_invoices(
+    apis: &Apis,
+    agreements: &HashMap<String, NodeId>,
+    paid: &HashSet<String>,
+    allocation: &str,
+    fee: &str,
+) -> anyhow::Result<Vec<String>> {
+    let invoices = apis
+        .requestor
+        .payment
+        .get_invoices::<Utc>(None, None)
+        .await?;
+
+    let max_amount = f64::from_str(fee)?;
+    let mut accepted = vec![];
+    for invoice in invoices.into_iter().filter(|invoice| {
+        agreements.contains_key(&invoice.agreement_id) && !paid.contains(&invoice.invoice_id)
+    }) {
+        if f64::from_str(&invoice.amount)? > max_amount {
+            log::warn!(
+                "Rejecting invoice {}: {} GLM exceeds membership fee {} GLM.",
+                invoice.invoice_id,
+                invoice.amount,
+                fee
+            );
+            continue;
+        }
+
+        let acceptance = Acceptance {
+            total_amount_accepted: invoice.amount.clone(),
+            allocation_id: allocation.to_string(),
+        };
+        apis.requestor
+            .payment
+            .accept_invoice(&invoice.invoice_id, &acceptance)
+            .await?;
+        log::info!("Paid membership invoice {}.", invoice.invoice_id);
+        accepted.push(invoice.invoice_id);
+    }
+    Ok(accepted)
+}
+
+fn is_valid(agreement: &Agreement) -> bool {
+    matches!(agreement.state, AgreementState::Approved) && agreement.valid_to > Utc::now()
+}
+
+fn membership_properties(group: &str, fee: &str) -> (serde_json::Value, Constraints) {
+    let properties = serde_json::json!({
+        "yachat.membership.group": group.to_string(),
+        "yachat.membership.fee": fee.to_string()
+    });
+    let constraints = constraints!["yachat.membership.group" == group];
+    (properties, constraints)
+}
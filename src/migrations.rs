@@ -0,0 +1,124 @@
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use structopt::StructOpt;
+
+use crate::storage::{load_json, save_json};
+
+const VERSION_FILE: &str = "version.json";
+
+/// `yachat db` commands.
+#[derive(StructOpt)]
+pub enum DbCommand {
+    /// Apply pending migrations of data dir. Also done at every start.
+    Migrate,
+    /// Print schema version of data dir and pending migrations.
+    Status,
+}
+
+/// Single step upgrading data dir from `version - 1` to `version`.
+struct Migration {
+    version: u32,
+    description: &'static str,
+    run: fn(&Path) -> anyhow::Result<()>,
+}
+
+/// Append only. Released migrations must never change, since data dirs
+/// of users were already upgraded with them.
+const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description: "Record schema version in data dir.",
+    run: |_| Ok(()),
+}];
+
+/// Version written by this build.
+pub fn latest() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+/// Data dirs from before versioning have no version file and are version 0.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SchemaVersion {
+    schema: u32,
+}
+
+fn current(data_dir: &Path) -> anyhow::Result<u32> {
+    let version = load_json::<SchemaVersion>(&data_dir.join(VERSION_FILE))?.schema;
+    if version > latest() {
+        bail!(
+            "Data dir {} was written by newer yachat (schema {}, we know {}). Upgrade yachat, \
+             so data isn't damaged.",
+            data_dir.display(),
+            version,
+            latest()
+        );
+    }
+    Ok(version)
+}
+
+/// Applies pending migrations in order. Version is saved after every
+/// step, so interrupted upgrade continues where it stopped.
+pub fn migrate(data_dir: &Path) -> anyhow::Result<()> {
+    if is_fresh(data_dir) {
+        return save_json(
+            &data_dir.join(VERSION_FILE),
+            &SchemaVersion { schema: latest() },
+        );
+    }
+
+    let version = current(data_dir)?;
+    for migration in MIGRATIONS.iter().filter(|m| m.version > version) {
+        log::info!(
+            "Migrating data dir to schema {}: {}",
+            migration.version,
+            migration.description
+        );
+        (migration.run)(data_dir).map_err(|e| {
+            anyhow::anyhow!(
+                "Migration to schema {} failed. Data dir was left at schema {}. Error: {}",
+                migration.version,
+                migration.version - 1,
+                e
+            )
+        })?;
+        save_json(
+            &data_dir.join(VERSION_FILE),
+            &SchemaVersion {
+                schema: migration.version,
+            },
+        )?;
+    }
+    Ok(())
+}
+
+/// Fresh data dir has nothing to migrate. Logs are created before
+/// migrations run.
+fn is_fresh(data_dir: &Path) -> bool {
+    match data_dir.read_dir() {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .all(|entry| entry.file_name() == "logs"),
+        Err(_) => true,
+    }
+}
+
+impl DbCommand {
+    pub fn run(self, data_dir: &Path) -> anyhow::Result<()> {
+        match self {
+            DbCommand::Migrate => {
+                migrate(data_dir)?;
+                println!("Data dir {} is at schema {}.", data_dir.display(), latest());
+            }
+            DbCommand::Status => {
+                let version = current(data_dir)?;
+                println!("Data dir: {}", data_dir.display());
+                println!("Schema: {} (latest {})", version, latest());
+                for migration in MIGRATIONS.iter().filter(|m| m.version > version) {
+                    println!("  pending {}: {}", migration.version, migration.description);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::encryption::{load_sealed, save_sealed, Cipher};
+use crate::storage::file_name;
+
+const PINS_DIR: &str = "pins";
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pin {
+    pub message_id: Uuid,
+    pub user: String,
+    pub content: String,
+    pub timestamp: DateTime<Utc>,
+    pub pinned_by: String,
+}
+
+/// Messages pinned in single group, persisted in data dir.
+pub struct Pins {
+    path: PathBuf,
+    pins: Vec<Pin>,
+    cipher: Option<Cipher>,
+}
+
+impl Pins {
+    pub fn load(data_dir: &Path, group: &str, cipher: Option<Cipher>) -> anyhow::Result<Pins> {
+        let path = pins_path(data_dir, group);
+        Ok(Pins {
+            pins: load_sealed(&path, cipher.as_ref())?,
+            path,
+            cipher,
+        })
+    }
+
+    /// Returns false if message was already pinned.
+    pub fn add(&mut self, pin: Pin) -> anyhow::Result<bool> {
+        if self.pins.iter().any(|p| p.message_id == pin.message_id) {
+            return Ok(false);
+        }
+
+        self.pins.push(pin);
+        save_sealed(&self.path, &self.pins, self.cipher.as_ref())?;
+        Ok(true)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pins.is_empty()
+    }
+
+    pub fn list(&self) -> String {
+        self.pins
+            .iter()
+            .map(format_pin)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Removes pins file of group.
+pub fn delete(data_dir: &Path, group: &str) -> anyhow::Result<()> {
+    let path = pins_path(data_dir, group);
+    if path.exists() {
+        std::fs::remove_file(&path)?;
+    }
+    Ok(())
+}
+
+fn pins_path(data_dir: &Path, group: &str) -> PathBuf {
+    data_dir
+        .join(PINS_DIR)
+        .join(format!("{}.json", file_name(group)))
+}
+
+pub fn format_pin(pin: &Pin) -> String {
+    format!(
+        "  📌 {} {} > {} (pinned by {})",
+        pin.timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+        pin.user,
+        pin.content,
+        pin.pinned_by
+    )
+}
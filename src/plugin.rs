@@ -0,0 +1,172 @@
+use actix::prelude::*;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+
+/// Delay before first restart. Doubled with each crash in a row.
+const RESTART_DELAY_SECS: i64 = 1;
+const MAX_RESTART_DELAY_SECS: i64 = 60;
+
+/// Line written by plugin to its stdout.
+#[derive(Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum PluginCommand {
+    /// Posts to group. Defaults to active group.
+    Send { group: Option<String>, text: String },
+    /// Sends direct message to user given by name or NodeId.
+    Direct { user: String, text: String },
+    /// Sets away status. No text means back.
+    Status { text: Option<String> },
+    /// Posts structured content of given schema to group. Text for users of
+    /// older clients is generated, when not given.
+    Structured {
+        group: Option<String>,
+        schema: String,
+        data: serde_json::Value,
+        text: Option<String>,
+    },
+    /// Handles `!name` in groups. Advertised to other users in profile.
+    Register { name: String, help: Option<String> },
+}
+
+/// Command read from plugin with given index.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct FromPlugin {
+    pub idx: usize,
+    pub command: PluginCommand,
+}
+
+/// Plugin process ended. It is restarted by chat.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct PluginExited {
+    pub idx: usize,
+}
+
+/// External process given with `--plugin`. Chat events are written to its
+/// stdin and commands are read from its stdout, both as newline-delimited
+/// json. Events use the same format as hooks.
+pub struct Plugin {
+    pub path: PathBuf,
+    events: Option<mpsc::Sender<String>>,
+    started: DateTime<Utc>,
+    crashes: u32,
+}
+
+impl Plugin {
+    pub fn new(path: PathBuf) -> Plugin {
+        Plugin {
+            path,
+            events: None,
+            started: Utc::now(),
+            crashes: 0,
+        }
+    }
+
+    pub fn spawn(
+        &mut self,
+        idx: usize,
+        commands: Recipient<FromPlugin>,
+        exited: Recipient<PluginExited>,
+    ) -> anyhow::Result<()> {
+        let mut child = Command::new(&self.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let mut stdin = child.stdin.take();
+        let stdout = child.stdout.take();
+
+        // Events are written from separate thread, so plugin, which doesn't
+        // read them, can't block chat.
+        let (events, received) = mpsc::channel::<String>();
+        std::thread::spawn(move || {
+            for event in received {
+                let written = stdin
+                    .as_mut()
+                    .map(|stdin| writeln!(stdin, "{}", event).and_then(|_| stdin.flush()));
+                if let Some(Err(_)) = written {
+                    // Closed stdin. Reader notices, that process ended.
+                    stdin = None;
+                }
+            }
+        });
+
+        let path = self.path.clone();
+        std::thread::spawn(move || {
+            if let Some(stdout) = stdout {
+                for line in BufReader::new(stdout).lines() {
+                    let line = match line {
+                        Ok(line) => line,
+                        Err(_) => break,
+                    };
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    match serde_json::from_str::<PluginCommand>(&line) {
+                        Ok(command) => {
+                            commands.do_send(FromPlugin { idx, command }).ok();
+                        }
+                        Err(e) => log::warn!(
+                            "Invalid command from plugin {}: {}. Error: {}",
+                            path.display(),
+                            line,
+                            e
+                        ),
+                    }
+                }
+            }
+            match child.wait() {
+                Ok(status) => log::warn!("Plugin {} exited with {}.", path.display(), status),
+                Err(e) => log::warn!("Plugin {} failed. Error: {}", path.display(), e),
+            }
+            exited.do_send(PluginExited { idx }).ok();
+        });
+
+        self.events = Some(events);
+        self.started = Utc::now();
+        Ok(())
+    }
+
+    pub fn send(&self, event: &str) {
+        if let Some(events) = &self.events {
+            events.send(event.to_string()).ok();
+        }
+    }
+
+    /// Plugin, which crashes right after start, is restarted less and less
+    /// often. Delay is reset, when it ran for a while.
+    pub fn restart_delay(&mut self) -> std::time::Duration {
+        self.events = None;
+        if (Utc::now() - self.started).num_seconds() > MAX_RESTART_DELAY_SECS {
+            self.crashes = 0;
+        }
+        let delay = (RESTART_DELAY_SECS << self.crashes.min(6)).min(MAX_RESTART_DELAY_SECS);
+        self.crashes += 1;
+        std::time::Duration::from_secs(delay as u64)
+    }
+}
+
+/// Sandboxed plugin from `[[wasm-plugin]]` section of config file. Runs
+/// only with yachat built with `wasm` feature.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WasmPluginConfig {
+    pub path: PathBuf,
+    /// Groups, in which plugin gets messages. Empty means all groups and
+    /// direct messages.
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+impl WasmPluginConfig {
+    pub fn applies(&self, group: Option<&str>) -> bool {
+        match group {
+            Some(group) => self.groups.is_empty() || self.groups.iter().any(|g| g == group),
+            None => self.groups.is_empty(),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use anyhow::anyhow;
+
+use ya_core_model::activity;
+use ya_service_bus::{typed as bus, RpcEndpoint};
+
+use crate::protocol::{NodeFacts, PresenceUpdate};
+
+/// Activity states, which don't occupy provider.
+const IDLE_STATES: &[&str] = &["New", "Terminated", "Unresponsive"];
+
+/// Reads activity counters of local yagna. Provider is busy, while any
+/// activity is deployed or running.
+pub async fn node_facts() -> anyhow::Result<NodeFacts> {
+    let stats = bus::service(activity::local::BUS_ID)
+        .send(activity::local::Stats {})
+        .await?
+        .map_err(|e| anyhow!("Failed to query activities. Error: {}", e))?;
+    let tasks = stats
+        .total
+        .iter()
+        .filter(|(state, _)| !IDLE_STATES.contains(&state.as_str()))
+        .map(|(_, count)| count)
+        .sum::<u64>();
+    Ok(NodeFacts {
+        busy: tasks > 0,
+        tasks,
+    })
+}
+
+/// Displayed in `/users` after online state.
+pub fn describe(presence: &PresenceUpdate) -> String {
+    let mut parts = vec![];
+    if presence.away {
+        parts.push(match &presence.status {
+            Some(status) => format!("away: {}", status),
+            None => "away".to_string(),
+        });
+    }
+    if let Some(node) = &presence.node {
+        parts.push(match node.busy {
+            true => format!("provider busy, {} task(s)", node.tasks),
+            false => "provider idle".to_string(),
+        });
+    }
+    parts.join(", ")
+}
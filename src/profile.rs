@@ -0,0 +1,114 @@
+use ansi_term::Colour;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use ya_client::model::NodeId;
+
+use crate::protocol::Profile;
+use crate::storage::{load_json, save_json};
+
+const PROFILE_FILE: &str = "profile.json";
+const PROFILES_FILE: &str = "profiles.json";
+
+const MAX_NAME: usize = 64;
+const MAX_BIO: usize = 280;
+const MAX_LINKS: usize = 5;
+const MAX_COMMANDS: usize = 20;
+
+/// Our profile and profiles received from other users, persisted in data dir.
+pub struct Profiles {
+    data_dir: PathBuf,
+    pub own: Profile,
+    /// Keyed by NodeId of sender.
+    peers: BTreeMap<String, Profile>,
+}
+
+impl Profiles {
+    pub fn load(data_dir: &Path) -> anyhow::Result<Profiles> {
+        Ok(Profiles {
+            data_dir: data_dir.to_path_buf(),
+            own: load_json(&data_dir.join(PROFILE_FILE))?,
+            peers: load_json(&data_dir.join(PROFILES_FILE))?,
+        })
+    }
+
+    pub fn save_own(&self) -> anyhow::Result<()> {
+        save_json(&self.data_dir.join(PROFILE_FILE), &self.own)
+    }
+
+    pub fn get(&self, node_id: &NodeId) -> Option<&Profile> {
+        self.peers.get(&node_id.to_string())
+    }
+
+    /// Profile is truncated, so peer can't flood our screen and disk.
+    pub fn insert(&mut self, node_id: NodeId, profile: Profile) -> anyhow::Result<()> {
+        self.peers.insert(node_id.to_string(), sanitize(profile));
+        save_json(&self.data_dir.join(PROFILES_FILE), &self.peers)
+    }
+
+    pub fn remove(&mut self, node_ids: &[NodeId]) -> anyhow::Result<()> {
+        let count = self.peers.len();
+        for node_id in node_ids {
+            self.peers.remove(&node_id.to_string());
+        }
+        if self.peers.len() != count {
+            save_json(&self.data_dir.join(PROFILES_FILE), &self.peers)?;
+        }
+        Ok(())
+    }
+}
+
+fn sanitize(mut profile: Profile) -> Profile {
+    let truncate = |text: &str, max: usize| text.chars().take(max).collect::<String>();
+    profile.name = truncate(&profile.name, MAX_NAME);
+    profile.bio = profile.bio.map(|bio| truncate(&bio, MAX_BIO));
+    profile.avatar = profile.avatar.map(|avatar| truncate(&avatar, MAX_NAME));
+    profile.links.truncate(MAX_LINKS);
+    profile.commands.truncate(MAX_COMMANDS);
+    for command in profile.commands.iter_mut() {
+        command.name = truncate(&command.name, MAX_NAME);
+        command.help = truncate(&command.help, MAX_BIO);
+    }
+    profile
+}
+
+/// Up to two uppercase initials of name, shown in place of avatar image.
+pub fn initials(name: &str) -> String {
+    let initials = name
+        .split_whitespace()
+        .filter_map(|word| word.chars().next())
+        .take(2)
+        .flat_map(char::to_uppercase)
+        .collect::<String>();
+    match initials.is_empty() {
+        true => "?".to_string(),
+        false => initials,
+    }
+}
+
+/// Stable color of user, so the same initials of different users can
+/// be told apart.
+pub fn avatar_colour(node_id: &NodeId) -> Colour {
+    let sum = node_id.to_string().bytes().fold(0u32, |sum, byte| {
+        sum.wrapping_mul(31).wrapping_add(byte as u32)
+    });
+    // 216 color cube of 256-color terminals.
+    Colour::Fixed(16 + (sum % 216) as u8)
+}
+
+pub fn format(profile: &Profile) -> String {
+    let mut lines = vec![format!("  Name: {}", profile.name)];
+    if let Some(bio) = &profile.bio {
+        lines.push(format!("  Bio: {}", bio));
+    }
+    if let Some(avatar) = &profile.avatar {
+        lines.push(format!("  Avatar: {}", avatar));
+    }
+    for link in profile.links.iter() {
+        lines.push(format!("  Link: {}", link));
+    }
+    for command in profile.commands.iter() {
+        lines.push(format!("  Bot command: !{}", command.name));
+    }
+    lines.join("\n")
+}
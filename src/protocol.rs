@@ -1,8 +1,21 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
 
+use ya_client::model::NodeId;
 use ya_service_bus::RpcMessage;
 
+use crate::history::HistoryEntry;
+use crate::ratchet::Header;
+
+/// Advertised in discovery. Raised, when messages change in a way older
+/// clients can't understand.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Error returned by peer and sent over the network. Errors added by
+/// newer clients are read as `Unknown`, so new variants can be added
+/// without breaking deserialization, but they must stay unit variants.
 #[derive(Debug, thiserror::Error, Serialize, Deserialize)]
 pub enum ChatError {
     #[error("Text Message Rejected")]
@@ -11,20 +24,137 @@ pub enum ChatError {
     UnknownUser,
     #[error("NodeId is invalid. Wrong format.")]
     InvalidNodeId,
+    #[error("Unknown poll.")]
+    UnknownPoll,
+    #[error("Invalid poll option.")]
+    InvalidOption,
+    #[error("Identity not available.")]
+    IdentityUnavailable,
+    #[error("Too many messages waiting for display.")]
+    QueueFull,
+    #[error("Unknown group.")]
+    UnknownGroup,
+    #[error("Invalid or expired pairing code.")]
+    InvalidPairingCode,
+    #[error("Unknown encryption session.")]
+    UnknownSession,
+    #[error("Message can't be decrypted.")]
+    DecryptionFailed,
+    #[error("Group reached its member limit.")]
+    GroupFull,
+    #[serde(other)]
+    #[error("Unknown error.")]
+    Unknown,
+}
+
+impl ChatError {
+    /// Transient state of the peer. The same request can succeed later.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ChatError::QueueFull | ChatError::IdentityUnavailable | ChatError::UnknownSession => {
+                true
+            }
+            ChatError::Rejected
+            | ChatError::UnknownUser
+            | ChatError::InvalidNodeId
+            | ChatError::UnknownPoll
+            | ChatError::InvalidOption
+            | ChatError::UnknownGroup
+            | ChatError::InvalidPairingCode
+            | ChatError::DecryptionFailed
+            | ChatError::GroupFull
+            | ChatError::Unknown => false,
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TextMessage {
+    /// Older clients don't send message ids, so we generate them on our side.
+    #[serde(default = "Uuid::new_v4")]
+    pub id: Uuid,
     pub content: String,
     pub timestamp: DateTime<Utc>,
+    /// Seconds after timestamp, when undelivered message is dropped
+    /// instead of being resent out of context. None means no limit.
+    #[serde(default)]
+    pub ttl: Option<i64>,
+    /// Message, this one answers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relayed: Option<Relayed>,
+    /// Channel inside group, like `dev` for `#dev`. None for `#general`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// Machine readable content. `content` holds its plain text version for
+    /// clients, which don't know the schema.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured: Option<Structured>,
+}
+
+/// JSON payload tagged with schema, like `location` or `task-status`,
+/// sent by bots and bridges.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Structured {
+    pub schema: String,
+    pub data: serde_json::Value,
+}
+
+/// Marks message relayed by bridge from other group. Bridges never relay
+/// marked messages again, so linked groups can't form a loop.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Relayed {
+    pub group: String,
+    /// Author in the original group.
+    pub user: String,
+}
+
+impl TextMessage {
+    pub fn expired(&self, now: &DateTime<Utc>) -> bool {
+        match self.ttl {
+            Some(ttl) => *now > self.timestamp + Duration::seconds(ttl),
+            None => false,
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SendText {
-    pub messages: Vec<TextMessage>,
+    /// Shared, so fanning batch out to many recipients and queues doesn't
+    /// copy message contents.
+    pub messages: Vec<Arc<TextMessage>>,
     pub user: String,
+    /// Not sent by older clients, which support single group only.
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Direct message sent to all devices of single user, outside of groups.
+    #[serde(default)]
+    pub direct: bool,
+    /// Sent automatically by away responder. Never answered with auto-reply,
+    /// so two away users don't reply to each other.
+    #[serde(default)]
+    pub auto_reply: bool,
+    /// Resent from queue after recipient reappeared.
+    #[serde(default)]
+    pub delayed: bool,
+}
+
+impl SendText {
+    /// Approximate size on the wire, computed without serializing.
+    pub fn size(&self) -> usize {
+        self.user.len()
+            + self.group.as_ref().map_or(0, String::len)
+            + self
+                .messages
+                .iter()
+                .map(|text| text.content.len())
+                .sum::<usize>()
+    }
 }
 
 impl RpcMessage for SendText {
@@ -32,3 +162,263 @@ impl RpcMessage for SendText {
     type Item = ();
     type Error = ChatError;
 }
+
+/// Poll broadcasted to group. Votes are sent to originator, which
+/// aggregates them and broadcasts results.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Poll {
+    pub id: Uuid,
+    #[serde(default)]
+    pub group: Option<String>,
+    pub question: String,
+    pub options: Vec<String>,
+    pub user: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl RpcMessage for Poll {
+    const ID: &'static str = "Poll";
+    type Item = ();
+    type Error = ChatError;
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Vote {
+    pub poll_id: Uuid,
+    /// Index of option counting from 0.
+    pub option: usize,
+}
+
+impl RpcMessage for Vote {
+    const ID: &'static str = "Vote";
+    type Item = ();
+    type Error = ChatError;
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PollResults {
+    pub poll_id: Uuid,
+    /// Number of votes for each option.
+    pub votes: Vec<u32>,
+}
+
+impl RpcMessage for PollResults {
+    const ID: &'static str = "PollResults";
+    type Item = ();
+    type Error = ChatError;
+}
+
+/// Announces that message was pinned in group. Contains copy of the message,
+/// since not all members had to receive it.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PinMessage {
+    pub group: String,
+    pub message_id: Uuid,
+    pub user: String,
+    pub content: String,
+    pub timestamp: DateTime<Utc>,
+    pub pinned_by: String,
+}
+
+impl RpcMessage for PinMessage {
+    const ID: &'static str = "PinMessage";
+    type Item = ();
+    type Error = ChatError;
+}
+
+/// List of members of paid group, broadcasted by group owner, whenever
+/// membership Agreements change. Only owner's list is trusted.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Members {
+    pub group: String,
+    pub members: Vec<NodeId>,
+}
+
+impl RpcMessage for Members {
+    const ID: &'static str = "Members";
+    type Item = ();
+    type Error = ChatError;
+}
+
+/// Public profile of user. Sent to users after admitting them and
+/// whenever we change it.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bio: Option<String>,
+    /// Hash of avatar image. Images themselves aren't sent over chat.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<String>,
+    /// Commands of bots run by user, invoked with `!name` in groups.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<BotCommand>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BotCommand {
+    pub name: String,
+    #[serde(default)]
+    pub help: String,
+}
+
+impl RpcMessage for Profile {
+    const ID: &'static str = "Profile";
+    type Item = ();
+    type Error = ChatError;
+}
+
+/// What provider running on our yagna node is doing.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeFacts {
+    pub busy: bool,
+    /// Activities, which weren't terminated yet.
+    pub tasks: u64,
+}
+
+/// Presence of user. Sent after admitting, when going away or back and,
+/// with `--node-presence`, whenever node facts change.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresenceUpdate {
+    pub away: bool,
+    /// Message given to `/away`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<NodeFacts>,
+}
+
+impl RpcMessage for PresenceUpdate {
+    const ID: &'static str = "PresenceUpdate";
+    type Item = ();
+    type Error = ChatError;
+}
+
+/// Challenge sent on first contact. Peer proves control of NodeId
+/// advertised in its proposal by signing the nonce.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WhoAreYou {
+    pub nonce: Vec<u8>,
+    /// Group, we discovered peer in. Full group refuses to answer.
+    #[serde(default)]
+    pub group: Option<String>,
+}
+
+impl RpcMessage for WhoAreYou {
+    const ID: &'static str = "WhoAreYou";
+    type Item = IAm;
+    type Error = ChatError;
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IAm {
+    pub name: String,
+    /// Signature of challenge hash made with node key.
+    pub signature: Vec<u8>,
+    /// Present, when responding node is linked device of other user.
+    #[serde(default)]
+    pub device: Option<DeviceCert>,
+}
+
+/// Proof, that device belongs to user: device NodeId signed by user's
+/// primary node. All devices of user are displayed as single user.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceCert {
+    pub user: NodeId,
+    pub signature: Vec<u8>,
+}
+
+/// Sent by device to user's primary node with pairing code displayed there.
+/// Primary responds with certificate for the calling device.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pair {
+    pub code: String,
+}
+
+impl RpcMessage for Pair {
+    const ID: &'static str = "Pair";
+    type Item = DeviceCert;
+    type Error = ChatError;
+}
+
+/// Asks other device of the same user for group messages newer than `since`.
+/// Answered only for devices linked with the same user.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncHistory {
+    pub group: String,
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl RpcMessage for SyncHistory {
+    const ID: &'static str = "SyncHistory";
+    type Item = Vec<HistoryEntry>;
+    type Error = ChatError;
+}
+
+/// Asks peer to resend messages, he queued for us while we were offline,
+/// without waiting for his discovery to find us again. Answered with number
+/// of messages, which will be resent.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FetchQueued {}
+
+impl RpcMessage for FetchQueued {
+    const ID: &'static str = "FetchQueued";
+    type Item = usize;
+    type Error = ChatError;
+}
+
+/// Starts double ratchet session for direct messages. Initiator sends his
+/// handshake key signed with node key, responder answers with his own.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RatchetInit {
+    pub session: Uuid,
+    pub key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl RpcMessage for RatchetInit {
+    const ID: &'static str = "RatchetInit";
+    type Item = RatchetKey;
+    type Error = ChatError;
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RatchetKey {
+    pub key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+/// Direct message encrypted in double ratchet session. Ciphertext
+/// contains `SendText` serialized as json.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendSealed {
+    pub session: Uuid,
+    pub header: Header,
+    pub ciphertext: Vec<u8>,
+}
+
+impl RpcMessage for SendSealed {
+    const ID: &'static str = "SendSealed";
+    type Item = ();
+    type Error = ChatError;
+}
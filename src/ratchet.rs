@@ -0,0 +1,538 @@
+use anyhow::{anyhow, bail};
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac, NewMac};
+use secp256k1::ecdh::SharedSecret;
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use ya_client::model::NodeId;
+
+use crate::encryption::{load_sealed, save_sealed, Cipher};
+
+/// Limits number of message keys stored for messages, which didn't arrive yet.
+const MAX_SKIP: u32 = 1000;
+const ROOT_INFO: &[u8] = b"yachat ratchet root";
+const NONCE_SIZE: usize = 24;
+const SESSIONS_FILE: &str = "ratchet.json";
+/// Every handshake creates new session, so without limit any peer could
+/// grow our sessions file without bound.
+const MAX_SESSIONS_PER_PEER: usize = 4;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Sent in clear with every message. Tells receiver, which ratchet key
+/// and chain position message was encrypted with.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Header {
+    /// Sender's current ratchet public key, compressed.
+    pub key: Vec<u8>,
+    /// Number of messages in sender's previous sending chain.
+    pub previous: u32,
+    pub number: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SkippedKey {
+    key: Vec<u8>,
+    number: u32,
+    message_key: Vec<u8>,
+}
+
+/// Double ratchet session with single peer. Every message is encrypted
+/// with its own key, which is deleted after use, and keys are refreshed
+/// with new Diffie-Hellman exchange, whenever direction of conversation
+/// changes. Leaked state doesn't reveal past messages.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Ratchet {
+    our_secret: Vec<u8>,
+    their_key: Option<Vec<u8>>,
+    root_key: Vec<u8>,
+    sending_chain: Option<Vec<u8>>,
+    receiving_chain: Option<Vec<u8>>,
+    sent: u32,
+    received: u32,
+    previous: u32,
+    skipped: Vec<SkippedKey>,
+}
+
+pub fn generate() -> (SecretKey, PublicKey) {
+    loop {
+        if let Ok(secret) = SecretKey::from_slice(&rand::random::<[u8; 32]>()) {
+            let public = PublicKey::from_secret_key(&Secp256k1::signing_only(), &secret);
+            return (secret, public);
+        }
+    }
+}
+
+pub fn shared_secret(secret: &SecretKey, public: &PublicKey) -> Vec<u8> {
+    SharedSecret::new(public, secret).to_vec()
+}
+
+impl Ratchet {
+    /// Side, which started handshake, knows peer's ratchet key and can send first.
+    pub fn initiator(shared: &[u8], their_key: &PublicKey) -> anyhow::Result<Ratchet> {
+        let (secret, _) = generate();
+        let (root_key, chain) = kdf_root(shared, &shared_secret(&secret, their_key))?;
+        Ok(Ratchet {
+            our_secret: secret[..].to_vec(),
+            their_key: Some(their_key.serialize().to_vec()),
+            root_key,
+            sending_chain: Some(chain),
+            receiving_chain: None,
+            sent: 0,
+            received: 0,
+            previous: 0,
+            skipped: vec![],
+        })
+    }
+
+    /// Side, which answered handshake, can send only after first message
+    /// from initiator.
+    pub fn responder(shared: &[u8], our_secret: &SecretKey) -> Ratchet {
+        Ratchet {
+            our_secret: our_secret[..].to_vec(),
+            their_key: None,
+            root_key: shared.to_vec(),
+            sending_chain: None,
+            receiving_chain: None,
+            sent: 0,
+            received: 0,
+            previous: 0,
+            skipped: vec![],
+        }
+    }
+
+    pub fn can_send(&self) -> bool {
+        self.sending_chain.is_some()
+    }
+
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> anyhow::Result<(Header, Vec<u8>)> {
+        let chain = self
+            .sending_chain
+            .as_ref()
+            .ok_or_else(|| anyhow!("Session can't send before receiving first message."))?;
+        let (chain, message_key) = kdf_chain(chain)?;
+        self.sending_chain = Some(chain);
+
+        let header = Header {
+            key: public_key(&self.our_secret)?.serialize().to_vec(),
+            previous: self.previous,
+            number: self.sent,
+        };
+        self.sent += 1;
+        let ciphertext = seal(&message_key, &header, plaintext)?;
+        Ok((header, ciphertext))
+    }
+
+    /// State is changed only, when message decrypts correctly, so forged
+    /// messages can't break session.
+    pub fn decrypt(&mut self, header: &Header, ciphertext: &[u8]) -> anyhow::Result<Vec<u8>> {
+        if let Some(idx) = self
+            .skipped
+            .iter()
+            .position(|skipped| skipped.key == header.key && skipped.number == header.number)
+        {
+            let plaintext = open(&self.skipped[idx].message_key, header, ciphertext)?;
+            self.skipped.remove(idx);
+            return Ok(plaintext);
+        }
+
+        let mut next = self.clone();
+        if next.their_key.as_ref() != Some(&header.key) {
+            next.skip_until(header.previous)?;
+            next.turn(&header.key)?;
+        }
+        next.skip_until(header.number)?;
+
+        let chain = next
+            .receiving_chain
+            .as_ref()
+            .ok_or_else(|| anyhow!("No receiving chain."))?;
+        let (chain, message_key) = kdf_chain(chain)?;
+        next.receiving_chain = Some(chain);
+        next.received += 1;
+
+        let plaintext = open(&message_key, header, ciphertext)?;
+        *self = next;
+        Ok(plaintext)
+    }
+
+    /// Stores keys of messages from current receiving chain, which were
+    /// sent, but didn't arrive yet.
+    fn skip_until(&mut self, until: u32) -> anyhow::Result<()> {
+        let (their_key, mut chain) = match (&self.their_key, &self.receiving_chain) {
+            (Some(their_key), Some(chain)) => (their_key.clone(), chain.clone()),
+            _ => return Ok(()),
+        };
+        if until > self.received + MAX_SKIP {
+            bail!("Too many skipped messages.");
+        }
+
+        while self.received < until {
+            let (next, message_key) = kdf_chain(&chain)?;
+            self.skipped.push(SkippedKey {
+                key: their_key.clone(),
+                number: self.received,
+                message_key,
+            });
+            chain = next;
+            self.received += 1;
+        }
+        let excess = self.skipped.len().saturating_sub(MAX_SKIP as usize);
+        self.skipped.drain(..excess);
+        self.receiving_chain = Some(chain);
+        Ok(())
+    }
+
+    /// Diffie-Hellman ratchet step after peer sent new ratchet key.
+    fn turn(&mut self, their_key: &[u8]) -> anyhow::Result<()> {
+        let their_public = PublicKey::from_slice(their_key)?;
+
+        self.previous = self.sent;
+        self.sent = 0;
+        self.received = 0;
+        self.their_key = Some(their_key.to_vec());
+
+        let our_secret = SecretKey::from_slice(&self.our_secret)?;
+        let (root_key, receiving) =
+            kdf_root(&self.root_key, &shared_secret(&our_secret, &their_public))?;
+
+        let (secret, _) = generate();
+        let (root_key, sending) = kdf_root(&root_key, &shared_secret(&secret, &their_public))?;
+        self.root_key = root_key;
+        self.receiving_chain = Some(receiving);
+        self.sending_chain = Some(sending);
+        self.our_secret = secret[..].to_vec();
+        Ok(())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    pub peer: NodeId,
+    pub ratchet: Ratchet,
+    pub used: DateTime<Utc>,
+}
+
+/// Ratchet sessions with peers, persisted in data dir (encrypted with
+/// `--encrypt`), so direct conversations survive restart. Both sides
+/// can start session at the same time, so there can be more than one
+/// session with single peer. Messages are sent in the one used last.
+pub struct Sessions {
+    path: PathBuf,
+    sessions: HashMap<Uuid, Session>,
+    cipher: Option<Cipher>,
+}
+
+impl Sessions {
+    pub fn load(data_dir: &Path, cipher: Option<Cipher>) -> anyhow::Result<Sessions> {
+        let path = data_dir.join(SESSIONS_FILE);
+        Ok(Sessions {
+            sessions: load_sealed(&path, cipher.as_ref())?,
+            path,
+            cipher,
+        })
+    }
+
+    /// Session, in which we can send to `peer`.
+    pub fn sending(&self, peer: &NodeId) -> Option<Uuid> {
+        self.sessions
+            .iter()
+            .filter(|(_, session)| &session.peer == peer && session.ratchet.can_send())
+            .max_by_key(|(_, session)| session.used)
+            .map(|(id, _)| *id)
+    }
+
+    pub fn insert(&mut self, id: Uuid, peer: NodeId, ratchet: Ratchet) {
+        let session = Session {
+            peer,
+            ratchet,
+            used: Utc::now(),
+        };
+        self.sessions.insert(id, session);
+        self.evict(&peer);
+        self.save();
+    }
+
+    pub fn remove(&mut self, id: &Uuid) {
+        if self.sessions.remove(id).is_some() {
+            self.save();
+        }
+    }
+
+    /// Drops all sessions with `peer`. Returns false, if there were none.
+    pub fn forget(&mut self, peer: &NodeId) -> bool {
+        let count = self.sessions.len();
+        self.sessions.retain(|_, session| &session.peer != peer);
+        if self.sessions.len() == count {
+            return false;
+        }
+        self.save();
+        true
+    }
+
+    pub fn encrypt(&mut self, id: &Uuid, plaintext: &[u8]) -> anyhow::Result<(Header, Vec<u8>)> {
+        let session = self
+            .sessions
+            .get_mut(id)
+            .ok_or_else(|| anyhow!("Unknown session."))?;
+        let sealed = session.ratchet.encrypt(plaintext)?;
+        session.used = Utc::now();
+        self.save();
+        Ok(sealed)
+    }
+
+    /// Returns None, if there is no such session with `peer`.
+    pub fn decrypt(
+        &mut self,
+        id: &Uuid,
+        peer: &NodeId,
+        header: &Header,
+        ciphertext: &[u8],
+    ) -> Option<anyhow::Result<Vec<u8>>> {
+        let session = match self.sessions.get_mut(id) {
+            Some(session) if &session.peer == peer => session,
+            _ => return None,
+        };
+        let result = session.ratchet.decrypt(header, ciphertext);
+        if result.is_ok() {
+            session.used = Utc::now();
+            self.save();
+        }
+        Some(result)
+    }
+
+    /// Drops least recently used sessions with `peer` above limit.
+    fn evict(&mut self, peer: &NodeId) {
+        let mut sessions = self
+            .sessions
+            .iter()
+            .filter(|(_, session)| &session.peer == peer)
+            .map(|(id, session)| (session.used, *id))
+            .collect::<Vec<_>>();
+        let excess = sessions.len().saturating_sub(MAX_SESSIONS_PER_PEER);
+        sessions.sort();
+        for (_, id) in sessions.into_iter().take(excess) {
+            self.sessions.remove(&id);
+        }
+    }
+
+    /// Message keys must not survive on disk after use, so failure is
+    /// only logged and session stays usable in memory.
+    fn save(&self) {
+        save_sealed(&self.path, &self.sessions, self.cipher.as_ref())
+            .map_err(|e| log::error!("Failed to save ratchet sessions. Error: {}", e))
+            .ok();
+    }
+}
+
+fn public_key(secret: &[u8]) -> anyhow::Result<PublicKey> {
+    let secret = SecretKey::from_slice(secret)?;
+    Ok(PublicKey::from_secret_key(
+        &Secp256k1::signing_only(),
+        &secret,
+    ))
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut mac = HmacSha256::new_varkey(key).map_err(|_| anyhow!("Invalid key length."))?;
+    mac.update(data);
+    Ok(mac.finalize().into_bytes().to_vec())
+}
+
+/// HKDF-SHA256 with root key as salt. Returns new root key and chain key.
+fn kdf_root(root_key: &[u8], dh_output: &[u8]) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+    let prk = hmac(root_key, dh_output)?;
+    let first = hmac(&prk, &[ROOT_INFO, &[1]].concat())?;
+    let second = hmac(&prk, &[&first[..], ROOT_INFO, &[2]].concat())?;
+    Ok((first, second))
+}
+
+/// Returns next chain key and message key.
+fn kdf_chain(chain: &[u8]) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+    Ok((hmac(chain, &[2])?, hmac(chain, &[1])?))
+}
+
+fn cipher(message_key: &[u8]) -> anyhow::Result<XChaCha20Poly1305> {
+    let key: [u8; 32] = message_key
+        .try_into()
+        .map_err(|_| anyhow!("Invalid message key."))?;
+    Ok(XChaCha20Poly1305::new(&Key::from(key)))
+}
+
+/// Header is authenticated together with message, so it can't be swapped.
+fn seal(message_key: &[u8], header: &Header, plaintext: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let nonce = rand::random::<[u8; NONCE_SIZE]>();
+    let aad = serde_json::to_vec(header)?;
+    let ciphertext = cipher(message_key)?
+        .encrypt(
+            &XNonce::from(nonce),
+            Payload {
+                msg: plaintext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| anyhow!("Encryption failed."))?;
+    Ok([&nonce[..], &ciphertext].concat())
+}
+
+fn open(message_key: &[u8], header: &Header, sealed: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if sealed.len() < NONCE_SIZE {
+        bail!("Message too short.");
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_SIZE);
+    let nonce: [u8; NONCE_SIZE] = nonce.try_into()?;
+    let aad = serde_json::to_vec(header)?;
+    cipher(message_key)?
+        .decrypt(
+            &XNonce::from(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| anyhow!("Message can't be decrypted."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair() -> (Ratchet, Ratchet) {
+        let (secret, public) = generate();
+        let shared = rand::random::<[u8; 32]>();
+        let alice = Ratchet::initiator(&shared, &public).unwrap();
+        let bob = Ratchet::responder(&shared, &secret);
+        (alice, bob)
+    }
+
+    fn state(ratchet: &Ratchet) -> String {
+        serde_json::to_string(ratchet).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let (mut alice, mut bob) = pair();
+        assert!(!bob.can_send());
+
+        let (header, ciphertext) = alice.encrypt(b"hello").unwrap();
+        assert_ne!(ciphertext, b"hello");
+        assert_eq!(bob.decrypt(&header, &ciphertext).unwrap(), b"hello");
+        assert!(bob.can_send());
+    }
+
+    #[test]
+    fn out_of_order() {
+        let (mut alice, mut bob) = pair();
+        let messages = (0..3)
+            .map(|idx| alice.encrypt(format!("msg {}", idx).as_bytes()).unwrap())
+            .collect::<Vec<_>>();
+
+        for idx in [2, 0, 1] {
+            let (header, ciphertext) = &messages[idx];
+            let plaintext = bob.decrypt(header, ciphertext).unwrap();
+            assert_eq!(plaintext, format!("msg {}", idx).as_bytes());
+        }
+        assert!(bob.skipped.is_empty());
+
+        // Skipped key is deleted after use, so replay fails.
+        let (header, ciphertext) = &messages[0];
+        assert!(bob.decrypt(header, ciphertext).is_err());
+    }
+
+    #[test]
+    fn turn_after_reply() {
+        let (mut alice, mut bob) = pair();
+        let (first, ciphertext) = alice.encrypt(b"ping").unwrap();
+        bob.decrypt(&first, &ciphertext).unwrap();
+
+        let (reply, ciphertext) = bob.encrypt(b"pong").unwrap();
+        assert_eq!(alice.decrypt(&reply, &ciphertext).unwrap(), b"pong");
+
+        let (second, ciphertext) = alice.encrypt(b"ping again").unwrap();
+        assert_ne!(first.key, second.key);
+        assert_eq!(second.number, 0);
+        assert_eq!(second.previous, 1);
+        assert_eq!(bob.decrypt(&second, &ciphertext).unwrap(), b"ping again");
+    }
+
+    #[test]
+    fn skipped_limit() {
+        let (mut alice, mut bob) = pair();
+        let (header, ciphertext) = alice.encrypt(b"first").unwrap();
+        for _ in 0..MAX_SKIP {
+            alice.encrypt(b"lost").unwrap();
+        }
+        let (far, far_ciphertext) = alice.encrypt(b"too far").unwrap();
+
+        let before = state(&bob);
+        assert!(bob.decrypt(&far, &far_ciphertext).is_err());
+        assert_eq!(state(&bob), before);
+        assert_eq!(bob.decrypt(&header, &ciphertext).unwrap(), b"first");
+    }
+
+    #[test]
+    fn tampered_message() {
+        let (mut alice, mut bob) = pair();
+        let (header, ciphertext) = alice.encrypt(b"hello").unwrap();
+        let before = state(&bob);
+
+        let mut tampered = ciphertext.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(bob.decrypt(&header, &tampered).is_err());
+        assert_eq!(state(&bob), before);
+
+        let (_, forged_key) = generate();
+        let forged = Header {
+            key: forged_key.serialize().to_vec(),
+            ..header.clone()
+        };
+        assert!(bob.decrypt(&forged, &ciphertext).is_err());
+        assert_eq!(state(&bob), before);
+
+        assert_eq!(bob.decrypt(&header, &ciphertext).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn sessions_per_peer_limited() {
+        let dir = std::env::temp_dir().join(format!("yachat-ratchet-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut sessions = Sessions::load(&dir, None).unwrap();
+        let peer = NodeId::from([1; 20]);
+        let other = NodeId::from([2; 20]);
+        let (alice, _) = pair();
+
+        sessions.insert(Uuid::new_v4(), other, alice.clone());
+        let ids = (0..MAX_SESSIONS_PER_PEER)
+            .map(|_| Uuid::new_v4())
+            .collect::<Vec<_>>();
+        let start = Utc::now() - chrono::Duration::hours(1);
+        for (idx, id) in ids.iter().enumerate() {
+            sessions.insert(*id, peer, alice.clone());
+            sessions.sessions.get_mut(id).unwrap().used =
+                start + chrono::Duration::minutes(idx as i64);
+        }
+        // Using the oldest session protects it from eviction.
+        sessions.encrypt(&ids[0], b"hello").unwrap();
+
+        sessions.insert(Uuid::new_v4(), peer, alice);
+        assert_eq!(sessions.sessions.len(), MAX_SESSIONS_PER_PEER + 1);
+        assert!(sessions.sessions.contains_key(&ids[0]));
+        assert!(!sessions.sessions.contains_key(&ids[1]));
+        assert!(sessions.forget(&other));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
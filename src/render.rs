@@ -0,0 +1,451 @@
+use ansi_term::{Colour, Style};
+use linkify::{LinkFinder, LinkKind};
+use std::collections::VecDeque;
+
+use ya_client::model::NodeId;
+
+use crate::profile;
+use crate::theme::Theme;
+
+#[cfg(feature = "highlight")]
+use highlight::Highlighter;
+
+const FENCE: &str = "```";
+const CODE_INDENT: &str = "    │ ";
+/// Number of recent links, which can be opened with `/open`.
+const MAX_LINKS: usize = 1000;
+
+// =========================================== //
+// Markup rendering
+// =========================================== //
+
+/// Renders lightweight markup from `TextMessage::content` for terminal output.
+///
+/// Supported: `*bold*`, `_italics_`, inline `code` and fenced code blocks.
+/// Detected URLs are numbered, so they can be opened later with `/open <n>`.
+/// In plain mode content is printed as it was received, only with link numbers added.
+/// Otherwise text is colored according to theme and `@name` mentions of us
+/// and watched terms are highlighted.
+pub struct Renderer {
+    plain: bool,
+    accessible: bool,
+    hyperlinks: bool,
+    theme: Theme,
+    /// `@name` mentioning us.
+    mention: String,
+    /// Terms from `/watch` list.
+    watched: Vec<String>,
+    finder: LinkFinder,
+    links: VecDeque<String>,
+    /// Links dropped from `links`, so numbers shown to user stay valid.
+    evicted: usize,
+    #[cfg(feature = "highlight")]
+    highlighter: Highlighter,
+}
+
+impl Renderer {
+    /// Accessible mode implies plain rendering.
+    pub fn new(
+        plain: bool,
+        accessible: bool,
+        hyperlinks: bool,
+        theme: Theme,
+        me: &str,
+    ) -> Renderer {
+        let mut finder = LinkFinder::new();
+        finder.kinds(&[LinkKind::Url]);
+
+        Renderer {
+            plain: plain || accessible,
+            accessible,
+            hyperlinks,
+            theme,
+            mention: format!("@{}", me),
+            watched: vec![],
+            finder,
+            links: VecDeque::new(),
+            evicted: 0,
+            #[cfg(feature = "highlight")]
+            highlighter: Highlighter::new(),
+        }
+    }
+
+    /// Distinguishes our own name from names of other users.
+    pub fn own_name(&self, name: &str) -> String {
+        self.paint(self.theme.own, name)
+    }
+
+    pub fn timestamp(&self, timestamp: &str) -> String {
+        self.paint(self.theme.timestamp, timestamp)
+    }
+
+    /// System notices are displayed between markers, to separate them from messages.
+    pub fn notice(&self, text: &str) -> String {
+        match self.accessible {
+            true => format!("System: {}", text),
+            false => self.paint(self.theme.notice, &format!("<===> {} <===>", text)),
+        }
+    }
+
+    /// Date and session separators between messages.
+    pub fn separator(&self, text: &str) -> String {
+        match self.accessible {
+            true => text.to_string(),
+            false => self.paint(self.theme.timestamp, &format!("— {} —", text)),
+        }
+    }
+
+    pub fn mentions_me(&self, text: &str) -> bool {
+        find_mention(text, &self.mention).is_some()
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    pub fn set_watched(&mut self, terms: &[String]) {
+        self.watched = terms.to_vec();
+    }
+
+    /// Returns first watched term found in text.
+    pub fn watched_in(&self, text: &str) -> Option<&str> {
+        self.watched
+            .iter()
+            .find(|term| find_word(text, term).is_some())
+            .map(|term| term.as_str())
+    }
+
+    /// Initials in color of user, displayed in place of avatar.
+    pub fn avatar(&self, name: &str, node_id: &NodeId) -> String {
+        let initials = format!("[{}]", profile::initials(name));
+        match self.plain {
+            true => initials,
+            false => profile::avatar_colour(node_id)
+                .reverse()
+                .paint(initials)
+                .to_string(),
+        }
+    }
+
+    pub fn user_state(&self, text: &str, online: bool) -> String {
+        match online {
+            true => self.paint(self.theme.online, text),
+            false => self.paint(self.theme.offline, text),
+        }
+    }
+
+    fn paint(&self, style: Style, text: &str) -> String {
+        match self.plain {
+            true => text.to_string(),
+            false => style.paint(text).to_string(),
+        }
+    }
+
+    /// Returns link registered under index displayed to user. Only
+    /// `MAX_LINKS` most recent links are kept.
+    pub fn link(&self, index: usize) -> Option<&str> {
+        index
+            .checked_sub(self.evicted + 1)
+            .and_then(|idx| self.links.get(idx))
+            .map(|link| link.as_str())
+    }
+
+    pub fn render(&mut self, content: &str) -> String {
+        if self.plain {
+            return content
+                .lines()
+                .map(|line| self.render_line(line))
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+
+        let mut output = vec![];
+        let mut code: Option<(String, Vec<&str>)> = None;
+
+        for line in content.lines() {
+            let trimmed = line.trim_start();
+            match code.take() {
+                Some((lang, lines)) if trimmed.starts_with(FENCE) => {
+                    output.extend(self.render_code_block(&lang, &lines));
+                }
+                Some((lang, mut lines)) => {
+                    lines.push(line);
+                    code = Some((lang, lines));
+                }
+                None if trimmed.starts_with(FENCE) => {
+                    let lang = trimmed[FENCE.len()..].trim().to_string();
+                    code = Some((lang, vec![]));
+                }
+                None => output.push(self.render_line(line)),
+            }
+        }
+
+        // Unterminated fence: treat the rest of the message as code anyway.
+        if let Some((lang, lines)) = code {
+            output.extend(self.render_code_block(&lang, &lines));
+        }
+
+        // Code blocks always start in a new line, so they don't get glued
+        // to the message header.
+        if content.trim_start().starts_with(FENCE) {
+            output.insert(0, String::new());
+        }
+        output.join("\n")
+    }
+
+    /// Renders single line of text outside of code blocks. Links are excluded
+    /// from markup processing, because they often contain `_` and `*`.
+    fn render_line(&mut self, line: &str) -> String {
+        let links = self
+            .finder
+            .links(line)
+            .map(|link| (link.start(), link.end()))
+            .collect::<Vec<_>>();
+
+        let mut output = String::with_capacity(line.len());
+        let mut last = 0;
+        for (start, end) in links {
+            output.push_str(&self.render_text(&line[last..start]));
+            output.push_str(&self.render_link(&line[start..end]));
+            last = end;
+        }
+        output.push_str(&self.render_text(&line[last..]));
+        output
+    }
+
+    fn render_text(&self, text: &str) -> String {
+        match self.plain {
+            true => text.to_string(),
+            false => self.render_inline(text),
+        }
+    }
+
+    /// Renders inline markup in single line. Unmatched markers are left untouched.
+    fn render_inline(&self, line: &str) -> String {
+        let chars: Vec<char> = line.chars().collect();
+        let mut output = String::with_capacity(line.len());
+        let mut plain = String::new();
+        let mut idx = 0;
+
+        while idx < chars.len() {
+            let marker = chars[idx];
+            let style = match marker {
+                '`' => Some(code_style()),
+                '*' => Some(Style::new().bold()),
+                '_' => Some(Style::new().italic()),
+                _ => None,
+            };
+
+            if let Some(style) = style {
+                if let Some(end) = find_closing(&chars, idx, marker) {
+                    let inner: String = chars[idx + 1..end].iter().collect();
+                    let inner = match marker {
+                        // Code spans are literal.
+                        '`' => inner,
+                        _ => self.render_inline(&inner),
+                    };
+                    output.push_str(&self.render_plain(&std::mem::take(&mut plain)));
+                    output.push_str(&style.paint(inner).to_string());
+                    idx = end + 1;
+                    continue;
+                }
+            }
+
+            plain.push(marker);
+            idx += 1;
+        }
+        output.push_str(&self.render_plain(&plain));
+        output
+    }
+
+    /// Paints text outside of markup with theme text style and highlights
+    /// mentions and watched terms.
+    fn render_plain(&self, text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+        let text_style = self.theme.text;
+        let paint_text = |output: &mut String, text: &str| {
+            if !text.is_empty() {
+                output.push_str(&text_style.paint(text).to_string());
+            }
+        };
+
+        while let Some((start, end)) = self.find_highlight(rest) {
+            paint_text(&mut output, &rest[..start]);
+            output.push_str(&self.theme.mention.paint(&rest[start..end]).to_string());
+            rest = &rest[end..];
+        }
+        paint_text(&mut output, rest);
+        output
+    }
+
+    /// Earliest mention or watched term in text.
+    fn find_highlight(&self, text: &str) -> Option<(usize, usize)> {
+        let mention = find_mention(text, &self.mention).map(|start| (start, self.mention.len()));
+        let watched = self
+            .watched
+            .iter()
+            .filter_map(|term| find_word(text, term).map(|start| (start, term.len())));
+        mention
+            .into_iter()
+            .chain(watched)
+            .min_by_key(|(start, _)| *start)
+            .map(|(start, len)| (start, start + len))
+    }
+
+    fn render_link(&mut self, url: &str) -> String {
+        self.links.push_back(url.to_string());
+        if self.links.len() > MAX_LINKS {
+            self.links.pop_front();
+            self.evicted += 1;
+        }
+        let index = self.evicted + self.links.len();
+
+        if self.plain {
+            return format!("{} [{}]", url, index);
+        }
+
+        let label = Style::new().underline().paint(url).to_string();
+        let label = match self.hyperlinks {
+            // OSC 8 escape sequence, ignored by terminals which don't support it.
+            true => format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, label),
+            false => label,
+        };
+        format!(
+            "{} {}",
+            label,
+            Colour::Fixed(8).paint(format!("[{}]", index))
+        )
+    }
+
+    fn render_code_block(&self, lang: &str, lines: &[&str]) -> Vec<String> {
+        #[cfg(feature = "highlight")]
+        {
+            if let Some(highlighted) = self.highlighter.highlight(lang, lines) {
+                return highlighted
+                    .into_iter()
+                    .map(|line| format!("{}{}", Colour::Fixed(8).paint(CODE_INDENT), line))
+                    .collect();
+            }
+        }
+        #[cfg(not(feature = "highlight"))]
+        let _ = lang;
+
+        lines
+            .iter()
+            .map(|line| {
+                format!(
+                    "{}{}",
+                    Colour::Fixed(8).paint(CODE_INDENT),
+                    code_style().paint(*line)
+                )
+            })
+            .collect()
+    }
+}
+
+fn code_style() -> Style {
+    Colour::Yellow.normal()
+}
+
+/// Finds `@name` ending on word boundary. Names are compared ignoring ASCII case.
+fn find_mention(text: &str, mention: &str) -> Option<usize> {
+    text.char_indices().map(|(idx, _)| idx).find(|idx| {
+        let candidate = match text.get(*idx..*idx + mention.len()) {
+            Some(candidate) => candidate,
+            None => return false,
+        };
+        candidate.eq_ignore_ascii_case(mention)
+            && text[idx + mention.len()..]
+                .chars()
+                .next()
+                .map_or(true, |c| !c.is_alphanumeric())
+    })
+}
+
+/// Finds whole word or phrase, ignoring ASCII case.
+fn find_word(text: &str, word: &str) -> Option<usize> {
+    let boundary = |c: Option<char>| c.map_or(true, |c| !c.is_alphanumeric());
+    text.char_indices().map(|(idx, _)| idx).find(|idx| {
+        let candidate = match text.get(*idx..*idx + word.len()) {
+            Some(candidate) => candidate,
+            None => return false,
+        };
+        candidate.eq_ignore_ascii_case(word)
+            && boundary(text[..*idx].chars().next_back())
+            && boundary(text[idx + word.len()..].chars().next())
+    })
+}
+
+/// Finds closing marker for span opened at `start`. Spans can't be empty,
+/// can't begin or end with whitespace and `_` must be placed on word boundaries,
+/// so identifiers like `snake_case_name` stay intact.
+fn find_closing(chars: &[char], start: usize, marker: char) -> Option<usize> {
+    let word_boundary = |idx: Option<&char>| idx.map(|c| !c.is_alphanumeric()).unwrap_or(true);
+
+    if marker != '`' {
+        if start > 0 && !word_boundary(chars.get(start - 1)) && marker == '_' {
+            return None;
+        }
+        match chars.get(start + 1) {
+            Some(c) if !c.is_whitespace() => (),
+            _ => return None,
+        }
+    }
+
+    let end = start + 1 + chars[start + 1..].iter().position(|c| *c == marker)?;
+    if end == start + 1 {
+        return None;
+    }
+    if marker != '`' && chars[end - 1].is_whitespace() {
+        return None;
+    }
+    if marker == '_' && !word_boundary(chars.get(end + 1)) {
+        return None;
+    }
+    Some(end)
+}
+
+#[cfg(feature = "highlight")]
+mod highlight {
+    use syntect::easy::HighlightLines;
+    use syntect::highlighting::{Theme, ThemeSet};
+    use syntect::parsing::SyntaxSet;
+    use syntect::util::as_24_bit_terminal_escaped;
+
+    pub struct Highlighter {
+        syntaxes: SyntaxSet,
+        theme: Theme,
+    }
+
+    impl Highlighter {
+        pub fn new() -> Highlighter {
+            let mut themes = ThemeSet::load_defaults();
+            Highlighter {
+                syntaxes: SyntaxSet::load_defaults_nonewlines(),
+                theme: themes
+                    .themes
+                    .remove("base16-ocean.dark")
+                    .unwrap_or_default(),
+            }
+        }
+
+        /// Returns `None` if language is unknown, so the caller can fall back
+        /// to uncolored code block.
+        pub fn highlight(&self, lang: &str, lines: &[&str]) -> Option<Vec<String>> {
+            let syntax = self.syntaxes.find_syntax_by_token(lang)?;
+            let mut highlighter = HighlightLines::new(syntax, &self.theme);
+
+            lines
+                .iter()
+                .map(|line| {
+                    let ranges = highlighter.highlight_line(line, &self.syntaxes).ok()?;
+                    Some(format!(
+                        "{}\x1b[0m",
+                        as_24_bit_terminal_escaped(&ranges, false)
+                    ))
+                })
+                .collect()
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::bail;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::str::FromStr;
+use uuid::Uuid;
+
+use ya_client::model::NodeId;
+
+use crate::chat::Delivery;
+
+/// Format of delivery digest selected with `--report`.
+#[derive(Clone, Copy)]
+pub enum ReportFormat {
+    Text,
+    /// Single line, for scripts.
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ReportFormat::Text),
+            "json" => Ok(ReportFormat::Json),
+            _ => bail!("Expected `text` or `json`, got `{}`.", s),
+        }
+    }
+}
+
+/// Outcome of our messages for single recipient.
+#[derive(Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecipientReport {
+    pub node_id: String,
+    pub name: String,
+    pub delivered: usize,
+    /// Still waiting in queue for recipient to reappear.
+    pub queued: usize,
+    pub expired: usize,
+    pub rejected: usize,
+    /// Discarded from queue with `/queue drop`.
+    pub dropped: usize,
+}
+
+#[derive(Default)]
+struct Outcomes {
+    delivered: usize,
+    expired: usize,
+    rejected: usize,
+    dropped: usize,
+    /// Messages, which can still change state.
+    queued: HashSet<Uuid>,
+}
+
+/// Counts delivery outcomes of our messages per recipient since start.
+/// Only queued messages are tracked by id, so memory doesn't grow with
+/// number of sent messages.
+#[derive(Default)]
+pub struct DeliveryDigest {
+    recipients: BTreeMap<NodeId, Outcomes>,
+}
+
+impl DeliveryDigest {
+    pub fn record(&mut self, recipient: NodeId, ids: &[Uuid], delivery: Delivery) {
+        let outcomes = self.recipients.entry(recipient).or_default();
+        for id in ids {
+            outcomes.queued.remove(id);
+            match delivery {
+                Delivery::Pending | Delivery::Queued => {
+                    outcomes.queued.insert(*id);
+                }
+                Delivery::Delivered => outcomes.delivered += 1,
+                Delivery::Expired => outcomes.expired += 1,
+                Delivery::Rejected => outcomes.rejected += 1,
+                Delivery::Dropped => outcomes.dropped += 1,
+            }
+        }
+    }
+
+    pub fn reports(&self, name: impl Fn(&NodeId) -> String) -> Vec<RecipientReport> {
+        self.recipients
+            .iter()
+            .map(|(node_id, outcomes)| RecipientReport {
+                node_id: node_id.to_string(),
+                name: name(node_id),
+                delivered: outcomes.delivered,
+                queued: outcomes.queued.len(),
+                expired: outcomes.expired,
+                rejected: outcomes.rejected,
+                dropped: outcomes.dropped,
+            })
+            .collect()
+    }
+}
+
+pub fn format(reports: &[RecipientReport], format: ReportFormat) -> String {
+    match format {
+        ReportFormat::Json => serde_json::to_string(reports).unwrap_or_default(),
+        ReportFormat::Text if reports.is_empty() => "No messages were sent.".to_string(),
+        ReportFormat::Text => {
+            let lines = reports
+                .iter()
+                .map(|report| {
+                    format!(
+                        "  {} [{}]: {} delivered, {} queued, {} expired, {} rejected, {} dropped",
+                        report.name,
+                        report.node_id,
+                        report.delivered,
+                        report.queued,
+                        report.expired,
+                        report.rejected,
+                        report.dropped
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!("Delivery report:\n{}", lines.join("\n"))
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use anyhow::anyhow;
+use chrono::{DateTime, Duration, Utc};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+
+use ya_client::model::NodeId;
+
+fn default_interval() -> i64 {
+    300
+}
+
+/// Scripted responder from `[[responder]]` section of config file.
+/// Messages matching `trigger` regex are answered with `reply`, in which
+/// `{user}`, `{group}` and `{me}` are substituted.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ResponderRule {
+    pub trigger: String,
+    pub reply: String,
+    /// Groups, the responder answers in. Empty means all groups and direct messages.
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// Minimal time between replies to the same user.
+    #[serde(default = "default_interval")]
+    pub interval_seconds: i64,
+    /// Limit of replies of this responder to everybody.
+    pub max_per_hour: Option<usize>,
+}
+
+struct Responder {
+    rule: ResponderRule,
+    trigger: Regex,
+    replied: HashMap<NodeId, DateTime<Utc>>,
+    /// Times of replies in last hour.
+    recent: VecDeque<DateTime<Utc>>,
+}
+
+impl Responder {
+    fn applies(&self, group: Option<&str>, content: &str) -> bool {
+        let in_group = match group {
+            Some(group) => {
+                self.rule.groups.is_empty() || self.rule.groups.iter().any(|g| g == group)
+            }
+            None => self.rule.groups.is_empty(),
+        };
+        in_group && self.trigger.is_match(content)
+    }
+
+    fn limited(&mut self, user: NodeId, now: DateTime<Utc>) -> bool {
+        while let Some(oldest) = self.recent.front() {
+            if now - *oldest < Duration::hours(1) {
+                break;
+            }
+            self.recent.pop_front();
+        }
+        if let Some(max) = self.rule.max_per_hour {
+            if self.recent.len() >= max {
+                return true;
+            }
+        }
+        match self.replied.get(&user) {
+            Some(last) => now - *last < Duration::seconds(self.rule.interval_seconds),
+            None => false,
+        }
+    }
+}
+
+/// Answers messages with replies configured for them. First matching
+/// responder, which isn't rate limited, replies.
+#[derive(Default)]
+pub struct Responders {
+    responders: Vec<Responder>,
+}
+
+impl Responders {
+    pub fn from_config(rules: &[ResponderRule]) -> anyhow::Result<Responders> {
+        let mut responders = Responders::default();
+        responders.configure(rules)?;
+        Ok(responders)
+    }
+
+    /// Replaces responders. Rate limits start over.
+    pub fn configure(&mut self, rules: &[ResponderRule]) -> anyhow::Result<()> {
+        self.responders = rules
+            .iter()
+            .map(|rule| {
+                let trigger = Regex::new(&rule.trigger).map_err(|e| {
+                    anyhow!("Invalid responder trigger {}. Error: {}", rule.trigger, e)
+                })?;
+                Ok(Responder {
+                    rule: rule.clone(),
+                    trigger,
+                    replied: HashMap::new(),
+                    recent: VecDeque::new(),
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(())
+    }
+
+    /// Returns reply for message from `user` or None, if no responder
+    /// should answer it.
+    pub fn respond(
+        &mut self,
+        user: NodeId,
+        name: &str,
+        me: &str,
+        group: Option<&str>,
+        content: &str,
+    ) -> Option<String> {
+        let now = Utc::now();
+        let responder = self
+            .responders
+            .iter_mut()
+            .filter(|responder| responder.applies(group, content))
+            .find_map(|responder| match responder.limited(user, now) {
+                true => None,
+                false => Some(responder),
+            })?;
+        responder.replied.insert(user, now);
+        responder.recent.push_back(now);
+        Some(
+            responder
+                .rule
+                .reply
+                .replace("{user}", name)
+                .replace("{group}", group.unwrap_or(""))
+                .replace("{me}", me),
+        )
+    }
+}
@@ -0,0 +1,76 @@
+use anyhow::{anyhow, bail};
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::session::Session;
+use crate::storage::Storage;
+
+/// How long history of group is kept. Unset limits mean unlimited.
+#[derive(Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct Retention {
+    /// Messages older than this number of days are removed.
+    pub days: Option<i64>,
+    /// Only this number of newest messages is kept.
+    pub messages: Option<usize>,
+}
+
+impl Retention {
+    pub fn is_unlimited(&self) -> bool {
+        self.days.is_none() && self.messages.is_none()
+    }
+
+    pub fn cutoff(&self) -> Option<DateTime<Utc>> {
+        self.days.map(|days| Utc::now() - Duration::days(days))
+    }
+}
+
+/// `[retention]` section of config file. Limits of single group can be
+/// overridden in `[retention.groups.<name>]`.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RetentionConfig {
+    #[serde(flatten)]
+    pub defaults: Retention,
+    pub groups: HashMap<String, Retention>,
+}
+
+impl RetentionConfig {
+    pub fn for_group(&self, group: &str) -> &Retention {
+        self.groups.get(group).unwrap_or(&self.defaults)
+    }
+}
+
+/// Parses date like `2021-03-15` as local midnight.
+pub fn parse_date(date: &str) -> anyhow::Result<DateTime<Utc>> {
+    let day = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| anyhow!("Invalid date: {}. Expected YYYY-MM-DD.", date))?;
+    let local = day
+        .and_hms_opt(0, 0, 0)
+        .and_then(|midnight| Local.from_local_datetime(&midnight).earliest())
+        .ok_or_else(|| anyhow!("Date {} doesn't exist in local timezone.", date))?;
+    Ok(local.with_timezone(&Utc))
+}
+
+/// `yachat prune`. Removes messages older than `before` from storage.
+pub fn prune_offline(
+    data_dir: &Path,
+    mut groups: Vec<String>,
+    before: DateTime<Utc>,
+    storage: &dyn Storage,
+) -> anyhow::Result<()> {
+    if groups.is_empty() {
+        groups = Session::load(data_dir)?.groups;
+    }
+    if groups.is_empty() {
+        bail!("No group to prune. Give group name or use --group.");
+    }
+
+    for group in groups {
+        let removed = storage.prune(&group, Some(before), None)?;
+        println!("{}: removed {} messages.", group, removed);
+    }
+    Ok(())
+}
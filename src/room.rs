@@ -0,0 +1,170 @@
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+use ya_client::model::NodeId;
+
+use crate::config::Config;
+use crate::keys;
+use crate::storage::{self, file_name};
+
+/// `yachat group` commands, sharing group definitions.
+#[derive(StructOpt)]
+pub enum GroupCommand {
+    /// Print definition of group as TOML or write it to file.
+    Export {
+        group: String,
+        /// Output file. `.json` extension selects JSON, TOML otherwise.
+        #[structopt(long, short)]
+        output: Option<PathBuf>,
+        /// Short description of the group.
+        #[structopt(long)]
+        topic: Option<String>,
+    },
+    /// Store group definition from file and add the group to config file.
+    Import { file: PathBuf },
+}
+
+/// Shareable description of group, published by communities, so users
+/// can join it with one command. Group messages are signed, not encrypted,
+/// so there are no encryption settings to share.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RoomDefinition {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+    /// Creator of the group. For paid groups the one collecting fees.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<NodeId>,
+    /// Makes group announcement-only.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub announcers: Vec<NodeId>,
+    /// Membership fee in GLM.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_members: Option<usize>,
+}
+
+impl RoomDefinition {
+    fn path(data_dir: &Path, name: &str) -> PathBuf {
+        data_dir
+            .join("rooms")
+            .join(format!("{}.json", file_name(name)))
+    }
+
+    /// Definition imported earlier. None for groups joined by name only.
+    pub fn load(data_dir: &Path, name: &str) -> anyhow::Result<Option<RoomDefinition>> {
+        let path = RoomDefinition::path(data_dir, name);
+        match path.exists() {
+            true => Ok(Some(storage::load_json(&path)?)),
+            false => Ok(None),
+        }
+    }
+
+    pub fn save(&self, data_dir: &Path) -> anyhow::Result<()> {
+        storage::save_json(&RoomDefinition::path(data_dir, &self.name), self)
+    }
+
+    pub fn read(file: &Path) -> anyhow::Result<RoomDefinition> {
+        let content = fs::read_to_string(file)
+            .map_err(|e| anyhow!("Failed to read {}. Error: {}", file.display(), e))?;
+        let definition: RoomDefinition = match is_json(file) {
+            true => serde_json::from_str(&content)?,
+            false => toml::from_str(&content)?,
+        };
+        if definition.name.trim().is_empty() {
+            bail!("Group definition {} has no name.", file.display());
+        }
+        Ok(definition)
+    }
+
+    fn format(&self, json: bool) -> anyhow::Result<String> {
+        Ok(match json {
+            true => serde_json::to_string_pretty(self)?,
+            false => toml::to_string(self)?,
+        })
+    }
+}
+
+impl GroupCommand {
+    /// Groups without imported definition are exported with settings
+    /// from command line, which apply to first group.
+    pub async fn run(
+        self,
+        config_path: &Path,
+        data_dir: &Path,
+        first_group: Option<&str>,
+        announcers: Vec<NodeId>,
+        fee: Option<String>,
+        max_members: Option<usize>,
+    ) -> anyhow::Result<()> {
+        match self {
+            GroupCommand::Export {
+                group,
+                output,
+                topic,
+            } => {
+                let mut definition = match RoomDefinition::load(data_dir, &group)? {
+                    Some(definition) => definition,
+                    None if first_group == Some(group.as_str()) => RoomDefinition {
+                        name: group,
+                        announcers,
+                        fee,
+                        max_members,
+                        ..Default::default()
+                    },
+                    None => RoomDefinition {
+                        name: group,
+                        ..Default::default()
+                    },
+                };
+                if topic.is_some() {
+                    definition.topic = topic;
+                }
+                if definition.owner.is_none() {
+                    definition.owner = keys::default_identity()
+                        .await
+                        .map_err(|e| log::warn!("Owner not set. Error: {}", e))
+                        .ok();
+                }
+
+                match output {
+                    Some(output) => {
+                        storage::save_atomic(
+                            &output,
+                            definition.format(is_json(&output))?.as_bytes(),
+                        )?;
+                        println!(
+                            "Group {} exported to {}.",
+                            definition.name,
+                            output.display()
+                        );
+                    }
+                    None => print!("{}", definition.format(false)?),
+                }
+            }
+            GroupCommand::Import { file } => {
+                let definition = RoomDefinition::read(&file)?;
+                definition.save(data_dir)?;
+                Config::add_group(config_path, &definition.name)?;
+                println!(
+                    "Group {} added to {}. Run yachat to join it.",
+                    definition.name,
+                    config_path.display()
+                );
+                if let Some(topic) = &definition.topic {
+                    println!("Topic: {}", topic);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_json(file: &Path) -> bool {
+    file.extension().map_or(false, |ext| ext == "json")
+}
@@ -0,0 +1,94 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use ya_client::model::NodeId;
+
+use crate::storage::Storage;
+
+/// User verified in group. Restored users are offline, until discovery
+/// confirms, that they are still there.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserDesc {
+    pub name: String,
+    pub node_id: NodeId,
+    pub group: String,
+    pub last_seen: DateTime<Utc>,
+    /// Primary NodeId of user, if this is his linked device.
+    #[serde(default)]
+    pub user: Option<NodeId>,
+    #[serde(skip)]
+    pub online: bool,
+}
+
+impl UserDesc {
+    /// Identifies user across all his devices.
+    pub fn user_id(&self) -> NodeId {
+        self.user.unwrap_or(self.node_id)
+    }
+}
+
+/// Users of single group persisted in storage, so messages can be
+/// sent (or queued) to them right after restart.
+#[derive(Clone)]
+pub struct Roster {
+    group: String,
+    users: Vec<UserDesc>,
+}
+
+impl Roster {
+    pub fn load(storage: &dyn Storage, group: &str) -> anyhow::Result<Roster> {
+        Ok(Roster {
+            group: group.to_string(),
+            users: storage.load_roster(group)?,
+        })
+    }
+
+    pub fn save(&self, storage: &dyn Storage) -> anyhow::Result<()> {
+        storage.store_roster(&self.group, &self.users)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.users.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &UserDesc> {
+        self.users.iter()
+    }
+
+    pub fn add(&mut self, name: &str, node_id: NodeId, group: &str, user: Option<NodeId>) {
+        self.users.retain(|desc| desc.node_id != node_id);
+        self.users.push(UserDesc {
+            name: name.to_string(),
+            node_id,
+            group: group.to_string(),
+            last_seen: Utc::now(),
+            user,
+            online: true,
+        });
+    }
+
+    /// Returns false, if user wasn't in roster.
+    pub fn remove(&mut self, node_id: &NodeId) -> bool {
+        let count = self.users.len();
+        self.users.retain(|desc| &desc.node_id != node_id);
+        self.users.len() != count
+    }
+
+    /// Marks user as online after rediscovering him. Returns false
+    /// if user wasn't online before.
+    pub fn confirm(&mut self, node_id: &NodeId, name: &str) -> bool {
+        match self.users.iter_mut().find(|desc| &desc.node_id == node_id) {
+            Some(desc) => {
+                desc.name = name.to_string();
+                desc.last_seen = Utc::now();
+                std::mem::replace(&mut desc.online, true)
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use anyhow::anyhow;
+use chrono::{DateTime, Duration, Local, NaiveTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::cron::Cron;
+use crate::encryption::{load_sealed, save_sealed, Cipher};
+
+const SCHEDULE_FILE: &str = "schedule.json";
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Scheduled {
+    pub id: Uuid,
+    pub at: DateTime<Utc>,
+    /// Group, to which message is sent. None for reminders, which are
+    /// only displayed to us.
+    pub group: Option<String>,
+    pub text: String,
+}
+
+/// Message broadcast to group on schedule, defined in `[[recurring]]`
+/// section of config file.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Recurring {
+    /// Cron expression in local time, for example `0 9 * * 1-5`.
+    pub schedule: Cron,
+    pub group: String,
+    pub text: String,
+}
+
+/// Messages and reminders waiting for their time. Persisted, so they
+/// survive restart. Entries, which passed while chat was off, are
+/// handled right after start.
+pub struct Schedule {
+    path: PathBuf,
+    entries: Vec<Scheduled>,
+    cipher: Option<Cipher>,
+}
+
+impl Schedule {
+    pub fn load(data_dir: &Path, cipher: Option<Cipher>) -> anyhow::Result<Schedule> {
+        let path = data_dir.join(SCHEDULE_FILE);
+        Ok(Schedule {
+            entries: load_sealed(&path, cipher.as_ref())?,
+            path,
+            cipher,
+        })
+    }
+
+    pub fn entries(&self) -> &[Scheduled] {
+        &self.entries
+    }
+
+    pub fn add(&mut self, entry: Scheduled) -> anyhow::Result<()> {
+        self.entries.push(entry);
+        self.save()
+    }
+
+    pub fn remove(&mut self, id: &Uuid) -> anyhow::Result<Option<Scheduled>> {
+        let idx = match self.entries.iter().position(|entry| &entry.id == id) {
+            Some(idx) => idx,
+            None => return Ok(None),
+        };
+        let entry = self.entries.remove(idx);
+        self.save()?;
+        Ok(Some(entry))
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        save_sealed(&self.path, &self.entries, self.cipher.as_ref())
+    }
+}
+
+/// Parses local time like `18:00`. Time, which already passed today,
+/// means tomorrow.
+pub fn parse_time(time: &str) -> anyhow::Result<DateTime<Utc>> {
+    let time = NaiveTime::parse_from_str(time, "%H:%M")
+        .map_err(|_| anyhow!("Invalid time: {}. Expected HH:MM.", time))?;
+
+    let now = Local::now();
+    let mut date = now.naive_local().date();
+    if now.naive_local().time() >= time {
+        date += Duration::days(1);
+    }
+    let local = Local
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+        .ok_or_else(|| anyhow!("Time {} doesn't exist in local timezone.", time))?;
+    Ok(local.with_timezone(&Utc))
+}
+
+/// Parses delay like `20m`, `1h30m` or `2d`.
+pub fn parse_delay(delay: &str) -> anyhow::Result<Duration> {
+    let invalid = || {
+        anyhow!(
+            "Invalid delay: {}. Expected for example 20m or 1h30m.",
+            delay
+        )
+    };
+
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in delay.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let value = i64::from(number.parse::<u32>().map_err(|_| invalid())?);
+        number.clear();
+        let part = match c {
+            's' => Duration::seconds(value),
+            'm' => Duration::minutes(value),
+            'h' => Duration::hours(value),
+            'd' => Duration::days(value),
+            _ => return Err(invalid()),
+        };
+        total = total.checked_add(&part).ok_or_else(invalid)?;
+    }
+    if !number.is_empty() || total <= Duration::zero() {
+        return Err(invalid());
+    }
+    Ok(total)
+}
@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::storage::{load_json, save_json};
+
+const SESSION_FILE: &str = "session.json";
+
+/// Groups joined at last shutdown. Rejoined at startup with `--resume`.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    pub groups: Vec<String>,
+}
+
+impl Session {
+    pub fn load(data_dir: &Path) -> anyhow::Result<Session> {
+        load_json(&data_dir.join(SESSION_FILE))
+    }
+
+    pub fn save(&self, data_dir: &Path) -> anyhow::Result<()> {
+        save_json(&data_dir.join(SESSION_FILE), self)
+    }
+}
@@ -0,0 +1,125 @@
+use anyhow::{anyhow, bail};
+use serde::Serialize;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use ya_client::model::NodeId;
+use ya_core_model::identity;
+use ya_service_bus::{typed as bus, RpcEndpoint};
+
+use crate::keys;
+
+const APP_KEY_ENV: &str = "YAGNA_APPKEY";
+const DEFAULT_GROUP: &str = "yachat";
+
+/// Config written by the wizard. Subset of `Config` fields.
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct FirstRun {
+    name: String,
+    groups: Vec<String>,
+    app_key: String,
+}
+
+/// Wizard runs on first interactive start, when nothing tells us,
+/// which app-key to use.
+pub fn needed(config_path: &Path) -> bool {
+    !config_path.exists()
+        && std::env::var(APP_KEY_ENV).is_err()
+        && !std::env::args().any(|arg| arg.starts_with("--app-key"))
+        && atty::is(atty::Stream::Stdin)
+        && atty::is(atty::Stream::Stdout)
+}
+
+/// Walks user through yagna connection, app-key, name and group and
+/// writes config file.
+pub async fn run(config_path: &Path) -> anyhow::Result<()> {
+    println!(
+        "No config file found at {}. Let's set up yachat.",
+        config_path.display()
+    );
+
+    let info = match bus::service(identity::BUS_ID)
+        .send(identity::Get::ByDefault)
+        .await
+    {
+        Ok(Ok(Some(info))) => info,
+        Ok(Ok(None)) => bail!("Yagna has no default identity. Create one with `yagna id create`."),
+        Ok(Err(e)) => bail!("Yagna identity service failed. Error: {}", e),
+        Err(e) => bail!(
+            "Can't connect to yagna. Start it with `yagna service run` and run yachat again. Error: {}",
+            e
+        ),
+    };
+    println!(
+        "Connected to yagna. Identity: {} [{}]",
+        info.alias.as_deref().unwrap_or("no alias"),
+        info.node_id
+    );
+
+    let app_key = pick_app_key(info.node_id).await?;
+
+    let name = loop {
+        let name = match &info.alias {
+            Some(alias) => prompt(&format!("Display name [{}]", alias), alias)?,
+            None => prompt("Display name", "")?,
+        };
+        if !name.is_empty() {
+            break name;
+        }
+        println!("Name is required.");
+    };
+    let group = prompt(&format!("Default group [{}]", DEFAULT_GROUP), DEFAULT_GROUP)?;
+
+    let config = FirstRun {
+        name,
+        groups: vec![group],
+        app_key,
+    };
+    keys::save_private(config_path, toml::to_string(&config)?.as_bytes())?;
+    println!(
+        "Config written to {}. Edit it to change defaults.",
+        config_path.display()
+    );
+    Ok(())
+}
+
+/// Offers existing app-keys of identity or creates new one.
+async fn pick_app_key(node_id: NodeId) -> anyhow::Result<String> {
+    let keys = keys::list(Some(node_id)).await?;
+    if !keys.is_empty() {
+        println!("App-keys of this identity:");
+        for (idx, key) in keys.iter().enumerate() {
+            println!("  [{}] {}", idx + 1, key.name);
+        }
+        let choice = prompt("Use app-key number or type `new` to create one [1]", "1")?;
+        if choice != "new" {
+            let idx = choice
+                .parse::<usize>()
+                .ok()
+                .filter(|idx| (1..=keys.len()).contains(idx))
+                .ok_or_else(|| anyhow!("Invalid app-key number: {}", choice))?;
+            return Ok(keys[idx - 1].key.clone());
+        }
+    }
+
+    let name = prompt(
+        &format!("Name of new app-key [{}]", keys::DEFAULT_KEY_NAME),
+        keys::DEFAULT_KEY_NAME,
+    )?;
+    let key = keys::create(&name, node_id).await?;
+    println!("Created app-key {}.", name);
+    Ok(key)
+}
+
+pub(crate) fn prompt(question: &str, default: &str) -> anyhow::Result<String> {
+    print!("{}: ", question);
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    Ok(match line.trim() {
+        "" => default.to_string(),
+        answer => answer.to_string(),
+    })
+}
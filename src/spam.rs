@@ -0,0 +1,150 @@
+use chrono::{DateTime, Duration, Utc};
+use linkify::{LinkFinder, LinkKind};
+use serde::Deserialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+
+use ya_client::model::NodeId;
+
+/// Messages older than this don't count to the score.
+const SPAM_WINDOW_SECONDS: i64 = 60;
+
+/// Limits, at which single signal alone gets peer muted.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct SpamThresholds {
+    pub messages_per_minute: usize,
+    /// Identical messages within a minute.
+    pub repeated: usize,
+    /// Links in single message.
+    pub links: usize,
+    pub mute_minutes: i64,
+}
+
+impl Default for SpamThresholds {
+    fn default() -> Self {
+        SpamThresholds {
+            messages_per_minute: 20,
+            repeated: 4,
+            links: 5,
+            mute_minutes: 10,
+        }
+    }
+}
+
+/// `[spam]` section of config file. Thresholds of single group can be
+/// overridden in `[spam.groups.<name>]`.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SpamConfig {
+    #[serde(flatten)]
+    pub defaults: SpamThresholds,
+    pub groups: HashMap<String, SpamThresholds>,
+}
+
+pub enum Verdict {
+    Accept,
+    /// Peer was muted by this message until given time.
+    Muted(DateTime<Utc>),
+    /// Peer is muted already.
+    Dropped,
+}
+
+/// Scores received messages and mutes peers locally. Every signal adds
+/// its fraction of the threshold, so peer is muted, when signals
+/// together reach 1.
+pub struct SpamFilter {
+    config: SpamConfig,
+    finder: LinkFinder,
+    /// Time and content hash of recent messages per peer.
+    recent: HashMap<NodeId, VecDeque<(DateTime<Utc>, u64)>>,
+    muted: HashMap<NodeId, DateTime<Utc>>,
+    /// Peers unmuted with `/unmute`, never muted again in this session.
+    trusted: HashSet<NodeId>,
+}
+
+impl SpamFilter {
+    pub fn new(config: SpamConfig) -> SpamFilter {
+        let mut finder = LinkFinder::new();
+        finder.kinds(&[LinkKind::Url]);
+
+        SpamFilter {
+            config,
+            finder,
+            recent: HashMap::new(),
+            muted: HashMap::new(),
+            trusted: HashSet::new(),
+        }
+    }
+
+    pub fn set_config(&mut self, config: SpamConfig) {
+        self.config = config;
+    }
+
+    /// Direct messages are scored with default thresholds.
+    pub fn check(&mut self, sender: NodeId, group: Option<&str>, content: &str) -> Verdict {
+        let now = Utc::now();
+        match self.muted.get(&sender) {
+            Some(until) if *until > now => return Verdict::Dropped,
+            Some(_) => {
+                self.muted.remove(&sender);
+            }
+            None => (),
+        }
+        if self.trusted.contains(&sender) {
+            return Verdict::Accept;
+        }
+
+        let config = &self.config;
+        let thresholds = group
+            .and_then(|group| config.groups.get(group))
+            .unwrap_or(&config.defaults);
+
+        let hash = hash(content);
+        let recent = self.recent.entry(sender).or_default();
+        let window = Duration::seconds(SPAM_WINDOW_SECONDS);
+        while let Some((received, _)) = recent.front() {
+            if now - *received < window {
+                break;
+            }
+            recent.pop_front();
+        }
+        recent.push_back((now, hash));
+
+        let repeated = recent.iter().filter(|(_, other)| *other == hash).count();
+        let links = self.finder.links(content).count();
+        let score = ratio(recent.len(), thresholds.messages_per_minute)
+            + ratio(repeated, thresholds.repeated)
+            + ratio(links, thresholds.links);
+        if score < 1.0 {
+            return Verdict::Accept;
+        }
+
+        let until = now + Duration::minutes(thresholds.mute_minutes);
+        self.recent.remove(&sender);
+        self.muted.insert(sender, until);
+        Verdict::Muted(until)
+    }
+
+    /// Returns false, if peer wasn't muted.
+    pub fn unmute(&mut self, peer: NodeId) -> bool {
+        self.trusted.insert(peer);
+        self.recent.remove(&peer);
+        self.muted.remove(&peer).is_some()
+    }
+}
+
+/// Zero threshold disables the signal.
+fn ratio(count: usize, threshold: usize) -> f64 {
+    match threshold {
+        0 => 0.0,
+        threshold => count as f64 / threshold as f64,
+    }
+}
+
+fn hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.trim().to_lowercase().hash(&mut hasher);
+    hasher.finish()
+}
@@ -0,0 +1,155 @@
+use anyhow::{anyhow, bail};
+use chrono::{DateTime, Duration, Local, NaiveDate, Timelike, Utc};
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::history::HistoryEntry;
+use crate::schedule::parse_delay;
+use crate::session::Session;
+use crate::storage::Storage;
+
+/// Width of the longest bar in histograms.
+const BAR_WIDTH: usize = 30;
+/// Users listed in report.
+const TOP_USERS: usize = 10;
+
+/// Time span covered by statistics: `all`, `day`, `week`, `month`
+/// or delay like `3d`.
+#[derive(Clone, Copy, Debug)]
+pub struct Period(Option<Duration>);
+
+impl FromStr for Period {
+    type Err = anyhow::Error;
+
+    fn from_str(period: &str) -> anyhow::Result<Period> {
+        Ok(Period(match period {
+            "all" => None,
+            "day" => Some(Duration::days(1)),
+            "week" => Some(Duration::weeks(1)),
+            "month" => Some(Duration::days(30)),
+            delay => Some(parse_delay(delay).map_err(|_| {
+                anyhow!(
+                    "Invalid period: {}. Use all, day, week, month or delay like 3d.",
+                    delay
+                )
+            })?),
+        }))
+    }
+}
+
+impl Period {
+    pub fn all() -> Period {
+        Period(None)
+    }
+
+    pub fn day() -> Period {
+        Period(Some(Duration::days(1)))
+    }
+
+    pub fn since(&self) -> Option<DateTime<Utc>> {
+        self.0.map(|duration| Utc::now() - duration)
+    }
+}
+
+/// Report of activity in group: messages per user, per hour of day
+/// (local time) and busiest day.
+pub fn report(group: &str, entries: &[HistoryEntry]) -> String {
+    if entries.is_empty() {
+        return format!("No messages in group {} in this period.", group);
+    }
+
+    let mut users: HashMap<&str, usize> = HashMap::new();
+    let mut hours = [0usize; 24];
+    let mut days: HashMap<NaiveDate, usize> = HashMap::new();
+    for entry in entries {
+        let local = entry.timestamp.with_timezone(&Local);
+        *users.entry(&entry.user).or_default() += 1;
+        hours[local.hour() as usize] += 1;
+        *days.entry(local.naive_local().date()).or_default() += 1;
+    }
+
+    let mut users = users.into_iter().collect::<Vec<_>>();
+    users.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let busiest = days
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)));
+
+    let mut lines = vec![format!(
+        "Group {}: {} message(s) from {} user(s).",
+        group,
+        entries.len(),
+        users.len()
+    )];
+
+    lines.push("Most active users:".to_string());
+    let width = users
+        .iter()
+        .take(TOP_USERS)
+        .map(|(user, _)| user.chars().count())
+        .max()
+        .unwrap_or_default();
+    let max = users.first().map(|(_, count)| *count).unwrap_or_default();
+    for (user, count) in users.iter().take(TOP_USERS) {
+        lines.push(format!(
+            "  {:width$}  {:5}  {}",
+            user,
+            count,
+            bar(*count, max),
+            width = width
+        ));
+    }
+
+    lines.push("Messages per hour:".to_string());
+    let max = hours.iter().copied().max().unwrap_or_default();
+    for (hour, count) in hours.iter().enumerate() {
+        lines.push(format!(
+            "  {:02}:00  {:5}  {}",
+            hour,
+            count,
+            bar(*count, max)
+        ));
+    }
+
+    if let Some((day, count)) = busiest {
+        lines.push(format!(
+            "Busiest day: {} with {} message(s).",
+            day.format("%Y-%m-%d"),
+            count
+        ));
+    }
+    lines.join("\n")
+}
+
+/// `yachat stats`: reports over history in data dir, without connecting
+/// to yagna. Without groups given, groups of last session are used.
+pub fn print_offline(
+    data_dir: &Path,
+    mut groups: Vec<String>,
+    period: Period,
+    storage: &dyn Storage,
+) -> anyhow::Result<()> {
+    if groups.is_empty() {
+        groups = Session::load(data_dir)?.groups;
+    }
+    if groups.is_empty() {
+        bail!("No group to analyze. Give group name or use --group.");
+    }
+
+    let reports = groups
+        .iter()
+        .map(|group| {
+            let entries = storage.query(group, period.since(), None, usize::MAX)?;
+            Ok(report(group, &entries))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    println!("{}", reports.join("\n\n"));
+    Ok(())
+}
+
+fn bar(count: usize, max: usize) -> String {
+    match max {
+        0 => String::new(),
+        max => "█".repeat((count * BAR_WIDTH + max - 1) / max),
+    }
+}
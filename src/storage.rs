@@ -0,0 +1,260 @@
+use anyhow::{anyhow, bail};
+use chrono::{DateTime, Utc};
+use directories::ProjectDirs;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use ya_client::model::NodeId;
+
+use crate::encryption::Cipher;
+use crate::history::HistoryEntry;
+use crate::protocol::SendText;
+use crate::roster::UserDesc;
+
+mod files;
+mod memory;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+pub use files::FileStorage;
+pub use memory::MemoryStorage;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStorage;
+
+/// Backend keeping history, rosters and undelivered messages. Embedders
+/// can supply their own with `Chat::with_storage`. Calls are blocking,
+/// history is written from per-group worker threads.
+pub trait Storage: Send + Sync {
+    fn append_message(&self, entry: &HistoryEntry) -> anyhow::Result<()>;
+    /// Messages of group in time range, oldest first. At most `limit`
+    /// newest ones are returned.
+    fn query(
+        &self,
+        group: &str,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<HistoryEntry>>;
+    /// Messages of group containing `text`, ignoring case. Oldest first.
+    fn search(&self, group: &str, text: &str, limit: usize) -> anyhow::Result<Vec<HistoryEntry>>;
+    /// Undelivered messages per recipient.
+    fn load_queue(&self) -> anyhow::Result<Vec<(NodeId, SendText)>>;
+    /// Replaces stored queue.
+    fn store_queue(&self, queue: &[(NodeId, SendText)]) -> anyhow::Result<()>;
+    fn load_roster(&self, group: &str) -> anyhow::Result<Vec<UserDesc>>;
+    fn store_roster(&self, group: &str, users: &[UserDesc]) -> anyhow::Result<()>;
+    /// Removes messages of group older than `before` and all except
+    /// `keep_last` newest ones. Returns number of removed messages.
+    fn prune(
+        &self,
+        group: &str,
+        before: Option<DateTime<Utc>>,
+        keep_last: Option<usize>,
+    ) -> anyhow::Result<usize>;
+    /// Removes messages of group with given ids. Returns number of
+    /// removed messages.
+    fn delete_messages(&self, group: &str, ids: &HashSet<Uuid>) -> anyhow::Result<usize>;
+    /// Removes all messages of group sent by `senders`, including ones
+    /// not loaded in memory. Returns number of removed messages.
+    fn delete_by_sender(&self, group: &str, senders: &[NodeId]) -> anyhow::Result<usize>;
+    /// Removes history and roster of group.
+    fn delete_group(&self, group: &str) -> anyhow::Result<()>;
+}
+
+/// Storage backend selected with `--storage` or in config file.
+#[derive(Clone, Copy, Default)]
+pub enum StorageKind {
+    /// Json files in data dir.
+    #[default]
+    Files,
+    /// Nothing survives restart.
+    Memory,
+    /// Single database file in data dir. Requires `sqlite` feature.
+    Sqlite,
+}
+
+impl FromStr for StorageKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "files" => Ok(StorageKind::Files),
+            "memory" => Ok(StorageKind::Memory),
+            "sqlite" => Ok(StorageKind::Sqlite),
+            _ => bail!("Expected `files`, `memory` or `sqlite`, got `{}`.", s),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for StorageKind {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Seals data of all backends written before encryption was enabled.
+/// Returns number of sealed entries.
+pub fn seal_plaintext(data_dir: &Path, cipher: &Cipher) -> anyhow::Result<usize> {
+    #[allow(unused_mut)]
+    let mut sealed = files::seal_plaintext(data_dir, cipher)?;
+    #[cfg(feature = "sqlite")]
+    {
+        sealed += sqlite::seal_plaintext(data_dir, cipher)?;
+    }
+    Ok(sealed)
+}
+
+/// Stored data is sealed with `cipher`, when given.
+pub fn open(
+    kind: StorageKind,
+    data_dir: &Path,
+    cipher: Option<Cipher>,
+) -> anyhow::Result<Arc<dyn Storage>> {
+    Ok(match kind {
+        StorageKind::Files => Arc::new(FileStorage::new(data_dir, cipher)),
+        StorageKind::Memory => Arc::new(MemoryStorage::default()),
+        #[cfg(feature = "sqlite")]
+        StorageKind::Sqlite => Arc::new(SqliteStorage::open(data_dir, cipher)?),
+        #[cfg(not(feature = "sqlite"))]
+        StorageKind::Sqlite => bail!("SQLite storage requires yachat built with `sqlite` feature."),
+    })
+}
+
+/// Keeps `limit` newest of entries sorted by timestamp.
+fn newest(mut entries: Vec<HistoryEntry>, limit: usize) -> Vec<HistoryEntry> {
+    entries.sort_by_key(|entry| entry.timestamp);
+    let skip = entries.len().saturating_sub(limit);
+    entries.drain(..skip);
+    entries
+}
+
+fn in_range(
+    entry: &HistoryEntry,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+) -> bool {
+    since.map_or(true, |since| entry.timestamp > since)
+        && until.map_or(true, |until| entry.timestamp <= until)
+}
+
+/// Ids of entries, which `delete_by_sender` removes.
+fn sent_by(entries: &[HistoryEntry], senders: &[NodeId]) -> HashSet<Uuid> {
+    entries
+        .iter()
+        .filter(|entry| {
+            entry
+                .sender
+                .map_or(false, |sender| senders.contains(&sender))
+        })
+        .map(|entry| entry.id)
+        .collect()
+}
+
+/// Ids of entries, which `prune` removes.
+fn pruned(
+    entries: &[HistoryEntry],
+    before: Option<DateTime<Utc>>,
+    keep_last: Option<usize>,
+) -> HashSet<Uuid> {
+    let mut pruned = entries
+        .iter()
+        .filter(|entry| before.map_or(false, |before| entry.timestamp < before))
+        .map(|entry| entry.id)
+        .collect::<HashSet<_>>();
+    if let Some(keep_last) = keep_last {
+        let mut sorted = entries.iter().collect::<Vec<_>>();
+        sorted.sort_by_key(|entry| entry.timestamp);
+        let skip = sorted.len().saturating_sub(keep_last);
+        pruned.extend(sorted[..skip].iter().map(|entry| entry.id));
+    }
+    pruned
+}
+
+fn contains(entry: &HistoryEntry, text: &str) -> bool {
+    entry.content.to_lowercase().contains(&text.to_lowercase())
+}
+
+/// Platform specific directories, for example `~/.local/share/yachat`
+/// and `~/.config/yachat` on Linux. None, when home dir is unknown.
+pub fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("network", "golem", "yachat")
+}
+
+/// Used, when neither `--data-dir` nor config file sets data dir.
+pub fn default_data_dir() -> PathBuf {
+    match project_dirs() {
+        Some(dirs) => dirs.data_dir().to_path_buf(),
+        None => PathBuf::from("data"),
+    }
+}
+
+/// Loads json file from data dir. Missing file isn't an error, since
+/// nothing was stored yet on first run.
+pub fn load_json<T: DeserializeOwned + Default>(path: &Path) -> anyhow::Result<T> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+
+    let content = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read {}. Error: {}", path.display(), e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| anyhow!("Failed to parse {}. Error: {}", path.display(), e))
+}
+
+/// Writes to temporary file first and renames it, so we never leave
+/// half-written file, when process is killed.
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> anyhow::Result<()> {
+    save_atomic(path, serde_json::to_string_pretty(value)?.as_bytes())
+}
+
+pub fn save_atomic(path: &Path, content: &[u8]) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| anyhow!("Failed to create dir {}. Error: {}", dir.display(), e))?;
+    }
+
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, content)
+        .map_err(|e| anyhow!("Failed to write {}. Error: {}", tmp.display(), e))?;
+    fs::rename(&tmp, path)
+        .map_err(|e| anyhow!("Failed to write {}. Error: {}", path.display(), e))?;
+    Ok(())
+}
+
+/// Makes name (for example group name) safe to use as file name.
+pub fn file_name(name: &str) -> String {
+    let mut file_name = name
+        .chars()
+        .map(|c| match c.is_alphanumeric() || c == '-' || c == '_' {
+            true => c,
+            false => '_',
+        })
+        .collect::<String>();
+    if cfg!(windows) && reserved_on_windows(&file_name) {
+        file_name.push('_');
+    }
+    file_name
+}
+
+/// Device names, which can't be used as file names on Windows, even
+/// with extension.
+fn reserved_on_windows(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    match name.as_str() {
+        "CON" | "PRN" | "AUX" | "NUL" => true,
+        _ => {
+            (name.starts_with("COM") || name.starts_with("LPT"))
+                && name.len() == 4
+                && name.as_bytes()[3].is_ascii_digit()
+                && name.as_bytes()[3] != b'0'
+        }
+    }
+}
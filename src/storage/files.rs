@@ -0,0 +1,256 @@
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use ya_client::model::NodeId;
+
+use super::{
+    contains, file_name, in_range, load_json, newest, pruned, save_atomic, save_json, sent_by,
+    Storage,
+};
+use crate::encryption::{is_plaintext, load_sealed, save_sealed, Cipher};
+use crate::history::HistoryEntry;
+use crate::protocol::SendText;
+use crate::roster::UserDesc;
+
+const HISTORY_DIR: &str = "history";
+const ROSTER_DIR: &str = "roster";
+const QUEUE_FILE: &str = "queue.json";
+
+/// Default backend. History of group is json lines file, one message per
+/// line. With encryption enabled each line is sealed separately, so
+/// appending stays cheap.
+pub struct FileStorage {
+    data_dir: PathBuf,
+    cipher: Option<Cipher>,
+}
+
+impl FileStorage {
+    pub fn new(data_dir: &Path, cipher: Option<Cipher>) -> FileStorage {
+        FileStorage {
+            data_dir: data_dir.to_path_buf(),
+            cipher,
+        }
+    }
+
+    fn history_path(&self, group: &str) -> PathBuf {
+        self.data_dir
+            .join(HISTORY_DIR)
+            .join(format!("{}.jsonl", file_name(group)))
+    }
+
+    fn roster_path(&self, group: &str) -> PathBuf {
+        self.data_dir
+            .join(ROSTER_DIR)
+            .join(format!("{}.json", file_name(group)))
+    }
+
+    fn read_history(&self, group: &str) -> anyhow::Result<Vec<HistoryEntry>> {
+        Ok(self
+            .read_lines(group)?
+            .into_iter()
+            .filter_map(|(_, entry)| entry)
+            .collect())
+    }
+
+    /// Lines of history file with decoded entries. Entries, which can't
+    /// be read, are None.
+    fn read_lines(&self, group: &str) -> anyhow::Result<Vec<(String, Option<HistoryEntry>)>> {
+        let path = self.history_path(group);
+        let mut lines = vec![];
+        if !path.exists() {
+            return Ok(lines);
+        }
+
+        let (mut sealed, mut plaintext) = (0, 0);
+        let file = fs::File::open(&path)
+            .map_err(|e| anyhow!("Failed to open {}. Error: {}", path.display(), e))?;
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            let json = match (&self.cipher, is_plaintext(&line)) {
+                (None, true) => line.as_bytes().to_vec(),
+                (Some(_), true) => {
+                    plaintext += 1;
+                    lines.push((line, None));
+                    continue;
+                }
+                (Some(cipher), false) => match cipher.open(&line) {
+                    Ok(json) => json,
+                    Err(e) => {
+                        log::warn!("Skipping history entry. Error: {}", e);
+                        lines.push((line, None));
+                        continue;
+                    }
+                },
+                (None, false) => {
+                    sealed += 1;
+                    lines.push((line, None));
+                    continue;
+                }
+            };
+            match serde_json::from_slice::<HistoryEntry>(&json) {
+                Ok(entry) => lines.push((line, Some(entry))),
+                Err(e) => {
+                    log::warn!("Skipping invalid history entry. Error: {}", e);
+                    lines.push((line, None));
+                }
+            }
+        }
+        if sealed > 0 {
+            log::warn!(
+                "Skipped {} encrypted entries of {}. Run with --encrypt to read them.",
+                sealed,
+                path.display()
+            );
+        }
+        if plaintext > 0 {
+            log::warn!(
+                "Skipped {} unencrypted entries of {}, though encryption is enabled.",
+                plaintext,
+                path.display()
+            );
+        }
+        Ok(lines)
+    }
+}
+
+/// Seals plaintext lines of all history files. Returns number of sealed
+/// entries.
+pub(super) fn seal_plaintext(data_dir: &Path, cipher: &Cipher) -> anyhow::Result<usize> {
+    let dir = data_dir.join(HISTORY_DIR);
+    if !dir.is_dir() {
+        return Ok(0);
+    }
+
+    let mut sealed = 0;
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        let content = fs::read_to_string(&path)
+            .map_err(|e| anyhow!("Failed to read {}. Error: {}", path.display(), e))?;
+        if !content.lines().any(is_plaintext) {
+            continue;
+        }
+        let mut output = String::new();
+        for line in content.lines() {
+            match is_plaintext(line) {
+                true => {
+                    output.push_str(&cipher.seal(line.as_bytes())?);
+                    sealed += 1;
+                }
+                false => output.push_str(line),
+            }
+            output.push('\n');
+        }
+        save_atomic(&path, output.as_bytes())?;
+    }
+    Ok(sealed)
+}
+
+impl Storage for FileStorage {
+    fn append_message(&self, entry: &HistoryEntry) -> anyhow::Result<()> {
+        let path = self.history_path(&entry.group);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| anyhow!("Failed to open {}. Error: {}", path.display(), e))?;
+        let line = serde_json::to_string(entry)?;
+        match &self.cipher {
+            Some(cipher) => writeln!(file, "{}", cipher.seal(line.as_bytes())?)?,
+            None => writeln!(file, "{}", line)?,
+        }
+        Ok(())
+    }
+
+    // Entries synced from other devices are appended out of order, so
+    // the whole file is read and sorted.
+    fn query(
+        &self,
+        group: &str,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<HistoryEntry>> {
+        let mut entries = self.read_history(group)?;
+        entries.retain(|entry| in_range(entry, since, until));
+        Ok(newest(entries, limit))
+    }
+
+    fn search(&self, group: &str, text: &str, limit: usize) -> anyhow::Result<Vec<HistoryEntry>> {
+        let mut entries = self.read_history(group)?;
+        entries.retain(|entry| contains(entry, text));
+        Ok(newest(entries, limit))
+    }
+
+    fn load_queue(&self) -> anyhow::Result<Vec<(NodeId, SendText)>> {
+        load_sealed(&self.data_dir.join(QUEUE_FILE), self.cipher.as_ref())
+    }
+
+    fn store_queue(&self, queue: &[(NodeId, SendText)]) -> anyhow::Result<()> {
+        save_sealed(
+            &self.data_dir.join(QUEUE_FILE),
+            &queue,
+            self.cipher.as_ref(),
+        )
+    }
+
+    fn load_roster(&self, group: &str) -> anyhow::Result<Vec<UserDesc>> {
+        load_json(&self.roster_path(group))
+    }
+
+    fn store_roster(&self, group: &str, users: &[UserDesc]) -> anyhow::Result<()> {
+        save_json(&self.roster_path(group), &users)
+    }
+
+    fn prune(
+        &self,
+        group: &str,
+        before: Option<DateTime<Utc>>,
+        keep_last: Option<usize>,
+    ) -> anyhow::Result<usize> {
+        let pruned = pruned(&self.read_history(group)?, before, keep_last);
+        self.delete_messages(group, &pruned)
+    }
+
+    // Lines, which can't be read, are kept, so pruning without `--encrypt`
+    // doesn't destroy encrypted history.
+    fn delete_messages(&self, group: &str, ids: &HashSet<Uuid>) -> anyhow::Result<usize> {
+        let mut removed = 0;
+        let mut content = String::new();
+        for (line, entry) in self.read_lines(group)? {
+            if entry.map_or(false, |entry| ids.contains(&entry.id)) {
+                removed += 1;
+                continue;
+            }
+            content.push_str(&line);
+            content.push('\n');
+        }
+        if removed > 0 {
+            save_atomic(&self.history_path(group), content.as_bytes())?;
+        }
+        Ok(removed)
+    }
+
+    fn delete_by_sender(&self, group: &str, senders: &[NodeId]) -> anyhow::Result<usize> {
+        let ids = sent_by(&self.read_history(group)?, senders);
+        self.delete_messages(group, &ids)
+    }
+
+    fn delete_group(&self, group: &str) -> anyhow::Result<()> {
+        for path in &[self.history_path(group), self.roster_path(group)] {
+            if path.exists() {
+                fs::remove_file(path)
+                    .map_err(|e| anyhow!("Failed to remove {}. Error: {}", path.display(), e))?;
+            }
+        }
+        Ok(())
+    }
+}
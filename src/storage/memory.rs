@@ -0,0 +1,126 @@
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use ya_client::model::NodeId;
+
+use super::{contains, in_range, newest, pruned, sent_by, Storage};
+use crate::history::HistoryEntry;
+use crate::protocol::SendText;
+use crate::roster::UserDesc;
+
+/// Keeps everything in memory, for tests and throwaway sessions.
+#[derive(Default)]
+pub struct MemoryStorage {
+    history: Mutex<HashMap<String, Vec<HistoryEntry>>>,
+    queue: Mutex<Vec<(NodeId, SendText)>>,
+    rosters: Mutex<HashMap<String, Vec<UserDesc>>>,
+}
+
+impl MemoryStorage {
+    fn filtered(
+        &self,
+        group: &str,
+        limit: usize,
+        filter: impl Fn(&HistoryEntry) -> bool,
+    ) -> Vec<HistoryEntry> {
+        let history = self.history.lock().unwrap();
+        let entries = history
+            .get(group)
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter(|entry| filter(entry))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        newest(entries, limit)
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn append_message(&self, entry: &HistoryEntry) -> anyhow::Result<()> {
+        self.history
+            .lock()
+            .unwrap()
+            .entry(entry.group.clone())
+            .or_default()
+            .push(entry.clone());
+        Ok(())
+    }
+
+    fn query(
+        &self,
+        group: &str,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<HistoryEntry>> {
+        Ok(self.filtered(group, limit, |entry| in_range(entry, since, until)))
+    }
+
+    fn search(&self, group: &str, text: &str, limit: usize) -> anyhow::Result<Vec<HistoryEntry>> {
+        Ok(self.filtered(group, limit, |entry| contains(entry, text)))
+    }
+
+    fn load_queue(&self) -> anyhow::Result<Vec<(NodeId, SendText)>> {
+        Ok(self.queue.lock().unwrap().clone())
+    }
+
+    fn store_queue(&self, queue: &[(NodeId, SendText)]) -> anyhow::Result<()> {
+        *self.queue.lock().unwrap() = queue.to_vec();
+        Ok(())
+    }
+
+    fn load_roster(&self, group: &str) -> anyhow::Result<Vec<UserDesc>> {
+        Ok(self
+            .rosters
+            .lock()
+            .unwrap()
+            .get(group)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn store_roster(&self, group: &str, users: &[UserDesc]) -> anyhow::Result<()> {
+        self.rosters
+            .lock()
+            .unwrap()
+            .insert(group.to_string(), users.to_vec());
+        Ok(())
+    }
+
+    fn prune(
+        &self,
+        group: &str,
+        before: Option<DateTime<Utc>>,
+        keep_last: Option<usize>,
+    ) -> anyhow::Result<usize> {
+        let entries = self.filtered(group, usize::MAX, |_| true);
+        self.delete_messages(group, &pruned(&entries, before, keep_last))
+    }
+
+    fn delete_messages(&self, group: &str, ids: &HashSet<Uuid>) -> anyhow::Result<usize> {
+        let mut history = self.history.lock().unwrap();
+        let entries = match history.get_mut(group) {
+            Some(entries) => entries,
+            None => return Ok(0),
+        };
+        let count = entries.len();
+        entries.retain(|entry| !ids.contains(&entry.id));
+        Ok(count - entries.len())
+    }
+
+    fn delete_by_sender(&self, group: &str, senders: &[NodeId]) -> anyhow::Result<usize> {
+        let entries = self.filtered(group, usize::MAX, |_| true);
+        self.delete_messages(group, &sent_by(&entries, senders))
+    }
+
+    fn delete_group(&self, group: &str) -> anyhow::Result<()> {
+        self.history.lock().unwrap().remove(group);
+        self.rosters.lock().unwrap().remove(group);
+        Ok(())
+    }
+}
@@ -0,0 +1,278 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use ya_client::model::NodeId;
+
+use super::{contains, newest, sent_by, Storage};
+use crate::encryption::{is_plaintext, Cipher};
+use crate::history::HistoryEntry;
+use crate::protocol::SendText;
+use crate::roster::UserDesc;
+
+const DATABASE_FILE: &str = "yachat.sqlite";
+/// Stored in `user_version` pragma. Bumped with every schema change.
+const DATABASE_VERSION: u32 = 1;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS messages (
+        id TEXT PRIMARY KEY,
+        grp TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS messages_grp_timestamp ON messages (grp, timestamp);
+    CREATE TABLE IF NOT EXISTS queue (data TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS roster (grp TEXT PRIMARY KEY, data TEXT NOT NULL);
+";
+
+/// Single database file in data dir. Group and timestamp of messages are
+/// kept in plain columns for range queries, the rest is json sealed with
+/// cipher, when encryption is enabled. Search therefore runs over
+/// decrypted rows.
+pub struct SqliteStorage {
+    connection: Mutex<Connection>,
+    cipher: Option<Cipher>,
+}
+
+/// Seals plaintext rows of existing database. Returns number of sealed rows.
+pub(super) fn seal_plaintext(data_dir: &Path, cipher: &Cipher) -> anyhow::Result<usize> {
+    let path = data_dir.join(DATABASE_FILE);
+    if !path.exists() {
+        return Ok(0);
+    }
+
+    let mut connection = Connection::open(path)?;
+    let transaction = connection.transaction()?;
+    let mut sealed = 0;
+    for (table, key) in &[("messages", "id"), ("queue", "rowid"), ("roster", "grp")] {
+        let rows = {
+            let mut statement =
+                transaction.prepare(&format!("SELECT {}, data FROM {}", key, table))?;
+            let rows = statement
+                .query_map(params![], |row| {
+                    Ok((
+                        row.get::<_, rusqlite::types::Value>(0)?,
+                        row.get::<_, String>(1)?,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+        for (key_value, data) in rows.into_iter().filter(|(_, data)| is_plaintext(data)) {
+            transaction.execute(
+                &format!("UPDATE {} SET data = ?1 WHERE {} = ?2", table, key),
+                params![cipher.seal(data.as_bytes())?, key_value],
+            )?;
+            sealed += 1;
+        }
+    }
+    transaction.commit()?;
+    Ok(sealed)
+}
+
+impl SqliteStorage {
+    pub fn open(data_dir: &Path, cipher: Option<Cipher>) -> anyhow::Result<SqliteStorage> {
+        std::fs::create_dir_all(data_dir)?;
+        let connection = Connection::open(data_dir.join(DATABASE_FILE))?;
+        let version: u32 =
+            connection.query_row("PRAGMA user_version", params![], |row| row.get(0))?;
+        if version > DATABASE_VERSION {
+            anyhow::bail!(
+                "Database was written by newer yachat (version {}). Upgrade yachat.",
+                version
+            );
+        }
+        connection.execute_batch(SCHEMA)?;
+        connection.execute_batch(&format!("PRAGMA user_version = {}", DATABASE_VERSION))?;
+        Ok(SqliteStorage {
+            connection: Mutex::new(connection),
+            cipher,
+        })
+    }
+
+    fn seal<T: Serialize + ?Sized>(&self, value: &T) -> anyhow::Result<String> {
+        let json = serde_json::to_string(value)?;
+        match &self.cipher {
+            Some(cipher) => cipher.seal(json.as_bytes()),
+            None => Ok(json),
+        }
+    }
+
+    fn open_data<T: DeserializeOwned>(&self, data: &str) -> anyhow::Result<T> {
+        let json = match (&self.cipher, is_plaintext(data)) {
+            (None, true) => data.as_bytes().to_vec(),
+            (Some(_), true) => anyhow::bail!("Data isn't encrypted, though encryption is enabled."),
+            (Some(cipher), false) => cipher.open(data)?,
+            (None, false) => anyhow::bail!("Data is encrypted. Run with --encrypt to read it."),
+        };
+        Ok(serde_json::from_slice(&json)?)
+    }
+
+    fn rows(
+        &self,
+        sql: &str,
+        group: &str,
+        from: i64,
+        to: i64,
+    ) -> anyhow::Result<Vec<HistoryEntry>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(sql)?;
+        let rows = statement
+            .query_map(params![group, from, to], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows
+            .iter()
+            .filter_map(|data| {
+                self.open_data(data)
+                    .map_err(|e| log::warn!("Skipping history entry. Error: {}", e))
+                    .ok()
+            })
+            .collect())
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn append_message(&self, entry: &HistoryEntry) -> anyhow::Result<()> {
+        let data = self.seal(entry)?;
+        self.connection.lock().unwrap().execute(
+            "INSERT OR IGNORE INTO messages (id, grp, timestamp, data) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                entry.id.to_string(),
+                entry.group,
+                entry.timestamp.timestamp_millis(),
+                data
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn query(
+        &self,
+        group: &str,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<HistoryEntry>> {
+        let from = since.map_or(i64::MIN, |since| since.timestamp_millis());
+        let to = until.map_or(i64::MAX, |until| until.timestamp_millis());
+        let sql = format!(
+            "SELECT data FROM messages WHERE grp = ?1 AND timestamp > ?2 AND timestamp <= ?3 \
+             ORDER BY timestamp DESC LIMIT {}",
+            limit.min(i64::MAX as usize)
+        );
+        Ok(newest(self.rows(&sql, group, from, to)?, limit))
+    }
+
+    fn search(&self, group: &str, text: &str, limit: usize) -> anyhow::Result<Vec<HistoryEntry>> {
+        let sql = "SELECT data FROM messages WHERE grp = ?1 AND timestamp > ?2 AND timestamp <= ?3";
+        let mut entries = self.rows(sql, group, i64::MIN, i64::MAX)?;
+        entries.retain(|entry| contains(entry, text));
+        Ok(newest(entries, limit))
+    }
+
+    fn load_queue(&self) -> anyhow::Result<Vec<(NodeId, SendText)>> {
+        let connection = self.connection.lock().unwrap();
+        let data = connection
+            .query_row("SELECT data FROM queue", params![], |row| {
+                row.get::<_, String>(0)
+            })
+            .ok();
+        match data {
+            Some(data) => self.open_data(&data),
+            None => Ok(vec![]),
+        }
+    }
+
+    fn store_queue(&self, queue: &[(NodeId, SendText)]) -> anyhow::Result<()> {
+        let data = self.seal(queue)?;
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        transaction.execute("DELETE FROM queue", params![])?;
+        transaction.execute("INSERT INTO queue (data) VALUES (?1)", params![data])?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn load_roster(&self, group: &str) -> anyhow::Result<Vec<UserDesc>> {
+        let connection = self.connection.lock().unwrap();
+        let data = connection
+            .query_row(
+                "SELECT data FROM roster WHERE grp = ?1",
+                params![group],
+                |row| row.get::<_, String>(0),
+            )
+            .ok();
+        match data {
+            Some(data) => self.open_data(&data),
+            None => Ok(vec![]),
+        }
+    }
+
+    fn store_roster(&self, group: &str, users: &[UserDesc]) -> anyhow::Result<()> {
+        let data = self.seal(users)?;
+        self.connection.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO roster (grp, data) VALUES (?1, ?2)",
+            params![group, data],
+        )?;
+        Ok(())
+    }
+
+    fn prune(
+        &self,
+        group: &str,
+        before: Option<DateTime<Utc>>,
+        keep_last: Option<usize>,
+    ) -> anyhow::Result<usize> {
+        let connection = self.connection.lock().unwrap();
+        let mut removed = 0;
+        if let Some(before) = before {
+            removed += connection.execute(
+                "DELETE FROM messages WHERE grp = ?1 AND timestamp < ?2",
+                params![group, before.timestamp_millis()],
+            )?;
+        }
+        if let Some(keep_last) = keep_last {
+            removed += connection.execute(
+                "DELETE FROM messages WHERE grp = ?1 AND id NOT IN \
+                 (SELECT id FROM messages WHERE grp = ?1 ORDER BY timestamp DESC LIMIT ?2)",
+                params![group, keep_last.min(i64::MAX as usize) as i64],
+            )?;
+        }
+        Ok(removed)
+    }
+
+    fn delete_messages(&self, group: &str, ids: &HashSet<Uuid>) -> anyhow::Result<usize> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        let mut removed = 0;
+        for id in ids {
+            removed += transaction.execute(
+                "DELETE FROM messages WHERE grp = ?1 AND id = ?2",
+                params![group, id.to_string()],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(removed)
+    }
+
+    fn delete_by_sender(&self, group: &str, senders: &[NodeId]) -> anyhow::Result<usize> {
+        let sql = "SELECT data FROM messages WHERE grp = ?1 AND timestamp > ?2 AND timestamp <= ?3";
+        let entries = self.rows(sql, group, i64::MIN, i64::MAX)?;
+        self.delete_messages(group, &sent_by(&entries, senders))
+    }
+
+    fn delete_group(&self, group: &str) -> anyhow::Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        transaction.execute("DELETE FROM messages WHERE grp = ?1", params![group])?;
+        transaction.execute("DELETE FROM roster WHERE grp = ?1", params![group])?;
+        transaction.commit()?;
+        Ok(())
+    }
+}
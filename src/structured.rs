@@ -0,0 +1,101 @@
+use serde::Deserialize;
+
+use crate::protocol::Structured;
+
+/// Width of progress bar of task status.
+const PROGRESS_WIDTH: usize = 20;
+
+/// `location` schema.
+#[derive(Deserialize)]
+struct Location {
+    lat: f64,
+    lon: f64,
+    name: Option<String>,
+}
+
+/// `code` schema.
+#[derive(Deserialize)]
+struct Code {
+    code: String,
+    language: Option<String>,
+    file: Option<String>,
+}
+
+/// `task-status` schema.
+#[derive(Deserialize)]
+struct TaskStatus {
+    task: String,
+    status: String,
+    /// Percent of work done.
+    progress: Option<f64>,
+    url: Option<String>,
+}
+
+/// Renders structured content of known schema as markup. Returns None for
+/// unknown schemas and data not matching schema, so message content is
+/// displayed instead.
+pub fn render(structured: &Structured) -> Option<String> {
+    let data = structured.data.clone();
+    match structured.schema.as_str() {
+        "location" => {
+            let location = serde_json::from_value::<Location>(data).ok()?;
+            let name = match &location.name {
+                Some(name) => format!("*{}* ", name),
+                None => String::new(),
+            };
+            Some(format!(
+                "Location: {}({:.5}, {:.5})\nhttps://www.openstreetmap.org/?mlat={}&mlon={}#map=15/{}/{}",
+                name,
+                location.lat,
+                location.lon,
+                location.lat,
+                location.lon,
+                location.lat,
+                location.lon
+            ))
+        }
+        "code" => {
+            let code = serde_json::from_value::<Code>(data).ok()?;
+            let file = match &code.file {
+                Some(file) => format!("_{}_\n", file),
+                None => String::new(),
+            };
+            Some(format!(
+                "{}```{}\n{}\n```",
+                file,
+                code.language.unwrap_or_default(),
+                code.code.trim_end()
+            ))
+        }
+        "task-status" => {
+            let task = serde_json::from_value::<TaskStatus>(data).ok()?;
+            let mut text = format!("Task *{}*: {}", task.task, task.status);
+            if let Some(progress) = task.progress {
+                let progress = progress.clamp(0.0, 100.0);
+                let done = (progress / 100.0 * PROGRESS_WIDTH as f64).round() as usize;
+                text.push_str(&format!(
+                    " [{}{}] {:.0}%",
+                    "#".repeat(done),
+                    "-".repeat(PROGRESS_WIDTH - done),
+                    progress
+                ));
+            }
+            if let Some(url) = &task.url {
+                text.push_str(&format!("\n{}", url));
+            }
+            Some(text)
+        }
+        _ => None,
+    }
+}
+
+/// Schemas, which `render` can show.
+pub fn known(schema: &str) -> bool {
+    matches!(schema, "location" | "code" | "task-status")
+}
+
+/// Plain text version of structured content, sent as message content for
+/// clients, which don't know the schema.
+pub fn fallback(structured: &Structured) -> String {
+    render(structured).unwrap_or_else(|| format!("[{}] {}", structured.schema, structured.data))
+}
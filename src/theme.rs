@@ -0,0 +1,153 @@
+use ansi_term::{Colour, Style};
+use anyhow::{anyhow, bail};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Styles of chat output elements. Not used in `--plain` mode.
+#[derive(Clone)]
+pub struct Theme {
+    pub text: Style,
+    pub own: Style,
+    pub mention: Style,
+    pub notice: Style,
+    pub timestamp: Style,
+    pub online: Style,
+    pub offline: Style,
+}
+
+/// User-defined theme from config file. Every element is described as
+/// space separated color and modifiers, for example `bold #268bd2`.
+/// Elements not given are taken from `base` theme.
+#[derive(Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct Palette {
+    pub base: Option<String>,
+    pub text: Option<String>,
+    pub own: Option<String>,
+    pub mention: Option<String>,
+    pub notice: Option<String>,
+    pub timestamp: Option<String>,
+    pub online: Option<String>,
+    pub offline: Option<String>,
+}
+
+pub const BUILTIN: &[&str] = &["dark", "light", "solarized"];
+
+impl Theme {
+    pub fn dark() -> Theme {
+        Theme {
+            text: Style::new(),
+            own: Colour::Cyan.bold(),
+            mention: Colour::Yellow.bold(),
+            notice: Colour::Fixed(245).normal(),
+            timestamp: Colour::Fixed(8).normal(),
+            online: Colour::Green.normal(),
+            offline: Colour::Fixed(8).normal(),
+        }
+    }
+
+    pub fn light() -> Theme {
+        Theme {
+            text: Style::new(),
+            own: Colour::Blue.bold(),
+            mention: Colour::Purple.bold(),
+            notice: Colour::Fixed(242).normal(),
+            timestamp: Colour::Fixed(245).normal(),
+            online: Colour::Green.normal(),
+            offline: Colour::Fixed(250).normal(),
+        }
+    }
+
+    pub fn solarized() -> Theme {
+        Theme {
+            text: Colour::RGB(0x83, 0x94, 0x96).normal(),
+            own: Colour::RGB(0x26, 0x8b, 0xd2).bold(),
+            mention: Colour::RGB(0xb5, 0x89, 0x00).bold(),
+            notice: Colour::RGB(0x2a, 0xa1, 0x98).normal(),
+            timestamp: Colour::RGB(0x58, 0x6e, 0x75).normal(),
+            online: Colour::RGB(0x85, 0x99, 0x00).normal(),
+            offline: Colour::RGB(0x58, 0x6e, 0x75).normal(),
+        }
+    }
+
+    /// Finds theme by name. User-defined palettes take precedence over
+    /// built-in themes.
+    pub fn find(name: &str, palettes: &HashMap<String, Palette>) -> anyhow::Result<Theme> {
+        if let Some(palette) = palettes.get(name) {
+            return Theme::from_palette(palette);
+        }
+
+        match name {
+            "dark" => Ok(Theme::dark()),
+            "light" => Ok(Theme::light()),
+            "solarized" => Ok(Theme::solarized()),
+            _ => bail!(
+                "Unknown theme: {}. Built-in themes: {}.",
+                name,
+                BUILTIN.join(", ")
+            ),
+        }
+    }
+
+    fn from_palette(palette: &Palette) -> anyhow::Result<Theme> {
+        let base = match palette.base.as_deref() {
+            // Palette can't be based on another palette, so there are no cycles.
+            Some(base) => Theme::find(base, &HashMap::new())?,
+            None => Theme::dark(),
+        };
+
+        let style = |spec: &Option<String>, default: Style| match spec {
+            Some(spec) => parse_style(spec),
+            None => Ok(default),
+        };
+        Ok(Theme {
+            text: style(&palette.text, base.text)?,
+            own: style(&palette.own, base.own)?,
+            mention: style(&palette.mention, base.mention)?,
+            notice: style(&palette.notice, base.notice)?,
+            timestamp: style(&palette.timestamp, base.timestamp)?,
+            online: style(&palette.online, base.online)?,
+            offline: style(&palette.offline, base.offline)?,
+        })
+    }
+}
+
+/// Parses style like `bold underline red`, `#268bd2` or `244` (terminal palette index).
+pub fn parse_style(spec: &str) -> anyhow::Result<Style> {
+    let mut style = Style::new();
+    for word in spec.split_whitespace() {
+        style = match word.to_lowercase().as_str() {
+            "bold" => style.bold(),
+            "italic" => style.italic(),
+            "underline" => style.underline(),
+            "dimmed" => style.dimmed(),
+            "default" => style,
+            color => style.fg(parse_colour(color)?),
+        };
+    }
+    Ok(style)
+}
+
+fn parse_colour(color: &str) -> anyhow::Result<Colour> {
+    Ok(match color {
+        "black" => Colour::Black,
+        "red" => Colour::Red,
+        "green" => Colour::Green,
+        "yellow" => Colour::Yellow,
+        "blue" => Colour::Blue,
+        "purple" | "magenta" => Colour::Purple,
+        "cyan" => Colour::Cyan,
+        "white" => Colour::White,
+        hex if hex.starts_with('#') && hex.len() == 7 => {
+            let channel = |range| {
+                u8::from_str_radix(&hex[range], 16).map_err(|_| anyhow!("Invalid color: {}", hex))
+            };
+            Colour::RGB(channel(1..3)?, channel(3..5)?, channel(5..7)?)
+        }
+        index => Colour::Fixed(
+            index
+                .parse()
+                .map_err(|_| anyhow!("Invalid color or modifier: {}", index))?,
+        ),
+    })
+}
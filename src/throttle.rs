@@ -0,0 +1,75 @@
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+/// Sends within this time can go out at once, before limits apply.
+const BURST: Duration = Duration::from_secs(1);
+
+/// `[throttle]` section of config file. Limits apply to all outgoing
+/// messages together. Zero disables the limit.
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct ThrottleConfig {
+    pub messages_per_second: f64,
+    pub bytes_per_second: f64,
+}
+
+impl Default for ThrottleConfig {
+    fn default() -> Self {
+        ThrottleConfig {
+            messages_per_second: 20.0,
+            bytes_per_second: 64.0 * 1024.0,
+        }
+    }
+}
+
+/// Outbound rate limiter. Every send reserves its share of both limits
+/// and waits until the reservation is due, so excess drains smoothly
+/// in order of sending.
+pub struct Throttle {
+    config: ThrottleConfig,
+    /// Time, when all reserved messages are sent.
+    messages_due: Instant,
+    bytes_due: Instant,
+}
+
+impl Throttle {
+    pub fn new(config: ThrottleConfig) -> Throttle {
+        let now = Instant::now();
+        Throttle {
+            config,
+            messages_due: now,
+            bytes_due: now,
+        }
+    }
+
+    pub fn set_config(&mut self, config: ThrottleConfig) {
+        self.config = config;
+    }
+
+    /// Returns time to wait before sending.
+    pub fn reserve(&mut self, bytes: usize) -> Duration {
+        let now = Instant::now();
+        let messages = reserve(
+            &mut self.messages_due,
+            now,
+            1.0,
+            self.config.messages_per_second,
+        );
+        let bytes = reserve(
+            &mut self.bytes_due,
+            now,
+            bytes as f64,
+            self.config.bytes_per_second,
+        );
+        messages.max(bytes)
+    }
+}
+
+fn reserve(due: &mut Instant, now: Instant, amount: f64, rate: f64) -> Duration {
+    if rate <= 0.0 {
+        return Duration::default();
+    }
+    let idle = now.checked_sub(BURST).unwrap_or(now);
+    *due = (*due).max(idle) + Duration::from_secs_f64(amount / rate);
+    due.saturating_duration_since(now)
+}
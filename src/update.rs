@@ -0,0 +1,95 @@
+use anyhow::{anyhow, bail};
+use serde::Deserialize;
+use std::process::Command;
+
+const LATEST_RELEASE: &str =
+    "https://api.github.com/repos/nieznanysprawiciel/yachat/releases/latest";
+
+/// Version of this build, advertised to other users.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    pub html_url: String,
+}
+
+impl Release {
+    pub fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+}
+
+/// Asks GitHub with curl, same as feeds. Blocking.
+pub fn latest_release() -> anyhow::Result<Release> {
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--location"])
+        .args([
+            "--max-time",
+            "30",
+            "--header",
+            "Accept: application/vnd.github.v3+json",
+        ])
+        .arg(LATEST_RELEASE)
+        .output()
+        .map_err(|e| anyhow!("Failed to run curl. Error: {}", e))?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| anyhow!("Invalid release description. Error: {}", e))
+}
+
+/// Release newer than this build, if there is one.
+pub fn check() -> anyhow::Result<Option<Release>> {
+    let release = latest_release()?;
+    Ok(match newer(release.version(), VERSION) {
+        true => Some(release),
+        false => None,
+    })
+}
+
+pub fn hint(release: &Release) -> String {
+    format!(
+        "yachat {} is available, you run {}. Download it from {}",
+        release.version(),
+        VERSION,
+        release.html_url
+    )
+}
+
+/// `yachat update`. With `--check` outdated build is reported as failure,
+/// for scripts.
+pub fn run(check_only: bool) -> anyhow::Result<()> {
+    match check()? {
+        Some(release) if check_only => bail!("{}", hint(&release)),
+        Some(release) => println!("{}", hint(&release)),
+        None => println!("yachat {} is up to date.", VERSION),
+    }
+    Ok(())
+}
+
+fn components(version: &str) -> Vec<u64> {
+    version
+        .split(['.', '-'])
+        .filter_map(|part| part.parse().ok())
+        .collect()
+}
+
+fn newer(version: &str, than: &str) -> bool {
+    components(version) > components(than)
+}
+
+/// Versions differing in major version, or minor before 1.0, may not
+/// understand each other's messages.
+pub fn compatible(version: &str, other: &str) -> bool {
+    let (version, other) = (components(version), components(other));
+    let significant = match version.first() {
+        Some(0) => 2,
+        _ => 1,
+    };
+    version
+        .iter()
+        .take(significant)
+        .eq(other.iter().take(significant))
+}
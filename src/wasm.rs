@@ -0,0 +1,74 @@
+use anyhow::{anyhow, bail};
+use wasmtime::{Config, Engine, Instance, Memory, Module, Store, TypedFunc};
+
+use crate::plugin::{PluginCommand, WasmPluginConfig};
+
+/// Budget of single `on_message` call, so looping plugin can't hang chat.
+const FUEL_PER_CALL: u64 = 10_000_000;
+const MAX_OUTPUT: usize = 64 * 1024;
+
+/// Bot compiled to WebAssembly. Module gets no imports, so it can't reach
+/// anything outside of its own memory. It must export:
+/// - `memory`,
+/// - `alloc(len: i32) -> i32`, returning buffer for event json,
+/// - `on_message(ptr: i32, len: i32) -> i64`, returning pointer to json
+///   array of commands in upper 32 bits and its length in lower ones.
+///
+/// Events and commands have the same format as for `--plugin` processes.
+pub struct WasmPlugin {
+    pub config: WasmPluginConfig,
+    store: Store,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    on_message: TypedFunc<(i32, i32), i64>,
+    fuel_added: u64,
+}
+
+impl WasmPlugin {
+    pub fn load(config: WasmPluginConfig) -> anyhow::Result<WasmPlugin> {
+        let engine = Engine::new(Config::new().consume_fuel(true))?;
+        let module = Module::from_file(&engine, &config.path)
+            .map_err(|e| anyhow!("Failed to load {}. Error: {}", config.path.display(), e))?;
+        let store = Store::new(&engine);
+        let instance = Instance::new(&store, &module, &[])?;
+        let memory = instance
+            .get_memory("memory")
+            .ok_or_else(|| anyhow!("{} doesn't export memory.", config.path.display()))?;
+        let alloc = instance.get_typed_func::<i32, i32>("alloc")?;
+        let on_message = instance.get_typed_func::<(i32, i32), i64>("on_message")?;
+        Ok(WasmPlugin {
+            config,
+            store,
+            memory,
+            alloc,
+            on_message,
+            fuel_added: 0,
+        })
+    }
+
+    pub fn on_message(&mut self, event: &str) -> anyhow::Result<Vec<PluginCommand>> {
+        // Fuel left from previous call counts into the budget.
+        let consumed = self.store.fuel_consumed().unwrap_or(0);
+        let remaining = self.fuel_added.saturating_sub(consumed);
+        if remaining < FUEL_PER_CALL {
+            self.store.add_fuel(FUEL_PER_CALL - remaining)?;
+            self.fuel_added += FUEL_PER_CALL - remaining;
+        }
+
+        let len = event.len() as i32;
+        let ptr = self.alloc.call(len)?;
+        self.memory.write(ptr as usize, event.as_bytes())?;
+        let output = self.on_message.call((ptr, len))? as u64;
+
+        let (ptr, len) = ((output >> 32) as usize, (output & 0xffff_ffff) as usize);
+        if len == 0 {
+            return Ok(vec![]);
+        }
+        if len > MAX_OUTPUT {
+            bail!("Output of {} bytes exceeds limit.", len);
+        }
+        let mut output = vec![0; len];
+        self.memory.read(ptr, &mut output)?;
+        Ok(serde_json::from_slice(&output)?)
+    }
+}
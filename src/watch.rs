@@ -0,0 +1,53 @@
+use std::path::{Path, PathBuf};
+
+use crate::storage::{load_json, save_json};
+
+const WATCH_FILE: &str = "watch.json";
+
+/// Terms highlighted in messages of all groups, persisted in data dir.
+pub struct Watchlist {
+    path: PathBuf,
+    terms: Vec<String>,
+}
+
+impl Watchlist {
+    pub fn load(data_dir: &Path) -> anyhow::Result<Watchlist> {
+        let path = data_dir.join(WATCH_FILE);
+        Ok(Watchlist {
+            terms: load_json(&path)?,
+            path,
+        })
+    }
+
+    pub fn terms(&self) -> &[String] {
+        &self.terms
+    }
+
+    /// Returns false, if term was already watched.
+    pub fn add(&mut self, term: &str) -> anyhow::Result<bool> {
+        if self.position(term).is_some() {
+            return Ok(false);
+        }
+        self.terms.push(term.to_string());
+        save_json(&self.path, &self.terms)?;
+        Ok(true)
+    }
+
+    /// Returns false, if term wasn't watched.
+    pub fn remove(&mut self, term: &str) -> anyhow::Result<bool> {
+        match self.position(term) {
+            Some(idx) => {
+                self.terms.remove(idx);
+                save_json(&self.path, &self.terms)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn position(&self, term: &str) -> Option<usize> {
+        self.terms
+            .iter()
+            .position(|watched| watched.eq_ignore_ascii_case(term))
+    }
+}
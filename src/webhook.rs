@@ -0,0 +1,220 @@
+use actix::prelude::*;
+use anyhow::{anyhow, bail};
+use hmac::{Hmac, Mac, NewMac};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use sha2::Sha256;
+
+const MAX_BODY: usize = 64 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `[incoming-webhook]` section of config file. Accepts payloads of Slack
+/// incoming webhooks at `/hooks/<token>`, so tools posting to Slack can post
+/// to groups, and GitHub webhooks at `/github/<token>`.
+#[derive(Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct WebhookConfig {
+    /// Address like `127.0.0.1:8089`. Webhook is disabled, when not set.
+    pub listen: Option<String>,
+    /// Secret part of URL: payloads are accepted only at `/hooks/<token>`
+    /// and `/github/<token>`.
+    pub token: Option<String>,
+    /// Group for payloads without channel. Defaults to active group.
+    pub group: Option<String>,
+    /// Slack channel to group mapping. Unmapped channels are posted to
+    /// group of the same name.
+    pub channels: HashMap<String, String>,
+    /// Secret of GitHub webhook. Payloads without valid signature are
+    /// rejected, when set.
+    pub github_secret: Option<String>,
+    /// GitHub repository, like `golemfactory/yagna`, to group mapping.
+    /// Unmapped repositories are posted to `group`.
+    pub repositories: HashMap<String, String>,
+}
+
+/// Slack incoming webhook payload. Other fields are ignored.
+#[derive(Deserialize)]
+struct Payload {
+    text: String,
+    username: Option<String>,
+    channel: Option<String>,
+}
+
+/// Payload to be posted by chat. Result is returned to webhook caller.
+#[derive(Message)]
+#[rtype(result = "anyhow::Result<()>")]
+pub struct IncomingWebhook {
+    /// None for active group.
+    pub group: Option<String>,
+    pub text: String,
+}
+
+impl WebhookConfig {
+    fn group(&self, channel: Option<&str>) -> Option<String> {
+        match channel {
+            Some(channel) => Some(
+                self.channels
+                    .get(channel)
+                    .cloned()
+                    .unwrap_or_else(|| channel.trim_start_matches('#').to_string()),
+            ),
+            None => self.group.clone(),
+        }
+    }
+
+    /// Serves requests in background thread. Does nothing, when `listen`
+    /// isn't set.
+    pub fn start(&self, chat: Recipient<IncomingWebhook>) -> anyhow::Result<()> {
+        let address = match &self.listen {
+            Some(address) => address,
+            None => return Ok(()),
+        };
+        let listener = TcpListener::bind(address)
+            .map_err(|e| anyhow!("Failed to listen for webhooks on {}. Error: {}", address, e))?;
+        log::info!("Listening for webhooks on {}.", address);
+
+        let config = self.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        log::warn!("Failed to accept webhook connection. Error: {}", e);
+                        continue;
+                    }
+                };
+                let (status, body) = match config.handle(&mut stream, &chat) {
+                    Ok(()) => ("200 OK", "ok".to_string()),
+                    Err(e) => ("400 Bad Request", e.to_string()),
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                )
+                .ok();
+            }
+        });
+        Ok(())
+    }
+
+    fn handle(
+        &self,
+        stream: &mut TcpStream,
+        chat: &Recipient<IncomingWebhook>,
+    ) -> anyhow::Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut reader = BufReader::new(stream);
+
+        let mut request = String::new();
+        reader.read_line(&mut request)?;
+        let mut parts = request.split_whitespace();
+        let (method, path) = (parts.next(), parts.next());
+        if method != Some("POST") {
+            bail!("invalid_method");
+        }
+        let github = path.map_or(false, |path| path.starts_with("/github"));
+        if let Some(token) = &self.token {
+            let route = if github { "github" } else { "hooks" };
+            if path != Some(format!("/{}/{}", route, token).as_str()) {
+                bail!("invalid_token");
+            }
+        }
+
+        let mut length = 0;
+        let mut event = None;
+        let mut signature = None;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header)?;
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(colon) = header.find(':') {
+                let (name, value) = (&header[..colon], header[colon + 1..].trim());
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.parse::<usize>()?;
+                } else if name.eq_ignore_ascii_case("x-github-event") {
+                    event = Some(value.to_string());
+                } else if name.eq_ignore_ascii_case("x-hub-signature-256") {
+                    signature = Some(value.to_string());
+                }
+            }
+        }
+        if length > MAX_BODY {
+            bail!("payload_too_large");
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+
+        if github {
+            return self.handle_github(event, signature, &body, chat);
+        }
+        let payload =
+            serde_json::from_slice::<Payload>(&body).map_err(|_| anyhow!("invalid_payload"))?;
+        if payload.text.trim().is_empty() {
+            bail!("no_text");
+        }
+        let text = match payload.username {
+            Some(username) => format!("{}: {}", username, payload.text),
+            None => payload.text,
+        };
+        let message = IncomingWebhook {
+            group: self.group(payload.channel.as_deref()),
+            text,
+        };
+        futures::executor::block_on(chat.send(message))?
+    }
+
+    fn handle_github(
+        &self,
+        event: Option<String>,
+        signature: Option<String>,
+        body: &[u8],
+        chat: &Recipient<IncomingWebhook>,
+    ) -> anyhow::Result<()> {
+        if let Some(secret) = &self.github_secret {
+            let signature = signature
+                .as_deref()
+                .and_then(|signature| signature.strip_prefix("sha256="))
+                .and_then(from_hex)
+                .ok_or_else(|| anyhow!("Missing signature."))?;
+            let mut mac = Hmac::<Sha256>::new_varkey(secret.as_bytes())
+                .map_err(|_| anyhow!("Invalid secret."))?;
+            mac.update(body);
+            mac.verify(&signature)
+                .map_err(|_| anyhow!("Invalid signature."))?;
+        }
+        let event = event.ok_or_else(|| anyhow!("Missing X-GitHub-Event header."))?;
+        let (repository, text) = match crate::github::format(&event, body)? {
+            Some(formatted) => formatted,
+            None => return Ok(()),
+        };
+        let message = IncomingWebhook {
+            group: self
+                .repositories
+                .get(&repository)
+                .cloned()
+                .or_else(|| self.group.clone()),
+            text,
+        };
+        futures::executor::block_on(chat.send(message))?
+    }
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,68 @@
+use std::path::Path;
+
+use ya_client::model::NodeId;
+use ya_core_model::identity;
+use ya_service_bus::{typed as bus, RpcEndpoint};
+
+use crate::device::Device;
+use crate::discover::SubscriptionInfo;
+use crate::protocol::DeviceCert;
+
+/// GSB address, chat handlers are bound to.
+pub const GSB_ENDPOINT: &str = "/public/yachat";
+
+/// Lines of `/whoami` output. Subscriptions are None, when chat isn't running.
+pub fn describe(
+    node_id: Option<NodeId>,
+    alias: Option<&str>,
+    device: Option<&DeviceCert>,
+    subscriptions: Option<&[SubscriptionInfo]>,
+) -> Vec<String> {
+    let mut lines = vec![format!("yachat {}", env!("CARGO_PKG_VERSION"))];
+    lines.push(match node_id {
+        Some(node_id) => format!("NodeId: {}", node_id),
+        None => "NodeId: unknown yet".to_string(),
+    });
+    lines.push(format!("Identity alias: {}", alias.unwrap_or("none")));
+    if let Some(cert) = device {
+        lines.push(format!("Linked device of: {}", cert.user));
+    }
+
+    lines.push("GSB endpoints:".to_string());
+    lines.push(format!("  {}", GSB_ENDPOINT));
+    if let Some(node_id) = node_id {
+        lines.push(format!("  /net/{}/yachat (address used by peers)", node_id));
+    }
+
+    match subscriptions {
+        None => (),
+        Some([]) => lines.push("Market subscriptions: none".to_string()),
+        Some(subscriptions) => {
+            lines.push("Market subscriptions:".to_string());
+            for sub in subscriptions {
+                lines.push(format!(
+                    "  {}: offer {}, demand {}",
+                    sub.group, sub.offer, sub.demand
+                ));
+            }
+        }
+    }
+    lines
+}
+
+/// `yachat whoami`: identity from yagna without joining any group.
+pub async fn print_offline(data_dir: &Path) -> anyhow::Result<()> {
+    let info = bus::service(identity::BUS_ID)
+        .send(identity::Get::ByDefault)
+        .await??;
+    let device = Device::load(data_dir)?;
+    let lines = describe(
+        info.as_ref().map(|info| info.node_id),
+        info.as_ref().and_then(|info| info.alias.as_deref()),
+        device.cert.as_ref(),
+        None,
+    );
+    println!("{}", lines.join("\n"));
+    println!("Market subscriptions exist only while chat is running. Use /whoami there.");
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use std::path::Path;
+
+use crate::chatlog::ChatLog;
+use crate::pins;
+use crate::session::Session;
+use crate::setup::prompt;
+use crate::storage::Storage;
+
+/// `yachat wipe --group <group>`. Removes everything stored about group:
+/// history, roster, pins, queued messages and plaintext chat logs. Must
+/// run while chat is stopped, otherwise it writes its state back.
+pub fn wipe_group(
+    data_dir: &Path,
+    group: &str,
+    storage: &dyn Storage,
+    yes: bool,
+) -> anyhow::Result<()> {
+    if !yes {
+        println!(
+            "This permanently removes history, roster, pins, queued messages and chat logs \
+             of group {} from {}.",
+            group,
+            data_dir.display()
+        );
+        if prompt("Type group name to confirm", "")? != group {
+            println!("Nothing was removed.");
+            return Ok(());
+        }
+    }
+
+    storage.delete_group(group)?;
+    let mut queue = storage.load_queue()?;
+    queue.retain(|(_, batch)| batch.group.as_deref() != Some(group));
+    storage.store_queue(&queue)?;
+    pins::delete(data_dir, group)?;
+    ChatLog::new(&data_dir.join("logs")).delete(group)?;
+
+    let mut session = Session::load(data_dir)?;
+    if session.groups.iter().any(|joined| joined == group) {
+        session.groups.retain(|joined| joined != group);
+        session.save(data_dir)?;
+    }
+
+    println!(
+        "Wiped group {}. Remove it from config file, so it isn't joined again.",
+        group
+    );
+    Ok(())
+}
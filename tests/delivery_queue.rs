@@ -0,0 +1,248 @@
+use chrono::{DateTime, Duration, Utc};
+use proptest::prelude::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use ya_client::model::NodeId;
+
+use yachat::delivery::DeliveryQueue;
+use yachat::protocol::{SendText, TextMessage};
+use yachat::storage::{self, Storage, StorageKind};
+
+/// Message queued for single peer. Restart stores queue and loads it back.
+#[derive(Clone, Debug)]
+struct Queued {
+    peer: u8,
+    group: u8,
+    direct: bool,
+    delayed: bool,
+    offset: i64,
+    ttl: Option<i64>,
+    restart: bool,
+}
+
+fn queued() -> impl Strategy<Value = Queued> {
+    (
+        0..3u8,
+        0..2u8,
+        any::<bool>(),
+        any::<bool>(),
+        0..3600i64,
+        proptest::option::of(0..7200i64),
+        proptest::bool::weighted(0.1),
+    )
+        .prop_map(
+            |(peer, group, direct, delayed, offset, ttl, restart)| Queued {
+                peer,
+                group,
+                direct,
+                delayed,
+                offset,
+                ttl,
+                restart,
+            },
+        )
+}
+
+fn base() -> DateTime<Utc> {
+    "2021-01-01T12:00:00Z".parse().unwrap()
+}
+
+fn peer(idx: u8) -> NodeId {
+    NodeId::from([idx + 1; 20])
+}
+
+fn send_text(idx: usize, queued: &Queued) -> SendText {
+    SendText {
+        messages: vec![Arc::new(TextMessage {
+            id: Uuid::from_u128(idx as u128),
+            content: format!("message {}", idx),
+            timestamp: base() + Duration::seconds(queued.offset),
+            ttl: queued.ttl,
+            reply_to: None,
+            relayed: None,
+            channel: None,
+            structured: None,
+        })],
+        user: "sender".to_string(),
+        group: Some(format!("group-{}", queued.group)),
+        direct: queued.direct,
+        auto_reply: false,
+        delayed: queued.delayed,
+    }
+}
+
+/// Files storage in fresh directory, removed on drop.
+struct TempStorage {
+    dir: PathBuf,
+    storage: Arc<dyn Storage>,
+}
+
+impl TempStorage {
+    fn new() -> TempStorage {
+        let dir = std::env::temp_dir().join(format!("yachat-queue-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let storage = storage::open(StorageKind::Files, &dir, None).unwrap();
+        TempStorage { dir, storage }
+    }
+
+    fn restart(&self, queue: DeliveryQueue) -> DeliveryQueue {
+        self.storage.store_queue(&queue.stored()).unwrap();
+        DeliveryQueue::restore(self.storage.load_queue().unwrap())
+    }
+}
+
+impl Drop for TempStorage {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.dir).ok();
+    }
+}
+
+/// Pushes all messages, restarting where requested, and returns messages
+/// expected per peer.
+fn fill(queue: &mut DeliveryQueue, all: &[Queued]) -> HashMap<NodeId, Vec<Arc<TextMessage>>> {
+    let storage = TempStorage::new();
+    let mut expected = HashMap::<NodeId, Vec<Arc<TextMessage>>>::new();
+    for (idx, queued) in all.iter().enumerate() {
+        let text = send_text(idx, queued);
+        expected
+            .entry(peer(queued.peer))
+            .or_default()
+            .extend(text.messages.iter().cloned());
+        queue.push(peer(queued.peer), text);
+        if queued.restart {
+            *queue = storage.restart(std::mem::take(queue));
+        }
+    }
+    *queue = storage.restart(std::mem::take(queue));
+    expected
+}
+
+fn sorted_ids<'a>(messages: impl Iterator<Item = &'a Arc<TextMessage>>) -> Vec<Uuid> {
+    let mut ids = messages.map(|text| text.id).collect::<Vec<_>>();
+    ids.sort();
+    ids
+}
+
+proptest! {
+    #[test]
+    fn no_message_lost_or_duplicated(all in proptest::collection::vec(queued(), 0..40)) {
+        let mut queue = DeliveryQueue::default();
+        let expected = fill(&mut queue, &all);
+
+        for idx in 0..3 {
+            let batches = queue.take(&peer(idx)).unwrap_or_default();
+            let taken = sorted_ids(batches.iter().flat_map(|batch| batch.messages.iter()));
+            let expected = sorted_ids(expected.get(&peer(idx)).into_iter().flatten());
+            prop_assert_eq!(taken, expected);
+        }
+        prop_assert!(queue.stored().is_empty());
+    }
+
+    #[test]
+    fn resent_in_timestamp_order(all in proptest::collection::vec(queued(), 0..40)) {
+        let mut queue = DeliveryQueue::default();
+        fill(&mut queue, &all);
+
+        for idx in 0..3 {
+            let batches = queue.take(&peer(idx)).unwrap_or_default();
+            for batch in batches.iter() {
+                prop_assert!(!batch.messages.is_empty());
+                let timestamps = batch.messages.iter().map(|text| text.timestamp).collect::<Vec<_>>();
+                prop_assert!(timestamps.windows(2).all(|pair| pair[0] <= pair[1]));
+            }
+            let firsts = batches.iter().map(|batch| batch.messages[0].timestamp).collect::<Vec<_>>();
+            prop_assert!(firsts.windows(2).all(|pair| pair[0] <= pair[1]));
+
+            // Single batch per group and kind.
+            let mut kinds = batches
+                .iter()
+                .map(|batch| (batch.group.clone(), batch.direct, batch.auto_reply, batch.delayed))
+                .collect::<Vec<_>>();
+            let count = kinds.len();
+            kinds.sort();
+            kinds.dedup();
+            prop_assert_eq!(kinds.len(), count);
+        }
+    }
+
+    #[test]
+    fn interrupted_flush_with_live_messages(
+        all in proptest::collection::vec(queued(), 1..40),
+        live in proptest::collection::vec(queued(), 0..20),
+        failed in any::<proptest::sample::Index>(),
+    ) {
+        let mut queue = DeliveryQueue::default();
+        let expected = fill(&mut queue, &all);
+        let address = peer(all[0].peer);
+
+        // Resending stops at failed batch, which comes back through
+        // `DeliverLater`, while live messages are held in queue.
+        let batches = queue.take(&address).unwrap();
+        let failed = failed.index(batches.len());
+        let mut batches = batches.into_iter();
+        let sent = batches.by_ref().take(failed).collect::<Vec<_>>();
+        queue.push(address, batches.next().unwrap());
+        let mut live_messages = vec![];
+        for (idx, live) in live.iter().enumerate() {
+            let live = Queued {
+                offset: 3600 + idx as i64,
+                ..live.clone()
+            };
+            let text = send_text(all.len() + idx, &live);
+            live_messages.extend(text.messages.iter().cloned());
+            queue.push(address, text);
+        }
+        queue.requeue(address, batches.collect());
+
+        let sent = sorted_ids(sent.iter().flat_map(|batch| batch.messages.iter()));
+        let mut should_remain = expected[&address]
+            .iter()
+            .chain(live_messages.iter())
+            .map(|text| text.id)
+            .filter(|id| !sent.contains(id))
+            .collect::<Vec<_>>();
+        should_remain.sort();
+
+        let batches = queue.take(&address).unwrap_or_default();
+        let remaining = sorted_ids(batches.iter().flat_map(|batch| batch.messages.iter()));
+        prop_assert_eq!(remaining, should_remain);
+        for batch in batches.iter() {
+            let timestamps = batch.messages.iter().map(|text| text.timestamp).collect::<Vec<_>>();
+            prop_assert!(timestamps.windows(2).all(|pair| pair[0] <= pair[1]));
+        }
+        let firsts = batches.iter().map(|batch| batch.messages[0].timestamp).collect::<Vec<_>>();
+        prop_assert!(firsts.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn expired_messages_dropped(
+        all in proptest::collection::vec(queued(), 0..40),
+        elapsed in 0..14400i64,
+    ) {
+        let mut queue = DeliveryQueue::default();
+        let expected = fill(&mut queue, &all);
+        let now = base() + Duration::seconds(elapsed);
+
+        let expired = queue.expire(&now);
+        for idx in 0..3 {
+            let messages = expected.get(&peer(idx)).cloned().unwrap_or_default();
+            let should_expire = sorted_ids(messages.iter().filter(|text| text.expired(&now)));
+            let should_remain = sorted_ids(messages.iter().filter(|text| !text.expired(&now)));
+
+            let mut dropped = expired
+                .iter()
+                .filter(|(recipient, _)| recipient == &peer(idx))
+                .flat_map(|(_, ids)| ids.iter().cloned())
+                .collect::<Vec<_>>();
+            dropped.sort();
+            prop_assert_eq!(dropped, should_expire);
+
+            let batches = queue.take(&peer(idx)).unwrap_or_default();
+            let remaining = sorted_ids(batches.iter().flat_map(|batch| batch.messages.iter()));
+            prop_assert_eq!(remaining, should_remain);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use chrono::{DateTime, Duration, Utc};
+use std::path::PathBuf;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use ya_client::model::NodeId;
+
+use yachat::history::{History, HistoryEntry};
+use yachat::storage::{self, Storage, StorageKind};
+
+/// Number of newest messages `History` keeps in memory.
+const LOADED: usize = 10000;
+const GROUP: &str = "forget";
+
+fn base() -> DateTime<Utc> {
+    "2021-01-01T12:00:00Z".parse().unwrap()
+}
+
+fn entry(idx: usize, sender: NodeId) -> HistoryEntry {
+    HistoryEntry {
+        id: Uuid::from_u128(idx as u128),
+        group: GROUP.to_string(),
+        sender: Some(sender),
+        user: sender.to_string(),
+        content: format!("message {}", idx),
+        timestamp: base() + Duration::seconds(idx as i64),
+        channel: None,
+    }
+}
+
+/// Storage in fresh directory, removed on drop.
+struct TempStorage {
+    dir: PathBuf,
+    storage: Arc<dyn Storage>,
+}
+
+impl TempStorage {
+    fn new(kind: StorageKind) -> TempStorage {
+        let dir = std::env::temp_dir().join(format!("yachat-forget-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let storage = storage::open(kind, &dir, None).unwrap();
+        TempStorage { dir, storage }
+    }
+}
+
+impl Drop for TempStorage {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.dir).ok();
+    }
+}
+
+/// Messages of forgotten sender older than loaded window are removed
+/// from storage, though history in memory never had them.
+fn forget_beyond_window(kind: StorageKind) {
+    let temp = TempStorage::new(kind);
+    let storage = temp.storage.as_ref();
+    let forgotten = NodeId::from([1; 20]);
+    let other = NodeId::from([2; 20]);
+
+    storage.append_message(&entry(0, forgotten)).unwrap();
+    storage.append_message(&entry(1, forgotten)).unwrap();
+    for idx in 2..LOADED + 2 {
+        storage.append_message(&entry(idx, other)).unwrap();
+    }
+    storage
+        .append_message(&entry(LOADED + 2, forgotten))
+        .unwrap();
+
+    let mut history = History::load(storage, GROUP).unwrap();
+    assert_eq!(history.forget(&[forgotten]), 1);
+
+    assert_eq!(storage.delete_by_sender(GROUP, &[forgotten]).unwrap(), 3);
+    let remaining = storage.query(GROUP, None, None, usize::MAX).unwrap();
+    assert_eq!(remaining.len(), LOADED);
+    assert!(remaining.iter().all(|entry| entry.sender == Some(other)));
+}
+
+#[test]
+fn forget_beyond_window_files() {
+    forget_beyond_window(StorageKind::Files);
+}
+
+#[test]
+fn forget_beyond_window_memory() {
+    forget_beyond_window(StorageKind::Memory);
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn forget_beyond_window_sqlite() {
+    forget_beyond_window(StorageKind::Sqlite);
+}